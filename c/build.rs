@@ -16,7 +16,7 @@ fn find_target_dir() -> PathBuf {
             || (dir.join("CACHEDIR.TAG").exists()
                 && !(dir
                     .parent()
-                    .map_or(true, |p| p.join("CACHEDIR.TAG").exists())))
+                    .is_none_or(|p| p.join("CACHEDIR.TAG").exists())))
         {
             return dir;
         }
@@ -68,11 +68,20 @@ class Compression(Enum):
         Uncompressed
     ZSTD
         Zstandard compressed.
+    GZIP
+        gzip compressed.
+    LZ4
+        LZ4 frame compressed.
+    XZ
+        xz (LZMA2) compressed.
 
     """
 
     NONE: str
     ZSTD: str
+    GZIP: str
+    LZ4: str
+    XZ: str
 
     @classmethod
     def from_str(cls, value: str) -> Compression: ...
@@ -89,12 +98,15 @@ class Encoding(Enum):
         Comma-separated values.
     JSON
         JavaScript object notation.
+    PARQUET
+        Apache Parquet. Not supported by `Transcoder`.
 
     """
 
     DBN: str
     CSV: str
     JSON: str
+    PARQUET: str
 
     @classmethod
     def from_str(cls, value: str) -> Encoding: ...
@@ -3568,7 +3580,7 @@ class Transcoder:
     file : BinaryIO | TextIO
         The file-like object to write the transcoded output to.
     encoding : Encoding
-        The encoding for the output.
+        The encoding for the output. `Encoding.PARQUET` isn't supported.
     compression : Compression
         The compression for the output.
     pretty_px : bool, default True
@@ -3598,6 +3610,11 @@ class Transcoder:
         metadata.
     upgrade_policy : VersionUpgradePolicy
         How to decode data from prior DBN versions. Defaults to decoding as-is.
+
+    Raises
+    ------
+    ValueError
+        When `encoding` is `Encoding.PARQUET`.
     """
 
     def __init__(
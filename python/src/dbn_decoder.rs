@@ -101,9 +101,8 @@ impl DbnDecoder {
             }
             Ok(())
         })
-        .map_err(|e| {
+        .inspect_err(|_e| {
             self.buffer.set_position(orig_position);
-            e
         })?;
         if recs.is_empty() {
             self.buffer.set_position(orig_position);
@@ -1,4 +1,6 @@
 //! Python bindings for the [`dbn`] crate using [`pyo3`].
+// `pymethods` expands to non-local impls with pyo3 0.20
+#![allow(non_local_definitions)]
 
 use pyo3::{prelude::*, wrap_pyfunction, PyClass};
 
@@ -100,6 +100,11 @@ impl Transcoder {
                 input_version,
                 upgrade_policy,
             )?),
+            Encoding::Parquet => {
+                return Err(PyValueError::new_err(
+                    "Transcoder doesn't support Parquet encoding",
+                ))
+            }
         }))
     }
 
@@ -475,6 +480,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parquet_unsupported() {
+        setup();
+        let file = MockPyFile::new();
+        Python::with_gil(|py| {
+            let res = Transcoder::new(
+                Py::new(py, file).unwrap().extract(py).unwrap(),
+                Encoding::Parquet,
+                Compression::None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            );
+            let Err(err) = res else {
+                panic!("expected Parquet to be rejected");
+            };
+            assert!(err.is_instance_of::<PyValueError>(py));
+            assert!(err.to_string().contains("Parquet"));
+        });
+    }
+
     #[test]
     fn test_partial_metadata_and_records() {
         setup();
@@ -0,0 +1,103 @@
+//! The `dbn cat` subcommand for concatenating DBN files.
+
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use clap::{ArgAction, Args};
+use dbn::{
+    decode::{DbnMetadata, DecodeRecordRef, DynDecoder},
+    encode::{DbnEncoder, DynWriter, EncodeRecord, EncodeRecordRef},
+    Compression, Metadata, VersionUpgradePolicy,
+};
+
+use crate::{open_output, split_compression_extension};
+
+#[derive(Debug, Args)]
+pub struct CatArgs {
+    #[clap(
+        help = "The DBN files to concatenate, in order",
+        value_name = "FILE",
+        required = true,
+        num_args = 1..
+    )]
+    pub inputs: Vec<PathBuf>,
+    #[clap(
+        short,
+        long,
+        help = "Saves the result to FILE. If no path is specified, the output will be written to standard output",
+        value_name = "FILE"
+    )]
+    pub output: Option<PathBuf>,
+    #[clap(short, long, action = ArgAction::SetTrue, default_value = "false", help = "Zstd compress the output")]
+    pub zstd: bool,
+    #[clap(
+        short,
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Allow overwriting of existing files, such as the output file"
+    )]
+    pub force: bool,
+}
+
+impl CatArgs {
+    /// Returns the output compression, inferred from the output file extension if not
+    /// explicitly requested.
+    pub fn compression(&self) -> Compression {
+        if self.zstd {
+            Compression::ZStd
+        } else {
+            self.output.as_ref().map_or(Compression::None, |output| {
+                split_compression_extension(&output.to_string_lossy()).1
+            })
+        }
+    }
+}
+
+/// Writes the records of all inputs in `args` as a single DBN stream with metadata
+/// merged with [`Metadata::merge()`]. Records are written in the order of the inputs
+/// and aren't re-sorted. Inputs from earlier DBN versions are upgraded if the inputs
+/// differ in version.
+pub fn cat_files(args: &CatArgs) -> anyhow::Result<()> {
+    // Opening the output truncates it, so it can't also be read as an input
+    if let Some(output) = args.output.as_ref().and_then(|o| o.canonicalize().ok()) {
+        if let Some(input) = args
+            .inputs
+            .iter()
+            .find(|input| input.canonicalize().is_ok_and(|i| i == output))
+        {
+            return Err(anyhow!(
+                "Unable to write output to '{}' because it's also an input",
+                input.display()
+            ));
+        }
+    }
+    let metadata = args
+        .inputs
+        .iter()
+        .map(|input| {
+            Ok(DynDecoder::from_file(input, VersionUpgradePolicy::AsIs)?
+                .metadata()
+                .clone())
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let merged = Metadata::merge(&metadata)?;
+    let writer = DynWriter::new(
+        open_output(args.output.as_ref(), args.force)?,
+        args.compression(),
+    )?;
+    let mut encoder = DbnEncoder::new(writer, &merged)?;
+    for (input, metadata) in args.inputs.iter().zip(metadata.iter()) {
+        let upgrade_policy = if metadata.version < merged.version {
+            VersionUpgradePolicy::Upgrade
+        } else {
+            VersionUpgradePolicy::AsIs
+        };
+        let mut decoder = DynDecoder::from_file(input, upgrade_policy)?;
+        while let Some(record) = decoder.decode_record_ref()? {
+            encoder.encode_record_ref(record)?;
+        }
+    }
+    encoder.flush()?;
+    Ok(())
+}
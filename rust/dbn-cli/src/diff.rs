@@ -0,0 +1,621 @@
+//! The `dbn diff` subcommand for comparing two DBN files.
+
+use std::{collections::BTreeSet, fmt::Debug, io, path::PathBuf};
+
+use clap::{ArgAction, Args};
+use dbn::{
+    decode::{DbnMetadata, DecodeRecordRef, DynDecoder},
+    rtype_dispatch, Metadata, Record, RecordRef, VersionUpgradePolicy,
+};
+
+#[derive(Debug, Args)]
+#[cfg_attr(test, derive(Default))]
+pub struct DiffArgs {
+    #[clap(help = "The first DBN file to compare", value_name = "FILE1")]
+    pub left: PathBuf,
+    #[clap(help = "The second DBN file to compare", value_name = "FILE2")]
+    pub right: PathBuf,
+    #[clap(
+        short = 'n',
+        long = "max-mismatches",
+        default_value = "10",
+        value_name = "NUM",
+        help = "Report at most NUM mismatched records"
+    )]
+    pub max_mismatches: usize,
+    #[clap(
+        long = "unordered",
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Compare records with the same index timestamp as sets, ignoring the order between them"
+    )]
+    pub unordered: bool,
+    #[clap(
+        short = 'u',
+        long = "upgrade",
+        default_value = "false",
+        action = ArgAction::SetTrue,
+        help = "Upgrade data when decoding previous DBN versions. By default data is decoded as-is."
+    )]
+    pub should_upgrade: bool,
+}
+
+impl DiffArgs {
+    pub fn upgrade_policy(&self) -> VersionUpgradePolicy {
+        if self.should_upgrade {
+            VersionUpgradePolicy::Upgrade
+        } else {
+            VersionUpgradePolicy::AsIs
+        }
+    }
+}
+
+/// Counts of the differences found by [`diff`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffSummary {
+    /// The number of metadata fields that differ.
+    pub metadata_fields: usize,
+    /// The number of records present in both inputs at the same position with different
+    /// contents. Always zero when comparing unordered.
+    pub mismatched_records: usize,
+    /// The number of records only present in the first input.
+    pub left_only_records: usize,
+    /// The number of records only present in the second input.
+    pub right_only_records: usize,
+}
+
+impl DiffSummary {
+    /// Returns `true` if no differences were found.
+    pub fn is_identical(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Decodes the two files in `args` and writes a report of their differences to
+/// `writer`.
+pub fn diff_files(args: &DiffArgs, writer: impl io::Write) -> anyhow::Result<DiffSummary> {
+    let left = DynDecoder::from_file(&args.left, args.upgrade_policy())?;
+    let right = DynDecoder::from_file(&args.right, args.upgrade_policy())?;
+    let names = (
+        args.left.display().to_string(),
+        args.right.display().to_string(),
+    );
+    diff(
+        left,
+        right,
+        &names,
+        args.max_mismatches,
+        args.unordered,
+        writer,
+    )
+}
+
+/// Compares the metadata and then the records of `left` and `right`, writing a report
+/// of at most `max_mismatches` differing records to `writer`. With `unordered`, records
+/// with the same index timestamp are compared as multisets.
+pub fn diff(
+    left: impl DecodeRecordRef + DbnMetadata,
+    right: impl DecodeRecordRef + DbnMetadata,
+    names: &(String, String),
+    max_mismatches: usize,
+    unordered: bool,
+    mut writer: impl io::Write,
+) -> anyhow::Result<DiffSummary> {
+    let mut summary = DiffSummary::default();
+    let metadata_diffs = diff_metadata(left.metadata(), right.metadata());
+    summary.metadata_fields = metadata_diffs.len();
+    if !metadata_diffs.is_empty() {
+        writeln!(writer, "metadata differs:")?;
+        for (field, l, r) in metadata_diffs {
+            writeln!(writer, "  {field}: {l} != {r}")?;
+        }
+    }
+    let mut reporter = Reporter {
+        writer: &mut writer,
+        names,
+        max_mismatches,
+        reported: 0,
+    };
+    if unordered {
+        diff_unordered(left, right, &mut summary, &mut reporter)?;
+    } else {
+        diff_ordered(left, right, &mut summary, &mut reporter)?;
+    }
+    let reportable = if unordered {
+        summary.left_only_records + summary.right_only_records
+    } else {
+        summary.mismatched_records
+    };
+    if reportable > reporter.reported {
+        writeln!(
+            writer,
+            "... stopped reporting after {max_mismatches} mismatches"
+        )?;
+    }
+    if summary.is_identical() {
+        writeln!(writer, "files are identical")?;
+    } else {
+        writeln!(
+            writer,
+            "{} metadata field(s) differ, {} record(s) differ, {} record(s) only in {}, {} record(s) only in {}",
+            summary.metadata_fields,
+            summary.mismatched_records,
+            summary.left_only_records,
+            names.0,
+            summary.right_only_records,
+            names.1
+        )?;
+    }
+    writer.flush()?;
+    Ok(summary)
+}
+
+/// Placeholder for a field only present in one of the records, e.g. when comparing
+/// records from different DBN versions.
+const MISSING: &str = "<missing>";
+
+struct Reporter<'a, W> {
+    writer: W,
+    names: &'a (String, String),
+    max_mismatches: usize,
+    reported: usize,
+}
+
+impl<W: io::Write> Reporter<'_, W> {
+    fn is_full(&self) -> bool {
+        self.reported >= self.max_mismatches
+    }
+
+    fn mismatch(&mut self, index: usize, left: RecordRef, right: RecordRef) -> io::Result<()> {
+        if self.is_full() {
+            return Ok(());
+        }
+        self.reported += 1;
+        if left.header().rtype != right.header().rtype {
+            writeln!(
+                self.writer,
+                "record {index} differs (rtype {} != {}):",
+                rtype_name(left),
+                rtype_name(right)
+            )?;
+            writeln!(self.writer, "  - {}", record_debug(left, false))?;
+            return writeln!(self.writer, "  + {}", record_debug(right, false));
+        }
+        writeln!(
+            self.writer,
+            "record {index} differs ({}):",
+            rtype_name(left)
+        )?;
+        let left_fields = debug_fields(&record_debug(left, true));
+        let right_fields = debug_fields(&record_debug(right, true));
+        let mut any_field = false;
+        for (name, l) in left_fields.iter() {
+            let r = right_fields
+                .iter()
+                .find(|(r_name, _)| r_name == name)
+                .map_or(MISSING, |(_, r)| r.as_str());
+            if l != r {
+                any_field = true;
+                writeln!(self.writer, "  {name}: {l} != {r}")?;
+            }
+        }
+        for (name, r) in right_fields.iter() {
+            if !left_fields.iter().any(|(l_name, _)| l_name == name) {
+                any_field = true;
+                writeln!(self.writer, "  {name}: {MISSING} != {r}")?;
+            }
+        }
+        if !any_field {
+            // Differences outside the fields, e.g. in padding or an appended `ts_out`
+            writeln!(
+                self.writer,
+                "  bytes: {:02x?} != {:02x?}",
+                left.as_ref(),
+                right.as_ref()
+            )?;
+        }
+        Ok(())
+    }
+
+    fn one_sided(&mut self, index: usize, rec: RecordRef, is_left: bool) -> io::Result<()> {
+        if self.is_full() {
+            return Ok(());
+        }
+        self.reported += 1;
+        let (name, sign) = if is_left {
+            (&self.names.0, '-')
+        } else {
+            (&self.names.1, '+')
+        };
+        writeln!(self.writer, "record {index} only in {name}:")?;
+        writeln!(self.writer, "  {sign} {}", record_debug(rec, false))
+    }
+}
+
+fn diff_ordered<W: io::Write>(
+    mut left: impl DecodeRecordRef,
+    mut right: impl DecodeRecordRef,
+    summary: &mut DiffSummary,
+    reporter: &mut Reporter<W>,
+) -> anyhow::Result<()> {
+    let mut index = 0;
+    loop {
+        match (left.decode_record_ref()?, right.decode_record_ref()?) {
+            (Some(l), Some(r)) => {
+                if l.as_ref() != r.as_ref() {
+                    summary.mismatched_records += 1;
+                    reporter.mismatch(index, l, r)?;
+                }
+            }
+            (Some(_), None) => {
+                summary.left_only_records += 1;
+                while left.decode_record_ref()?.is_some() {
+                    summary.left_only_records += 1;
+                }
+                return Ok(());
+            }
+            (None, Some(_)) => {
+                summary.right_only_records += 1;
+                while right.decode_record_ref()?.is_some() {
+                    summary.right_only_records += 1;
+                }
+                return Ok(());
+            }
+            (None, None) => return Ok(()),
+        }
+        index += 1;
+    }
+}
+
+/// A group of consecutive records sharing the same index timestamp along with their
+/// positions in the input.
+type Group = (u64, Vec<(usize, Vec<u8>)>);
+
+/// Reads consecutive records with the same index timestamp from a decoder.
+struct GroupReader<D> {
+    decoder: D,
+    index: usize,
+    pending: Option<(usize, Vec<u8>)>,
+}
+
+impl<D: DecodeRecordRef> GroupReader<D> {
+    fn new(decoder: D) -> Self {
+        Self {
+            decoder,
+            index: 0,
+            pending: None,
+        }
+    }
+
+    fn next_record(&mut self) -> dbn::Result<Option<(usize, Vec<u8>)>> {
+        if let Some(pending) = self.pending.take() {
+            return Ok(Some(pending));
+        }
+        Ok(self.decoder.decode_record_ref()?.map(|rec| {
+            self.index += 1;
+            (self.index - 1, rec.as_ref().to_vec())
+        }))
+    }
+
+    fn next_group(&mut self) -> dbn::Result<Option<Group>> {
+        let Some(first) = self.next_record()? else {
+            return Ok(None);
+        };
+        let ts = index_ts(&first.1);
+        let mut group = vec![first];
+        while let Some(next) = self.next_record()? {
+            if index_ts(&next.1) != ts {
+                self.pending = Some(next);
+                break;
+            }
+            group.push(next);
+        }
+        Ok(Some((ts, group)))
+    }
+}
+
+fn diff_unordered<W: io::Write>(
+    left: impl DecodeRecordRef,
+    right: impl DecodeRecordRef,
+    summary: &mut DiffSummary,
+    reporter: &mut Reporter<W>,
+) -> anyhow::Result<()> {
+    let mut left = GroupReader::new(left);
+    let mut right = GroupReader::new(right);
+    let mut left_group = left.next_group()?;
+    let mut right_group = right.next_group()?;
+    loop {
+        match (left_group.take(), right_group.take()) {
+            (None, None) => return Ok(()),
+            (Some((l_ts, l_recs)), Some((r_ts, r_recs))) if l_ts == r_ts => {
+                let mut r_recs: Vec<_> = r_recs.into_iter().map(Some).collect();
+                for (index, l_rec) in l_recs {
+                    if let Some(matching) = r_recs
+                        .iter_mut()
+                        .find(|r| r.as_ref().is_some_and(|(_, r_rec)| *r_rec == l_rec))
+                    {
+                        *matching = None;
+                    } else {
+                        summary.left_only_records += 1;
+                        reporter.one_sided(index, as_record_ref(&l_rec), true)?;
+                    }
+                }
+                for (index, r_rec) in r_recs.into_iter().flatten() {
+                    summary.right_only_records += 1;
+                    reporter.one_sided(index, as_record_ref(&r_rec), false)?;
+                }
+                left_group = left.next_group()?;
+                right_group = right.next_group()?;
+            }
+            (Some((l_ts, l_recs)), r) if r.as_ref().is_none_or(|(r_ts, _)| l_ts < *r_ts) => {
+                for (index, rec) in l_recs {
+                    summary.left_only_records += 1;
+                    reporter.one_sided(index, as_record_ref(&rec), true)?;
+                }
+                left_group = left.next_group()?;
+                right_group = r;
+            }
+            (l, Some((_, r_recs))) => {
+                for (index, rec) in r_recs {
+                    summary.right_only_records += 1;
+                    reporter.one_sided(index, as_record_ref(&rec), false)?;
+                }
+                left_group = l;
+                right_group = right.next_group()?;
+            }
+            (Some(_), None) => unreachable!("handled by guard above"),
+        }
+    }
+}
+
+fn as_record_ref(bytes: &[u8]) -> RecordRef<'_> {
+    // Safety: `bytes` was copied from a decoded record.
+    unsafe { RecordRef::new(bytes) }
+}
+
+fn index_ts(bytes: &[u8]) -> u64 {
+    as_record_ref(bytes).raw_index_ts()
+}
+
+fn rtype_name(rec: RecordRef) -> String {
+    rec.rtype()
+        .map(|rtype| format!("{rtype:?}"))
+        .unwrap_or_else(|_| format!("{:#04x}", rec.header().rtype))
+}
+
+fn record_debug(rec: RecordRef, pretty: bool) -> String {
+    fn debug_string<R: Debug>(rec: &R, pretty: bool) -> String {
+        if pretty {
+            format!("{rec:#?}")
+        } else {
+            format!("{rec:?}")
+        }
+    }
+
+    // Dispatches on the record size as well as rtype to handle records from previous
+    // DBN versions
+    rtype_dispatch!(rec, debug_string, pretty)
+        .unwrap_or_else(|_| debug_string(rec.header(), pretty))
+}
+
+/// Flattens pretty-printed `Debug` output into pairs of field paths and values, e.g.
+/// `levels.0.bid_px`. Unnamed wrappers at the top level like enum variants are
+/// skipped.
+fn debug_fields(pretty: &str) -> Vec<(String, String)> {
+    let is_open = |s: &str| s.ends_with(['{', '[', '(']);
+    let mut lines = pretty
+        .lines()
+        .map(|l| l.trim().trim_end_matches(','))
+        .peekable();
+    while lines.next_if(|l| is_open(l) && !l.contains(": ")).is_some() {}
+
+    let mut path: Vec<String> = Vec::new();
+    let mut counters = vec![0_usize];
+    let mut fields = Vec::new();
+    for line in lines {
+        if matches!(line, "}" | "]" | ")") {
+            path.pop();
+            counters.pop();
+            continue;
+        }
+        let (name, value) = if let Some((name, value)) = line.split_once(": ") {
+            (name.to_owned(), value)
+        } else {
+            let counter = counters.last_mut().expect("non-empty");
+            *counter += 1;
+            ((*counter - 1).to_string(), line)
+        };
+        if is_open(value) {
+            path.push(name);
+            counters.push(0);
+        } else {
+            let full_name = path
+                .iter()
+                .chain(std::iter::once(&name))
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(".");
+            fields.push((full_name, value.to_owned()));
+        }
+    }
+    fields
+}
+
+fn diff_metadata(left: &Metadata, right: &Metadata) -> Vec<(String, String, String)> {
+    fn push_if_ne<T: PartialEq + Debug>(
+        diffs: &mut Vec<(String, String, String)>,
+        name: &str,
+        left: &T,
+        right: &T,
+    ) {
+        if left != right {
+            diffs.push((name.to_owned(), format!("{left:?}"), format!("{right:?}")));
+        }
+    }
+
+    let mut diffs = Vec::new();
+    macro_rules! compare_fields {
+        ($($field:ident),*) => {
+            $(push_if_ne(&mut diffs, stringify!($field), &left.$field, &right.$field);)*
+        };
+    }
+    compare_fields!(
+        version,
+        dataset,
+        schema,
+        start,
+        end,
+        limit,
+        stype_in,
+        stype_out,
+        ts_out,
+        symbol_cstr_len,
+        symbols,
+        partial,
+        not_found
+    );
+    let raw_symbols: BTreeSet<_> = left
+        .mappings
+        .iter()
+        .chain(right.mappings.iter())
+        .map(|m| m.raw_symbol.as_str())
+        .collect();
+    for raw_symbol in raw_symbols {
+        let intervals = |metadata: &Metadata| {
+            metadata
+                .mappings
+                .iter()
+                .find(|m| m.raw_symbol == raw_symbol)
+                .map(|m| m.intervals.clone())
+        };
+        push_if_ne(
+            &mut diffs,
+            &format!("mappings[{raw_symbol}]"),
+            &intervals(left),
+            &intervals(right),
+        );
+    }
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use dbn::{
+        decode::dbn::Decoder,
+        encode::{dbn::Encoder, EncodeRecord},
+        enums::rtype,
+        record::{BidAskPair, Mbp1Msg, RecordHeader, TradeMsg},
+        Dataset, MetadataBuilder, SType, Schema,
+    };
+
+    use super::*;
+
+    fn trade(ts: u64, price: i64) -> TradeMsg {
+        TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 1, ts),
+            price,
+            size: 1,
+            action: b'T' as i8,
+            side: b'A' as i8,
+            flags: 0,
+            depth: 0,
+            ts_recv: ts,
+            ts_in_delta: 0,
+            sequence: 0,
+        }
+    }
+
+    fn encode(start: u64, recs: &[TradeMsg]) -> Decoder<io::Cursor<Vec<u8>>> {
+        let metadata = MetadataBuilder::new()
+            .dataset(Dataset::XnasItch.to_string())
+            .schema(Some(Schema::Trades))
+            .start(start)
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .build();
+        let mut buf = Vec::new();
+        let mut encoder = Encoder::new(&mut buf, &metadata).unwrap();
+        for rec in recs {
+            encoder.encode_record(rec).unwrap();
+        }
+        Decoder::new(io::Cursor::new(buf)).unwrap()
+    }
+
+    fn run(
+        left: Decoder<io::Cursor<Vec<u8>>>,
+        right: Decoder<io::Cursor<Vec<u8>>>,
+        unordered: bool,
+    ) -> (DiffSummary, String) {
+        let mut out = Vec::new();
+        let names = ("a.dbn".to_owned(), "b.dbn".to_owned());
+        let summary = diff(left, right, &names, 10, unordered, &mut out).unwrap();
+        (summary, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_identical() {
+        let recs = [trade(1, 10), trade(2, 20)];
+        let (summary, out) = run(encode(0, &recs), encode(0, &recs), false);
+        assert!(summary.is_identical());
+        assert_eq!(out, "files are identical\n");
+    }
+
+    #[test]
+    fn test_metadata_and_field_diff() {
+        let left = encode(0, &[trade(1, 10), trade(2, 20)]);
+        let right = encode(5, &[trade(1, 10), trade(2, 25), trade(3, 30)]);
+        let (summary, out) = run(left, right, false);
+        assert_eq!(
+            summary,
+            DiffSummary {
+                metadata_fields: 1,
+                mismatched_records: 1,
+                left_only_records: 0,
+                right_only_records: 1,
+            }
+        );
+        assert!(out.contains("  start: 0 != 5\n"), "{out}");
+        assert!(
+            out.contains("record 1 differs (Mbp0):\n  price: 0.000000020 != 0.000000025\n"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn test_unordered() {
+        let left = encode(0, &[trade(1, 10), trade(1, 20), trade(2, 30)]);
+        let right = encode(0, &[trade(1, 20), trade(1, 10), trade(3, 30)]);
+        let (summary, _) = run(left, right, false);
+        assert_eq!(summary.mismatched_records, 3);
+        let left = encode(0, &[trade(1, 10), trade(1, 20), trade(2, 30)]);
+        let right = encode(0, &[trade(1, 20), trade(1, 10), trade(3, 30)]);
+        let (summary, out) = run(left, right, true);
+        assert_eq!(summary.mismatched_records, 0);
+        assert_eq!(summary.left_only_records, 1);
+        assert_eq!(summary.right_only_records, 1);
+        assert!(out.contains("record 2 only in a.dbn:"), "{out}");
+        assert!(out.contains("record 2 only in b.dbn:"), "{out}");
+    }
+
+    #[test]
+    fn test_debug_fields_nested() {
+        let mut rec = Mbp1Msg {
+            hd: RecordHeader::new::<Mbp1Msg>(rtype::MBP_1, 1, 1, 0),
+            price: 1,
+            size: 2,
+            action: 0,
+            side: 0,
+            flags: 0,
+            depth: 0,
+            ts_recv: 0,
+            ts_in_delta: 0,
+            sequence: 0,
+            levels: [BidAskPair::default()],
+        };
+        rec.levels[0].ask_sz = 7;
+        let fields = debug_fields(&record_debug(RecordRef::from(&rec), true));
+        assert!(fields.contains(&("levels.0.ask_sz".to_owned(), "7".to_owned())));
+        assert!(fields.contains(&("hd.instrument_id".to_owned(), "1".to_owned())));
+        assert!(fields.contains(&("price".to_owned(), "0.000000001".to_owned())));
+    }
+}
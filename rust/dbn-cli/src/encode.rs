@@ -1,15 +1,38 @@
-use std::io;
+use std::{fs, io};
 
+use anyhow::Context;
 use dbn::{
     decode::{DbnMetadata, DecodeRecordRef},
     encode::{
-        json, DbnEncodable, DbnRecordEncoder, DynEncoder, DynWriter, EncodeDbn, EncodeRecordRef,
-        EncodeRecordTextExt,
+        json, DbnEncodable, DbnRecordEncoder, DynEncoder, DynEncoderBuilder, DynWriter, EncodeDbn,
+        EncodeRecordRef, EncodeRecordTextExt, ZstdOptions,
     },
-    rtype_dispatch, Compression, Encoding, MetadataBuilder, SType, SymbolIndex,
+    rtype_dispatch, Compression, Encoding, Metadata, MetadataBuilder, RType, SType, SymbolIndex,
 };
 
-use crate::{infer_encoding_and_compression, output_from_args, Args};
+use crate::{
+    filter::SharedSymbolMap, infer_encoding_and_compression, open_output, output_from_args, Args,
+};
+
+/// The record types included in a `--union` CSV when none are specified.
+const ALL_RTYPES: [RType; 16] = [
+    RType::Mbo,
+    RType::Mbp0,
+    RType::Mbp1,
+    RType::Mbp10,
+    RType::Ohlcv1S,
+    RType::Ohlcv1M,
+    RType::Ohlcv1H,
+    RType::Ohlcv1D,
+    RType::OhlcvEod,
+    RType::InstrumentDef,
+    RType::Imbalance,
+    RType::Statistics,
+    RType::Status,
+    RType::SymbolMapping,
+    RType::System,
+    RType::Error,
+];
 
 pub fn silence_broken_pipe(err: anyhow::Error) -> anyhow::Result<()> {
     // Handle broken pipe as a non-error.
@@ -22,11 +45,108 @@ pub fn silence_broken_pipe(err: anyhow::Error) -> anyhow::Result<()> {
     Err(err)
 }
 
-pub fn encode_from_dbn<D>(mut decoder: D, args: &Args) -> anyhow::Result<()>
+/// Creates an encoder builder for `args`, writing to a file per record type in the
+/// output directory if splitting, otherwise to the output.
+fn encoder_builder<'m>(
+    args: &Args,
+    encoding: Encoding,
+    compression: Compression,
+    metadata: &'m Metadata,
+) -> anyhow::Result<DynEncoderBuilder<'m, Box<dyn io::Write>>> {
+    let builder = if args.split {
+        if !matches!(encoding, Encoding::Csv | Encoding::Json) {
+            return Err(anyhow::format_err!(
+                "'--split' is only valid with CSV and JSON encodings"
+            ));
+        }
+        // Required by clap
+        let dir = args.output.clone().unwrap();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Unable to create output directory '{}'", dir.display()))?;
+        let extension = match compression.extension() {
+            Some(compression_ext) => format!("{encoding}.{compression_ext}"),
+            None => encoding.to_string(),
+        };
+        let force = args.force;
+        DynEncoderBuilder::new_split(
+            move |name| {
+                open_output(Some(&dir.join(format!("{name}.{extension}"))), force)
+                    .map_err(io::Error::other)
+            },
+            encoding,
+            compression,
+            metadata,
+        )
+    } else {
+        DynEncoder::builder(output_from_args(args)?, encoding, compression, metadata)
+    };
+    csv_options(
+        builder
+            .all_pretty(args.should_pretty_print)
+            .with_symbol(args.map_symbols)
+            .zstd_options(args.zstd_options()?),
+        encoding,
+        args,
+    )
+}
+
+/// Applies the CSV-only options from `args` to `builder`.
+fn csv_options<'m, W: io::Write>(
+    mut builder: DynEncoderBuilder<'m, W>,
+    encoding: Encoding,
+    args: &Args,
+) -> anyhow::Result<DynEncoderBuilder<'m, W>> {
+    if !args.has_csv_options() {
+        return Ok(builder);
+    }
+    if encoding != Encoding::Csv {
+        return Err(anyhow::format_err!(
+            "'--delimiter', '--fields', '--null', and '--union' are only valid with CSV encoding"
+        ));
+    }
+    if let Some(delimiter) = args.delimiter {
+        builder = builder.delimiter(delimiter);
+    }
+    if !args.fields.is_empty() {
+        let mut fields = Vec::with_capacity(args.fields.len());
+        for field in &args.fields {
+            if let Some((field, header)) = field.split_once(':') {
+                builder = builder.rename_field(field, header);
+                fields.push(field);
+            } else {
+                fields.push(field.as_str());
+            }
+        }
+        builder = builder.fields(fields);
+    }
+    if let Some(null_value) = &args.null_value {
+        builder = builder.null_value(null_value);
+    }
+    if let Some(union) = &args.union {
+        builder = builder.union(
+            if union.is_empty() {
+                ALL_RTYPES.as_slice()
+            } else {
+                union.as_slice()
+            }
+            .iter()
+            .copied(),
+        );
+    }
+    Ok(builder)
+}
+
+/// Encodes the records from `decoder`. When mapping symbols, the mappings in the
+/// metadata are used if present, otherwise `symbol_map`, which is updated from the
+/// symbol mapping records in the stream, e.g. for live data.
+pub fn encode_from_dbn<D>(
+    mut decoder: D,
+    args: &Args,
+    symbol_map: &SharedSymbolMap,
+) -> anyhow::Result<()>
 where
     D: DecodeRecordRef + DbnMetadata,
 {
-    let writer = output_from_args(args)?;
     let (encoding, compression) = infer_encoding_and_compression(args)?;
     Ok(if args.should_output_metadata {
         if encoding != Encoding::Json {
@@ -35,20 +155,33 @@ where
             ));
         }
         json::Encoder::new(
-            writer,
+            output_from_args(args)?,
             args.should_pretty_print,
             args.should_pretty_print,
             args.should_pretty_print,
         )
         .encode_metadata(decoder.metadata())
     } else if args.fragment {
-        encode_fragment(decoder, writer, compression)
+        encode_fragment(
+            decoder,
+            output_from_args(args)?,
+            compression,
+            &args.zstd_options()?,
+        )
     } else {
-        let mut encoder = DynEncoder::builder(writer, encoding, compression, decoder.metadata())
-            .all_pretty(args.should_pretty_print)
-            .with_symbol(args.map_symbols)
-            .build()?;
-        if args.map_symbols {
+        let mut encoder =
+            encoder_builder(args, encoding, compression, decoder.metadata())?.build()?;
+        if args.map_symbols && decoder.metadata().mappings.is_empty() {
+            let ts_out = decoder.metadata().ts_out;
+            while let Some(rec) = decoder.decode_record_ref()? {
+                let symbol_map = symbol_map.borrow();
+                let sym = symbol_map.get_for_rec(&rec).map(String::as_str);
+                // Safety: ts_out is accurate because we get it from the metadata
+                unsafe {
+                    encoder.encode_ref_ts_out_with_sym(rec, ts_out, sym)?;
+                }
+            }
+        } else if args.map_symbols {
             let symbol_map = decoder.metadata().symbol_map()?;
             let ts_out = decoder.metadata().ts_out;
             while let Some(rec) = decoder.decode_record_ref()? {
@@ -58,56 +191,70 @@ where
                     encoder.encode_ref_ts_out_with_sym(rec, ts_out, sym)?;
                 }
             }
-            Ok(())
         } else {
-            encoder.encode_decoded(decoder)
+            encoder.encode_decoded(decoder)?;
         }
+        encoder.finish()
     }?)
 }
 
-pub fn encode_from_frag<D>(mut decoder: D, args: &Args) -> anyhow::Result<()>
+/// Encodes the records from a fragment `decoder`. When mapping symbols, `symbol_map`
+/// is expected to be updated from the symbol mapping records in the stream.
+pub fn encode_from_frag<D>(
+    mut decoder: D,
+    args: &Args,
+    symbol_map: &SharedSymbolMap,
+) -> anyhow::Result<()>
 where
     D: DecodeRecordRef,
 {
-    let writer = output_from_args(args)?;
     let (encoding, compression) = infer_encoding_and_compression(args)?;
     if args.fragment {
-        encode_fragment(decoder, writer, compression)?;
+        encode_fragment(
+            decoder,
+            output_from_args(args)?,
+            compression,
+            &args.zstd_options()?,
+        )?;
         return Ok(());
     }
     assert!(!args.should_output_metadata);
 
-    let mut encoder = DynEncoder::builder(
-        writer,
-        encoding,
-        compression,
-        // dummy metadata won't be encoded
-        &MetadataBuilder::new()
-            .dataset(String::new())
-            .schema(None)
-            .start(0)
-            .stype_in(None)
-            .stype_out(SType::InstrumentId)
-            .build(),
-    )
-    // Can't write header until we know the record type
-    .write_header(false)
-    .all_pretty(args.should_pretty_print)
-    .build()?;
-    let mut has_written_header = encoding != Encoding::Csv;
+    // dummy metadata won't be encoded
+    let metadata = MetadataBuilder::new()
+        .dataset(String::new())
+        .schema(None)
+        .start(0)
+        .stype_in(None)
+        .stype_out(SType::InstrumentId)
+        .build();
+    let mut encoder = encoder_builder(args, encoding, compression, &metadata)?
+        // Can't write header until we know the record type, unless each record type is
+        // written to a separate file or all are in a union
+        .write_header(args.split || args.union.is_some())
+        .build()?;
+    let mut has_written_header = encoding != Encoding::Csv || args.split || args.union.is_some();
     fn write_header<T: DbnEncodable>(
         _record: &T,
         encoder: &mut DynEncoder<Box<dyn io::Write>>,
+        with_symbol: bool,
     ) -> dbn::Result<()> {
-        encoder.encode_header::<T>(false)
+        encoder.encode_header::<T>(with_symbol)
     }
     while let Some(record) = decoder.decode_record_ref()? {
         if !has_written_header {
-            rtype_dispatch!(record, write_header, &mut encoder)??;
+            rtype_dispatch!(record, write_header, &mut encoder, args.map_symbols)??;
             has_written_header = true;
         }
-        encoder.encode_record_ref(record)?;
+        if args.map_symbols {
+            let symbol_map = symbol_map.borrow();
+            let sym = symbol_map.get_for_rec(&record).map(String::as_str);
+            encoder.encode_ref_with_sym(record, sym)?;
+        } else {
+            encoder.encode_record_ref(record)?;
+        }
     }
+    encoder.finish()?;
     Ok(())
 }
 
@@ -115,8 +262,13 @@ fn encode_fragment<D: DecodeRecordRef>(
     mut decoder: D,
     writer: Box<dyn io::Write>,
     compression: Compression,
+    zstd_options: &ZstdOptions,
 ) -> dbn::Result<()> {
-    let mut encoder = DbnRecordEncoder::new(DynWriter::new(writer, compression)?);
+    let mut encoder = DbnRecordEncoder::new(DynWriter::with_zstd_options(
+        writer,
+        compression,
+        zstd_options,
+    )?);
     while let Some(record) = decoder.decode_record_ref()? {
         encoder.encode_record_ref(record)?;
     }
@@ -0,0 +1,637 @@
+//! A small expression language for filtering records by their fields, e.g.
+//! `size >= 100 && side == 'B' && action == 'T'`.
+//!
+//! Expressions are made up of comparisons between a field and a literal, combined
+//! with `&&`, `||`, `!`, and parentheses. Field names are the same as the columns of
+//! the CSV output. Literals are interpreted based on the field they're compared
+//! against:
+//! - integers are compared against the raw value of integer fields, or as whole units
+//!   when compared against a fixed-precision price
+//! - decimals like `100.25` are compared against fixed-precision prices
+//! - quoted single characters like `'B'` are compared against character fields
+//! - quoted strings are compared against string fields like `raw_symbol`, or parsed as
+//!   an RFC 3339 timestamp or ISO 8601 date (midnight UTC) when compared against a
+//!   timestamp field
+//!
+//! Literals are type-checked against the fields when the expression is parsed. A
+//! comparison against a field the record doesn't have evaluates to `false`.
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use dbn::{
+    reflect::{FieldDesc, FieldHint, FieldType, FieldValue, HasFields},
+    ErrorMsg, ImbalanceMsg, InstrumentDefMsg, MboMsg, Mbp10Msg, Mbp1Msg, OhlcvMsg, RecordRef,
+    StatMsg, StatusMsg, SymbolMappingMsg, SystemMsg, TradeMsg, FIXED_PRICE_SCALE,
+};
+use time::{format_description::well_known::Rfc3339, macros::format_description};
+
+/// A parsed filter expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    /// Both expressions must be true.
+    And(Box<Expr>, Box<Expr>),
+    /// Either expression must be true.
+    Or(Box<Expr>, Box<Expr>),
+    /// The expression must be false.
+    Not(Box<Expr>),
+    /// Compares the value of a field to a literal.
+    Cmp {
+        field: String,
+        op: CmpOp,
+        literal: Literal,
+    },
+}
+
+/// A comparison operator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A literal value in an expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Literal {
+    /// An integer.
+    Int(i128),
+    /// A decimal number scaled by [`FIXED_PRICE_SCALE`].
+    Decimal(i64),
+    /// A quoted string, with its value as a UNIX nanosecond timestamp if it's a valid
+    /// timestamp or date.
+    Str { value: String, ts: Option<u64> },
+}
+
+impl Expr {
+    /// Evaluates the expression against `rec`. A comparison against a field `rec`
+    /// doesn't have, or whose type doesn't match the literal, evaluates to `false`.
+    pub fn eval(&self, rec: RecordRef) -> bool {
+        match self {
+            Expr::And(lhs, rhs) => lhs.eval(rec) && rhs.eval(rec),
+            Expr::Or(lhs, rhs) => lhs.eval(rec) || rhs.eval(rec),
+            Expr::Not(expr) => !expr.eval(rec),
+            Expr::Cmp { field, op, literal } => rec
+                .fields()
+                .and_then(|fields| fields.iter().find(|f| f.name() == field))
+                .and_then(|desc| compare(desc, desc.value(rec)?, literal))
+                .is_some_and(|ord| op.matches(ord)),
+        }
+    }
+
+    /// Checks that every literal can be compared against the field of the same name in
+    /// `fields`. Comparisons against fields not in `fields` are ignored.
+    ///
+    /// # Errors
+    /// This function returns an error describing the first mismatched comparison.
+    pub fn check(&self, fields: &[FieldDesc]) -> Result<(), String> {
+        self.try_for_each_cmp(&mut |field, literal| {
+            fields
+                .iter()
+                .find(|f| f.name() == field)
+                .map_or(Ok(()), |desc| check(desc, literal))
+        })
+    }
+
+    /// Checks that every field exists in some record type and that every literal can be
+    /// compared against at least one of them.
+    fn check_any_record(&self) -> Result<(), String> {
+        self.try_for_each_cmp(&mut |field, literal| {
+            let mut descs = ALL_FIELDS
+                .iter()
+                .flat_map(|fields| fields())
+                .filter(|f| f.name() == field)
+                .peekable();
+            if descs.peek().is_none() {
+                return Err(format!("unknown field `{field}`"));
+            }
+            let mut res = Ok(());
+            for desc in descs {
+                res = check(desc, literal);
+                if res.is_ok() {
+                    break;
+                }
+            }
+            res
+        })
+    }
+
+    fn try_for_each_cmp<'a>(
+        &'a self,
+        f: &mut impl FnMut(&'a str, &'a Literal) -> Result<(), String>,
+    ) -> Result<(), String> {
+        match self {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                lhs.try_for_each_cmp(f)?;
+                rhs.try_for_each_cmp(f)
+            }
+            Expr::Not(expr) => expr.try_for_each_cmp(f),
+            Expr::Cmp { field, literal, .. } => f(field, literal),
+        }
+    }
+
+    /// Returns the names of all fields referenced in the expression.
+    pub fn fields(&self) -> Vec<&str> {
+        let mut fields = Vec::new();
+        self.collect_fields(&mut fields);
+        fields
+    }
+
+    fn collect_fields<'a>(&'a self, fields: &mut Vec<&'a str>) {
+        match self {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                lhs.collect_fields(fields);
+                rhs.collect_fields(fields);
+            }
+            Expr::Not(expr) => expr.collect_fields(fields),
+            Expr::Cmp { field, .. } => {
+                if !fields.contains(&field.as_str()) {
+                    fields.push(field);
+                }
+            }
+        }
+    }
+}
+
+impl CmpOp {
+    fn matches(self, ord: Ordering) -> bool {
+        match self {
+            CmpOp::Eq => ord.is_eq(),
+            CmpOp::Ne => ord.is_ne(),
+            CmpOp::Lt => ord.is_lt(),
+            CmpOp::Le => ord.is_le(),
+            CmpOp::Gt => ord.is_gt(),
+            CmpOp::Ge => ord.is_ge(),
+        }
+    }
+
+    /// Returns the operator with its operands swapped, e.g. `<` for `>`.
+    fn flip(self) -> Self {
+        match self {
+            CmpOp::Eq | CmpOp::Ne => self,
+            CmpOp::Lt => CmpOp::Gt,
+            CmpOp::Le => CmpOp::Ge,
+            CmpOp::Gt => CmpOp::Lt,
+            CmpOp::Ge => CmpOp::Le,
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Int(i) => write!(f, "{i}"),
+            Literal::Decimal(d) => write!(f, "{}", dbn::pretty::Px(*d)),
+            Literal::Str { value, .. } => write!(f, "'{value}'"),
+        }
+    }
+}
+
+/// The field descriptors of every record type, used to type-check expressions before
+/// the schema is known.
+const ALL_FIELDS: &[fn() -> &'static [FieldDesc]] = &[
+    MboMsg::fields,
+    TradeMsg::fields,
+    Mbp1Msg::fields,
+    Mbp10Msg::fields,
+    OhlcvMsg::fields,
+    StatusMsg::fields,
+    InstrumentDefMsg::fields,
+    ImbalanceMsg::fields,
+    StatMsg::fields,
+    ErrorMsg::fields,
+    SymbolMappingMsg::fields,
+    SystemMsg::fields,
+];
+
+/// Checks that `literal` can be compared against the field described by `desc`.
+fn check(desc: &FieldDesc, literal: &Literal) -> Result<(), String> {
+    let (is_match, expected) = match (desc.field_type(), desc.hint()) {
+        (FieldType::CStr(_), _) => (matches!(literal, Literal::Str { .. }), "a quoted string"),
+        (_, Some(hint)) if hint.is_c_char() => (
+            matches!(literal, Literal::Str { value, .. } if value.chars().count() == 1),
+            "a single character",
+        ),
+        (_, Some(FieldHint::FixedPrice)) => (
+            matches!(literal, Literal::Int(_) | Literal::Decimal(_)),
+            "a number",
+        ),
+        (_, Some(FieldHint::UnixNanos)) => (
+            matches!(literal, Literal::Int(_) | Literal::Str { ts: Some(_), .. }),
+            "an integer or an RFC 3339 timestamp",
+        ),
+        _ => (matches!(literal, Literal::Int(_)), "an integer"),
+    };
+    if is_match {
+        Ok(())
+    } else {
+        Err(format!(
+            "can't compare field `{}` with {literal}: expected {expected}",
+            desc.name()
+        ))
+    }
+}
+
+/// Compares `value` against `literal`, returning `None` if their types don't match.
+fn compare(desc: &FieldDesc, value: FieldValue, literal: &Literal) -> Option<Ordering> {
+    let is_price = desc.hint() == Some(FieldHint::FixedPrice);
+    match (value, literal) {
+        (FieldValue::Char(c), Literal::Str { value, .. }) => {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(lit), None) => Some(c.cmp(&lit)),
+                _ => None,
+            }
+        }
+        (FieldValue::CStr(_), Literal::Str { value: lit, .. }) => {
+            Some(value.to_string().as_str().cmp(lit.as_str()))
+        }
+        (FieldValue::Char(_) | FieldValue::CStr(_), _) => None,
+        (_, Literal::Int(lit)) if is_price => {
+            Some(to_i128(value).cmp(&lit.saturating_mul(i128::from(FIXED_PRICE_SCALE))))
+        }
+        (_, Literal::Int(lit)) => Some(to_i128(value).cmp(lit)),
+        (_, Literal::Decimal(lit)) if is_price => Some(to_i128(value).cmp(&i128::from(*lit))),
+        (_, Literal::Str { ts: Some(ts), .. }) if desc.hint() == Some(FieldHint::UnixNanos) => {
+            Some(to_i128(value).cmp(&i128::from(*ts)))
+        }
+        _ => None,
+    }
+}
+
+fn to_i128(value: FieldValue) -> i128 {
+    value
+        .as_i64()
+        .map(i128::from)
+        .or_else(|| value.as_u64().map(i128::from))
+        .unwrap_or_default()
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected {token} after expression"));
+        }
+        expr.check_any_record()?;
+        Ok(expr)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Literal(Literal),
+    Op(CmpOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "`{ident}`"),
+            Token::Literal(lit) => write!(f, "{lit}"),
+            Token::Op(op) => write!(
+                f,
+                "`{}`",
+                match op {
+                    CmpOp::Eq => "==",
+                    CmpOp::Ne => "!=",
+                    CmpOp::Lt => "<",
+                    CmpOp::Le => "<=",
+                    CmpOp::Gt => ">",
+                    CmpOp::Ge => ">=",
+                }
+            ),
+            Token::And => write!(f, "`&&`"),
+            Token::Or => write!(f, "`||`"),
+            Token::Not => write!(f, "`!`"),
+            Token::LParen => write!(f, "`(`"),
+            Token::RParen => write!(f, "`)`"),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let token = match c {
+            '(' => {
+                chars.next();
+                Token::LParen
+            }
+            ')' => {
+                chars.next();
+                Token::RParen
+            }
+            '&' | '|' => {
+                chars.next();
+                if chars.next_if(|&(_, next)| next == c).is_none() {
+                    return Err(format!("expected `{c}{c}` at position {start}"));
+                }
+                if c == '&' {
+                    Token::And
+                } else {
+                    Token::Or
+                }
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let has_eq = chars.next_if(|&(_, next)| next == '=').is_some();
+                match (c, has_eq) {
+                    ('=', true) => Token::Op(CmpOp::Eq),
+                    ('=', false) => return Err(format!("expected `==` at position {start}")),
+                    ('!', true) => Token::Op(CmpOp::Ne),
+                    ('!', false) => Token::Not,
+                    ('<', true) => Token::Op(CmpOp::Le),
+                    ('<', false) => Token::Op(CmpOp::Lt),
+                    ('>', true) => Token::Op(CmpOp::Ge),
+                    _ => Token::Op(CmpOp::Gt),
+                }
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, next)) if next == c => break,
+                        Some((_, next)) => value.push(next),
+                        None => return Err(format!("unterminated string at position {start}")),
+                    }
+                }
+                let ts = parse_ts(&value);
+                Token::Literal(Literal::Str { value, ts })
+            }
+            '-' | '0'..='9' => {
+                let mut end = start;
+                while let Some((i, _)) =
+                    chars.next_if(|&(i, next)| next.is_ascii_digit() || next == '.' || i == start)
+                {
+                    end = i + 1;
+                }
+                Token::Literal(parse_number(&s[start..end])?)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start;
+                while let Some((i, next)) =
+                    chars.next_if(|&(_, next)| next.is_ascii_alphanumeric() || next == '_')
+                {
+                    end = i + next.len_utf8();
+                }
+                Token::Ident(s[start..end].to_owned())
+            }
+            c => return Err(format!("unexpected character `{c}` at position {start}")),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn parse_number(s: &str) -> Result<Literal, String> {
+    let invalid = || format!("invalid number `{s}`");
+    let Some((int, frac)) = s.split_once('.') else {
+        return s.parse().map(Literal::Int).map_err(|_| invalid());
+    };
+    let scale_digits = FIXED_PRICE_SCALE.ilog10() as usize;
+    if frac.is_empty() || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    if frac.len() > scale_digits {
+        return Err(format!(
+            "`{s}` has more than {scale_digits} decimal places of precision"
+        ));
+    }
+    let is_negative = int.starts_with('-');
+    let int: i64 = match int {
+        "" | "-" => 0,
+        int => int.parse().map_err(|_| invalid())?,
+    };
+    let frac: i64 = format!("{frac:0<scale_digits$}")
+        .parse()
+        .map_err(|_| invalid())?;
+    int.checked_mul(FIXED_PRICE_SCALE)
+        .and_then(|scaled| {
+            if is_negative {
+                scaled.checked_sub(frac)
+            } else {
+                scaled.checked_add(frac)
+            }
+        })
+        .map(Literal::Decimal)
+        .ok_or_else(|| format!("`{s}` is out of range for a price"))
+}
+
+pub(crate) fn parse_ts(s: &str) -> Option<u64> {
+    let dt = time::OffsetDateTime::parse(s, &Rfc3339).ok().or_else(|| {
+        time::Date::parse(s, format_description!("[year]-[month]-[day]"))
+            .ok()
+            .map(|date| date.midnight().assume_utc())
+    })?;
+    u64::try_from(dt.unix_timestamp_nanos()).ok()
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    Some(token) => Err(format!("expected `)`, found {token}")),
+                    None => Err("expected `)`, found end of expression".to_owned()),
+                }
+            }
+            Some(lhs) => {
+                let op = match self.next() {
+                    Some(Token::Op(op)) => op,
+                    Some(token) => {
+                        return Err(format!("expected comparison operator, found {token}"))
+                    }
+                    None => {
+                        return Err(
+                            "expected comparison operator, found end of expression".to_owned()
+                        )
+                    }
+                };
+                let Some(rhs) = self.next() else {
+                    return Err("expected field or literal, found end of expression".to_owned());
+                };
+                match (lhs, rhs) {
+                    (Token::Ident(field), Token::Literal(literal)) => {
+                        Ok(Expr::Cmp { field, op, literal })
+                    }
+                    (Token::Literal(literal), Token::Ident(field)) => Ok(Expr::Cmp {
+                        field,
+                        op: op.flip(),
+                        literal,
+                    }),
+                    (lhs, rhs) => Err(format!(
+                        "comparisons must be between a field and a literal, found {lhs} and {rhs}"
+                    )),
+                }
+            }
+            None => Err("expected expression, found end of expression".to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_char;
+
+    use dbn::{record::str_to_c_chars, InstrumentDefMsg, MboMsg};
+    use rstest::rstest;
+
+    use super::*;
+
+    fn mbo() -> MboMsg {
+        MboMsg {
+            price: 100_250_000_000,
+            size: 150,
+            side: b'B' as c_char,
+            action: b'T' as c_char,
+            ts_recv: 1_704_186_000_000_000_000,
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case("size >= 100", true)]
+    #[case("size >= 100 && side == 'B' && action == 'T'", true)]
+    #[case("size > 150 || side == 'A'", false)]
+    #[case("!(side == 'A')", true)]
+    #[case("price == 100.25", true)]
+    #[case("price < 100.3 && price > 100.2", true)]
+    #[case("price > 100 && price < 101", true)]
+    #[case("price == 100250000000", false)]
+    #[case("100 < size", true)]
+    #[case("ts_recv == '2024-01-02T09:00:00Z'", true)]
+    #[case("ts_recv >= '2024-01-02' && ts_recv < '2024-01-03'", true)]
+    #[case("ts_recv < \"2024-01-02T09:00:00.000000001Z\"", true)]
+    #[case("size > 100 && (side == 'A' || action == 'T')", true)]
+    #[case("raw_symbol == 'ESH4'", false)]
+    fn test_eval(#[case] expr: &str, #[case] exp: bool) {
+        let rec = mbo();
+        let expr = Expr::from_str(expr).unwrap();
+        assert_eq!(expr.eval(RecordRef::from(&rec)), exp);
+    }
+
+    #[test]
+    fn test_eval_str() {
+        let rec = InstrumentDefMsg {
+            raw_symbol: str_to_c_chars("ESH4").unwrap(),
+            ..Default::default()
+        };
+        let expr = Expr::from_str("raw_symbol == 'ESH4' && raw_symbol > \"ES\"").unwrap();
+        assert!(expr.eval(RecordRef::from(&rec)));
+    }
+
+    #[rstest]
+    #[case("side == 1.5")]
+    #[case("size == 1.5")]
+    #[case("side == 'AB'")]
+    #[case("ts_recv > 'yesterday'")]
+    #[case("size == 'A'")]
+    #[case("raw_symbol == 1")]
+    fn test_parse_mismatch(#[case] expr: &str) {
+        let err = Expr::from_str(expr).unwrap_err();
+        assert!(err.contains("can't compare field"), "{err}");
+    }
+
+    #[test]
+    fn test_check() {
+        // `bid_px_00` is a price for MBP-1 but doesn't exist for MBO
+        let expr = Expr::from_str("bid_px_00 > 100.5 && size == 1").unwrap();
+        assert!(expr.check(MboMsg::fields()).is_ok());
+        assert!(expr.check(Mbp1Msg::fields()).is_ok());
+        let expr = Expr::Cmp {
+            field: "price".to_owned(),
+            op: CmpOp::Eq,
+            literal: Literal::Str {
+                value: "ESH4".to_owned(),
+                ts: None,
+            },
+        };
+        assert!(expr.check(MboMsg::fields()).is_err());
+    }
+
+    #[rstest]
+    #[case("-1.5", Literal::Decimal(-1_500_000_000))]
+    #[case("0.000000001", Literal::Decimal(1))]
+    #[case("-42", Literal::Int(-42))]
+    #[case("18446744073709551615", Literal::Int(u64::MAX as i128))]
+    fn test_parse_number(#[case] s: &str, #[case] exp: Literal) {
+        assert_eq!(parse_number(s).unwrap(), exp);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("size >")]
+    #[case("size = 1")]
+    #[case("size == 1 &&")]
+    #[case("(size == 1")]
+    #[case("size == 1)")]
+    #[case("size == side")]
+    #[case("size == 'B")]
+    #[case("price == 1.0000000001")]
+    #[case("size == 1 & side == 'B'")]
+    #[case("not_a_field == 1")]
+    fn test_parse_error(#[case] expr: &str) {
+        assert!(Expr::from_str(expr).is_err(), "{expr}");
+    }
+
+    #[test]
+    fn test_fields() {
+        let expr = Expr::from_str("size > 1 && (size < 5 || !(side == 'B'))").unwrap();
+        assert_eq!(expr.fields(), ["size", "side"]);
+    }
+}
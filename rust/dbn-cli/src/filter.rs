@@ -1,10 +1,14 @@
-use std::num::NonZeroU64;
+use std::{cell::RefCell, num::NonZeroU64, rc::Rc};
 
+use anyhow::anyhow;
 use dbn::{
     decode::{DbnMetadata, DecodeRecordRef},
-    RType, Record, RecordRef, Schema,
+    reflect::{FieldDesc, HasFields},
+    schema_dispatch, PitSymbolMap, RType, Record, RecordRef, Schema,
 };
 
+use crate::expr::Expr;
+
 #[derive(Debug)]
 pub struct SchemaFilter<D> {
     decoder: D,
@@ -43,7 +47,7 @@ impl<D: DbnMetadata> DbnMetadata for SchemaFilter<D> {
 }
 
 impl<D: DecodeRecordRef> DecodeRecordRef for SchemaFilter<D> {
-    fn decode_record_ref(&mut self) -> dbn::Result<Option<dbn::RecordRef>> {
+    fn decode_record_ref(&mut self) -> dbn::Result<Option<dbn::RecordRef<'_>>> {
         while let Some(record) = self.decoder.decode_record_ref()? {
             if self
                 .rtype
@@ -106,7 +110,7 @@ impl<D: DbnMetadata> DbnMetadata for LimitFilter<D> {
 }
 
 impl<D: DecodeRecordRef> DecodeRecordRef for LimitFilter<D> {
-    fn decode_record_ref(&mut self) -> dbn::Result<Option<RecordRef>> {
+    fn decode_record_ref(&mut self) -> dbn::Result<Option<RecordRef<'_>>> {
         if self
             .limit
             .map(|limit| self.record_count >= limit.get())
@@ -114,9 +118,123 @@ impl<D: DecodeRecordRef> DecodeRecordRef for LimitFilter<D> {
         {
             return Ok(None);
         }
-        Ok(self.decoder.decode_record_ref()?.map(|rec| {
+        Ok(self.decoder.decode_record_ref()?.inspect(|_rec| {
             self.record_count += 1;
-            rec
         }))
     }
 }
+
+#[derive(Debug)]
+pub struct WhereFilter<D> {
+    decoder: D,
+    expr: Option<Expr>,
+}
+
+impl<D> WhereFilter<D>
+where
+    D: DbnMetadata,
+{
+    /// Creates a new filter, validating the fields referenced in `expr` against the
+    /// schema in the metadata if it has one.
+    pub fn new(decoder: D, expr: Option<Expr>) -> anyhow::Result<Self> {
+        fn fields_of<R: HasFields>() -> &'static [FieldDesc] {
+            R::fields()
+        }
+
+        if let (Some(expr), Some(schema)) = (&expr, decoder.metadata().schema) {
+            let fields = schema_dispatch!(schema, fields_of);
+            if let Some(unknown) = expr
+                .fields()
+                .into_iter()
+                .find(|name| fields.iter().all(|f| f.name() != *name))
+            {
+                return Err(anyhow!(
+                    "Invalid --where expression: {schema} records have no field `{unknown}`"
+                ));
+            }
+            expr.check(fields)
+                .map_err(|e| anyhow!("Invalid --where expression: {e}"))?;
+        }
+        Ok(Self::new_no_metadata(decoder, expr))
+    }
+}
+
+impl<D> WhereFilter<D> {
+    pub fn new_no_metadata(decoder: D, expr: Option<Expr>) -> Self {
+        Self { decoder, expr }
+    }
+}
+
+impl<D: DbnMetadata> DbnMetadata for WhereFilter<D> {
+    fn metadata(&self) -> &dbn::Metadata {
+        self.decoder.metadata()
+    }
+
+    fn metadata_mut(&mut self) -> &mut dbn::Metadata {
+        self.decoder.metadata_mut()
+    }
+}
+
+impl<D: DecodeRecordRef> DecodeRecordRef for WhereFilter<D> {
+    fn decode_record_ref(&mut self) -> dbn::Result<Option<RecordRef<'_>>> {
+        while let Some(record) = self.decoder.decode_record_ref()? {
+            if self
+                .expr
+                .as_ref()
+                .map(|expr| expr.eval(record))
+                .unwrap_or(true)
+            {
+                // Safe: casting reference to pointer so the pointer will always be valid.
+                // Getting around borrow checker limitation.
+                return Ok(Some(unsafe {
+                    RecordRef::unchecked_from_header(record.header())
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A point-in-time symbol map shared between a [`SymbolMapUpdater`] and the encoding
+/// loop.
+pub type SharedSymbolMap = Rc<RefCell<PitSymbolMap>>;
+
+/// Updates a symbol map from the `SymbolMappingMsg` records in the stream, as found in
+/// live data. Should wrap the decoder directly so other filters can't drop the symbol
+/// mappings.
+#[derive(Debug)]
+pub struct SymbolMapUpdater<D> {
+    decoder: D,
+    symbol_map: Option<SharedSymbolMap>,
+}
+
+impl<D> SymbolMapUpdater<D> {
+    /// Creates a new updater. If `symbol_map` is `None`, records are passed through
+    /// unchanged.
+    pub fn new(decoder: D, symbol_map: Option<SharedSymbolMap>) -> Self {
+        Self {
+            decoder,
+            symbol_map,
+        }
+    }
+}
+
+impl<D: DbnMetadata> DbnMetadata for SymbolMapUpdater<D> {
+    fn metadata(&self) -> &dbn::Metadata {
+        self.decoder.metadata()
+    }
+
+    fn metadata_mut(&mut self) -> &mut dbn::Metadata {
+        self.decoder.metadata_mut()
+    }
+}
+
+impl<D: DecodeRecordRef> DecodeRecordRef for SymbolMapUpdater<D> {
+    fn decode_record_ref(&mut self) -> dbn::Result<Option<RecordRef<'_>>> {
+        let record = self.decoder.decode_record_ref()?;
+        if let (Some(record), Some(symbol_map)) = (record, &self.symbol_map) {
+            symbol_map.borrow_mut().on_record(record)?;
+        }
+        Ok(record)
+    }
+}
@@ -1,20 +1,29 @@
 use std::{
     fs::File,
-    io::{self, BufWriter},
+    io::{self, BufReader, BufWriter},
     num::NonZeroU64,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use anyhow::{anyhow, Context};
-use clap::{ArgAction, Parser, ValueEnum};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use serde::de::DeserializeOwned;
 
 use dbn::{
+    encode::ZstdOptions,
     enums::{Compression, Encoding},
-    Schema, VersionUpgradePolicy,
+    Metadata, RType, Schema, SymbologyResolution, VersionUpgradePolicy,
 };
 
+use crate::expr::Expr;
+
+pub mod cat;
+pub mod diff;
 pub mod encode;
+pub mod expr;
 pub mod filter;
+pub mod metadata;
+pub mod train_dict;
 
 /// How the output of the `dbn` command will be encoded.
 #[derive(Clone, Copy, Debug, ValueEnum)]
@@ -24,18 +33,41 @@ pub enum OutputEncoding {
     Dbn,
     Csv,
     Json,
+    Parquet,
     DbnFragment,
 }
 
+/// Subcommands of `dbn`. Without a subcommand, `dbn` converts `FILE` to another
+/// encoding.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Concatenate DBN files, merging their metadata
+    Cat(cat::CatArgs),
+    /// Compare the metadata and records of two DBN files
+    Diff(diff::DiffArgs),
+    /// Edit the metadata of a DBN file
+    Metadata(metadata::MetadataArgs),
+    /// Train a Zstd dictionary on DBN files for compressing many small files
+    TrainDict(train_dict::TrainDictArgs),
+}
+
 #[derive(Debug, Parser)]
-#[clap(version, about)]
+#[clap(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 #[cfg_attr(test, derive(Default))]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     #[clap(
-        help = "A DBN or legacy DBZ file to convert to another encoding. Pass '-' to read from standard input",
-        value_name = "FILE"
+        help = "A DBN or legacy DBZ file to convert to another encoding. Pass '-' to read from standard input. Pass '--' before FILE if it has the same name as a subcommand",
+        value_name = "FILE",
+        required = true
     )]
-    pub input: PathBuf,
+    pub input: Option<PathBuf>,
     #[clap(
         short,
         long,
@@ -61,6 +93,15 @@ pub struct Args {
         help = "Output the result as CSV"
     )]
     pub csv: bool,
+    #[clap(
+        short = 'P',
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        group = "output_encoding",
+        help = "Output the result as Parquet. With --zstd, the columns are Zstd compressed"
+    )]
+    pub parquet: bool,
     #[clap(
         short = 'D',
         long,
@@ -81,6 +122,32 @@ pub struct Args {
     pub fragment: bool,
     #[clap(short, long, action = ArgAction::SetTrue, default_value = "false", help = "Zstd compress the output")]
     pub zstd: bool,
+    #[clap(
+        long = "zstd-level",
+        value_name = "LEVEL",
+        allow_negative_numbers = true,
+        help = "Zstd compress the output with LEVEL, where higher levels compress better but slower, e.g. 19 for archival. Defaults to Zstd's default level. Implies --zstd"
+    )]
+    pub zstd_level: Option<i32>,
+    #[clap(
+        long = "zstd-threads",
+        value_name = "NUM_THREADS",
+        help = "Zstd compress the output with NUM_THREADS background threads. Implies --zstd"
+    )]
+    pub zstd_threads: Option<u32>,
+    #[clap(
+        long = "zstd-long",
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Enable Zstd long-distance matching, which improves the compression of large outputs. Implies --zstd"
+    )]
+    pub zstd_long: bool,
+    #[clap(
+        long = "zstd-dict",
+        value_name = "FILE",
+        help = "Compress Zstd output and decompress Zstd input with the dictionary in FILE, such as one created with 'dbn train-dict'"
+    )]
+    pub zstd_dict: Option<PathBuf>,
     #[clap(
         short = 'u',
         long = "upgrade",
@@ -120,10 +187,17 @@ pub struct Args {
          long = "map-symbols",
          action = ArgAction::SetTrue,
          default_value = "false",
-         conflicts_with_all = ["input_fragment", "dbn", "fragment"],
-         help ="Use symbology mappings from the metadata to create a 'symbol' field mapping the intstrument ID to its requested symbol."
+         conflicts_with_all = ["dbn", "fragment"],
+         help ="Use symbology mappings from the metadata to create a 'symbol' field mapping the intstrument ID to its requested symbol. For fragments and DBN without mappings, such as live data, the symbol mapping records in the stream are used instead"
     )]
     pub map_symbols: bool,
+    #[clap(
+        long = "symbology",
+        value_name = "FILE",
+        conflicts_with = "input_fragment",
+        help = "Replace the symbology mappings in the metadata with those from a Databento symbology.json file or symbology resolve response. Useful with --map-symbols for files without mappings"
+    )]
+    pub symbology: Option<PathBuf>,
     #[clap(
         short = 'l',
         long = "limit",
@@ -137,8 +211,8 @@ pub struct Args {
         action = ArgAction::SetTrue,
         default_value = "false",
         group = "input_fragment",
-        conflicts_with_all = ["is_input_zstd_fragment", "should_output_metadata", "dbn"],
-        help = "Interpret the input as an uncompressed DBN fragment, i.e. records without metadata. Only valid with text output encodings"
+        conflicts_with_all = ["is_input_zstd_fragment", "should_output_metadata"],
+        help = "Interpret the input as an uncompressed DBN fragment, i.e. records without metadata. Only valid with text output encodings unless --input-metadata is passed"
     )]
     pub is_input_fragment: bool,
     #[clap(
@@ -146,8 +220,8 @@ pub struct Args {
         action = ArgAction::SetTrue,
         default_value = "false",
         group = "input_fragment",
-        conflicts_with_all = ["should_output_metadata", "dbn"],
-        help = "Interpret the input as a Zstd-compressed DBN fragment, i.e. records without metadata. Only valid with text output encodings"
+        conflicts_with_all = ["should_output_metadata"],
+        help = "Interpret the input as a Zstd-compressed DBN fragment, i.e. records without metadata. Only valid with text output encodings unless --input-metadata is passed"
     )]
     pub is_input_zstd_fragment: bool,
     #[clap(
@@ -158,12 +232,69 @@ pub struct Args {
         requires = "input_fragment"
     )]
     pub input_dbn_version_override: Option<u8>,
+    #[clap(
+        long = "input-metadata",
+        help = "Read the metadata for the fragment from a JSON file, such as one created with --json --metadata, and treat the input as a complete DBN stream. Use with --dbn to build a DBN file from a fragment",
+        value_name = "FILE",
+        requires = "input_fragment",
+        conflicts_with = "input_dbn_version_override"
+    )]
+    pub input_metadata: Option<PathBuf>,
     #[clap(
         long = "schema",
-        help = "Only encode records of this schema. This is particularly useful for transcoding mixed-schema DBN to CSV, which doesn't support mixing schemas",
+        help = "Only encode records of this schema. This is particularly useful for transcoding mixed-schema DBN to CSV, which doesn't support mixing schemas. See also --split and --union",
         value_name = "SCHEMA"
     )]
     pub schema_filter: Option<Schema>,
+    #[clap(
+        long = "where",
+        help = "Only encode records matching this expression, e.g. \"size >= 100 && side == 'B'\". Supports comparisons of fields with integers, decimal prices, quoted characters and strings, and quoted RFC 3339 timestamps or dates, combined with '&&', '||', '!', and parentheses",
+        value_name = "EXPR"
+    )]
+    pub where_filter: Option<Expr>,
+    #[clap(
+        long = "delimiter",
+        value_name = "CHAR",
+        value_parser = parse_delimiter,
+        conflicts_with_all = ["json", "parquet", "dbn", "fragment", "should_output_metadata"],
+        help = "Separate CSV fields with CHAR instead of a comma, e.g. '|' or '\\t' for tabs"
+    )]
+    pub delimiter: Option<u8>,
+    #[clap(
+        long = "fields",
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        conflicts_with_all = ["json", "parquet", "dbn", "fragment", "should_output_metadata"],
+        help = "Only output these comma-separated CSV fields in the given order. Rename a field in the header with FIELD:HEADER, e.g. 'ts_event:time'"
+    )]
+    pub fields: Vec<String>,
+    #[clap(
+        long = "null",
+        value_name = "STRING",
+        conflicts_with_all = ["json", "parquet", "dbn", "fragment", "should_output_metadata"],
+        help = "Output undefined prices, timestamps, and sizes in CSV as STRING, e.g. '', 'NaN', or 'null'"
+    )]
+    pub null_value: Option<String>,
+    #[clap(
+        long = "union",
+        value_name = "TYPES",
+        value_parser = parse_rtype,
+        num_args = 0..=1,
+        require_equals = true,
+        value_delimiter = ',',
+        conflicts_with_all = ["json", "parquet", "dbn", "fragment", "should_output_metadata"],
+        help = "Output records of different types in a single CSV with the union of their fields and an 'rtype' column. Optionally limit it to comma-separated schemas or record types, e.g. '--union=trades,definition,status'. By default, all record types are included"
+    )]
+    pub union: Option<Vec<RType>>,
+    #[clap(
+        long = "split",
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        requires = "output",
+        conflicts_with_all = ["parquet", "dbn", "fragment", "should_output_metadata", "union"],
+        help = "Write each record type to a separate file in the output directory named after its schema, e.g. 'trades.csv' and 'definition.csv'. Requires --csv or --json"
+    )]
+    pub split: bool,
 }
 
 impl Args {
@@ -173,6 +304,8 @@ impl Args {
             OutputEncoding::Json
         } else if self.csv {
             OutputEncoding::Csv
+        } else if self.parquet {
+            OutputEncoding::Parquet
         } else if self.dbn {
             OutputEncoding::Dbn
         } else if self.fragment {
@@ -193,58 +326,186 @@ impl Args {
     pub fn input_version(&self) -> u8 {
         self.input_dbn_version_override.unwrap_or(dbn::DBN_VERSION)
     }
+
+    /// Returns an error if the combination of input and output arguments is invalid
+    /// in a way that can't be expressed through `clap`.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let input_fragment_flag = if self.is_input_fragment {
+            "--input-fragment"
+        } else if self.is_input_zstd_fragment {
+            "--input-zstd-fragment"
+        } else {
+            return Ok(());
+        };
+        if self.dbn && self.input_metadata.is_none() {
+            return Err(anyhow!(
+                "'{input_fragment_flag}' cannot be used with '--dbn' without '--input-metadata'"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if any of the CSV-only output options were passed.
+    pub fn has_csv_options(&self) -> bool {
+        self.delimiter.is_some()
+            || !self.fields.is_empty()
+            || self.null_value.is_some()
+            || self.union.is_some()
+    }
+
+    /// Returns `true` if any of the Zstd compression options that imply `--zstd` were
+    /// passed.
+    pub fn has_zstd_options(&self) -> bool {
+        self.zstd_level.is_some() || self.zstd_threads.is_some() || self.zstd_long
+    }
+
+    /// Returns the Zstd compression options, including the dictionary passed with
+    /// `--zstd-dict`, if any.
+    pub fn zstd_options(&self) -> anyhow::Result<ZstdOptions> {
+        Ok(ZstdOptions {
+            level: self
+                .zstd_level
+                .unwrap_or(dbn::encode::ZSTD_COMPRESSION_LEVEL),
+            threads: self.zstd_threads.unwrap_or_default(),
+            long_distance_matching: self.zstd_long,
+            dictionary: self.zstd_dictionary()?,
+        })
+    }
+
+    /// Loads the dictionary file passed with `--zstd-dict`, if any.
+    pub fn zstd_dictionary(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        self.zstd_dict
+            .as_ref()
+            .map(|path| {
+                std::fs::read(path).with_context(|| {
+                    format!("Unable to read zstd dictionary file '{}'", path.display())
+                })
+            })
+            .transpose()
+    }
+
+    /// Loads the symbology file passed with `--symbology`, if any.
+    pub fn symbology(&self) -> anyhow::Result<Option<SymbologyResolution>> {
+        self.symbology
+            .as_ref()
+            .map(|path| read_json_file(path, "symbology"))
+            .transpose()
+    }
+
+    /// Loads the metadata file passed with `--input-metadata`, if any.
+    pub fn input_metadata(&self) -> anyhow::Result<Option<Metadata>> {
+        self.input_metadata
+            .as_ref()
+            .map(|path| read_json_file(path, "metadata"))
+            .transpose()
+    }
 }
 
 /// Infer the [`Encoding`] and [`Compression`] from `args` if they aren't already explicitly
 /// set.
 pub fn infer_encoding_and_compression(args: &Args) -> anyhow::Result<(Encoding, Compression)> {
-    let compression = if args.zstd {
+    let compression = if args.zstd || args.has_zstd_options() {
         Compression::ZStd
     } else {
         Compression::None
     };
-    match args.output_encoding() {
-        OutputEncoding::DbnFragment | OutputEncoding::Dbn => Ok((Encoding::Dbn, compression)),
-        OutputEncoding::Csv => Ok((Encoding::Csv, compression)),
-        OutputEncoding::Json => Ok((Encoding::Json, compression)),
+    let (encoding, compression) = match args.output_encoding() {
+        OutputEncoding::DbnFragment | OutputEncoding::Dbn => (Encoding::Dbn, compression),
+        OutputEncoding::Csv => (Encoding::Csv, compression),
+        OutputEncoding::Json => (Encoding::Json, compression),
+        OutputEncoding::Parquet => (Encoding::Parquet, compression),
         OutputEncoding::Infer => {
-            if let Some(output) = args.output.as_ref().map(|o| o.to_string_lossy()) {
-                if output.ends_with(".dbn.zst") {
-                    Ok((Encoding::Dbn, Compression::ZStd))
-                } else if output.ends_with(".dbn") {
-                    Ok((Encoding::Dbn, Compression::None))
-                } else if output.ends_with(".csv.zst") {
-                    Ok((Encoding::Csv, Compression::ZStd))
-                } else if output.ends_with(".csv") {
-                    Ok((Encoding::Csv, Compression::None))
-                } else if output.ends_with(".json.zst") {
-                    Ok((Encoding::Json, Compression::ZStd))
-                } else if output.ends_with(".json") {
-                    Ok((Encoding::Json, Compression::None))
-                } else {
-                    Err(anyhow!(
-                        "Unable to infer output encoding from output path '{output}'",
-                    ))
-                }
-            } else {
-                Err(anyhow!(
+            let Some(output) = args.output.as_ref().map(|o| o.to_string_lossy()) else {
+                return Err(anyhow!(
                     "Unable to infer output encoding when no output was specified"
-                ))
+                ));
+            };
+            let (stem, ext_compression) = split_compression_extension(&output);
+            if stem.ends_with(".dbn") {
+                (Encoding::Dbn, ext_compression)
+            } else if stem.ends_with(".csv") {
+                (Encoding::Csv, ext_compression)
+            } else if stem.ends_with(".json") {
+                (Encoding::Json, ext_compression)
+            } else if output.ends_with(".parquet") {
+                (Encoding::Parquet, compression)
+            } else {
+                return Err(anyhow!(
+                    "Unable to infer output encoding from output path '{output}'",
+                ));
             }
         }
+    };
+    if args.has_zstd_options() && compression != Compression::ZStd {
+        return Err(anyhow!(
+            "'--zstd-level', '--zstd-threads', and '--zstd-long' require Zstd-compressed output, but the output path '{}' has a different extension",
+            args.output.as_deref().unwrap_or(Path::new("")).display()
+        ));
+    }
+    Ok((encoding, compression))
+}
+
+/// Splits a trailing compression extension like `.zst` or `.gz` from `path`, returning
+/// the remainder of the path and the [`Compression`] the extension corresponds to.
+pub fn split_compression_extension(path: &str) -> (&str, Compression) {
+    [
+        Compression::ZStd,
+        Compression::Gzip,
+        Compression::Lz4,
+        Compression::Xz,
+    ]
+    .into_iter()
+    .find_map(|compression| {
+        let stem = path
+            .strip_suffix(compression.extension()?)?
+            .strip_suffix('.')?;
+        Some((stem, compression))
+    })
+    .unwrap_or((path, Compression::None))
+}
+
+fn parse_delimiter(s: &str) -> Result<u8, String> {
+    match s {
+        "\\t" => Ok(b'\t'),
+        _ if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        _ => Err(format!(
+            "expected a single ASCII character or '\\t', found '{s}'"
+        )),
     }
 }
 
+fn parse_rtype(s: &str) -> Result<RType, String> {
+    s.parse::<Schema>()
+        .map(RType::from)
+        .or_else(|_| s.parse::<RType>())
+        .map_err(|_| format!("expected a schema or record type, found '{s}'"))
+}
+
 /// Returns a writeable object where the `dbn` output will be directed.
 pub fn output_from_args(args: &Args) -> anyhow::Result<Box<dyn io::Write>> {
-    if let Some(output) = &args.output {
-        let output_file = open_output_file(output, args.force)?;
+    open_output(args.output.as_ref(), args.force)
+}
+
+/// Returns a writer for `output`, or standard output if `None`.
+pub(crate) fn open_output(
+    output: Option<&PathBuf>,
+    force: bool,
+) -> anyhow::Result<Box<dyn io::Write>> {
+    if let Some(output) = output {
+        let output_file = open_output_file(output, force)?;
         Ok(Box::new(BufWriter::new(output_file)))
     } else {
         Ok(Box::new(io::stdout().lock()))
     }
 }
 
+pub(crate) fn read_json_file<T: DeserializeOwned>(path: &Path, desc: &str) -> anyhow::Result<T> {
+    let file = File::open(path)
+        .with_context(|| format!("Opening {desc} file at path '{}'", path.display()))?;
+    serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Parsing {desc} file at path '{}'", path.display()))
+}
+
 fn open_output_file(path: &PathBuf, force: bool) -> anyhow::Result<File> {
     let mut options = File::options();
     options.write(true).truncate(true);
@@ -300,6 +561,10 @@ mod tests {
             ("out.json.zst", Encoding::Json, Compression::ZStd),
             ("out.csv.zst", Encoding::Csv, Compression::ZStd),
             ("out.dbn.zst", Encoding::Dbn, Compression::ZStd),
+            ("out.csv.gz", Encoding::Csv, Compression::Gzip),
+            ("out.dbn.lz4", Encoding::Dbn, Compression::Lz4),
+            ("out.json.xz", Encoding::Json, Compression::Xz),
+            ("out.parquet", Encoding::Parquet, Compression::None),
         ];
         for (output, exp_enc, exp_comp) in combinations {
             let args = Args {
@@ -313,6 +578,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_delimiter() {
+        assert_eq!(parse_delimiter("|"), Ok(b'|'));
+        assert_eq!(parse_delimiter("\\t"), Ok(b'\t'));
+        assert_eq!(parse_delimiter("\t"), Ok(b'\t'));
+        assert!(parse_delimiter("||").is_err());
+        assert!(parse_delimiter("é").is_err());
+    }
+
+    #[test]
+    fn test_parse_rtype() {
+        assert_eq!(parse_rtype("trades"), Ok(RType::Mbp0));
+        assert_eq!(parse_rtype("tbbo"), Ok(RType::Mbp1));
+        assert_eq!(parse_rtype("definition"), Ok(RType::InstrumentDef));
+        assert_eq!(parse_rtype("system"), Ok(RType::System));
+        assert!(parse_rtype("trade").is_err());
+    }
+
     #[test]
     fn test_infer_encoding_and_compression_bad() {
         let args = Args {
@@ -1,33 +1,122 @@
 use std::{
     fs::File,
     io::{self, BufReader},
+    process,
 };
 
+use anyhow::Context;
 use clap::Parser;
-use dbn::decode::{DbnMetadata, DbnRecordDecoder, DecodeRecordRef, DynDecoder};
+use dbn::decode::{
+    DbnDecoder, DbnMetadata, DbnRecordDecoder, DecodeRecordRef, DynDecoder, DynReader,
+};
 use dbn_cli::{
+    cat::cat_files,
+    diff::diff_files,
     encode::{encode_from_dbn, encode_from_frag, silence_broken_pipe},
-    filter::{LimitFilter, SchemaFilter},
-    Args,
+    filter::{LimitFilter, SchemaFilter, SharedSymbolMap, SymbolMapUpdater, WhereFilter},
+    metadata,
+    train_dict::train_dict,
+    Args, Command,
 };
 
 const STDIN_SENTINEL: &str = "-";
 
-fn wrap_frag(args: &Args, reader: impl io::Read) -> anyhow::Result<impl DecodeRecordRef> {
+fn wrap_frag(
+    args: &Args,
+    symbol_map: &SharedSymbolMap,
+    reader: impl io::Read,
+) -> anyhow::Result<impl DecodeRecordRef> {
     Ok(LimitFilter::new_no_metadata(
-        SchemaFilter::new_no_metadata(
-            DbnRecordDecoder::with_version(reader, args.input_version(), args.upgrade_policy())?,
-            args.schema_filter,
+        WhereFilter::new_no_metadata(
+            SchemaFilter::new_no_metadata(
+                SymbolMapUpdater::new(
+                    DbnRecordDecoder::with_version(
+                        reader,
+                        args.input_version(),
+                        args.upgrade_policy(),
+                    )?,
+                    args.map_symbols.then(|| symbol_map.clone()),
+                ),
+                args.schema_filter,
+            ),
+            args.where_filter.clone(),
         ),
         args.limit,
     ))
 }
 
-fn wrap<R: io::BufRead>(
+fn wrap<D: DecodeRecordRef + DbnMetadata>(
     args: &Args,
-    decoder: DynDecoder<'static, R>,
-) -> impl DecodeRecordRef + DbnMetadata {
-    LimitFilter::new(SchemaFilter::new(decoder, args.schema_filter), args.limit)
+    symbol_map: &SharedSymbolMap,
+    mut decoder: D,
+) -> anyhow::Result<impl DecodeRecordRef + DbnMetadata> {
+    if let Some(symbology) = args.symbology()? {
+        decoder.metadata_mut().apply_symbology(&symbology);
+    }
+    Ok(LimitFilter::new(
+        WhereFilter::new(
+            SchemaFilter::new(
+                SymbolMapUpdater::new(decoder, args.map_symbols.then(|| symbol_map.clone())),
+                args.schema_filter,
+            ),
+            args.where_filter.clone(),
+        )?,
+        args.limit,
+    ))
+}
+
+/// Encodes a fragment, as a complete DBN stream if a metadata file was passed.
+fn encode_from_frag_input(
+    args: &Args,
+    symbol_map: &SharedSymbolMap,
+    reader: impl io::Read,
+) -> anyhow::Result<()> {
+    if let Some(metadata) = args.input_metadata()? {
+        let decoder = DbnDecoder::with_metadata(reader, metadata, args.upgrade_policy())?;
+        encode_from_dbn(wrap(args, symbol_map, decoder)?, args, symbol_map)
+    } else {
+        encode_from_frag(wrap_frag(args, symbol_map, reader)?, args, symbol_map)
+    }
+}
+
+/// Encodes a DBN stream, decompressing Zstd input with the dictionary passed with
+/// `--zstd-dict`, if any.
+fn encode_from_dbn_input(
+    args: &Args,
+    symbol_map: &SharedSymbolMap,
+    mut reader: impl io::BufRead,
+) -> anyhow::Result<()> {
+    if let Some(dictionary) = args.zstd_dictionary()? {
+        if dbn::decode::zstd::starts_with_prefix(reader.fill_buf()?) {
+            let decoder = DbnDecoder::with_upgrade_policy(
+                DynReader::with_zstd_dictionary(reader, &dictionary)?,
+                args.upgrade_policy(),
+            )?;
+            return encode_from_dbn(wrap(args, symbol_map, decoder)?, args, symbol_map);
+        }
+    }
+    encode_from_dbn(
+        wrap(
+            args,
+            symbol_map,
+            DynDecoder::inferred_with_buffer(reader, args.upgrade_policy())?,
+        )?,
+        args,
+        symbol_map,
+    )
+}
+
+/// Creates a decoder for a Zstd-compressed fragment, using the dictionary passed with
+/// `--zstd-dict`, if any.
+fn zstd_fragment_reader<'a, R: io::BufRead>(
+    args: &Args,
+    reader: R,
+) -> anyhow::Result<zstd::stream::Decoder<'a, R>> {
+    Ok(if let Some(dictionary) = args.zstd_dictionary()? {
+        zstd::stream::Decoder::with_dictionary(reader, &dictionary)?
+    } else {
+        zstd::stream::Decoder::with_buffer(reader)?
+    })
 }
 
 fn main() -> anyhow::Result<()> {
@@ -35,52 +124,78 @@ fn main() -> anyhow::Result<()> {
 }
 
 fn main_impl() -> anyhow::Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if let Some(command) = args.command.take() {
+        return run_command(command);
+    }
+    args.validate()?;
+    let input = args
+        .input
+        .clone()
+        .expect("clap requires FILE without a subcommand");
+    let symbol_map = SharedSymbolMap::default();
     // DBN fragment
     if args.is_input_fragment {
-        if args.input.as_os_str() == STDIN_SENTINEL {
-            encode_from_frag(wrap_frag(&args, io::stdin().lock())?, &args)
+        if input.as_os_str() == STDIN_SENTINEL {
+            encode_from_frag_input(&args, &symbol_map, io::stdin().lock())
         } else {
-            encode_from_frag(
-                wrap_frag(&args, BufReader::new(File::open(args.input.clone())?))?,
-                &args,
-            )
+            encode_from_frag_input(&args, &symbol_map, BufReader::new(File::open(&input)?))
         }
     // Zstd-compressed DBN fragment
     } else if args.is_input_zstd_fragment {
-        if args.input.as_os_str() == STDIN_SENTINEL {
-            encode_from_frag(
-                wrap_frag(
-                    &args,
-                    zstd::stream::Decoder::with_buffer(io::stdin().lock())?,
-                )?,
+        if input.as_os_str() == STDIN_SENTINEL {
+            encode_from_frag_input(
                 &args,
+                &symbol_map,
+                zstd_fragment_reader(&args, io::stdin().lock())?,
             )
         } else {
-            encode_from_frag(
-                wrap_frag(
-                    &args,
-                    zstd::stream::Decoder::new(File::open(args.input.clone())?)?,
-                )?,
+            encode_from_frag_input(
                 &args,
+                &symbol_map,
+                zstd_fragment_reader(&args, BufReader::new(File::open(&input)?))?,
             )
         }
     // DBN stream (with metadata)
-    } else if args.input.as_os_str() == STDIN_SENTINEL {
-        encode_from_dbn(
-            wrap(
-                &args,
-                DynDecoder::inferred_with_buffer(io::stdin().lock(), args.upgrade_policy())?,
-            ),
-            &args,
-        )
+    } else if input.as_os_str() == STDIN_SENTINEL {
+        encode_from_dbn_input(&args, &symbol_map, io::stdin().lock())
+    } else if args.zstd_dict.is_some() {
+        let file = File::open(&input).with_context(|| {
+            format!(
+                "Unable to open file to decode at path '{}'",
+                input.display()
+            )
+        })?;
+        encode_from_dbn_input(&args, &symbol_map, BufReader::new(file))
     } else {
         encode_from_dbn(
             wrap(
                 &args,
-                DynDecoder::from_file(&args.input, args.upgrade_policy())?,
-            ),
+                &symbol_map,
+                DynDecoder::from_file(&input, args.upgrade_policy())?,
+            )?,
             &args,
+            &symbol_map,
         )
     }
 }
+
+fn run_command(command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Cat(args) => cat_files(&args),
+        Command::Diff(args) => {
+            let summary = diff_files(&args, io::stdout().lock())?;
+            if !summary.is_identical() {
+                process::exit(1);
+            }
+            Ok(())
+        }
+        Command::Metadata(args) => {
+            if !metadata::run(&args, io::stdout().lock())? {
+                process::exit(1);
+            }
+            Ok(())
+        }
+        Command::TrainDict(args) => train_dict(&args),
+    }
+}
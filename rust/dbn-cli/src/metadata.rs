@@ -0,0 +1,244 @@
+//! The `dbn metadata` subcommand for editing the metadata of DBN files.
+
+use std::{
+    io,
+    num::NonZeroU64,
+    path::{Path, PathBuf},
+};
+
+use clap::{ArgAction, ArgGroup, Args, Subcommand};
+use dbn::{
+    decode::DynDecoder,
+    encode::dbn::{update_file_metadata, MetadataUpdate},
+    reconcile::reconcile,
+    Metadata, SType, Schema, SymbologyResolution, VersionUpgradePolicy,
+};
+
+use crate::read_json_file;
+
+#[derive(Debug, Args)]
+pub struct MetadataArgs {
+    #[clap(subcommand)]
+    pub command: MetadataCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MetadataCommand {
+    /// Update fields of the metadata of a DBN file, Zstd-compressed or not
+    Set(MetadataSetArgs),
+    /// Check the metadata of a DBN file against its records
+    Reconcile(MetadataReconcileArgs),
+}
+
+#[derive(Debug, Args)]
+#[cfg_attr(test, derive(Default))]
+#[clap(group(ArgGroup::new("fields").required(true).multiple(true)))]
+pub struct MetadataSetArgs {
+    #[clap(help = "The DBN file to update", value_name = "FILE")]
+    pub file: PathBuf,
+    #[clap(long, group = "fields", help = "Set the dataset code")]
+    pub dataset: Option<String>,
+    #[clap(long, group = "fields", help = "Set the schema")]
+    pub schema: Option<Schema>,
+    #[clap(
+        long,
+        group = "fields",
+        value_parser = parse_ts,
+        value_name = "TIME",
+        help = "Set the start as UNIX nanoseconds, an RFC 3339 timestamp, or a date"
+    )]
+    pub start: Option<u64>,
+    #[clap(
+        long,
+        group = "fields",
+        value_parser = parse_ts,
+        value_name = "TIME",
+        help = "Set the end as UNIX nanoseconds, an RFC 3339 timestamp, or a date"
+    )]
+    pub end: Option<u64>,
+    #[clap(long, group = "fields", help = "Set the record limit")]
+    pub limit: Option<NonZeroU64>,
+    #[clap(long, group = "fields", help = "Set the input symbology type")]
+    pub stype_in: Option<SType>,
+    #[clap(long, group = "fields", help = "Set the output symbology type")]
+    pub stype_out: Option<SType>,
+    #[clap(
+        long,
+        group = "fields",
+        value_delimiter = ',',
+        value_name = "SYMBOLS",
+        help = "Set the comma-separated list of requested symbols"
+    )]
+    pub symbols: Option<Vec<String>>,
+    #[clap(
+        long,
+        group = "fields",
+        value_name = "FILE",
+        help = "Set the symbology mappings, symbols, and stypes from a Databento symbology.json file or symbology resolve response"
+    )]
+    pub symbology: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct MetadataReconcileArgs {
+    #[clap(help = "The DBN file to check", value_name = "FILE")]
+    pub file: PathBuf,
+    #[clap(
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Replace the metadata with metadata corrected to match the records: the time range of the records, their schema, and only the symbol mappings they use"
+    )]
+    pub fix: bool,
+}
+
+impl MetadataSetArgs {
+    /// Applies the updates in `self` to `metadata`. The symbology is applied first so
+    /// `--symbols`, `--stype-in`, and `--stype-out` take precedence over it.
+    pub fn apply(&self, metadata: &mut Metadata, symbology: Option<&SymbologyResolution>) {
+        if let Some(symbology) = symbology {
+            metadata.apply_symbology(symbology);
+        }
+        if let Some(dataset) = &self.dataset {
+            metadata.dataset.clone_from(dataset);
+        }
+        if let Some(schema) = self.schema {
+            metadata.schema = Some(schema);
+        }
+        if let Some(start) = self.start {
+            metadata.start = start;
+        }
+        if let Some(end) = self.end {
+            metadata.end = NonZeroU64::new(end);
+        }
+        if let Some(limit) = self.limit {
+            metadata.limit = Some(limit);
+        }
+        if let Some(stype_in) = self.stype_in {
+            metadata.stype_in = Some(stype_in);
+        }
+        if let Some(stype_out) = self.stype_out {
+            metadata.stype_out = stype_out;
+        }
+        if let Some(symbols) = &self.symbols {
+            metadata.symbols.clone_from(symbols);
+        }
+    }
+}
+
+/// Runs the `metadata` subcommand. Returns `false` if reconciling found mismatches that
+/// weren't fixed.
+pub fn run(args: &MetadataArgs, mut writer: impl io::Write) -> anyhow::Result<bool> {
+    match &args.command {
+        MetadataCommand::Set(args) => {
+            let symbology = args
+                .symbology
+                .as_ref()
+                .map(|path| read_json_file::<SymbologyResolution>(path, "symbology"))
+                .transpose()?;
+            let update = update_file_metadata(&args.file, |metadata| {
+                args.apply(metadata, symbology.as_ref());
+                Ok(())
+            })?;
+            write_update(&mut writer, &args.file, update)?;
+            Ok(true)
+        }
+        MetadataCommand::Reconcile(args) => {
+            let mut decoder = DynDecoder::from_file(&args.file, VersionUpgradePolicy::AsIs)?;
+            let res = reconcile(&mut decoder)?;
+            drop(decoder);
+            let stats = &res.stats;
+            write!(
+                writer,
+                "{}: {} records",
+                args.file.display(),
+                stats.record_count
+            )?;
+            if let (Some(first_ts), Some(last_ts)) = (stats.first_ts, stats.last_ts) {
+                write!(writer, " from {first_ts} to {last_ts}")?;
+            }
+            writeln!(
+                writer,
+                " of schema {} with {} instrument IDs",
+                res.metadata
+                    .schema
+                    .map_or("mixed", |schema| schema.as_str()),
+                stats.instrument_ids.len()
+            )?;
+            if res.is_consistent() {
+                writeln!(writer, "Metadata is consistent with the records")?;
+                return Ok(true);
+            }
+            writeln!(writer, "Metadata mismatches:")?;
+            for mismatch in res.mismatches.iter() {
+                writeln!(writer, "  {mismatch}")?;
+            }
+            if args.fix {
+                let update = update_file_metadata(&args.file, |metadata| {
+                    metadata.clone_from(&res.metadata);
+                    Ok(())
+                })?;
+                write_update(&mut writer, &args.file, update)?;
+            }
+            Ok(args.fix)
+        }
+    }
+}
+
+fn write_update(mut writer: impl io::Write, path: &Path, update: MetadataUpdate) -> io::Result<()> {
+    let how = match update {
+        MetadataUpdate::InPlace => "in place",
+        MetadataUpdate::Copied => "by rewriting the file",
+    };
+    writeln!(writer, "Updated metadata of {} {how}", path.display())
+}
+
+fn parse_ts(s: &str) -> Result<u64, String> {
+    s.parse()
+        .ok()
+        .or_else(|| crate::expr::parse_ts(s))
+        .ok_or_else(|| format!("`{s}` isn't a valid timestamp"))
+}
+
+#[cfg(test)]
+mod tests {
+    use dbn::MetadataBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let mut metadata = MetadataBuilder::new()
+            .dataset("GLBX.MDP3".to_owned())
+            .schema(Some(Schema::Mbo))
+            .start(0)
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .build();
+        let args = MetadataSetArgs {
+            dataset: Some("XNAS.ITCH".to_owned()),
+            end: Some(parse_ts("2023-07-01").unwrap()),
+            stype_in: Some(SType::Parent),
+            symbols: Some(vec!["ES.FUT".to_owned()]),
+            ..Default::default()
+        };
+        let symbology = SymbologyResolution {
+            stype_in: Some(SType::RawSymbol),
+            symbols: vec!["ESU3".to_owned()],
+            ..Default::default()
+        };
+        args.apply(&mut metadata, Some(&symbology));
+        assert_eq!(metadata.dataset, "XNAS.ITCH");
+        assert_eq!(metadata.end, NonZeroU64::new(1688169600000000000));
+        assert_eq!(metadata.stype_in, Some(SType::Parent));
+        assert_eq!(metadata.symbols, ["ES.FUT"]);
+        assert_eq!(metadata.schema, Some(Schema::Mbo));
+    }
+
+    #[test]
+    fn test_parse_ts() {
+        assert_eq!(parse_ts("1688169600000000000"), Ok(1688169600000000000));
+        assert_eq!(parse_ts("2023-07-01T00:00:00.5Z"), Ok(1688169600500000000));
+        assert!(parse_ts("July").is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! The `dbn train-dict` subcommand for training Zstd dictionaries on DBN data.
+
+use std::{io::Write, mem, path::PathBuf};
+
+use anyhow::Context;
+use clap::{ArgAction, Args};
+use dbn::{
+    decode::{DbnMetadata, DecodeRecordRef, DynDecoder},
+    encode::DbnMetadataEncoder,
+    VersionUpgradePolicy,
+};
+
+use crate::open_output;
+
+/// The default maximum dictionary size, matching the `zstd` CLI.
+const DEFAULT_MAX_SIZE: usize = 110 * 1024;
+/// The default approximate size of each sample.
+const DEFAULT_SAMPLE_SIZE: usize = 16 * 1024;
+
+#[derive(Debug, Args)]
+pub struct TrainDictArgs {
+    #[clap(
+        help = "The DBN files to sample. Works best with many files similar to those that will be compressed",
+        value_name = "FILE",
+        required = true,
+        num_args = 1..
+    )]
+    pub inputs: Vec<PathBuf>,
+    #[clap(
+        short,
+        long,
+        help = "Saves the dictionary to FILE",
+        value_name = "FILE"
+    )]
+    pub output: PathBuf,
+    #[clap(
+        long = "max-size",
+        value_name = "BYTES",
+        default_value_t = DEFAULT_MAX_SIZE,
+        help = "The maximum size of the dictionary in bytes"
+    )]
+    pub max_size: usize,
+    #[clap(
+        long = "sample-size",
+        value_name = "BYTES",
+        default_value_t = DEFAULT_SAMPLE_SIZE,
+        help = "Split each input into samples of about BYTES bytes. The first sample of each input begins with its metadata"
+    )]
+    pub sample_size: usize,
+    #[clap(
+        short,
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Allow overwriting of existing files, such as the output file"
+    )]
+    pub force: bool,
+}
+
+/// Trains a Zstd dictionary on samples of the uncompressed DBN in the inputs of `args`
+/// and writes it to the output.
+pub fn train_dict(args: &TrainDictArgs) -> anyhow::Result<()> {
+    let samples = collect_samples(args)?;
+    let dictionary = zstd::dict::from_samples(&samples, args.max_size).with_context(|| {
+        format!(
+            "Unable to train a dictionary from {} samples. Pass more inputs or a smaller --sample-size",
+            samples.len()
+        )
+    })?;
+    let mut output = open_output(Some(&args.output), args.force)?;
+    output.write_all(&dictionary)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Splits the decompressed DBN of each input into samples of about
+/// `args.sample_size` bytes, never splitting a record.
+fn collect_samples(args: &TrainDictArgs) -> anyhow::Result<Vec<Vec<u8>>> {
+    let mut samples = Vec::new();
+    for input in args.inputs.iter() {
+        let mut decoder = DynDecoder::from_file(input, VersionUpgradePolicy::AsIs)?;
+        let mut sample = Vec::with_capacity(args.sample_size);
+        DbnMetadataEncoder::new(&mut sample).encode(decoder.metadata())?;
+        while let Some(record) = decoder.decode_record_ref()? {
+            sample.extend_from_slice(record.as_ref());
+            if sample.len() >= args.sample_size {
+                samples.push(mem::replace(
+                    &mut sample,
+                    Vec::with_capacity(args.sample_size),
+                ));
+            }
+        }
+        if !sample.is_empty() {
+            samples.push(sample);
+        }
+    }
+    Ok(samples)
+}
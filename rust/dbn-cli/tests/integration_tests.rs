@@ -5,7 +5,10 @@ use std::{
 };
 
 use assert_cmd::Command;
-use dbn::Schema;
+use dbn::{
+    encode::{DbnEncoder, DbnRecordEncoder, EncodeRecord},
+    MetadataBuilder, SType, Schema, SymbolMappingMsg, TradeMsg, UNDEF_TIMESTAMP,
+};
 use predicates::{
     ord::eq,
     str::{contains, ends_with, is_empty, is_match, starts_with},
@@ -110,6 +113,133 @@ fn write_csv(#[values("dbn", "dbn.zst")] extension: &str) {
     assert!(contents.ends_with('\n'));
 }
 
+#[rstest]
+fn compression_inferred_from_extensions(#[values("gz", "lz4", "xz")] extension: &str) {
+    let output_dir = tempdir().unwrap();
+    let output_path = format!("{}/a.dbn.{extension}", output_dir.path().to_str().unwrap());
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbp-1.dbn.zst"),
+            "--output",
+            &output_path,
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty());
+    assert!(!fs::read(&output_path).unwrap().starts_with(b"DBN"));
+    let expected = cmd()
+        .args([&format!("{TEST_DATA_PATH}/test_data.mbp-1.dbn"), "--csv"])
+        .output()
+        .unwrap()
+        .stdout;
+    cmd()
+        .args([&output_path, "--csv"])
+        .assert()
+        .success()
+        .stdout(eq(expected));
+}
+
+#[test]
+fn zstd_options() {
+    let output_dir = tempdir().unwrap();
+    let output_path = format!("{}/a.dbn.zst", output_dir.path().to_str().unwrap());
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn"),
+            "--output",
+            &output_path,
+            "--zstd-level",
+            "19",
+            "--zstd-threads",
+            "2",
+            "--zstd-long",
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty());
+    let expected = cmd()
+        .args([&format!("{TEST_DATA_PATH}/test_data.mbo.dbn"), "--json"])
+        .output()
+        .unwrap()
+        .stdout;
+    cmd()
+        .args([&output_path, "--json"])
+        .assert()
+        .success()
+        .stdout(eq(expected));
+}
+
+#[test]
+fn zstd_options_require_zstd_output() {
+    let output_dir = tempdir().unwrap();
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn"),
+            "--output",
+            &format!("{}/a.csv", output_dir.path().to_str().unwrap()),
+            "--zstd-level",
+            "19",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("require Zstd-compressed output"));
+}
+
+#[test]
+fn train_dict() {
+    let output_dir = tempdir().unwrap();
+    let dict_path = format!("{}/dbn.dict", output_dir.path().to_str().unwrap());
+    let inputs = fs::read_dir(TEST_DATA_PATH)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "dbn"))
+        .map(|path| path.to_str().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    cmd()
+        .arg("train-dict")
+        .args(&inputs)
+        .args([
+            "--output",
+            &dict_path,
+            "--sample-size",
+            "512",
+            "--max-size",
+            "4096",
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty());
+    assert!(fs::metadata(&dict_path).unwrap().len() <= 4096);
+    // compress with the dictionary
+    let output_path = format!("{}/a.dbn.zst", output_dir.path().to_str().unwrap());
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn"),
+            "--output",
+            &output_path,
+            "--zstd-dict",
+            &dict_path,
+        ])
+        .assert()
+        .success();
+    // can't decompress without the dictionary
+    cmd()
+        .args([&output_path, "--json"])
+        .assert()
+        .failure()
+        .stderr(contains("Dictionary mismatch"));
+    let expected = cmd()
+        .args([&format!("{TEST_DATA_PATH}/test_data.mbo.dbn"), "--json"])
+        .output()
+        .unwrap()
+        .stdout;
+    cmd()
+        .args([&output_path, "--json", "--zstd-dict", &dict_path])
+        .assert()
+        .success()
+        .stdout(eq(expected));
+}
+
 #[test]
 fn encoding_overrides_extension() {
     // create a directory whose contents will be cleaned up at the end of the test
@@ -135,6 +265,54 @@ fn encoding_overrides_extension() {
     assert!(contents.ends_with('\n'));
 }
 
+#[test]
+fn write_parquet() {
+    let output_dir = tempdir().unwrap();
+    let output_path = format!("{}/a.parquet", output_dir.path().to_str().unwrap());
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbp-1.dbn.zst"),
+            "--output",
+            &output_path,
+            "--map-symbols",
+            "--zstd",
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty());
+    let contents = fs::read(&output_path).unwrap();
+    assert!(contents.starts_with(b"PAR1"));
+    assert!(contents.ends_with(b"PAR1"));
+    let metadata =
+        dbn::encode::parquet::read_metadata(fs::File::open(&output_path).unwrap()).unwrap();
+    assert_eq!(metadata.schema, Some(Schema::Mbp1));
+}
+
+#[cfg(unix)]
+#[test]
+fn write_parquet_footer_error() {
+    let output_dir = tempdir().unwrap();
+    let output_path = format!("{}/a.parquet", output_dir.path().to_str().unwrap());
+    // Limit the output to a single 512-byte block so only writing the buffered row group
+    // and footer fails. Ignoring SIGXFSZ makes the write return an error instead of
+    // terminating the process.
+    let mut shell = process::Command::new("sh");
+    shell.args([
+        "-c",
+        r#"trap '' XFSZ; ulimit -f 1; exec "$@""#,
+        "sh",
+        assert_cmd::cargo::cargo_bin("dbn").to_str().unwrap(),
+        &format!("{TEST_DATA_PATH}/test_data.mbp-1.dbn.zst"),
+        "--output",
+        &output_path,
+    ]);
+    Command::from_std(shell)
+        .assert()
+        .failure()
+        .stdout(is_empty())
+        .stderr(contains("File too large"));
+}
+
 #[test]
 fn bad_infer() {
     let output_dir = tempdir().unwrap();
@@ -298,6 +476,156 @@ fn pretty_print_csv_data() {
         .stderr(is_empty());
 }
 
+#[test]
+fn csv_fields_delimiter_and_null() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.definition.dbn"),
+            "--csv",
+            "--fields",
+            "raw_symbol,strike_price:strike,instrument_id",
+            "--delimiter",
+            "|",
+            "--null",
+            "NaN",
+        ])
+        .assert()
+        .success()
+        .stdout(is_match(r"^raw_symbol\|strike\|instrument_id\n(MSFT\|NaN\|\d+\n)+$").unwrap())
+        .stderr(is_empty());
+}
+
+#[test]
+fn csv_options_with_inferred_json() {
+    let output_dir = tempdir().unwrap();
+    let output_path = format!("{}/a.json", output_dir.path().to_str().unwrap());
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.definition.dbn"),
+            "--output",
+            &output_path,
+            "--null",
+            "NaN",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("only valid with CSV encoding"));
+}
+
+/// Concatenates trades, statistics, and MBO test data into a mixed-schema DBN file.
+fn write_mixed_schema_dbn(dir: &std::path::Path) -> String {
+    let path = dir.join("mixed.dbn");
+    let path = path.to_str().unwrap().to_owned();
+    cmd()
+        .args([
+            "cat",
+            &format!("{TEST_DATA_PATH}/test_data.trades.dbn"),
+            &format!("{TEST_DATA_PATH}/test_data.statistics.dbn"),
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn"),
+            "-o",
+            &path,
+        ])
+        .assert()
+        .success();
+    path
+}
+
+#[rstest]
+#[case::csv("--csv", "csv")]
+#[case::json("--json", "json")]
+fn split_mixed_schemas(#[case] encoding_flag: &str, #[case] extension: &str) {
+    let output_dir = tempdir().unwrap();
+    let input = write_mixed_schema_dbn(output_dir.path());
+    let split_dir = output_dir.path().join("split");
+    cmd()
+        .args([
+            &input,
+            encoding_flag,
+            "--split",
+            "--output",
+            split_dir.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(is_empty());
+    let mut names = fs::read_dir(&split_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(
+        names,
+        ["mbo", "statistics", "trades"].map(|name| format!("{name}.{extension}"))
+    );
+    for schema in ["mbo", "statistics", "trades"] {
+        let expected = cmd()
+            .args([
+                &format!("{TEST_DATA_PATH}/test_data.{schema}.dbn"),
+                encoding_flag,
+            ])
+            .output()
+            .unwrap()
+            .stdout;
+        let contents = fs::read(split_dir.join(format!("{schema}.{extension}"))).unwrap();
+        assert_eq!(String::from_utf8(contents), String::from_utf8(expected));
+    }
+}
+
+#[test]
+fn split_requires_output() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn"),
+            "--csv",
+            "--split",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("--output"));
+}
+
+#[test]
+fn union_mixed_schemas() {
+    let output_dir = tempdir().unwrap();
+    let input = write_mixed_schema_dbn(output_dir.path());
+    cmd()
+        .args([&input, "--csv"])
+        .assert()
+        .failure()
+        .stderr(contains("mixed schemas"));
+    cmd()
+        .args([
+            &input,
+            "--csv",
+            "--union=trades,statistics,mbo",
+            "--fields",
+            "rtype,price,order_id,stat_type",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            is_match(
+                r"^rtype,price,order_id,stat_type\n(0,\d+,,\n){2}(24,\d+,,\d+\n){2}(160,\d+,\d+,\n){2}$",
+            )
+            .unwrap(),
+        )
+        .stderr(is_empty());
+    cmd()
+        .args([&input, "--csv", "--union"])
+        .assert()
+        .success()
+        .stdout(starts_with(
+            "ts_recv,ts_event,rtype,publisher_id,instrument_id,",
+        ))
+        .stderr(is_empty());
+    cmd()
+        .args([&input, "--csv", "--union=trades"])
+        .assert()
+        .failure()
+        .stderr(contains("isn't part of the CSV union"));
+}
+
 const PRETTY_TS_REGEX: &str = r"\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}.\d{9}Z";
 const PRETTY_PX_REGEX: &str = r"\d+\.\d{9}";
 
@@ -403,6 +731,67 @@ fn fragment_conflicts_with_dbn_output(#[case] flag: &str, #[case] extension: &st
         .stderr(contains(format!("'{flag}' cannot be used with '--dbn'")));
 }
 
+#[rstest]
+fn fragment_with_input_metadata_to_dbn(#[values(false, true)] pretty: bool) {
+    let output_dir = tempdir().unwrap();
+    let input_path = format!("{TEST_DATA_PATH}/test_data.mbo.dbn");
+    let metadata_path = output_dir.path().join("metadata.json");
+    let frag_path = output_dir.path().join("test.dbn.frag");
+    let output_path = output_dir.path().join("test.dbn");
+    let mut metadata_args = vec![
+        input_path.as_str(),
+        "--json",
+        "--metadata",
+        "--output",
+        metadata_path.to_str().unwrap(),
+    ];
+    if pretty {
+        metadata_args.push("--pretty");
+    }
+    cmd().args(&metadata_args).assert().success();
+    cmd()
+        .args([
+            &input_path,
+            "--fragment",
+            "--output",
+            frag_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    cmd()
+        .args([
+            frag_path.to_str().unwrap(),
+            "--input-fragment",
+            "--input-metadata",
+            metadata_path.to_str().unwrap(),
+            "--dbn",
+            "--output",
+            output_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(is_empty());
+    assert_eq!(
+        fs::read(output_path).unwrap(),
+        fs::read(input_path).unwrap()
+    );
+}
+
+#[test]
+fn input_metadata_requires_fragment() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn"),
+            "--json",
+            "--input-metadata",
+            "metadata.json",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("--input-metadata"));
+}
+
 #[rstest]
 #[case::uncompressed_to_csv("csv", "--input-fragment", "dbn.frag", 3)]
 #[case::uncompressed_to_json("json", "--input-fragment", "dbn.frag", 2)]
@@ -576,20 +965,26 @@ fn broken_pipe_is_silent(
     if !fragment_flag.is_empty() {
         dbn_cmd.arg(fragment_flag);
     }
-    let dbn_res = dbn_cmd
+    let mut dbn_res = dbn_cmd
         .stdout(process::Stdio::piped())
         .stderr(process::Stdio::piped())
         .spawn()
         .unwrap();
     let mut false_cmd = process::Command::new("false");
-    false_cmd.stdin(dbn_res.stdout.unwrap());
+    false_cmd.stdin(dbn_res.stdout.take().unwrap());
     Command::from_std(false_cmd)
         .assert()
         .failure()
         .stdout(is_empty())
         .stderr(is_empty());
     let mut stderr = String::new();
-    dbn_res.stderr.unwrap().read_to_string(&mut stderr).unwrap();
+    dbn_res
+        .stderr
+        .take()
+        .unwrap()
+        .read_to_string(&mut stderr)
+        .unwrap();
+    dbn_res.wait().unwrap();
     assert!(stderr.is_empty(), "Stderr: {stderr}");
 }
 
@@ -627,6 +1022,113 @@ fn map_symbols(#[case] output_flag: &str) {
     }
 }
 
+/// Writes live-style data where the symbology is only available through symbol
+/// mapping records.
+fn live_data(with_metadata: bool) -> NamedTempFile {
+    let file = NamedTempFile::new().unwrap();
+    let mapping = SymbolMappingMsg::new(
+        5482,
+        1,
+        SType::Parent,
+        "ES.FUT",
+        SType::RawSymbol,
+        "ESH1",
+        0,
+        UNDEF_TIMESTAMP,
+    )
+    .unwrap();
+    let mut trade = TradeMsg::default();
+    trade.hd.instrument_id = 5482;
+    let mut unmapped_trade = TradeMsg::default();
+    unmapped_trade.hd.instrument_id = 1;
+    if with_metadata {
+        let metadata = MetadataBuilder::new()
+            .dataset("GLBX.MDP3".to_owned())
+            .schema(None)
+            .start(0)
+            .stype_in(None)
+            .stype_out(SType::InstrumentId)
+            .build();
+        let mut encoder = DbnEncoder::new(file.reopen().unwrap(), &metadata).unwrap();
+        encoder.encode_record(&mapping).unwrap();
+        encoder.encode_record(&trade).unwrap();
+        encoder.encode_record(&unmapped_trade).unwrap();
+    } else {
+        let mut encoder = DbnRecordEncoder::new(file.reopen().unwrap());
+        encoder.encode_record(&mapping).unwrap();
+        encoder.encode_record(&trade).unwrap();
+        encoder.encode_record(&unmapped_trade).unwrap();
+    }
+    file
+}
+
+#[rstest]
+fn map_symbols_from_symbol_mapping_records(
+    #[values(true, false)] with_metadata: bool,
+    #[values("--csv", "--json")] output_flag: &str,
+) {
+    let input = live_data(with_metadata);
+    let mut args = vec![
+        input.path().to_str().unwrap(),
+        output_flag,
+        "--map-symbols",
+        "--schema",
+        "trades",
+    ];
+    if !with_metadata {
+        args.push("--input-fragment");
+    }
+    let cmd = cmd().args(&args).assert().success().stderr(is_empty());
+    if output_flag == "--csv" {
+        cmd.stdout(contains(",symbol\n").count(1))
+            .stdout(contains(",ESH1\n").count(1))
+            .stdout(contains('\n').count(3));
+    } else {
+        cmd.stdout(contains("\"symbol\":\"ESH1\"").count(1))
+            .stdout(contains("\"symbol\":null").count(1))
+            .stdout(contains('\n').count(2));
+    }
+}
+
+#[test]
+fn map_symbols_with_symbology_file() {
+    let symbology_file = NamedTempFile::new().unwrap();
+    std::fs::write(
+        symbology_file.path(),
+        r#"{"result":{"ES.FUT":[{"d0":"2020-12-28","d1":"2020-12-29","s":"5482"}]},"stype_in":"parent","stype_out":"instrument_id"}"#,
+    )
+    .unwrap();
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn.zst"),
+            "--json",
+            "--map-symbols",
+            "--symbology",
+            symbology_file.path().to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(contains("\"symbol\":\"ES.FUT\"").count(2))
+        .stderr(is_empty());
+}
+
+#[test]
+fn invalid_symbology_file() {
+    let symbology_file = NamedTempFile::new().unwrap();
+    std::fs::write(symbology_file.path(), "not json").unwrap();
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn.zst"),
+            "--json",
+            "--symbology",
+            symbology_file.path().to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stdout(is_empty())
+        .stderr(contains("Parsing symbology file"));
+}
+
 #[rstest]
 #[case::dbn("--dbn")]
 #[case::fragment("--fragment")]
@@ -713,3 +1215,347 @@ fn version() {
         .success()
         .stdout(contains(env!("CARGO_PKG_VERSION")));
 }
+
+#[rstest]
+fn diff_identical(#[values("dbn", "dbn.zst")] extension: &str) {
+    cmd()
+        .args([
+            "diff",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn"),
+            &format!("{TEST_DATA_PATH}/test_data.mbo.{extension}"),
+        ])
+        .assert()
+        .success()
+        .stdout(eq("files are identical\n"))
+        .stderr(is_empty());
+}
+
+#[test]
+fn diff_different_versions() {
+    cmd()
+        .args([
+            "diff",
+            &format!("{TEST_DATA_PATH}/test_data.definition.v1.dbn"),
+            &format!("{TEST_DATA_PATH}/test_data.definition.dbn"),
+        ])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(contains("  version: 1 != 2\n"))
+        .stdout(contains("record 0 differs"))
+        .stderr(is_empty());
+}
+
+#[test]
+fn diff_different_schemas() {
+    cmd()
+        .args([
+            "diff",
+            "--max-mismatches",
+            "1",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn"),
+            &format!("{TEST_DATA_PATH}/test_data.trades.dbn"),
+        ])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(contains("  schema: Some(Mbo) != Some(Trades)\n"))
+        .stdout(contains("record 0 differs (rtype Mbo != Mbp0):"))
+        .stdout(contains("... stopped reporting after 1 mismatches"))
+        .stderr(is_empty());
+}
+
+#[test]
+fn diff_help() {
+    cmd()
+        .args(["diff", "--help"])
+        .assert()
+        .success()
+        .stdout(contains("--unordered"));
+}
+
+#[test]
+fn input_named_like_subcommand() {
+    let input_dir = tempdir().unwrap();
+    let path = input_dir.path().join("diff");
+    fs::copy(format!("{TEST_DATA_PATH}/test_data.mbo.dbn"), &path).unwrap();
+    cmd()
+        .current_dir(input_dir.path())
+        .args(["--csv", "diff"])
+        .assert()
+        .success()
+        .stdout(contains("ts_recv"));
+    cmd()
+        .current_dir(input_dir.path())
+        .args(["--csv", "--", "diff"])
+        .assert()
+        .success()
+        .stdout(contains("ts_recv"));
+}
+
+#[test]
+fn help_lists_subcommands() {
+    cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(contains("train-dict"));
+}
+
+#[rstest]
+#[case::price(
+    "price > 3722.75",
+    "1609160400000711344,1609160400000431665,160,1,5482,C,A,3723000000000"
+)]
+#[case::side_and_size(
+    "side == 'A' && size == 1 && ts_event < '2020-12-28T13:00:00.000429832Z'",
+    "1609160400000704060,1609160400000429831,160,1,5482,C,A,3722750000000"
+)]
+fn where_filter(#[case] expr: &str, #[case] exp_row: &str) {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn.zst"),
+            "--csv",
+            "--where",
+            expr,
+        ])
+        .assert()
+        .success()
+        .stdout(contains(exp_row))
+        .stdout(is_match("\\A[^\\n]+\\n[^\\n]+\\n\\z").unwrap())
+        .stderr(is_empty());
+}
+
+#[test]
+fn where_filter_unknown_field() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn.zst"),
+            "--json",
+            "--where",
+            "bid_px_00 > 1.0",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("mbo records have no field `bid_px_00`"));
+}
+
+#[test]
+fn where_filter_parse_error() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn.zst"),
+            "--json",
+            "--where",
+            "size >",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("expected field or literal"));
+}
+
+#[rstest]
+#[case::in_place("dbn", &["--dataset", "XNAS.ITCH", "--symbols", "ESH1"], "in place")]
+#[case::grown(
+    "dbn",
+    &["--dataset", "XNAS.ITCH", "--symbols", "ESH1,ESM1,ESU1"],
+    "by rewriting the file"
+)]
+#[case::zstd(
+    "dbn.zst",
+    &["--dataset", "XNAS.ITCH", "--symbols", "ESH1"],
+    "by rewriting the file"
+)]
+fn metadata_set(#[case] extension: &str, #[case] args: &[&str], #[case] how: &str) {
+    let output_dir = tempdir().unwrap();
+    let path = output_dir.path().join(format!("test.{extension}"));
+    fs::copy(format!("{TEST_DATA_PATH}/test_data.mbo.{extension}"), &path).unwrap();
+    let path = path.to_str().unwrap();
+    cmd()
+        .args(["metadata", "set", path])
+        .args(args)
+        .assert()
+        .success()
+        .stdout(contains(how))
+        .stderr(is_empty());
+    cmd()
+        .args([path, "--json", "--metadata"])
+        .assert()
+        .success()
+        .stdout(contains(r#""dataset":"XNAS.ITCH""#));
+    let expected_records = cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.{extension}"),
+            "--json",
+        ])
+        .output()
+        .unwrap()
+        .stdout;
+    cmd()
+        .args([path, "--json"])
+        .assert()
+        .success()
+        .stdout(eq(expected_records));
+}
+
+#[test]
+fn metadata_set_symbology() {
+    let output_dir = tempdir().unwrap();
+    let path = output_dir.path().join("test.dbn");
+    fs::copy(format!("{TEST_DATA_PATH}/test_data.mbo.dbn"), &path).unwrap();
+    let symbology_path = output_dir.path().join("symbology.json");
+    fs::write(
+        &symbology_path,
+        r#"{"result":{"ES.FUT":[{"d0":"2020-12-28","d1":"2020-12-29","s":"5482"}]},"symbols":["ES.FUT"],"stype_in":"parent","stype_out":"instrument_id"}"#,
+    )
+    .unwrap();
+    cmd()
+        .args([
+            "metadata",
+            "set",
+            path.to_str().unwrap(),
+            "--symbology",
+            symbology_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    cmd()
+        .args([path.to_str().unwrap(), "--json", "--map-symbols"])
+        .assert()
+        .success()
+        .stdout(contains(r#""symbol":"ES.FUT""#).count(2));
+}
+
+#[test]
+fn metadata_set_requires_field() {
+    cmd()
+        .args([
+            "metadata",
+            "set",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn"),
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("required arguments were not provided"));
+}
+
+#[test]
+fn metadata_reconcile() {
+    let output_dir = tempdir().unwrap();
+    let path = output_dir.path().join("test.dbn.zst");
+    fs::copy(format!("{TEST_DATA_PATH}/test_data.mbo.dbn.zst"), &path).unwrap();
+    let path = path.to_str().unwrap();
+    cmd()
+        .args(["metadata", "reconcile", path])
+        .assert()
+        .success()
+        .stdout(contains("2 records"))
+        .stdout(contains("consistent"));
+    cmd()
+        .args(["metadata", "set", path, "--schema", "trades"])
+        .assert()
+        .success();
+    cmd()
+        .args(["metadata", "reconcile", path])
+        .assert()
+        .failure()
+        .stdout(contains("schema is trades but records are mbo"));
+    cmd()
+        .args(["metadata", "reconcile", path, "--fix"])
+        .assert()
+        .success()
+        .stdout(contains("Updated metadata"));
+    cmd()
+        .args([path, "--json", "--metadata"])
+        .assert()
+        .success()
+        .stdout(contains(r#""schema":"mbo""#));
+}
+
+#[test]
+fn cat_merges_metadata() {
+    let output_dir = tempdir().unwrap();
+    let path = output_dir.path().join("cat.dbn.zst");
+    let path = path.to_str().unwrap();
+    cmd()
+        .args([
+            "cat",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v1.dbn"),
+            &format!("{TEST_DATA_PATH}/test_data.trades.dbn.zst"),
+            "-o",
+            path,
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(is_empty());
+    cmd()
+        .args([path, "--json", "--metadata"])
+        .assert()
+        .success()
+        .stdout(contains(r#""version":2"#))
+        .stdout(contains(r#""schema":null"#))
+        .stdout(contains(r#""limit":"4""#));
+    let expected_records = [
+        cmd()
+            .args([
+                &format!("{TEST_DATA_PATH}/test_data.mbo.v1.dbn"),
+                "--json",
+                "--upgrade",
+            ])
+            .output()
+            .unwrap()
+            .stdout,
+        cmd()
+            .args([
+                &format!("{TEST_DATA_PATH}/test_data.trades.dbn.zst"),
+                "--json",
+            ])
+            .output()
+            .unwrap()
+            .stdout,
+    ]
+    .concat();
+    cmd()
+        .args([path, "--json"])
+        .assert()
+        .success()
+        .stdout(eq(expected_records));
+}
+
+#[test]
+fn cat_rejects_output_that_is_an_input() {
+    let output_dir = tempdir().unwrap();
+    let path = output_dir.path().join("cat.dbn");
+    fs::copy(format!("{TEST_DATA_PATH}/test_data.mbo.dbn"), &path).unwrap();
+    let orig = fs::read(&path).unwrap();
+    let path = path.to_str().unwrap();
+    cmd()
+        .args([
+            "cat",
+            &format!("{TEST_DATA_PATH}/test_data.trades.dbn"),
+            path,
+            "-o",
+            path,
+            "--force",
+        ])
+        .assert()
+        .failure()
+        .stdout(is_empty())
+        .stderr(contains("because it's also an input"));
+    assert_eq!(fs::read(path).unwrap(), orig);
+}
+
+#[test]
+fn cat_conflicting_datasets() {
+    cmd()
+        .args([
+            "cat",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.dbn"),
+            &format!("{TEST_DATA_PATH}/test_data.definition.dbn"),
+        ])
+        .assert()
+        .failure()
+        .stdout(is_empty())
+        .stderr(contains("dataset XNAS.ITCH of metadata 1 conflicts"));
+}
@@ -0,0 +1,102 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Fields, Meta};
+
+use crate::utils::crate_name;
+
+pub fn derive_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    columnar_impl(&input)
+        .unwrap_or_else(|e| e.into_compile_error())
+        .into()
+}
+
+fn columnar_impl(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(input.span(), "Expected a struct"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new(
+            input.span(),
+            "Expected a struct with named fields",
+        ));
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "Generic structs aren't supported",
+        ));
+    }
+    let Some(first_field) = fields.named.first() else {
+        return Err(syn::Error::new(
+            input.span(),
+            "Expected a struct with at least one field",
+        ));
+    };
+    let first_ident = first_field.ident.as_ref().unwrap();
+    let crate_name = crate_name();
+    let vis = &input.vis;
+    let row_type = &input.ident;
+    let columns_type = format_ident!("{row_type}Columns");
+    let struct_doc =
+        format!(" Struct-of-arrays buffer of [`{row_type}`] values with one column per field.");
+    // Propagates `#[doc(hidden)]`
+    let hidden_attrs = input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc") && matches!(a.meta, Meta::List(_)));
+    // Every field is included, even hidden ones, so rows can be reconstructed exactly
+    let column_fields = fields.named.iter().map(|f| {
+        let ident = f.ident.as_ref().unwrap();
+        let f_vis = &f.vis;
+        let ty = &f.ty;
+        let docs = f.attrs.iter().filter(|a| a.path().is_ident("doc"));
+        quote! {
+            #(#docs)*
+            #f_vis #ident: <#ty as #crate_name::columnar::Columnar>::Columns
+        }
+    });
+    let idents: Vec<_> = fields
+        .named
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap())
+        .collect();
+    Ok(quote! {
+        #[doc = #struct_doc]
+        #(#hidden_attrs)*
+        #[derive(Clone, Debug, Default, PartialEq)]
+        #vis struct #columns_type {
+            #(#column_fields,)*
+        }
+
+        impl #crate_name::columnar::Columns for #columns_type {
+            type Row = #row_type;
+
+            fn len(&self) -> usize {
+                #crate_name::columnar::Columns::len(&self.#first_ident)
+            }
+
+            fn push(&mut self, row: &#row_type) {
+                #(#crate_name::columnar::Columns::push(&mut self.#idents, &row.#idents);)*
+            }
+
+            fn row(&self, index: usize) -> #row_type {
+                #row_type {
+                    #(#idents: #crate_name::columnar::Columns::row(&self.#idents, index),)*
+                }
+            }
+
+            fn clear(&mut self) {
+                #(#crate_name::columnar::Columns::clear(&mut self.#idents);)*
+            }
+
+            fn reserve(&mut self, additional: usize) {
+                #(#crate_name::columnar::Columns::reserve(&mut self.#idents, additional);)*
+            }
+        }
+
+        impl #crate_name::columnar::Columnar for #row_type {
+            type Columns = #columns_type;
+        }
+    })
+}
@@ -7,10 +7,12 @@ use proc_macro2::Ident;
 use syn::{parenthesized, spanned::Spanned, token, Field, FieldsNamed, Meta};
 
 pub const C_CHAR_ATTR: &str = "c_char";
+pub const ENUM_TYPE_ATTR: &str = "enum_type";
 pub const FIXED_PRICE_ATTR: &str = "fixed_price";
 pub const FMT_BINARY: &str = "fmt_binary";
 pub const FMT_METHOD: &str = "fmt_method";
 pub const INDEX_TS_ATTR: &str = "index_ts";
+pub const ORDER_SIZE_ATTR: &str = "order_size";
 pub const SKIP_ATTR: &str = "skip";
 pub const UNIX_NANOS_ATTR: &str = "unix_nanos";
 
@@ -32,7 +34,7 @@ pub fn get_sorted_fields(fields: FieldsNamed) -> syn::Result<VecDeque<Field>> {
             encode_order_fields.push((encode_order, field.clone()));
         }
     }
-    encode_order_fields.sort_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+    encode_order_fields.sort_by_key(|lhs| lhs.0);
     for (encode_order, prioritized_field) in encode_order_fields {
         let idx = fields
             .iter()
@@ -44,7 +46,8 @@ pub fn get_sorted_fields(fields: FieldsNamed) -> syn::Result<VecDeque<Field>> {
     Ok(fields)
 }
 
-/// Note this ignores encode_order, which can be extracted through [`find_encode_order_attr`].
+/// Note this ignores encode_order and enum_type, which can be extracted through
+/// [`find_encode_order_attr`] and [`find_enum_type_attr`].
 pub fn find_dbn_attr_args(field: &Field) -> syn::Result<Vec<Ident>> {
     for attr in field.attrs.iter() {
         if let Meta::List(ref meta_list) = attr.meta {
@@ -61,12 +64,19 @@ pub fn find_dbn_attr_args(field: &Field) -> syn::Result<Vec<Ident>> {
                             let _lit: syn::LitInt = content.parse()?;
                         }
                         Ok(())
+                    } else if meta.path.is_ident(ENUM_TYPE_ATTR) {
+                        // Still need to parse (Type) here to consume it
+                        let content;
+                        parenthesized!(content in meta.input);
+                        let _ident: Ident = content.parse()?;
+                        Ok(())
                     } else if let Some(i) = meta.path.get_ident() {
                         if i == C_CHAR_ATTR
                             || i == FIXED_PRICE_ATTR
                             || i == FMT_BINARY
                             || i == FMT_METHOD
                             || i == INDEX_TS_ATTR
+                            || i == ORDER_SIZE_ATTR
                             || i == SKIP_ATTR
                             || i == UNIX_NANOS_ATTR
                         {
@@ -104,6 +114,11 @@ fn find_encode_order_attr(field: &Field) -> syn::Result<Option<usize>> {
                             // defaults to 0
                             encode_order = Some(0)
                         }
+                    } else if meta.path.is_ident(ENUM_TYPE_ATTR) {
+                        // Still need to parse (Type) here to consume it
+                        let content;
+                        parenthesized!(content in meta.input);
+                        let _ident: Ident = content.parse()?;
                     }
                     Ok(())
                 })?;
@@ -114,6 +129,32 @@ fn find_encode_order_attr(field: &Field) -> syn::Result<Option<usize>> {
     Ok(None)
 }
 
+/// Returns the name of the `FieldEnum` variant from `#[dbn(enum_type(Side))]`, if any.
+pub fn find_enum_type_attr(field: &Field) -> syn::Result<Option<Ident>> {
+    for attr in field.attrs.iter() {
+        if let Meta::List(ref meta_list) = attr.meta {
+            if meta_list.path.is_ident("dbn") {
+                let mut enum_type = None;
+                meta_list.parse_nested_meta(|meta| {
+                    if meta.path.is_ident(ENUM_TYPE_ATTR) {
+                        let content;
+                        parenthesized!(content in meta.input);
+                        enum_type = Some(content.parse()?);
+                    } else if meta.input.peek(token::Paren) {
+                        // Consume the arguments of other attributes like encode_order
+                        let content;
+                        parenthesized!(content in meta.input);
+                        let _lit: syn::LitInt = content.parse()?;
+                    }
+                    Ok(())
+                })?;
+                return Ok(enum_type);
+            }
+        }
+    }
+    Ok(None)
+}
+
 pub fn is_hidden(field: &Field) -> bool {
     let ident = field.ident.as_ref().unwrap();
     ident.to_string().starts_with('_')
@@ -235,6 +276,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn find_enum_type_attr_with_others() {
+        let input = quote!({
+            #[dbn(c_char, encode_order(3), enum_type(Side))]
+            pub side: c_char,
+        });
+        let fields = syn::parse2::<FieldsNamed>(input).unwrap();
+        let field = fields.named.first().unwrap();
+        assert_eq!(find_enum_type_attr(field).unwrap().unwrap(), "Side");
+        assert_eq!(find_encode_order_attr(field).unwrap().unwrap(), 3);
+        assert_eq!(find_dbn_attr_args(field).unwrap(), ["c_char"]);
+    }
+
     #[test]
     fn find_encode_order_attr_default() {
         let input = quote!({
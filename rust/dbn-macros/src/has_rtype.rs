@@ -29,6 +29,7 @@ pub fn attribute_macro_impl(
     let rtypes = args.args.iter();
     let crate_name = crate::utils::crate_name();
     let impl_debug = crate::debug::record_debug_impl(&input_struct);
+    let impl_has_fields = crate::reflect::has_fields_impl(&input_struct);
     quote! (
         #input_struct
 
@@ -70,6 +71,8 @@ pub fn attribute_macro_impl(
         }
 
         #impl_debug
+
+        #impl_has_fields
     )
     .into()
 }
@@ -1,9 +1,11 @@
 use proc_macro::TokenStream;
 
+mod columnar;
 mod dbn_attr;
 mod debug;
 mod has_rtype;
 mod py_field_desc;
+mod reflect;
 mod serialize;
 mod utils;
 
@@ -58,6 +60,17 @@ pub fn derive_json_serialize(input: TokenStream) -> TokenStream {
     serialize::derive_json_macro_impl(input)
 }
 
+/// Derive macro for a struct-of-arrays buffer type named after the struct with a
+/// `Columns` suffix, e.g. `MboMsgColumns` for `MboMsg`, implementing `Columns`.
+///
+/// The buffer has a field with the same name for every field of the struct, including
+/// hidden ones, whose type is the `Columnar::Columns` of the field type, so every field
+/// type must implement `Columnar`.
+#[proc_macro_derive(Columnar)]
+pub fn derive_columnar(input: TokenStream) -> TokenStream {
+    columnar::derive_impl(input)
+}
+
 /// Derive macro for field descriptions exposed to Python.
 ///
 /// Supports the following `dbn` attributes:
@@ -73,19 +86,24 @@ pub fn derive_py_field_desc(input: TokenStream) -> TokenStream {
 }
 
 /// Attribute macro that acts like a derive macro for `Debug` (with customization),
-/// `Record`, `RecordMut`, `HasRType`, `PartialOrd`, and `AsRef<[u8]>`.
+/// `Record`, `RecordMut`, `HasRType`, `HasFields`, `PartialOrd`, and `AsRef<[u8]>`.
 ///
 /// Expects 1 or more paths to `u8` constants that are the RTypes associated
 /// with this record.
 ///
 /// Supports the following `dbn` attributes:
 /// - `c_char`: format the type as a `char` instead of as a numeric
+/// - `encode_order`: overrides the position of the field in the field descriptors
+/// - `enum_type`: indicates the field holds the raw value of the named `FieldEnum`
+///   variant, e.g. `#[dbn(c_char, enum_type(Side))]`
 /// - `fixed_price`: format the integer as a fixed-precision decimal
 /// - `fmt_binary`: format as a binary
 /// - `fmt_method`: try to format by calling the getter method with the same name as the
 /// - `index_ts`: indicates this field is the primary timestamp for the record
 ///   field. If the getter returns an error, the raw field value will be used
-/// - `skip`: won't be included in the `Debug` output
+/// - `order_size`: indicates this is an order size field where `u32::MAX` is undefined
+/// - `skip`: won't be included in the `Debug` output or the field descriptors
+/// - `unix_nanos`: indicates this is a UNIX nanosecond timestamp field
 ///
 /// Note: attribute macros don't support helper attributes on their own. If not deriving
 /// `CsvSerialize` or `JsonSerialize`, derive `DbnAttr` to use the `dbn` helper attribute
@@ -0,0 +1,77 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{spanned::Spanned, Field, Fields, ItemStruct};
+
+use crate::{
+    dbn_attr::{
+        find_dbn_attr_args, find_dbn_serialize_attr, find_enum_type_attr, get_sorted_fields,
+        is_hidden, C_CHAR_ATTR, FIXED_PRICE_ATTR, ORDER_SIZE_ATTR, UNIX_NANOS_ATTR,
+    },
+    utils::crate_name,
+};
+
+pub fn has_fields_impl(input_struct: &ItemStruct) -> TokenStream {
+    let record_type = &input_struct.ident;
+    let Fields::Named(fields) = &input_struct.fields else {
+        return syn::Error::new(input_struct.span(), "Expected a struct with named fields")
+            .into_compile_error();
+    };
+    let sorted_fields = match get_sorted_fields(fields.clone()) {
+        Ok(fields) => fields,
+        Err(e) => return e.into_compile_error(),
+    };
+    let crate_name = crate_name();
+    let field_iter = sorted_fields
+        .iter()
+        .filter(|f| !is_hidden(f))
+        .map(|f| append_field(record_type, f).unwrap_or_else(|e| e.into_compile_error()));
+    quote! {
+        impl #crate_name::reflect::HasFields for #record_type {
+            fn fields() -> &'static [#crate_name::reflect::FieldDesc] {
+                static FIELDS: ::std::sync::OnceLock<::std::vec::Vec<#crate_name::reflect::FieldDesc>> =
+                    ::std::sync::OnceLock::new();
+                FIELDS.get_or_init(|| {
+                    let mut fields = ::std::vec::Vec::new();
+                    #(#field_iter)*
+                    fields
+                })
+            }
+        }
+    }
+}
+
+fn append_field(record_type: &syn::Ident, field: &Field) -> syn::Result<TokenStream> {
+    let ident = field.ident.as_ref().unwrap();
+    let f_type = &field.ty;
+    let crate_name = crate_name();
+    let hint = if let Some(enum_type) = find_enum_type_attr(field)? {
+        quote!(Some(#crate_name::reflect::FieldHint::Enum(
+            #crate_name::reflect::FieldEnum::#enum_type
+        )))
+    } else {
+        match find_dbn_serialize_attr(field)? {
+            Some(id) if id == C_CHAR_ATTR => quote!(Some(#crate_name::reflect::FieldHint::CChar)),
+            Some(id) if id == FIXED_PRICE_ATTR => {
+                quote!(Some(#crate_name::reflect::FieldHint::FixedPrice))
+            }
+            Some(id) if id == UNIX_NANOS_ATTR => {
+                quote!(Some(#crate_name::reflect::FieldHint::UnixNanos))
+            }
+            _ if find_dbn_attr_args(field)?
+                .iter()
+                .any(|id| id == ORDER_SIZE_ATTR) =>
+            {
+                quote!(Some(#crate_name::reflect::FieldHint::OrderSize))
+            }
+            _ => quote!(None),
+        }
+    };
+    Ok(quote! {
+        <#f_type as #crate_name::reflect::ReflectField>::append_fields(
+            &mut fields,
+            stringify!(#ident),
+            ::std::mem::offset_of!(#record_type, #ident),
+            #hint,
+        );
+    })
+}
@@ -30,7 +30,7 @@ pub fn derive_csv_macro_impl(input: proc_macro::TokenStream) -> proc_macro::Toke
                 .unwrap_or_else(|e| vec![syn::Error::to_compile_error(&e)]);
             return quote! {
                 impl #crate_name::encode::csv::serialize::CsvSerialize for #ident {
-                    fn serialize_header<W: ::std::io::Write>(writer: &mut ::csv::Writer<W>) -> ::csv::Result<()> {
+                    fn serialize_header<W: ::std::io::Write>(writer: &mut #crate_name::encode::csv::serialize::csv::Writer<W>) -> #crate_name::encode::csv::serialize::csv::Result<()> {
                         use #crate_name::encode::csv::serialize::WriteField;
 
                         #(#serialize_header_iter)*
@@ -39,8 +39,8 @@ pub fn derive_csv_macro_impl(input: proc_macro::TokenStream) -> proc_macro::Toke
 
                     fn serialize_to<W: ::std::io::Write, const PRETTY_PX: bool, const PRETTY_TS: bool>(
                         &self,
-                        writer: &mut ::csv::Writer<W>
-                    ) -> ::csv::Result<()> {
+                        writer: &mut #crate_name::encode::csv::serialize::csv::Writer<W>
+                    ) -> #crate_name::encode::csv::serialize::csv::Result<()> {
                         use #crate_name::encode::csv::serialize::WriteField;
 
                         #(#serialize_fields)*
@@ -74,7 +74,7 @@ pub fn derive_json_macro_impl(input: proc_macro::TokenStream) -> proc_macro::Tok
                 .collect::<syn::Result<Vec<_>>>()
                 .unwrap_or_else(|e| vec![syn::Error::to_compile_error(&e)]);
             return quote! {
-                impl crate::encode::json::serialize::JsonSerialize for #ident {
+                impl #crate_name::encode::json::serialize::JsonSerialize for #ident {
                     fn to_json<J: #crate_name::json_writer::JsonWriter, const PRETTY_PX: bool, const PRETTY_TS: bool>(
                         &self,
                         writer: &mut #crate_name::json_writer::JsonObjectWriter<J>,
@@ -111,18 +111,19 @@ fn write_csv_field_token_stream(field: &Field) -> syn::Result<TokenStream> {
     if is_hidden(field) {
         return Ok(quote! {});
     }
+    let crate_name = crate_name();
     if let Some(dbn_attr_id) = find_dbn_serialize_attr(field)? {
         if dbn_attr_id == UNIX_NANOS_ATTR {
             Ok(quote! {
-                crate::encode::csv::serialize::write_ts_field::<_, PRETTY_TS>(writer, self.#ident)?;
+                #crate_name::encode::csv::serialize::write_ts_field::<_, PRETTY_TS>(writer, self.#ident)?;
             })
         } else if dbn_attr_id == FIXED_PRICE_ATTR {
             Ok(quote! {
-                crate::encode::csv::serialize::write_px_field::<_, PRETTY_PX>(writer, self.#ident)?;
+                #crate_name::encode::csv::serialize::write_px_field::<_, PRETTY_PX>(writer, self.#ident)?;
             })
         } else if dbn_attr_id == C_CHAR_ATTR {
             Ok(quote! {
-                crate::encode::csv::serialize::write_c_char_field(writer, self.#ident)?;
+                #crate_name::encode::csv::serialize::write_c_char_field(writer, self.#ident)?;
             })
         } else {
             Err(syn::Error::new(
@@ -143,18 +144,19 @@ fn write_json_field_token_stream(field: &Field) -> syn::Result<TokenStream> {
     if is_hidden(field) {
         return Ok(quote! {});
     }
+    let crate_name = crate_name();
     if let Some(dbn_attr_id) = find_dbn_serialize_attr(field)? {
         if dbn_attr_id == UNIX_NANOS_ATTR {
             Ok(quote! {
-                crate::encode::json::serialize::write_ts_field::<_, PRETTY_TS>(writer, stringify!(#ident), self.#ident);
+                #crate_name::encode::json::serialize::write_ts_field::<_, PRETTY_TS>(writer, stringify!(#ident), self.#ident);
             })
         } else if dbn_attr_id == FIXED_PRICE_ATTR {
             Ok(quote! {
-                crate::encode::json::serialize::write_px_field::<_, PRETTY_PX>(writer, stringify!(#ident), self.#ident);
+                #crate_name::encode::json::serialize::write_px_field::<_, PRETTY_PX>(writer, stringify!(#ident), self.#ident);
             })
         } else if dbn_attr_id == C_CHAR_ATTR {
             Ok(quote! {
-                crate::encode::json::serialize::write_c_char_field(writer, stringify!(#ident), self.#ident);
+                #crate_name::encode::json::serialize::write_c_char_field(writer, stringify!(#ident), self.#ident);
             })
         } else {
             Err(syn::Error::new(
@@ -4,7 +4,8 @@ use quote::quote;
 
 pub fn crate_name() -> TokenStream {
     match proc_macro_crate::crate_name("dbn").expect("dbn crate in Cargo.toml") {
-        FoundCrate::Itself => quote!(crate),
+        // `dbn` aliases itself with `extern crate self as dbn`
+        FoundCrate::Itself => quote!(::dbn),
         FoundCrate::Name(name) => {
             let ident = Ident::new(&name, Span::call_site());
             quote!( ::#ident )
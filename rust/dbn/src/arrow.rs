@@ -0,0 +1,753 @@
+//! Conversion of records to Apache Arrow [`RecordBatch`]es for loading DBN data into
+//! DataFrame engines without going through CSV.
+//!
+//! The Arrow schema is derived from the [`HasFields`] descriptors of the record type,
+//! so column names and order match the CSV encoder. Timestamps become
+//! `Timestamp(Nanosecond, "UTC")`, `c_char` fields and arrays become strings, and
+//! fixed-precision prices are converted according to the [`PriceFormat`]. Undefined
+//! timestamps and, for decimal and scaled prices, undefined prices are null.
+//!
+//! ```
+//! use dbn::{arrow::{ArrowOptions, PriceFormat, RecordBatchBuilder}, TradeMsg};
+//!
+//! let mut builder = RecordBatchBuilder::<TradeMsg>::new(ArrowOptions {
+//!     price_format: PriceFormat::Scaled,
+//!     ..Default::default()
+//! });
+//! builder.append(&TradeMsg {
+//!     price: 1_500_000_000,
+//!     ..Default::default()
+//! });
+//! let batch = builder.finish().unwrap();
+//! assert_eq!(batch.num_rows(), 1);
+//! assert_eq!(batch.schema().field(0).name(), "ts_recv");
+//! ```
+
+use std::{marker::PhantomData, num::NonZeroUsize, sync::Arc};
+
+use arrow_array::{
+    builder::{
+        ArrayBuilder, Decimal128Builder, Float64Builder, Int16Builder, Int32Builder, Int64Builder,
+        Int8Builder, StringBuilder, TimestampNanosecondBuilder, UInt16Builder, UInt32Builder,
+        UInt64Builder, UInt8Builder,
+    },
+    ArrayRef, FixedSizeListArray, RecordBatch, StructArray,
+};
+use arrow_schema::{DataType, Field, FieldRef, Fields, Schema, SchemaRef, TimeUnit};
+
+use crate::{
+    decode::DecodeRecord,
+    reflect::{FieldDesc, FieldHint, FieldType, FieldValue, HasFields},
+    HasRType, RecordRef, SymbolIndex, TsSymbolMap, FIXED_PRICE_SCALE, UNDEF_PRICE, UNDEF_TIMESTAMP,
+};
+
+/// The precision of [`PriceFormat::Decimal`] columns, enough for any `i64`.
+pub const DECIMAL_PRECISION: u8 = 19;
+/// The scale of [`PriceFormat::Decimal`] columns, matching [`FIXED_PRICE_SCALE`].
+pub const DECIMAL_SCALE: i8 = 9;
+
+/// How fixed-precision price fields are represented in Arrow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PriceFormat {
+    /// The raw `Int64` where every 1 unit corresponds to 1e-9.
+    #[default]
+    Fixed,
+    /// An exact `Decimal128` with a scale of 9.
+    Decimal,
+    /// A `Float64` scaled to the price in currency units.
+    Scaled,
+}
+
+/// Options for converting records to Arrow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ArrowOptions {
+    /// How prices are represented.
+    pub price_format: PriceFormat,
+    /// If `true`, book levels like those in [`Mbp10Msg`](crate::Mbp10Msg) become a
+    /// single `levels` column of fixed-size lists of structs instead of being
+    /// flattened to columns like `bid_px_00`.
+    pub nested_levels: bool,
+    /// If `true`, a nullable `symbol` string column is appended.
+    pub with_symbol: bool,
+}
+
+/// The names of the fields of a [`BidAskPair`](crate::BidAskPair) as flattened by
+/// [`HasFields`].
+const LEVEL_FIELDS: [&str; 6] = ["bid_px", "ask_px", "bid_sz", "ask_sz", "bid_ct", "ask_ct"];
+
+/// Returns the Arrow schema of record type `R` converted with `options`.
+pub fn schema<R: HasFields>(options: &ArrowOptions) -> Schema {
+    fields_schema(R::fields(), options, false)
+}
+
+fn fields_schema(fields: &'static [FieldDesc], options: &ArrowOptions, ts_out: bool) -> Schema {
+    Schema::new(
+        plan(fields, options)
+            .iter()
+            .map(Output::field)
+            .chain(ts_out.then(ts_out_field))
+            .chain(options.with_symbol.then(symbol_field))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Accumulates records of type `R` and converts them to Arrow [`RecordBatch`]es.
+pub struct RecordBatchBuilder<R> {
+    inner: DynRecordBatchBuilder,
+    _record: PhantomData<fn(&R)>,
+}
+
+impl<R: HasRType + HasFields> RecordBatchBuilder<R> {
+    /// Creates a new builder converting records according to `options`.
+    pub fn new(options: ArrowOptions) -> Self {
+        Self {
+            inner: DynRecordBatchBuilder::new(R::fields(), options, false),
+            _record: PhantomData,
+        }
+    }
+
+    /// Returns the schema of the batches.
+    pub fn schema(&self) -> SchemaRef {
+        self.inner.schema()
+    }
+
+    /// Returns the number of records appended since the last batch was finished.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if no records have been appended since the last batch was
+    /// finished.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Appends `record` to the current batch. The symbol column, if any, will be null.
+    pub fn append(&mut self, record: &R) {
+        self.append_with_sym(record, None);
+    }
+
+    /// Appends `record` with `symbol` to the current batch. `symbol` is ignored if the
+    /// builder was created without [`ArrowOptions::with_symbol`].
+    pub fn append_with_sym(&mut self, record: &R, symbol: Option<&str>) {
+        self.inner.append(RecordRef::from(record), None, symbol);
+    }
+
+    /// Converts the records appended since the last call into a [`RecordBatch`] and
+    /// resets the builder.
+    ///
+    /// # Errors
+    /// This function returns an error if the columns don't match the schema, which
+    /// indicates a bug.
+    pub fn finish(&mut self) -> crate::Result<RecordBatch> {
+        self.inner.finish()
+    }
+}
+
+/// A [`RecordBatchBuilder`] whose record type is only known at runtime, for encoders
+/// working with [`RecordRef`]s.
+pub(crate) struct DynRecordBatchBuilder {
+    schema: SchemaRef,
+    outputs: Vec<Output>,
+    ts_out: Option<ColumnBuilder>,
+    symbols: Option<StringBuilder>,
+    len: usize,
+}
+
+impl DynRecordBatchBuilder {
+    /// Creates a new builder for records described by `fields`. If `ts_out` is `true`,
+    /// a `ts_out` column is added after the record fields.
+    pub(crate) fn new(fields: &'static [FieldDesc], options: ArrowOptions, ts_out: bool) -> Self {
+        Self {
+            schema: Arc::new(fields_schema(fields, &options, ts_out)),
+            outputs: plan(fields, &options),
+            ts_out: ts_out.then(|| ColumnBuilder::new(ts_out_field().data_type())),
+            symbols: options.with_symbol.then(StringBuilder::new),
+            len: 0,
+        }
+    }
+
+    pub(crate) fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends `record` to the current batch. `record` must be of the type whose
+    /// `fields` the builder was created with.
+    pub(crate) fn append(&mut self, record: RecordRef, ts_out: Option<u64>, symbol: Option<&str>) {
+        for output in self.outputs.iter_mut() {
+            output.append(record);
+        }
+        if let Some(ts_outs) = self.ts_out.as_mut() {
+            ts_outs.append(ts_out.map(FieldValue::U64));
+        }
+        if let Some(symbols) = self.symbols.as_mut() {
+            symbols.append_option(symbol);
+        }
+        self.len += 1;
+    }
+
+    pub(crate) fn finish(&mut self) -> crate::Result<RecordBatch> {
+        let columns = self
+            .outputs
+            .iter_mut()
+            .map(Output::finish)
+            .chain(self.ts_out.as_mut().map(ColumnBuilder::finish))
+            .chain(self.symbols.as_mut().map(|symbols| {
+                let symbols: ArrayRef = Arc::new(symbols.finish());
+                symbols
+            }))
+            .collect();
+        self.len = 0;
+        RecordBatch::try_new(self.schema.clone(), columns)
+            .map_err(|e| crate::Error::encode(format!("creating Arrow record batch: {e}")))
+    }
+}
+
+/// Decodes all records of type `R` from `decoder` into [`RecordBatch`]es of at most
+/// `batch_size` rows.
+///
+/// # Errors
+/// This function returns an error if it fails to decode a record or the input contains
+/// records of another type.
+pub fn decode_record_batches<R, D>(
+    decoder: &mut D,
+    options: ArrowOptions,
+    batch_size: NonZeroUsize,
+) -> crate::Result<Vec<RecordBatch>>
+where
+    R: HasRType + HasFields,
+    D: DecodeRecord,
+{
+    decode_impl::<R, D, TsSymbolMap>(decoder, options, batch_size, None)
+}
+
+/// Decodes all records of type `R` from `decoder` into [`RecordBatch`]es of at most
+/// `batch_size` rows with a `symbol` column looked up in `symbol_index`.
+///
+/// # Errors
+/// This function returns an error if it fails to decode a record or the input contains
+/// records of another type.
+pub fn decode_record_batches_with_symbols<R, D, S>(
+    decoder: &mut D,
+    options: ArrowOptions,
+    batch_size: NonZeroUsize,
+    symbol_index: &S,
+) -> crate::Result<Vec<RecordBatch>>
+where
+    R: HasRType + HasFields,
+    D: DecodeRecord,
+    S: SymbolIndex,
+{
+    let options = ArrowOptions {
+        with_symbol: true,
+        ..options
+    };
+    decode_impl::<R, D, S>(decoder, options, batch_size, Some(symbol_index))
+}
+
+fn decode_impl<R, D, S>(
+    decoder: &mut D,
+    options: ArrowOptions,
+    batch_size: NonZeroUsize,
+    symbol_index: Option<&S>,
+) -> crate::Result<Vec<RecordBatch>>
+where
+    R: HasRType + HasFields,
+    D: DecodeRecord,
+    S: SymbolIndex,
+{
+    let mut builder = RecordBatchBuilder::<R>::new(options);
+    let mut res = Vec::new();
+    while let Some(record) = decoder.decode_record::<R>()? {
+        let symbol = symbol_index.and_then(|index| index.get_for_rec(record));
+        builder.append_with_sym(record, symbol.map(String::as_str));
+        if builder.len() >= batch_size.get() {
+            res.push(builder.finish()?);
+        }
+    }
+    if !builder.is_empty() {
+        res.push(builder.finish()?);
+    }
+    Ok(res)
+}
+
+fn symbol_field() -> Field {
+    Field::new("symbol", DataType::Utf8, true)
+}
+
+fn ts_out_field() -> Field {
+    Field::new(
+        "ts_out",
+        DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+        true,
+    )
+}
+
+/// A single Arrow column fed by one or more record fields.
+struct Column {
+    name: String,
+    data_type: DataType,
+    /// The record fields appended in order for each record. Contains more than one
+    /// field for nested book levels.
+    descs: Vec<&'static FieldDesc>,
+    builder: ColumnBuilder,
+}
+
+impl Column {
+    fn new(name: &str, desc: &'static FieldDesc, price_format: PriceFormat) -> Self {
+        let data_type = data_type(desc, price_format);
+        Self {
+            name: name.to_owned(),
+            builder: ColumnBuilder::new(&data_type),
+            data_type,
+            descs: vec![desc],
+        }
+    }
+
+    fn field(&self) -> Field {
+        let is_nullable = matches!(
+            self.data_type,
+            DataType::Timestamp(..) | DataType::Decimal128(..) | DataType::Float64 | DataType::Utf8
+        );
+        Field::new(&self.name, self.data_type.clone(), is_nullable)
+    }
+
+    fn append(&mut self, rec_ref: RecordRef) {
+        for desc in self.descs.iter() {
+            self.builder.append(desc.value(rec_ref));
+        }
+    }
+}
+
+enum Output {
+    Column(Column),
+    Levels { count: usize, columns: Vec<Column> },
+}
+
+impl Output {
+    fn field(&self) -> Field {
+        match self {
+            Output::Column(column) => column.field(),
+            Output::Levels { count, columns } => Field::new(
+                "levels",
+                DataType::FixedSizeList(level_item_field(columns), *count as i32),
+                false,
+            ),
+        }
+    }
+
+    fn append(&mut self, rec_ref: RecordRef) {
+        match self {
+            Output::Column(column) => column.append(rec_ref),
+            Output::Levels { columns, .. } => {
+                // appending row-major keeps each record's levels contiguous
+                for level in 0..columns[0].descs.len() {
+                    for column in columns.iter_mut() {
+                        column.builder.append(column.descs[level].value(rec_ref));
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            Output::Column(column) => column.builder.finish(),
+            Output::Levels { count, columns } => {
+                let values = StructArray::new(
+                    level_fields(columns),
+                    columns
+                        .iter_mut()
+                        .map(|column| column.builder.finish())
+                        .collect(),
+                    None,
+                );
+                Arc::new(FixedSizeListArray::new(
+                    level_item_field(columns),
+                    *count as i32,
+                    Arc::new(values),
+                    None,
+                ))
+            }
+        }
+    }
+}
+
+fn level_fields(columns: &[Column]) -> Fields {
+    columns.iter().map(Column::field).collect()
+}
+
+fn level_item_field(columns: &[Column]) -> FieldRef {
+    Arc::new(Field::new(
+        "item",
+        DataType::Struct(level_fields(columns)),
+        false,
+    ))
+}
+
+/// Groups `fields` into the columns of the output.
+fn plan(fields: &'static [FieldDesc], options: &ArrowOptions) -> Vec<Output> {
+    let mut res = Vec::<Output>::new();
+    for desc in fields {
+        let level_field = options
+            .nested_levels
+            .then(|| level_field(desc.name()))
+            .flatten();
+        let Some(name) = level_field else {
+            res.push(Output::Column(Column::new(
+                desc.name(),
+                desc,
+                options.price_format,
+            )));
+            continue;
+        };
+        if let Some(Output::Levels { count, columns }) = res.last_mut() {
+            if let Some(column) = columns.iter_mut().find(|column| column.name == name) {
+                column.descs.push(desc);
+                *count = (*count).max(column.descs.len());
+            } else {
+                columns.push(Column::new(name, desc, options.price_format));
+            }
+        } else {
+            res.push(Output::Levels {
+                count: 1,
+                columns: vec![Column::new(name, desc, options.price_format)],
+            });
+        }
+    }
+    res
+}
+
+/// Returns the name of the book level field if `name` is a flattened one like
+/// `bid_px_00`.
+fn level_field(name: &str) -> Option<&str> {
+    let (prefix, level) = name.rsplit_once('_')?;
+    (level.len() == 2
+        && level.bytes().all(|b| b.is_ascii_digit())
+        && LEVEL_FIELDS.contains(&prefix))
+    .then_some(prefix)
+}
+
+fn data_type(desc: &FieldDesc, price_format: PriceFormat) -> DataType {
+    match (desc.field_type(), desc.hint()) {
+        (_, Some(FieldHint::UnixNanos)) => {
+            DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
+        }
+        (_, Some(FieldHint::FixedPrice)) => match price_format {
+            PriceFormat::Fixed => DataType::Int64,
+            PriceFormat::Decimal => DataType::Decimal128(DECIMAL_PRECISION, DECIMAL_SCALE),
+            PriceFormat::Scaled => DataType::Float64,
+        },
+        (_, Some(hint)) if hint.is_c_char() => DataType::Utf8,
+        (FieldType::CStr(_), _) => DataType::Utf8,
+        (FieldType::I8, _) => DataType::Int8,
+        (FieldType::I16, _) => DataType::Int16,
+        (FieldType::I32, _) => DataType::Int32,
+        (FieldType::I64, _) => DataType::Int64,
+        (FieldType::U8, _) => DataType::UInt8,
+        (FieldType::U16, _) => DataType::UInt16,
+        (FieldType::U32, _) => DataType::UInt32,
+        (FieldType::U64, _) => DataType::UInt64,
+    }
+}
+
+enum ColumnBuilder {
+    I8(Int8Builder),
+    I16(Int16Builder),
+    I32(Int32Builder),
+    I64(Int64Builder),
+    U8(UInt8Builder),
+    U16(UInt16Builder),
+    U32(UInt32Builder),
+    U64(UInt64Builder),
+    Timestamp(TimestampNanosecondBuilder),
+    Decimal(Decimal128Builder),
+    Float(Float64Builder),
+    Str(StringBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Int8 => Self::I8(Int8Builder::new()),
+            DataType::Int16 => Self::I16(Int16Builder::new()),
+            DataType::Int32 => Self::I32(Int32Builder::new()),
+            DataType::Int64 => Self::I64(Int64Builder::new()),
+            DataType::UInt8 => Self::U8(UInt8Builder::new()),
+            DataType::UInt16 => Self::U16(UInt16Builder::new()),
+            DataType::UInt32 => Self::U32(UInt32Builder::new()),
+            DataType::UInt64 => Self::U64(UInt64Builder::new()),
+            DataType::Timestamp(_, tz) => {
+                Self::Timestamp(TimestampNanosecondBuilder::new().with_timezone_opt(tz.clone()))
+            }
+            DataType::Decimal128(precision, scale) => Self::Decimal(
+                Decimal128Builder::new()
+                    .with_precision_and_scale(*precision, *scale)
+                    .expect("valid decimal precision and scale"),
+            ),
+            DataType::Float64 => Self::Float(Float64Builder::new()),
+            _ => Self::Str(StringBuilder::new()),
+        }
+    }
+
+    /// Appends `value`, or null if the value is missing or undefined.
+    fn append(&mut self, value: Option<FieldValue>) {
+        let int = value.and_then(|value| value.as_i64());
+        match self {
+            ColumnBuilder::I8(builder) => {
+                builder.append_option(int.and_then(|v| v.try_into().ok()))
+            }
+            ColumnBuilder::I16(builder) => {
+                builder.append_option(int.and_then(|v| v.try_into().ok()))
+            }
+            ColumnBuilder::I32(builder) => {
+                builder.append_option(int.and_then(|v| v.try_into().ok()))
+            }
+            ColumnBuilder::I64(builder) => builder.append_option(int),
+            ColumnBuilder::U8(builder) => {
+                builder.append_option(int.and_then(|v| v.try_into().ok()))
+            }
+            ColumnBuilder::U16(builder) => {
+                builder.append_option(int.and_then(|v| v.try_into().ok()))
+            }
+            ColumnBuilder::U32(builder) => {
+                builder.append_option(int.and_then(|v| v.try_into().ok()))
+            }
+            ColumnBuilder::U64(builder) => {
+                builder.append_option(value.and_then(|value| value.as_u64()))
+            }
+            ColumnBuilder::Timestamp(builder) => builder.append_option(
+                value
+                    .and_then(|value| value.as_u64())
+                    .filter(|ts| *ts != UNDEF_TIMESTAMP)
+                    .and_then(|ts| ts.try_into().ok()),
+            ),
+            ColumnBuilder::Decimal(builder) => {
+                builder.append_option(int.filter(|px| *px != UNDEF_PRICE).map(i128::from))
+            }
+            ColumnBuilder::Float(builder) => builder.append_option(
+                int.filter(|px| *px != UNDEF_PRICE)
+                    .map(|px| px as f64 / FIXED_PRICE_SCALE as f64),
+            ),
+            ColumnBuilder::Str(builder) => match value {
+                Some(FieldValue::Char('\0')) | None => builder.append_null(),
+                Some(value) => builder.append_value(value.to_string()),
+            },
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::I8(builder) => ArrayBuilder::finish(builder),
+            ColumnBuilder::I16(builder) => ArrayBuilder::finish(builder),
+            ColumnBuilder::I32(builder) => ArrayBuilder::finish(builder),
+            ColumnBuilder::I64(builder) => ArrayBuilder::finish(builder),
+            ColumnBuilder::U8(builder) => ArrayBuilder::finish(builder),
+            ColumnBuilder::U16(builder) => ArrayBuilder::finish(builder),
+            ColumnBuilder::U32(builder) => ArrayBuilder::finish(builder),
+            ColumnBuilder::U64(builder) => ArrayBuilder::finish(builder),
+            ColumnBuilder::Timestamp(builder) => ArrayBuilder::finish(builder),
+            ColumnBuilder::Decimal(builder) => ArrayBuilder::finish(builder),
+            ColumnBuilder::Float(builder) => ArrayBuilder::finish(builder),
+            ColumnBuilder::Str(builder) => ArrayBuilder::finish(builder),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_char;
+
+    use arrow_array::{
+        cast::AsArray,
+        types::{Decimal128Type, Float64Type, Int64Type, TimestampNanosecondType, UInt32Type},
+        Array,
+    };
+
+    use super::*;
+    use crate::{
+        decode::{dbn::Decoder, DbnMetadata},
+        record::str_to_c_chars,
+        InstrumentDefMsg, Mbp10Msg, RecordHeader, TradeMsg,
+    };
+
+    const TEST_DATA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/data");
+
+    fn trade(price: i64) -> TradeMsg {
+        TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(crate::rtype::MBP_0, 1, 5482, 1_700_000_000),
+            ts_recv: UNDEF_TIMESTAMP,
+            price,
+            size: 3,
+            action: b'T' as c_char,
+            side: b'B' as c_char,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_price_formats() {
+        for (price_format, data_type) in [
+            (PriceFormat::Fixed, DataType::Int64),
+            (
+                PriceFormat::Decimal,
+                DataType::Decimal128(DECIMAL_PRECISION, DECIMAL_SCALE),
+            ),
+            (PriceFormat::Scaled, DataType::Float64),
+        ] {
+            let mut target = RecordBatchBuilder::<TradeMsg>::new(ArrowOptions {
+                price_format,
+                ..Default::default()
+            });
+            target.append(&trade(1_250_500_000));
+            target.append(&trade(UNDEF_PRICE));
+            let batch = target.finish().unwrap();
+            assert!(target.is_empty());
+            let price = batch.column_by_name("price").unwrap();
+            assert_eq!(*price.data_type(), data_type);
+            match price_format {
+                PriceFormat::Fixed => {
+                    assert_eq!(price.as_primitive::<Int64Type>().value(0), 1_250_500_000);
+                    assert_eq!(price.as_primitive::<Int64Type>().value(1), UNDEF_PRICE);
+                }
+                PriceFormat::Decimal => {
+                    assert_eq!(
+                        price.as_primitive::<Decimal128Type>().value(0),
+                        1_250_500_000
+                    );
+                    assert!(price.is_null(1));
+                }
+                PriceFormat::Scaled => {
+                    assert_eq!(price.as_primitive::<Float64Type>().value(0), 1.2505);
+                    assert!(price.is_null(1));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_timestamps_and_chars() {
+        let mut target = RecordBatchBuilder::<TradeMsg>::new(ArrowOptions::default());
+        target.append(&trade(1));
+        let batch = target.finish().unwrap();
+        let ts_event = batch.column_by_name("ts_event").unwrap();
+        assert_eq!(
+            *ts_event.data_type(),
+            DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
+        );
+        assert_eq!(
+            ts_event.as_primitive::<TimestampNanosecondType>().value(0),
+            1_700_000_000
+        );
+        assert!(batch.column_by_name("ts_recv").unwrap().is_null(0));
+        assert_eq!(
+            batch
+                .column_by_name("side")
+                .unwrap()
+                .as_string::<i32>()
+                .value(0),
+            "B"
+        );
+        assert!(batch.column_by_name("symbol").is_none());
+
+        let mut target = RecordBatchBuilder::<InstrumentDefMsg>::new(ArrowOptions::default());
+        target.append(&InstrumentDefMsg {
+            raw_symbol: str_to_c_chars("ESZ4").unwrap(),
+            ..Default::default()
+        });
+        let batch = target.finish().unwrap();
+        assert_eq!(
+            batch
+                .column_by_name("raw_symbol")
+                .unwrap()
+                .as_string::<i32>()
+                .value(0),
+            "ESZ4"
+        );
+    }
+
+    #[test]
+    fn test_levels_layout() {
+        let flat = schema::<Mbp10Msg>(&ArrowOptions::default());
+        assert!(flat.field_with_name("bid_px_00").is_ok());
+        assert!(flat.field_with_name("ask_ct_09").is_ok());
+        let nested = schema::<Mbp10Msg>(&ArrowOptions {
+            nested_levels: true,
+            ..Default::default()
+        });
+        assert_eq!(nested.fields().len(), flat.fields().len() - 10 * 6 + 1);
+        assert!(nested.field_with_name("bid_px_00").is_err());
+        let DataType::FixedSizeList(item, 10) =
+            nested.field_with_name("levels").unwrap().data_type()
+        else {
+            panic!("Expected fixed-size list of levels");
+        };
+        let DataType::Struct(level_fields) = item.data_type() else {
+            panic!("Expected struct levels");
+        };
+        let names: Vec<_> = level_fields.iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, LEVEL_FIELDS);
+
+        let mut mbp10 = Mbp10Msg::default();
+        mbp10.levels[3].ask_sz = 7;
+        let mut target = RecordBatchBuilder::<Mbp10Msg>::new(ArrowOptions {
+            nested_levels: true,
+            ..Default::default()
+        });
+        target.append(&Mbp10Msg::default());
+        target.append(&mbp10);
+        let batch = target.finish().unwrap();
+        let levels = batch
+            .column_by_name("levels")
+            .unwrap()
+            .as_fixed_size_list()
+            .value(1);
+        let ask_sz = levels
+            .as_struct()
+            .column_by_name("ask_sz")
+            .unwrap()
+            .as_primitive::<UInt32Type>();
+        assert_eq!(ask_sz.len(), 10);
+        assert_eq!(ask_sz.value(3), 7);
+    }
+
+    #[test]
+    fn test_decode_record_batches() {
+        let mut decoder =
+            Decoder::from_file(format!("{TEST_DATA_PATH}/test_data.mbp-10.dbn")).unwrap();
+        let symbol_map = decoder.metadata().symbol_map().unwrap();
+        let batches = decode_record_batches_with_symbols::<Mbp10Msg, _, _>(
+            &mut decoder,
+            ArrowOptions::default(),
+            NonZeroUsize::new(1).unwrap(),
+            &symbol_map,
+        )
+        .unwrap();
+        assert_eq!(batches.len(), 2);
+        let symbol = batches[0].column_by_name("symbol").unwrap();
+        assert_eq!(symbol.as_string::<i32>().value(0), "ESH1");
+
+        let mut decoder =
+            Decoder::from_file(format!("{TEST_DATA_PATH}/test_data.mbp-10.dbn")).unwrap();
+        let batches = decode_record_batches::<Mbp10Msg, _>(
+            &mut decoder,
+            ArrowOptions::default(),
+            NonZeroUsize::new(10).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_rows(), 2);
+        assert_eq!(
+            batches[0].schema(),
+            Arc::new(schema::<Mbp10Msg>(&Default::default()))
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! Struct-of-arrays buffers of records for columnar processing.
+//!
+//! Every record type implements [`Columnar`] with a generated buffer type named after
+//! the record with a `Columns` suffix, e.g. [`MboMsgColumns`](crate::record::MboMsgColumns)
+//! for [`MboMsg`](crate::MboMsg). The buffer has a field for every field of the
+//! record. The [`RecordHeader`](crate::RecordHeader) and book levels are nested
+//! buffers themselves, so every primitive field ends up in its own [`Vec`]:
+//!
+//! ```
+//! use dbn::{columnar::Columns, record::Mbp1MsgColumns, BidAskPair, Mbp1Msg};
+//!
+//! let mut columns = Mbp1MsgColumns::default();
+//! let mbp1 = Mbp1Msg {
+//!     price: 1_500_000_000,
+//!     levels: [BidAskPair {
+//!         bid_px: 1_250_000_000,
+//!         ..Default::default()
+//!     }],
+//!     ..Default::default()
+//! };
+//! columns.push(&mbp1);
+//! assert_eq!(columns.price, [1_500_000_000]);
+//! assert_eq!(columns.hd.ts_event, [mbp1.hd.ts_event]);
+//! assert_eq!(columns.levels[0].bid_px, [1_250_000_000]);
+//! assert_eq!(columns.row(0), mbp1);
+//! ```
+
+use crate::{
+    decode::DecodeRecord,
+    enums::{SecurityUpdateAction, UserDefinedInstrument},
+    record::{BidAskPair, BidAskPairColumns},
+    HasRType,
+};
+
+/// A struct-of-arrays buffer of `Row` values.
+pub trait Columns: Default {
+    /// The type of the values stored in the buffer.
+    type Row;
+
+    /// Returns the number of rows in the buffer.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if the buffer contains no rows.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends `row` to the end of the buffer.
+    fn push(&mut self, row: &Self::Row);
+
+    /// Reconstructs the row at `index`.
+    ///
+    /// # Panics
+    /// This function panics if `index` is out of bounds.
+    fn row(&self, index: usize) -> Self::Row;
+
+    /// Reconstructs the row at `index` or returns `None` if `index` is out of bounds.
+    fn get(&self, index: usize) -> Option<Self::Row> {
+        (index < self.len()).then(|| self.row(index))
+    }
+
+    /// Removes all rows from the buffer.
+    fn clear(&mut self);
+
+    /// Reserves capacity for at least `additional` more rows in every column.
+    fn reserve(&mut self, additional: usize);
+
+    /// Appends all remaining records from `decoder`.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to decode a record or the input
+    /// contains records of another type.
+    fn extend_from_decoder<D: DecodeRecord>(&mut self, decoder: &mut D) -> crate::Result<()>
+    where
+        Self::Row: HasRType,
+    {
+        while let Some(record) = decoder.decode_record::<Self::Row>()? {
+            self.push(record);
+        }
+        Ok(())
+    }
+}
+
+/// A trait for types with a struct-of-arrays buffer type. Can be derived for structs
+/// with the [`Columnar`](crate::macros::Columnar) macro.
+pub trait Columnar: Sized {
+    /// The buffer type for values of this type.
+    type Columns: Columns<Row = Self>;
+}
+
+impl<T: Copy> Columns for Vec<T> {
+    type Row = T;
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn push(&mut self, row: &T) {
+        Vec::push(self, *row);
+    }
+
+    fn row(&self, index: usize) -> T {
+        self[index]
+    }
+
+    fn clear(&mut self) {
+        Vec::clear(self);
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
+    }
+}
+
+impl<C: Columns, const N: usize> Columns for [C; N]
+where
+    [C; N]: Default,
+{
+    type Row = [C::Row; N];
+
+    fn len(&self) -> usize {
+        self.first().map_or(0, Columns::len)
+    }
+
+    fn push(&mut self, row: &Self::Row) {
+        for (columns, value) in self.iter_mut().zip(row.iter()) {
+            columns.push(value);
+        }
+    }
+
+    fn row(&self, index: usize) -> Self::Row {
+        std::array::from_fn(|i| self[i].row(index))
+    }
+
+    fn clear(&mut self) {
+        self.iter_mut().for_each(Columns::clear);
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        for columns in self.iter_mut() {
+            columns.reserve(additional);
+        }
+    }
+}
+
+macro_rules! impl_columnar_vec {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Columnar for $ty {
+                type Columns = Vec<$ty>;
+            }
+        )*
+    };
+}
+
+impl_columnar_vec!(
+    i8,
+    i16,
+    i32,
+    i64,
+    u8,
+    u16,
+    u32,
+    u64,
+    SecurityUpdateAction,
+    UserDefinedInstrument,
+);
+
+// Fixed-length strings and padding are stored whole rather than as a column per byte.
+// Implemented for `i8` and `u8` rather than `c_char` because `c_char` is `u8` on some
+// platforms.
+impl<const N: usize> Columnar for [i8; N] {
+    type Columns = Vec<[i8; N]>;
+}
+
+impl<const N: usize> Columnar for [u8; N] {
+    type Columns = Vec<[u8; N]>;
+}
+
+impl<const N: usize> Columnar for [BidAskPair; N]
+where
+    [BidAskPairColumns; N]: Default,
+{
+    type Columns = [BidAskPairColumns; N];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compat::InstrumentDefMsgV1,
+        decode::{dbn::Decoder, DecodeRecord},
+        record::str_to_c_chars,
+        record::{MboMsgColumns, Mbp10MsgColumns},
+        InstrumentDefMsg, MboMsg, Mbp10Msg,
+    };
+
+    const TEST_DATA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/data");
+
+    #[test]
+    fn test_round_trip_levels() {
+        let mut mbp10 = Mbp10Msg::default();
+        mbp10.levels[9].ask_ct = 5;
+        let mut target = Mbp10MsgColumns::default();
+        target.push(&Mbp10Msg::default());
+        target.push(&mbp10);
+        assert_eq!(target.len(), 2);
+        assert_eq!(target.levels[9].ask_ct, [0, 5]);
+        assert_eq!(target.row(1), mbp10);
+        assert!(target.get(2).is_none());
+        target.clear();
+        assert!(target.is_empty());
+    }
+
+    #[test]
+    fn test_round_trip_strings() {
+        let def = InstrumentDefMsg {
+            raw_symbol: str_to_c_chars("ESZ4").unwrap(),
+            ..Default::default()
+        };
+        let mut target = <InstrumentDefMsg as Columnar>::Columns::default();
+        target.push(&def);
+        assert_eq!(target.row(0), def);
+
+        let mut target = <InstrumentDefMsgV1 as Columnar>::Columns::default();
+        let def_v1 = InstrumentDefMsgV1::default();
+        target.push(&def_v1);
+        assert_eq!(target.row(0), def_v1);
+    }
+
+    #[test]
+    fn test_extend_from_decoder() {
+        let mut decoder =
+            Decoder::from_file(format!("{TEST_DATA_PATH}/test_data.mbo.dbn")).unwrap();
+        let mut target = MboMsgColumns::default();
+        target.extend_from_decoder(&mut decoder).unwrap();
+        assert_eq!(target.len(), 2);
+
+        let mut decoder =
+            Decoder::from_file(format!("{TEST_DATA_PATH}/test_data.mbo.dbn")).unwrap();
+        let first = decoder.decode_record::<MboMsg>().unwrap().unwrap();
+        assert_eq!(target.row(0), *first);
+        assert_eq!(target.hd.ts_event[0], first.hd.ts_event);
+    }
+}
@@ -2,7 +2,7 @@
 use std::os::raw::c_char;
 
 use crate::{
-    macros::{dbn_record, CsvSerialize, JsonSerialize},
+    macros::{dbn_record, Columnar, CsvSerialize, JsonSerialize},
     record::{transmute_header_bytes, transmute_record_bytes},
     rtype, HasRType, RecordHeader, RecordRef, SecurityUpdateAction, UserDefinedInstrument,
     VersionUpgradePolicy,
@@ -84,7 +84,7 @@ pub unsafe fn decode_record_ref<'a>(
 /// Definition of an instrument in DBN version 1. The record of the
 /// [`Definition`](crate::enums::Schema::Definition) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -258,7 +258,7 @@ pub struct InstrumentDefMsgV1 {
     #[dbn(fmt_method)]
     pub strike_price_currency: [c_char; 4],
     /// The classification of the instrument.
-    #[dbn(c_char, encode_order(4))]
+    #[dbn(c_char, encode_order(4), enum_type(InstrumentClass))]
     #[pyo3(set)]
     pub instrument_class: c_char,
     #[doc(hidden)]
@@ -271,7 +271,7 @@ pub struct InstrumentDefMsgV1 {
     #[doc(hidden)]
     pub _reserved5: [u8; 6],
     /// The matching algorithm used for the instrument, typically **F**IFO.
-    #[dbn(c_char)]
+    #[dbn(c_char, enum_type(MatchAlgorithm))]
     #[pyo3(set)]
     pub match_algorithm: c_char,
     /// The current trading state of the instrument.
@@ -325,7 +325,7 @@ pub struct InstrumentDefMsgV1 {
 /// An error message from the Databento Live Subscription Gateway (LSG) in DBN version
 /// 1.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -349,7 +349,7 @@ pub struct ErrorMsgV1 {
 /// A symbol mapping message in DBN version 1 which maps a symbol of one
 /// [`SType`](crate::SType) to another.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -388,7 +388,7 @@ pub struct SymbolMappingMsgV1 {
 /// A non-error message from the Databento Live Subscription Gateway (LSG) in DBN
 /// version 1. Also used for heartbeating.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
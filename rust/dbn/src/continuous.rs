@@ -0,0 +1,614 @@
+//! Local resolution of [`SType::Continuous`](crate::SType::Continuous) symbols like
+//! `ES.c.0` from stored instrument definitions and statistics.
+//!
+//! A continuous symbol has the form `ROOT.RULE.RANK`, where `ROOT` is the
+//! [`asset`](InstrumentDefMsg::asset) of the futures, `RULE` is the [`RollRule`], and
+//! `RANK` selects the contract, with `0` being the front contract. Feed definition and
+//! statistics records to a [`ContinuousResolver`] and then call
+//! [`ContinuousResolver::resolve()`] to get [`SymbolMapping`]s, which can be passed to
+//! [`TsSymbolMap::from_mappings()`](crate::TsSymbolMap::from_mappings).
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    os::raw::c_char,
+    str::FromStr,
+};
+
+use crate::{
+    compat::InstrumentDefMsgV1, enums::StatUpdateAction, symbol_map::date_to_ts, InstrumentClass,
+    InstrumentDefMsg, MappingInterval, RType, Record, RecordRef, SecurityUpdateAction, StatMsg,
+    StatType, SymbolMapping, UNDEF_TIMESTAMP,
+};
+
+/// How the contracts of a continuous symbol are ranked on each day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RollRule {
+    /// Rank by expiration, i.e. the front contract is the next one to expire. Encoded
+    /// as `c`.
+    Calendar,
+    /// Rank by the latest open interest known at the start of the day, highest first.
+    /// Encoded as `n`.
+    OpenInterest,
+    /// Rank by the latest cleared volume known at the start of the day, highest first.
+    /// Encoded as `v`.
+    Volume,
+}
+
+impl RollRule {
+    /// Returns the character used for the rule in continuous symbols.
+    pub const fn as_char(&self) -> char {
+        match self {
+            RollRule::Calendar => 'c',
+            RollRule::OpenInterest => 'n',
+            RollRule::Volume => 'v',
+        }
+    }
+}
+
+/// A parsed continuous symbol like `ES.c.0`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ContinuousSymbol {
+    /// The asset of the futures contracts, e.g. `ES`.
+    pub root: String,
+    /// How contracts are ranked.
+    pub rule: RollRule,
+    /// The rank of the contract to select, where `0` is the front contract.
+    pub rank: u32,
+}
+
+impl FromStr for ContinuousSymbol {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || crate::Error::BadArgument {
+            param_name: "symbol".to_owned(),
+            desc: format!(
+                "'{s}' isn't a continuous symbol of the form ROOT.RULE.RANK, e.g. ES.c.0"
+            ),
+        };
+        let mut parts = s.rsplitn(3, '.');
+        let (Some(rank), Some(rule), Some(root)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(err());
+        };
+        let rule = match rule {
+            "c" => RollRule::Calendar,
+            "n" => RollRule::OpenInterest,
+            "v" => RollRule::Volume,
+            _ => return Err(err()),
+        };
+        if root.is_empty() {
+            return Err(err());
+        }
+        Ok(Self {
+            root: root.to_owned(),
+            rule,
+            rank: rank.parse().map_err(|_| err())?,
+        })
+    }
+}
+
+impl fmt::Display for ContinuousSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.root, self.rule.as_char(), self.rank)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Contract {
+    root: String,
+    activation: u64,
+    expiration: u64,
+}
+
+impl Contract {
+    /// Returns `true` if the contract can be traded at some point during the day
+    /// `[start_ts, end_ts)`.
+    fn is_live(&self, start_ts: u64, end_ts: u64) -> bool {
+        (self.activation == UNDEF_TIMESTAMP || self.activation < end_ts)
+            && self.expiration > start_ts
+    }
+}
+
+/// Resolves continuous symbols to instrument IDs from a stream of
+/// [`InstrumentDefMsg`]s and, for the [`RollRule::OpenInterest`] and
+/// [`RollRule::Volume`] rules, [`StatMsg`]s.
+///
+/// Only outright futures are considered. Statistics are attributed to their
+/// `ts_recv`, and a day is ranked by the latest statistics received before it started
+/// to avoid lookahead. Contracts without statistics rank last, including those whose
+/// latest statistic was deleted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContinuousResolver {
+    contracts: HashMap<u32, Contract>,
+    open_interest: HashMap<u32, StatHistory>,
+    volume: HashMap<u32, StatHistory>,
+}
+
+/// The `ts_ref` and value of a statistic by `ts_recv`, where `None` is a tombstone
+/// for a deleted statistic.
+type StatHistory = BTreeMap<u64, Option<(u64, i32)>>;
+
+impl ContinuousResolver {
+    /// Creates a new empty resolver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if no futures definitions have been processed.
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty()
+    }
+
+    /// Handles updating the resolver for a generic record. Records other than
+    /// definitions and statistics are ignored.
+    ///
+    /// # Errors
+    /// This function returns an error when `record` is a definition whose `asset`
+    /// contains invalid UTF-8 or when `record` is a definition or statistics record
+    /// that's too short for its `rtype`.
+    pub fn on_record(&mut self, record: RecordRef) -> crate::Result<()> {
+        match record.rtype() {
+            Ok(RType::InstrumentDef) => {
+                // >= to allow WithTsOut
+                if record.record_size() >= std::mem::size_of::<InstrumentDefMsg>() {
+                    // Safety: checked rtype and length
+                    self.on_definition(unsafe { record.get_unchecked::<InstrumentDefMsg>() })
+                } else if record.record_size() >= std::mem::size_of::<InstrumentDefMsgV1>() {
+                    // Safety: checked rtype and length
+                    let definition = unsafe { record.get_unchecked::<InstrumentDefMsgV1>() };
+                    self.on_definition(&InstrumentDefMsg::from(definition))
+                } else {
+                    Err(short_record_error::<InstrumentDefMsgV1>(record))
+                }
+            }
+            Ok(RType::Statistics) => {
+                if record.record_size() >= std::mem::size_of::<StatMsg>() {
+                    // Safety: checked rtype and length
+                    self.on_stat(unsafe { record.get_unchecked::<StatMsg>() });
+                    Ok(())
+                } else {
+                    Err(short_record_error::<StatMsg>(record))
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Handles updating the contracts for an instrument definition.
+    ///
+    /// # Errors
+    /// This function returns an error when `definition`'s `asset` contains invalid
+    /// UTF-8.
+    pub fn on_definition(&mut self, definition: &InstrumentDefMsg) -> crate::Result<()> {
+        let instrument_id = definition.hd.instrument_id;
+        if definition.security_update_action == SecurityUpdateAction::Delete as c_char
+            || definition.instrument_class != InstrumentClass::Future as c_char
+        {
+            self.contracts.remove(&instrument_id);
+            return Ok(());
+        }
+        self.contracts.insert(
+            instrument_id,
+            Contract {
+                root: definition.asset()?.to_owned(),
+                activation: definition.activation,
+                expiration: definition.expiration,
+            },
+        );
+        Ok(())
+    }
+
+    /// Handles updating the open interest or volume of an instrument. A delete removes
+    /// the current statistic with the same `ts_ref` from the delete's `ts_recv`
+    /// onwards. Other statistics are ignored.
+    pub fn on_stat(&mut self, stat: &StatMsg) {
+        let stats = if stat.stat_type == StatType::OpenInterest as u16 {
+            &mut self.open_interest
+        } else if stat.stat_type == StatType::ClearedVolume as u16 {
+            &mut self.volume
+        } else {
+            return;
+        };
+        let values = stats.entry(stat.hd.instrument_id).or_default();
+        if stat.update_action == StatUpdateAction::Delete as u8 {
+            let is_current = values.range(..=stat.ts_recv).next_back().is_some_and(
+                |(_, value)| matches!(value, Some((ts_ref, _)) if *ts_ref == stat.ts_ref),
+            );
+            if is_current {
+                values.insert(stat.ts_recv, None);
+            }
+        } else {
+            values.insert(stat.ts_recv, Some((stat.ts_ref, stat.quantity)));
+        }
+    }
+
+    /// Returns the instrument ID `symbol` resolves to on `date`, or `None` if there
+    /// are fewer than `symbol.rank + 1` live contracts on that day.
+    pub fn resolve_on(&self, symbol: &ContinuousSymbol, date: time::Date) -> Option<u32> {
+        let start_ts = date_to_ts(date);
+        let end_ts = date.next_day().map_or(UNDEF_TIMESTAMP, date_to_ts);
+        let mut live = self
+            .contracts
+            .iter()
+            .filter(|(_, contract)| {
+                contract.root == symbol.root && contract.is_live(start_ts, end_ts)
+            })
+            .map(|(instrument_id, contract)| (*instrument_id, contract.expiration))
+            .collect::<Vec<_>>();
+        let stats = match symbol.rule {
+            RollRule::Calendar => None,
+            RollRule::OpenInterest => Some(&self.open_interest),
+            RollRule::Volume => Some(&self.volume),
+        };
+        if let Some(stats) = stats {
+            live.sort_by_cached_key(|(instrument_id, expiration)| {
+                let value = stats
+                    .get(instrument_id)
+                    .and_then(|values| values.range(..start_ts).next_back())
+                    .and_then(|(_, value)| value.map(|(_, value)| value));
+                (Reverse(value), *expiration, *instrument_id)
+            });
+        } else {
+            live.sort_by_key(|(instrument_id, expiration)| (*expiration, *instrument_id));
+        }
+        live.get(symbol.rank as usize)
+            .map(|(instrument_id, _)| *instrument_id)
+    }
+
+    /// Resolves each of `symbols` for every day in `[start_date, end_date)`, returning a
+    /// mapping per symbol from the continuous symbol to instrument IDs. Consecutive
+    /// days resolving to the same instrument are combined into one interval and days
+    /// where a symbol doesn't resolve are omitted.
+    ///
+    /// # Errors
+    /// This function returns an error if one of `symbols` isn't a valid continuous
+    /// symbol or `start_date` comes after `end_date`.
+    pub fn resolve<S: AsRef<str>>(
+        &self,
+        symbols: &[S],
+        start_date: time::Date,
+        end_date: time::Date,
+    ) -> crate::Result<Vec<SymbolMapping>> {
+        if start_date > end_date {
+            return Err(crate::Error::BadArgument {
+                param_name: "start_date".to_owned(),
+                desc: "start_date cannot come after end_date".to_owned(),
+            });
+        }
+        let mut res = Vec::with_capacity(symbols.len());
+        for raw_symbol in symbols {
+            let raw_symbol = raw_symbol.as_ref();
+            let symbol = ContinuousSymbol::from_str(raw_symbol)?;
+            let mut intervals = Vec::<MappingInterval>::new();
+            let mut date = start_date;
+            while date < end_date {
+                let Some(next_date) = date.next_day() else {
+                    break;
+                };
+                if let Some(instrument_id) = self.resolve_on(&symbol, date) {
+                    let instrument_id = instrument_id.to_string();
+                    match intervals.last_mut() {
+                        Some(last) if last.end_date == date && last.symbol == instrument_id => {
+                            last.end_date = next_date;
+                        }
+                        _ => intervals.push(MappingInterval {
+                            start_date: date,
+                            end_date: next_date,
+                            symbol: instrument_id,
+                        }),
+                    }
+                }
+                date = next_date;
+            }
+            res.push(SymbolMapping {
+                raw_symbol: raw_symbol.to_owned(),
+                intervals,
+            });
+        }
+        Ok(res)
+    }
+}
+
+fn short_record_error<T>(record: RecordRef) -> crate::Error {
+    crate::Error::decode(format!(
+        "malformed `{}` record: expected length of at least {} bytes, found {} bytes",
+        std::any::type_name::<T>(),
+        std::mem::size_of::<T>(),
+        record.record_size()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{date, datetime};
+
+    use crate::{RecordHeader, TsSymbolMap};
+
+    use super::*;
+
+    fn ts(dt: time::OffsetDateTime) -> u64 {
+        dt.unix_timestamp_nanos() as u64
+    }
+
+    fn future(
+        instrument_id: u32,
+        asset: &str,
+        activation: time::OffsetDateTime,
+        expiration: time::OffsetDateTime,
+    ) -> InstrumentDefMsg {
+        let mut res = InstrumentDefMsg {
+            hd: RecordHeader::new::<InstrumentDefMsg>(
+                crate::rtype::INSTRUMENT_DEF,
+                1,
+                instrument_id,
+                ts(activation),
+            ),
+            activation: ts(activation),
+            expiration: ts(expiration),
+            instrument_class: InstrumentClass::Future as c_char,
+            security_update_action: SecurityUpdateAction::Add as c_char,
+            ..Default::default()
+        };
+        for (c, b) in res.asset.iter_mut().zip(asset.bytes()) {
+            *c = b as c_char;
+        }
+        res
+    }
+
+    fn stat(
+        instrument_id: u32,
+        stat_type: StatType,
+        ts_recv: time::OffsetDateTime,
+        quantity: i32,
+    ) -> StatMsg {
+        StatMsg {
+            hd: RecordHeader::new::<StatMsg>(
+                crate::rtype::STATISTICS,
+                1,
+                instrument_id,
+                ts(ts_recv),
+            ),
+            ts_recv: ts(ts_recv),
+            stat_type: stat_type as u16,
+            update_action: StatUpdateAction::New as u8,
+            quantity,
+            ..Default::default()
+        }
+    }
+
+    fn intervals(mapping: &SymbolMapping) -> Vec<(time::Date, time::Date, &str)> {
+        mapping
+            .intervals
+            .iter()
+            .map(|interval| {
+                (
+                    interval.start_date,
+                    interval.end_date,
+                    interval.symbol.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    fn resolver() -> ContinuousResolver {
+        let mut target = ContinuousResolver::new();
+        for definition in [
+            future(
+                1,
+                "ES",
+                datetime!(2022-12-16 14:30 UTC),
+                datetime!(2023-03-17 13:30 UTC),
+            ),
+            future(
+                2,
+                "ES",
+                datetime!(2023-03-17 14:30 UTC),
+                datetime!(2023-06-16 13:30 UTC),
+            ),
+            // listed before contract 1 expires
+            future(
+                3,
+                "ES",
+                datetime!(2022-06-17 14:30 UTC),
+                datetime!(2023-06-16 13:30 UTC),
+            ),
+            future(
+                4,
+                "NQ",
+                datetime!(2022-12-16 14:30 UTC),
+                datetime!(2023-03-17 13:30 UTC),
+            ),
+        ] {
+            target.on_record(RecordRef::from(&definition)).unwrap();
+        }
+        target
+    }
+
+    #[test]
+    fn test_parse_symbol() {
+        assert_eq!(
+            "ES.c.0".parse::<ContinuousSymbol>().unwrap(),
+            ContinuousSymbol {
+                root: "ES".to_owned(),
+                rule: RollRule::Calendar,
+                rank: 0
+            }
+        );
+        let symbol = "BRN.FUT.n.12".parse::<ContinuousSymbol>().unwrap();
+        assert_eq!(symbol.root, "BRN.FUT");
+        assert_eq!(symbol.rule, RollRule::OpenInterest);
+        assert_eq!(symbol.rank, 12);
+        assert_eq!(symbol.to_string(), "BRN.FUT.n.12");
+        for invalid in ["ES", "ES.c", "ES.x.0", ".c.0", "ES.v.-1", "ESH3"] {
+            assert!(invalid.parse::<ContinuousSymbol>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_resolve_calendar() {
+        let target = resolver();
+        let mappings = target
+            .resolve(
+                &["ES.c.0", "ES.c.1", "ES.c.2", "NQ.c.0"],
+                date!(2023 - 03 - 16),
+                date!(2023 - 03 - 20),
+            )
+            .unwrap();
+        assert_eq!(mappings[0].raw_symbol, "ES.c.0");
+        assert_eq!(
+            intervals(&mappings[0]),
+            [
+                (date!(2023 - 03 - 16), date!(2023 - 03 - 18), "1"),
+                (date!(2023 - 03 - 18), date!(2023 - 03 - 20), "2"),
+            ]
+        );
+        assert_eq!(
+            intervals(&mappings[1]),
+            [
+                (date!(2023 - 03 - 16), date!(2023 - 03 - 17), "3"),
+                // both 2 and 3 expire at the same time
+                (date!(2023 - 03 - 17), date!(2023 - 03 - 18), "2"),
+                (date!(2023 - 03 - 18), date!(2023 - 03 - 20), "3"),
+            ]
+        );
+        assert_eq!(
+            intervals(&mappings[2]),
+            [(date!(2023 - 03 - 17), date!(2023 - 03 - 18), "3")]
+        );
+        assert_eq!(
+            intervals(&mappings[3]),
+            [(date!(2023 - 03 - 16), date!(2023 - 03 - 18), "4")]
+        );
+        let symbol_map = TsSymbolMap::from_mappings(&mappings).unwrap();
+        assert_eq!(
+            symbol_map
+                .get(date!(2023 - 03 - 19), 2)
+                .map(|symbol| symbol.as_str()),
+            Some("ES.c.0")
+        );
+    }
+
+    #[test]
+    fn test_resolve_open_interest() {
+        let mut target = resolver();
+        for stat in [
+            stat(
+                1,
+                StatType::OpenInterest,
+                datetime!(2023-03-14 22:00 UTC),
+                2_000_000,
+            ),
+            stat(
+                3,
+                StatType::OpenInterest,
+                datetime!(2023-03-14 22:00 UTC),
+                1_000_000,
+            ),
+            stat(
+                1,
+                StatType::OpenInterest,
+                datetime!(2023-03-15 22:00 UTC),
+                800_000,
+            ),
+            stat(
+                3,
+                StatType::OpenInterest,
+                datetime!(2023-03-15 22:00 UTC),
+                2_200_000,
+            ),
+            // ignored
+            stat(
+                1,
+                StatType::ClearedVolume,
+                datetime!(2023-03-15 22:00 UTC),
+                5_000_000,
+            ),
+        ] {
+            target.on_record(RecordRef::from(&stat)).unwrap();
+        }
+        let symbol = "ES.n.0".parse().unwrap();
+        assert_eq!(target.resolve_on(&symbol, date!(2023 - 03 - 14)), Some(1));
+        assert_eq!(target.resolve_on(&symbol, date!(2023 - 03 - 15)), Some(1));
+        assert_eq!(target.resolve_on(&symbol, date!(2023 - 03 - 16)), Some(3));
+        let symbol = "ES.v.0".parse().unwrap();
+        assert_eq!(target.resolve_on(&symbol, date!(2023 - 03 - 16)), Some(1));
+    }
+
+    #[test]
+    fn test_short_stat_is_error() {
+        let mut target = resolver();
+        let mut short = stat(
+            1,
+            StatType::OpenInterest,
+            datetime!(2023-03-14 22:00 UTC),
+            2_000_000,
+        );
+        short.hd.length =
+            (std::mem::size_of::<RecordHeader>() / RecordHeader::LENGTH_MULTIPLIER) as u8;
+        assert!(matches!(
+            target.on_record(RecordRef::from(&short)),
+            Err(crate::Error::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn test_delete_stat() {
+        let mut target = resolver();
+        let mut add_1 = stat(
+            1,
+            StatType::OpenInterest,
+            datetime!(2023-03-14 22:00 UTC),
+            2_000_000,
+        );
+        add_1.ts_ref = ts(datetime!(2023-03-14 00:00 UTC));
+        let mut add_3 = stat(
+            3,
+            StatType::OpenInterest,
+            datetime!(2023-03-14 22:00 UTC),
+            1_000_000,
+        );
+        add_3.ts_ref = add_1.ts_ref;
+        let mut delete_1 = stat(
+            1,
+            StatType::OpenInterest,
+            datetime!(2023-03-15 12:00 UTC),
+            2_000_000,
+        );
+        delete_1.ts_ref = add_1.ts_ref;
+        delete_1.update_action = StatUpdateAction::Delete as u8;
+        // refers to a statistic that was never added
+        let mut stale_delete_3 = stat(
+            3,
+            StatType::OpenInterest,
+            datetime!(2023-03-15 12:00 UTC),
+            1_000_000,
+        );
+        stale_delete_3.ts_ref = ts(datetime!(2023-03-13 00:00 UTC));
+        stale_delete_3.update_action = StatUpdateAction::Delete as u8;
+        for stat in [add_1, add_3, delete_1, stale_delete_3] {
+            target.on_record(RecordRef::from(&stat)).unwrap();
+        }
+        let symbol = "ES.n.0".parse().unwrap();
+        // The delete isn't known yet at the start of the 15th
+        assert_eq!(target.resolve_on(&symbol, date!(2023 - 03 - 15)), Some(1));
+        assert_eq!(target.resolve_on(&symbol, date!(2023 - 03 - 16)), Some(3));
+    }
+
+    #[test]
+    fn test_delete_definition() {
+        let mut target = resolver();
+        let mut definition = future(
+            1,
+            "ES",
+            datetime!(2022-12-16 14:30 UTC),
+            datetime!(2023-03-17 13:30 UTC),
+        );
+        definition.security_update_action = SecurityUpdateAction::Delete as c_char;
+        target.on_definition(&definition).unwrap();
+        let symbol = "ES.c.0".parse().unwrap();
+        assert_eq!(target.resolve_on(&symbol, date!(2023 - 03 - 16)), Some(3));
+    }
+}
@@ -1,4 +1,4 @@
-//! Decoding DBN and Zstd-compressed DBN files and streams. Decoders implement the
+//! Decoding DBN and compressed DBN files and streams. Decoders implement the
 //! [`DecodeDbn`] trait.
 pub mod dbn;
 // Having any tests in a deprecated module emits many warnings that can't be silenced, see
@@ -48,7 +48,25 @@ pub trait DecodeRecordRef {
     ///
     /// If the `length` property of the record is invalid, an
     /// [`Error::Decode`](crate::Error::Decode) will be returned.
-    fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef>>;
+    fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>>;
+}
+
+/// Trait for types that asynchronously decode references to DBN records of a dynamic
+/// type.
+#[cfg(feature = "async")]
+pub trait AsyncDecodeRecordRef {
+    /// Tries to decode a generic reference a record. Returns `Ok(None)` if input
+    /// has been exhausted.
+    ///
+    /// # Errors
+    /// This function returns an error if the underlying reader returns an error of a
+    /// kind other than `io::ErrorKind::UnexpectedEof` upon reading.
+    ///
+    /// If the `length` property of the record is invalid, an
+    /// [`Error::Decode`](crate::Error::Decode) will be returned.
+    fn decode_record_ref(
+        &mut self,
+    ) -> impl std::future::Future<Output = crate::Result<Option<RecordRef<'_>>>> + Send;
 }
 
 /// Trait for decoders with metadata about what's being decoded.
@@ -122,7 +140,7 @@ where
     R: io::BufRead,
 {
     Dbn(dbn::Decoder<R>),
-    ZstdDbn(dbn::Decoder<::zstd::stream::Decoder<'a, R>>),
+    CompressedDbn(dbn::Decoder<DynReader<'a, R>>),
     #[allow(deprecated)]
     LegacyDbz(dbz::Decoder<R>),
 }
@@ -177,10 +195,9 @@ where
             Compression::None => Ok(Self(DynDecoderImpl::Dbn(
                 dbn::Decoder::with_upgrade_policy(reader, upgrade_policy)?,
             ))),
-            Compression::ZStd => Ok(Self(DynDecoderImpl::ZstdDbn(
+            compression => Ok(Self(DynDecoderImpl::CompressedDbn(
                 dbn::Decoder::with_upgrade_policy(
-                    ::zstd::stream::Decoder::with_buffer(reader)
-                        .map_err(|e| crate::Error::io(e, "creating zstd decoder"))?,
+                    DynReader::with_buffer(reader, compression)?,
                     upgrade_policy,
                 )?,
             ))),
@@ -210,11 +227,10 @@ where
             Ok(Self(DynDecoderImpl::Dbn(
                 dbn::Decoder::with_upgrade_policy(reader, upgrade_policy)?,
             )))
-        } else if zstd::starts_with_prefix(first_bytes) {
-            Ok(Self(DynDecoderImpl::ZstdDbn(
+        } else if let Some(compression) = infer_compression(first_bytes) {
+            Ok(Self(DynDecoderImpl::CompressedDbn(
                 dbn::Decoder::with_upgrade_policy(
-                    ::zstd::stream::Decoder::with_buffer(reader)
-                        .map_err(|e| crate::Error::io(e, "creating zstd decoder"))?,
+                    DynReader::with_buffer(reader, compression)?,
                     upgrade_policy,
                 )?,
             )))
@@ -252,10 +268,10 @@ impl<'a, R> DecodeRecordRef for DynDecoder<'a, R>
 where
     R: io::BufRead,
 {
-    fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef>> {
+    fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
         match &mut self.0 {
             DynDecoderImpl::Dbn(decoder) => decoder.decode_record_ref(),
-            DynDecoderImpl::ZstdDbn(decoder) => decoder.decode_record_ref(),
+            DynDecoderImpl::CompressedDbn(decoder) => decoder.decode_record_ref(),
             DynDecoderImpl::LegacyDbz(decoder) => decoder.decode_record_ref(),
         }
     }
@@ -269,7 +285,7 @@ where
     fn metadata(&self) -> &Metadata {
         match &self.0 {
             DynDecoderImpl::Dbn(decoder) => decoder.metadata(),
-            DynDecoderImpl::ZstdDbn(decoder) => decoder.metadata(),
+            DynDecoderImpl::CompressedDbn(decoder) => decoder.metadata(),
             DynDecoderImpl::LegacyDbz(decoder) => decoder.metadata(),
         }
     }
@@ -277,7 +293,7 @@ where
     fn metadata_mut(&mut self) -> &mut Metadata {
         match &mut self.0 {
             DynDecoderImpl::Dbn(decoder) => decoder.metadata_mut(),
-            DynDecoderImpl::ZstdDbn(decoder) => decoder.metadata_mut(),
+            DynDecoderImpl::CompressedDbn(decoder) => decoder.metadata_mut(),
             DynDecoderImpl::LegacyDbz(decoder) => decoder.metadata_mut(),
         }
     }
@@ -291,7 +307,7 @@ where
     fn decode_record<T: HasRType>(&mut self) -> crate::Result<Option<&T>> {
         match &mut self.0 {
             DynDecoderImpl::Dbn(decoder) => decoder.decode_record(),
-            DynDecoderImpl::ZstdDbn(decoder) => decoder.decode_record(),
+            DynDecoderImpl::CompressedDbn(decoder) => decoder.decode_record(),
             DynDecoderImpl::LegacyDbz(decoder) => decoder.decode_record(),
         }
     }
@@ -309,8 +325,8 @@ where
     }
 }
 
-/// Type for runtime polymorphism over whether decoding uncompressed or ZStd-compressed
-/// DBN records. Implements [`std::io::Write`].
+/// Type for runtime polymorphism over whether decoding uncompressed or compressed
+/// DBN records. Implements [`std::io::Read`].
 pub struct DynReader<'a, R>(DynReaderImpl<'a, R>)
 where
     R: io::BufRead;
@@ -321,6 +337,12 @@ where
 {
     Uncompressed(R),
     ZStd(::zstd::stream::Decoder<'a, R>),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::bufread::MultiGzDecoder<R>),
+    #[cfg(feature = "lz4")]
+    Lz4(lz4_flex::frame::FrameDecoder<R>),
+    #[cfg(feature = "xz")]
+    Xz(liblzma::bufread::XzDecoder<R>),
 }
 
 impl<'a, R> DynReader<'a, BufReader<R>>
@@ -330,7 +352,8 @@ where
     /// Creates a new [`DynReader`] from a reader, with the specified `compression`.
     ///
     /// # Errors
-    /// This function will return an error if it fails to create the zstd decoder.
+    /// This function will return an error if it fails to create the decompressor or the
+    /// feature for `compression` isn't enabled.
     pub fn new(reader: R, compression: Compression) -> crate::Result<Self> {
         Self::with_buffer(BufReader::new(reader), compression)
     }
@@ -341,7 +364,7 @@ where
     ///
     /// # Errors
     /// This function will return an error if it is unable to read from `reader`
-    /// or it fails to create the zstd decoder.
+    /// or it fails to create the decompressor.
     pub fn new_inferred(reader: R) -> crate::Result<Self> {
         Self::inferred_with_buffer(BufReader::new(reader))
     }
@@ -355,34 +378,55 @@ where
     /// `compression`.
     ///
     /// # Errors
-    /// This function will return an error if it fails to create the zstd decoder.
+    /// This function will return an error if it fails to create the decompressor or the
+    /// feature for `compression` isn't enabled.
     pub fn with_buffer(reader: R, compression: Compression) -> crate::Result<Self> {
-        match compression {
-            Compression::None => Ok(Self(DynReaderImpl::Uncompressed(reader))),
-            Compression::ZStd => Ok(Self(DynReaderImpl::ZStd(
+        Ok(Self(match compression {
+            Compression::None => DynReaderImpl::Uncompressed(reader),
+            Compression::ZStd => DynReaderImpl::ZStd(
                 ::zstd::stream::Decoder::with_buffer(reader)
                     .map_err(|e| crate::Error::io(e, "creating zstd decoder"))?,
-            ))),
-        }
+            ),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => DynReaderImpl::Gzip(flate2::bufread::MultiGzDecoder::new(reader)),
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => DynReaderImpl::Lz4(lz4_flex::frame::FrameDecoder::new(reader)),
+            #[cfg(feature = "xz")]
+            Compression::Xz => {
+                DynReaderImpl::Xz(liblzma::bufread::XzDecoder::new_multi_decoder(reader))
+            }
+            #[cfg(not(all(feature = "gzip", feature = "lz4", feature = "xz")))]
+            compression => return Err(crate::error::compression_feature_error(compression)),
+        }))
+    }
+
+    /// Creates a new [`DynReader`] from a buffered reader of Zstd-compressed data that
+    /// was compressed with `dictionary`, such as with
+    /// [`ZstdOptions::dictionary`](crate::encode::ZstdOptions::dictionary).
+    ///
+    /// # Errors
+    /// This function will return an error if it fails to create the zstd decoder, such
+    /// as when `dictionary` is invalid.
+    pub fn with_zstd_dictionary(reader: R, dictionary: &[u8]) -> crate::Result<Self> {
+        Ok(Self(DynReaderImpl::ZStd(
+            ::zstd::stream::Decoder::with_dictionary(reader, dictionary)
+                .map_err(|e| crate::Error::io(e, "creating zstd decoder"))?,
+        )))
     }
 
-    /// Creates a new [`DynReader`] from a buffered reader, inferring the compression.
+    /// Creates a new [`DynReader`] from a buffered reader, inferring the compression
+    /// from the magic number at the start of the input. Input that doesn't begin
+    /// with a known magic number is treated as uncompressed.
     ///
     /// # Errors
     /// This function will return an error if it fails to read from `reader` or creating
-    /// the zstd decoder fails.
+    /// the decompressor fails.
     pub fn inferred_with_buffer(mut reader: R) -> crate::Result<Self> {
         let first_bytes = reader
             .fill_buf()
             .map_err(|e| crate::Error::io(e, "creating buffer to infer encoding"))?;
-        if zstd::starts_with_prefix(first_bytes) {
-            Ok(Self(DynReaderImpl::ZStd(
-                ::zstd::stream::Decoder::with_buffer(reader)
-                    .map_err(|e| crate::Error::io(e, "creating zstd decoder"))?,
-            )))
-        } else {
-            Ok(Self(DynReaderImpl::Uncompressed(reader)))
-        }
+        let compression = infer_compression(first_bytes).unwrap_or(Compression::None);
+        Self::with_buffer(reader, compression)
     }
 
     /// Returns a mutable reference to the inner reader.
@@ -390,6 +434,12 @@ where
         match &mut self.0 {
             DynReaderImpl::Uncompressed(reader) => reader,
             DynReaderImpl::ZStd(reader) => reader.get_mut(),
+            #[cfg(feature = "gzip")]
+            DynReaderImpl::Gzip(reader) => reader.get_mut(),
+            #[cfg(feature = "lz4")]
+            DynReaderImpl::Lz4(reader) => reader.get_mut(),
+            #[cfg(feature = "xz")]
+            DynReaderImpl::Xz(reader) => reader.get_mut(),
         }
     }
 
@@ -398,6 +448,12 @@ where
         match &self.0 {
             DynReaderImpl::Uncompressed(reader) => reader,
             DynReaderImpl::ZStd(reader) => reader.get_ref(),
+            #[cfg(feature = "gzip")]
+            DynReaderImpl::Gzip(reader) => reader.get_ref(),
+            #[cfg(feature = "lz4")]
+            DynReaderImpl::Lz4(reader) => reader.get_ref(),
+            #[cfg(feature = "xz")]
+            DynReaderImpl::Xz(reader) => reader.get_ref(),
         }
     }
 }
@@ -430,6 +486,12 @@ where
         match &mut self.0 {
             DynReaderImpl::Uncompressed(r) => r.read(buf),
             DynReaderImpl::ZStd(r) => r.read(buf),
+            #[cfg(feature = "gzip")]
+            DynReaderImpl::Gzip(r) => r.read(buf),
+            #[cfg(feature = "lz4")]
+            DynReaderImpl::Lz4(r) => r.read(buf),
+            #[cfg(feature = "xz")]
+            DynReaderImpl::Xz(r) => r.read(buf),
         }
     }
 }
@@ -441,12 +503,36 @@ where
     fn buffer_slice(&self) -> &[u8] {
         match &self.0 {
             DynDecoderImpl::Dbn(decoder) => decoder.buffer_slice(),
-            DynDecoderImpl::ZstdDbn(decoder) => decoder.buffer_slice(),
+            DynDecoderImpl::CompressedDbn(decoder) => decoder.buffer_slice(),
             DynDecoderImpl::LegacyDbz(decoder) => decoder.buffer_slice(),
         }
     }
 }
 
+/// Magic number at the beginning of a gzip member.
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+/// Magic number at the beginning of an LZ4 frame.
+const LZ4_MAGIC_NUMBER: u32 = 0x184D2204;
+/// Magic bytes at the beginning of an xz stream.
+const XZ_MAGIC: [u8; 6] = [0xFD, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Infers the [`Compression`] of the input beginning with `bytes` from its magic
+/// number. Returns `None` if `bytes` doesn't start with the magic number of a supported
+/// compression format.
+pub(crate) fn infer_compression(bytes: &[u8]) -> Option<Compression> {
+    if zstd::starts_with_prefix(bytes) {
+        Some(Compression::ZStd)
+    } else if bytes.starts_with(&GZIP_MAGIC) {
+        Some(Compression::Gzip)
+    } else if bytes.len() >= 4 && u32::from_le_slice(bytes) == LZ4_MAGIC_NUMBER {
+        Some(Compression::Lz4)
+    } else if bytes.starts_with(&XZ_MAGIC) {
+        Some(Compression::Xz)
+    } else {
+        None
+    }
+}
+
 mod private {
     /// An implementation detail for the interaction between [`StreamingIterator`] and
     /// implementors of [`DecodeDbn`].
@@ -496,6 +582,8 @@ impl FromLittleEndianSlice for u16 {
 mod tests {
     use std::io::Read;
 
+    use rstest::rstest;
+
     use crate::enums::VersionUpgradePolicy;
 
     use super::*;
@@ -526,6 +614,156 @@ mod tests {
         }
     }
 
+    fn compress(buf: &[u8], compression: Compression) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut compressed = Vec::new();
+        let mut writer = crate::encode::DynWriter::new(&mut compressed, compression).unwrap();
+        writer.write_all(buf).unwrap();
+        drop(writer);
+        compressed
+    }
+
+    #[rstest]
+    #[case::zstd(Compression::ZStd)]
+    #[cfg_attr(feature = "gzip", case::gzip(Compression::Gzip))]
+    #[cfg_attr(feature = "lz4", case::lz4(Compression::Lz4))]
+    #[cfg_attr(feature = "xz", case::xz(Compression::Xz))]
+    fn test_dyn_reader_infers_compression(#[case] compression: Compression) {
+        let uncompressed = std::fs::read(format!("{TEST_DATA_PATH}/test_data.mbo.dbn")).unwrap();
+        let compressed = compress(&uncompressed, compression);
+        assert_ne!(compressed, uncompressed);
+        assert_eq!(infer_compression(&compressed), Some(compression));
+        let mut res = Vec::new();
+        DynReader::new_inferred(compressed.as_slice())
+            .unwrap()
+            .read_to_end(&mut res)
+            .unwrap();
+        assert_eq!(res, uncompressed);
+        let mut decoder =
+            DynDecoder::new_inferred(compressed.as_slice(), VersionUpgradePolicy::AsIs).unwrap();
+        let mut expected =
+            DynDecoder::new_inferred(uncompressed.as_slice(), VersionUpgradePolicy::AsIs).unwrap();
+        assert_eq!(decoder.metadata(), expected.metadata());
+        while let Some(rec) = expected.decode_record_ref().unwrap() {
+            let rec = rec.as_ref().to_vec();
+            assert_eq!(decoder.decode_record_ref().unwrap().unwrap().as_ref(), rec);
+        }
+        assert!(decoder.decode_record_ref().unwrap().is_none());
+    }
+
+    #[cfg(not(feature = "xz"))]
+    #[test]
+    fn test_dyn_reader_compression_feature_disabled() {
+        let res = DynReader::new([].as_slice(), Compression::Xz);
+        assert!(
+            matches!(res, Err(crate::Error::BadArgument { param_name, .. }) if param_name == "compression")
+        );
+        assert!(crate::encode::DynWriter::new(Vec::new(), Compression::Xz).is_err());
+    }
+
+    #[test]
+    fn test_infer_compression_uncompressed() {
+        let uncompressed = std::fs::read(format!("{TEST_DATA_PATH}/test_data.mbo.dbn")).unwrap();
+        assert_eq!(infer_compression(&uncompressed), None);
+        assert_eq!(infer_compression(&[]), None);
+    }
+
+    #[cfg(feature = "async")]
+    #[rstest]
+    #[case::zstd(Compression::ZStd)]
+    #[cfg_attr(feature = "gzip", case::gzip(Compression::Gzip))]
+    #[cfg_attr(feature = "lz4", case::lz4(Compression::Lz4))]
+    #[cfg_attr(feature = "xz", case::xz(Compression::Xz))]
+    #[tokio::test]
+    async fn test_async_dyn_reader_infers_compression(#[case] compression: Compression) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let uncompressed = std::fs::read(format!("{TEST_DATA_PATH}/test_data.mbo.dbn")).unwrap();
+        let mut writer = crate::encode::DynAsyncWriter::new(Vec::new(), compression).unwrap();
+        writer.write_all(&uncompressed).await.unwrap();
+        writer.shutdown().await.unwrap();
+        let compressed = std::mem::take(writer.get_mut());
+        assert_eq!(infer_compression(&compressed), Some(compression));
+        let mut res = Vec::new();
+        AsyncDynReader::new_inferred(compressed.as_slice())
+            .await
+            .unwrap()
+            .read_to_end(&mut res)
+            .await
+            .unwrap();
+        assert_eq!(res, uncompressed);
+    }
+
+    #[cfg(feature = "async")]
+    #[rstest]
+    #[tokio::test]
+    async fn test_async_dyn_decoder_matches_sync(
+        #[values(
+            "test_data.mbo.dbn",
+            "test_data.mbo.dbn.zst",
+            "test_data.mbp-1.v1.dbn.zst",
+            "test_data.definition.dbz"
+        )]
+        file_name: &str,
+        #[values(VersionUpgradePolicy::AsIs, VersionUpgradePolicy::Upgrade)]
+        upgrade_policy: VersionUpgradePolicy,
+    ) {
+        let path = format!("{TEST_DATA_PATH}/{file_name}");
+        let mut expected = DynDecoder::from_file(&path, upgrade_policy).unwrap();
+        let mut decoder = AsyncDynDecoder::from_file(&path, upgrade_policy)
+            .await
+            .unwrap();
+        assert_eq!(decoder.metadata(), expected.metadata());
+        let mut count = 0;
+        while let Some(rec) = expected.decode_record_ref().unwrap() {
+            let rec = rec.as_ref().to_vec();
+            assert_eq!(
+                decoder.decode_record_ref().await.unwrap().unwrap().as_ref(),
+                rec
+            );
+            count += 1;
+        }
+        assert!(count > 0);
+        assert!(decoder.decode_record_ref().await.unwrap().is_none());
+    }
+
+    #[cfg(all(feature = "async", feature = "gzip"))]
+    #[tokio::test]
+    async fn test_async_dyn_decoder_infers_compression() {
+        use tokio::io::AsyncWriteExt;
+
+        let uncompressed = std::fs::read(format!("{TEST_DATA_PATH}/test_data.mbo.dbn")).unwrap();
+        let mut writer = crate::encode::DynAsyncWriter::new(Vec::new(), Compression::Gzip).unwrap();
+        writer.write_all(&uncompressed).await.unwrap();
+        writer.shutdown().await.unwrap();
+        let compressed = std::mem::take(writer.get_mut());
+        let mut decoder =
+            AsyncDynDecoder::new_inferred(compressed.as_slice(), VersionUpgradePolicy::AsIs)
+                .await
+                .unwrap();
+        let mut expected =
+            DynDecoder::new_inferred(uncompressed.as_slice(), VersionUpgradePolicy::AsIs).unwrap();
+        assert_eq!(decoder.metadata(), expected.metadata());
+        let mbo = expected.decode_record::<crate::MboMsg>().unwrap().unwrap();
+        assert_eq!(
+            decoder
+                .decode_record::<crate::MboMsg>()
+                .await
+                .unwrap()
+                .unwrap(),
+            mbo
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_dyn_decoder_unknown_encoding() {
+        let res =
+            AsyncDynDecoder::new_inferred(b"not dbn".as_slice(), VersionUpgradePolicy::AsIs).await;
+        assert!(matches!(res, Err(e) if e.to_string().contains("unable to determine encoding")));
+    }
+
     #[test]
     fn test_detects_any_dbn_version_as_dbn() {
         let mut buf = Vec::new();
@@ -546,17 +784,232 @@ pub use self::{
         AsyncDecoder as AsyncDbnDecoder, AsyncMetadataDecoder as AsyncDbnMetadataDecoder,
         AsyncRecordDecoder as AsyncDbnRecordDecoder,
     },
-    r#async::DynReader as AsyncDynReader,
+    r#async::{DynDecoder as AsyncDynDecoder, DynReader as AsyncDynReader},
 };
 
 #[cfg(feature = "async")]
 mod r#async {
-    use std::pin::Pin;
+    use std::{path::Path, pin::Pin};
 
+    #[cfg(feature = "gzip")]
+    use async_compression::tokio::bufread::GzipDecoder;
+    #[cfg(feature = "lz4")]
+    use async_compression::tokio::bufread::Lz4Decoder;
+    #[cfg(feature = "xz")]
+    use async_compression::tokio::bufread::XzDecoder;
     use async_compression::tokio::bufread::ZstdDecoder;
-    use tokio::io::{self, BufReader};
+    use tokio::{
+        fs::File,
+        io::{self, AsyncBufReadExt, AsyncReadExt, BufReader},
+    };
 
-    use crate::enums::Compression;
+    use super::{
+        dbn, infer_compression, AsyncDbnDecoder, AsyncDbnRecordDecoder, AsyncDecodeRecordRef,
+        DbnMetadata, FromLittleEndianSlice,
+    };
+    use crate::{
+        enums::{Compression, VersionUpgradePolicy},
+        HasRType, Metadata, RecordRef,
+    };
+
+    /// An async decoder whose [`Compression`] is determined at runtime, either explicitly
+    /// or by peeking at the first few bytes. Legacy DBZ input is also supported.
+    pub struct DynDecoder<R>(DynDecoderImpl<R>)
+    where
+        R: io::AsyncReadExt + Unpin;
+
+    enum DynDecoderImpl<R>
+    where
+        R: io::AsyncReadExt + Unpin,
+    {
+        Dbn(AsyncDbnDecoder<DynReader<R>>),
+        LegacyDbz {
+            metadata: Metadata,
+            decoder: AsyncDbnRecordDecoder<ZstdDecoder<BufReader<R>>>,
+        },
+    }
+
+    impl<R> DynDecoder<R>
+    where
+        R: io::AsyncReadExt + Unpin,
+    {
+        /// Creates a new [`DynDecoder`] from a reader, with the specified `compression`. It
+        /// will decode records from previous DBN versions according to `upgrade_policy`.
+        ///
+        /// # Errors
+        /// This function will return an error if it fails to parse the metadata.
+        pub async fn new(
+            reader: R,
+            compression: Compression,
+            upgrade_policy: VersionUpgradePolicy,
+        ) -> crate::Result<Self> {
+            Self::with_buffer(BufReader::new(reader), compression, upgrade_policy).await
+        }
+
+        /// Creates a new [`DynDecoder`] from a reader, inferring the encoding and
+        /// compression. It will decode records from previous DBN versions according to
+        /// `upgrade_policy`.
+        ///
+        /// # Errors
+        /// This function will return an error if it is unable to determine
+        /// the encoding of `reader` or it fails to parse the metadata.
+        pub async fn new_inferred(
+            reader: R,
+            upgrade_policy: VersionUpgradePolicy,
+        ) -> crate::Result<Self> {
+            Self::inferred_with_buffer(BufReader::new(reader), upgrade_policy).await
+        }
+
+        /// Creates a new [`DynDecoder`] from a buffered reader with the specified
+        /// `compression`. It will decode records from previous DBN versions according to
+        /// `upgrade_policy`.
+        ///
+        /// # Errors
+        /// This function will return an error if it fails to parse the metadata.
+        pub async fn with_buffer(
+            reader: BufReader<R>,
+            compression: Compression,
+            upgrade_policy: VersionUpgradePolicy,
+        ) -> crate::Result<Self> {
+            Ok(Self(DynDecoderImpl::Dbn(
+                AsyncDbnDecoder::with_upgrade_policy(
+                    DynReader::with_buffer(reader, compression)?,
+                    upgrade_policy,
+                )
+                .await?,
+            )))
+        }
+
+        /// Creates a new [`DynDecoder`] from a buffered reader, inferring the encoding
+        /// and compression. It will decode records from previous DBN versions according
+        /// to `upgrade_policy`.
+        ///
+        /// # Errors
+        /// This function will return an error if it is unable to determine
+        /// the encoding of `reader` or it fails to parse the metadata.
+        pub async fn inferred_with_buffer(
+            mut reader: BufReader<R>,
+            upgrade_policy: VersionUpgradePolicy,
+        ) -> crate::Result<Self> {
+            let first_bytes = reader
+                .fill_buf()
+                .await
+                .map_err(|e| crate::Error::io(e, "creating buffer to infer encoding"))?;
+            #[allow(deprecated)]
+            if super::dbz::starts_with_prefix(first_bytes) {
+                Self::legacy_dbz(reader, upgrade_policy).await
+            } else if dbn::starts_with_prefix(first_bytes) {
+                Self::with_buffer(reader, Compression::None, upgrade_policy).await
+            } else if let Some(compression) = infer_compression(first_bytes) {
+                Self::with_buffer(reader, compression, upgrade_policy).await
+            } else {
+                Err(crate::Error::decode("unable to determine encoding"))
+            }
+        }
+
+        /// Decodes the metadata from the Zstandard skippable frame at the start of
+        /// legacy DBZ input, followed by records in the Zstandard-compressed remainder.
+        async fn legacy_dbz(
+            mut reader: BufReader<R>,
+            upgrade_policy: VersionUpgradePolicy,
+        ) -> crate::Result<Self> {
+            let io_err = |e| crate::Error::io(e, "reading metadata");
+            let mut frame = vec![0; 2 * std::mem::size_of::<u32>()];
+            reader.read_exact(&mut frame).await.map_err(io_err)?;
+            let frame_size = u32::from_le_slice(&frame[4..]) as usize;
+            frame.resize(frame.len() + frame_size, 0);
+            reader.read_exact(&mut frame[8..]).await.map_err(io_err)?;
+            #[allow(deprecated)]
+            let mut metadata = super::dbz::MetadataDecoder::read(&mut frame.as_slice())?;
+            metadata.upgrade(upgrade_policy);
+            let mut zstd_decoder = ZstdDecoder::new(reader);
+            zstd_decoder.multiple_members(true);
+            Ok(Self(DynDecoderImpl::LegacyDbz {
+                metadata,
+                // DBZ records are the same as DBN version 1
+                decoder: AsyncDbnRecordDecoder::with_version(zstd_decoder, 1, upgrade_policy)?,
+            }))
+        }
+
+        /// Tries to decode a single record and returns a reference to the record that
+        /// lasts until the next method call. Returns `Ok(None)` if the input has been
+        /// exhausted.
+        ///
+        /// # Errors
+        /// This function returns an error if the underlying reader returns an error. If
+        /// the next record is of a different type than `T`, this function returns a
+        /// [`Error::Conversion`](crate::Error::Conversion) error.
+        ///
+        /// # Cancel safety
+        /// This method is cancel safe. It can be used within a `tokio::select!` statement
+        /// without the potential for corrupting the input stream.
+        pub async fn decode_record<'a, T: HasRType + 'a>(
+            &'a mut self,
+        ) -> crate::Result<Option<&'a T>> {
+            match &mut self.0 {
+                DynDecoderImpl::Dbn(decoder) => decoder.decode_record().await,
+                DynDecoderImpl::LegacyDbz { decoder, .. } => decoder.decode().await,
+            }
+        }
+    }
+
+    impl DynDecoder<File> {
+        /// Creates a new [`DynDecoder`] from the file at `path`. It will decode records
+        /// from previous DBN versions according to `upgrade_policy`.
+        ///
+        /// # Errors
+        /// This function will return an error if the file doesn't exist, it is unable to
+        /// determine the encoding of the file or it fails to parse the metadata.
+        pub async fn from_file(
+            path: impl AsRef<Path>,
+            upgrade_policy: VersionUpgradePolicy,
+        ) -> crate::Result<Self> {
+            let file = File::open(path.as_ref()).await.map_err(|e| {
+                crate::Error::io(
+                    e,
+                    format!(
+                        "opening file to decode at path '{}'",
+                        path.as_ref().display()
+                    ),
+                )
+            })?;
+            Self::new_inferred(file, upgrade_policy).await
+        }
+    }
+
+    impl<R> AsyncDecodeRecordRef for DynDecoder<R>
+    where
+        R: io::AsyncReadExt + Unpin + Send,
+    {
+        /// # Cancel safety
+        /// This method is cancel safe. It can be used within a `tokio::select!`
+        /// statement without the potential for corrupting the input stream.
+        async fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
+            match &mut self.0 {
+                DynDecoderImpl::Dbn(decoder) => decoder.decode_record_ref().await,
+                DynDecoderImpl::LegacyDbz { decoder, .. } => decoder.decode_ref().await,
+            }
+        }
+    }
+
+    impl<R> DbnMetadata for DynDecoder<R>
+    where
+        R: io::AsyncReadExt + Unpin,
+    {
+        fn metadata(&self) -> &Metadata {
+            match &self.0 {
+                DynDecoderImpl::Dbn(decoder) => decoder.metadata(),
+                DynDecoderImpl::LegacyDbz { metadata, .. } => metadata,
+            }
+        }
+
+        fn metadata_mut(&mut self) -> &mut Metadata {
+            match &mut self.0 {
+                DynDecoderImpl::Dbn(decoder) => decoder.metadata_mut(),
+                DynDecoderImpl::LegacyDbz { metadata, .. } => metadata,
+            }
+        }
+    }
 
     /// A type for runtime polymorphism on compressed and uncompressed input.
     pub struct DynReader<R>(DynReaderImpl<R>)
@@ -567,8 +1020,14 @@ mod r#async {
     where
         R: io::AsyncReadExt + Unpin,
     {
-        Uncompressed(R),
+        Uncompressed(BufReader<R>),
         ZStd(ZstdDecoder<BufReader<R>>),
+        #[cfg(feature = "gzip")]
+        Gzip(GzipDecoder<BufReader<R>>),
+        #[cfg(feature = "lz4")]
+        Lz4(Lz4Decoder<BufReader<R>>),
+        #[cfg(feature = "xz")]
+        Xz(XzDecoder<BufReader<R>>),
     }
 
     impl<R> DynReader<R>
@@ -576,11 +1035,74 @@ mod r#async {
         R: io::AsyncReadExt + Unpin,
     {
         /// Creates a new instance of [`DynReader`] with the specified `compression`.
-        pub fn new(reader: R, compression: Compression) -> Self {
-            Self(match compression {
+        ///
+        /// # Errors
+        /// This function will return an error if the feature for `compression` isn't
+        /// enabled.
+        pub fn new(reader: R, compression: Compression) -> crate::Result<Self> {
+            Self::with_buffer(BufReader::new(reader), compression)
+        }
+
+        /// Creates a new instance of [`DynReader`], inferring the compression from the
+        /// magic number at the start of the input. Input that doesn't begin with a
+        /// known magic number is treated as uncompressed.
+        ///
+        /// # Errors
+        /// This function will return an error if it fails to read from `reader`.
+        pub async fn new_inferred(reader: R) -> crate::Result<Self> {
+            Self::inferred_with_buffer(BufReader::new(reader)).await
+        }
+
+        /// Creates a new instance of [`DynReader`] from a buffered reader with the
+        /// specified `compression`.
+        ///
+        /// # Errors
+        /// This function will return an error if the feature for `compression` isn't
+        /// enabled.
+        pub fn with_buffer(reader: BufReader<R>, compression: Compression) -> crate::Result<Self> {
+            Ok(Self(match compression {
                 Compression::None => DynReaderImpl::Uncompressed(reader),
-                Compression::ZStd => DynReaderImpl::ZStd(ZstdDecoder::new(BufReader::new(reader))),
-            })
+                Compression::ZStd => {
+                    let mut decoder = ZstdDecoder::new(reader);
+                    decoder.multiple_members(true);
+                    DynReaderImpl::ZStd(decoder)
+                }
+                #[cfg(feature = "gzip")]
+                Compression::Gzip => {
+                    let mut decoder = GzipDecoder::new(reader);
+                    decoder.multiple_members(true);
+                    DynReaderImpl::Gzip(decoder)
+                }
+                #[cfg(feature = "lz4")]
+                Compression::Lz4 => {
+                    let mut decoder = Lz4Decoder::new(reader);
+                    decoder.multiple_members(true);
+                    DynReaderImpl::Lz4(decoder)
+                }
+                #[cfg(feature = "xz")]
+                Compression::Xz => {
+                    let mut decoder = XzDecoder::new(reader);
+                    decoder.multiple_members(true);
+                    DynReaderImpl::Xz(decoder)
+                }
+                #[cfg(not(all(feature = "gzip", feature = "lz4", feature = "xz")))]
+                compression => return Err(crate::error::compression_feature_error(compression)),
+            }))
+        }
+
+        /// Creates a new instance of [`DynReader`] from a buffered reader, inferring
+        /// the compression from the magic number at the start of the input.
+        ///
+        /// # Errors
+        /// This function will return an error if it fails to read from `reader` or the
+        /// feature for the inferred compression isn't enabled.
+        pub async fn inferred_with_buffer(mut reader: BufReader<R>) -> crate::Result<Self> {
+            let first_bytes = reader
+                .fill_buf()
+                .await
+                .map_err(|e| crate::Error::io(e, "creating buffer to infer encoding"))?;
+            let compression = infer_compression(first_bytes).unwrap_or(Compression::None);
+            Self::with_buffer(reader, compression)
         }
     }
 
@@ -598,6 +1120,12 @@ mod r#async {
                     io::AsyncRead::poll_read(Pin::new(reader), cx, buf)
                 }
                 DynReaderImpl::ZStd(dec) => io::AsyncRead::poll_read(Pin::new(dec), cx, buf),
+                #[cfg(feature = "gzip")]
+                DynReaderImpl::Gzip(dec) => io::AsyncRead::poll_read(Pin::new(dec), cx, buf),
+                #[cfg(feature = "lz4")]
+                DynReaderImpl::Lz4(dec) => io::AsyncRead::poll_read(Pin::new(dec), cx, buf),
+                #[cfg(feature = "xz")]
+                DynReaderImpl::Xz(dec) => io::AsyncRead::poll_read(Pin::new(dec), cx, buf),
             }
         }
     }
@@ -8,7 +8,7 @@ use tokio::{
 
 use crate::{
     compat,
-    decode::{FromLittleEndianSlice, VersionUpgradePolicy},
+    decode::{AsyncDecodeRecordRef, FromLittleEndianSlice, VersionUpgradePolicy},
     HasRType, Metadata, Record, RecordHeader, RecordRef, Result, DBN_VERSION, METADATA_FIXED_LEN,
 };
 
@@ -99,6 +99,11 @@ where
         &self.metadata
     }
 
+    /// Returns a mutable reference to the decoded metadata.
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
     /// Sets the behavior for decoding DBN data of previous versions.
     pub fn set_upgrade_policy(&mut self, upgrade_policy: VersionUpgradePolicy) {
         self.metadata.upgrade(upgrade_policy);
@@ -117,7 +122,7 @@ where
     /// # Cancel safety
     /// This method is cancel safe. It can be used within a `tokio::select!` statement
     /// without the potential for corrupting the input stream.
-    pub async fn decode_record<'a, T: HasRType + 'a>(&'a mut self) -> Result<Option<&T>> {
+    pub async fn decode_record<'a, T: HasRType + 'a>(&'a mut self) -> Result<Option<&'a T>> {
         self.decoder.decode().await
     }
 
@@ -132,7 +137,7 @@ where
     /// # Cancel safety
     /// This method is cancel safe. It can be used within a `tokio::select!` statement
     /// without the potential for corrupting the input stream.
-    pub async fn decode_record_ref(&mut self) -> Result<Option<RecordRef>> {
+    pub async fn decode_record_ref(&mut self) -> Result<Option<RecordRef<'_>>> {
         self.decoder.decode_ref().await
     }
 }
@@ -220,6 +225,18 @@ impl Decoder<ZstdDecoder<BufReader<File>>> {
     }
 }
 
+impl<R> AsyncDecodeRecordRef for Decoder<R>
+where
+    R: io::AsyncReadExt + Unpin + Send,
+{
+    /// # Cancel safety
+    /// This method is cancel safe. It can be used within a `tokio::select!`
+    /// statement without the potential for corrupting the input stream.
+    async fn decode_record_ref(&mut self) -> Result<Option<RecordRef<'_>>> {
+        self.decoder.decode_ref().await
+    }
+}
+
 /// An async decoder for files and streams of Databento Binary Encoding (DBN) records.
 pub struct RecordDecoder<R>
 where
@@ -308,7 +325,7 @@ where
     /// # Cancel safety
     /// This method is cancel safe. It can be used within a `tokio::select!` statement
     /// without the potential for corrupting the input stream.
-    pub async fn decode<'a, T: HasRType + 'a>(&'a mut self) -> Result<Option<&T>> {
+    pub async fn decode<'a, T: HasRType + 'a>(&'a mut self) -> Result<Option<&'a T>> {
         let rec_ref = self.decode_ref().await?;
         if let Some(rec_ref) = rec_ref {
             rec_ref
@@ -337,7 +354,7 @@ where
     /// # Cancel safety
     /// This method is cancel safe. It can be used within a `tokio::select!` statement
     /// without the potential for corrupting the input stream.
-    pub async fn decode_ref(&mut self) -> Result<Option<RecordRef>> {
+    pub async fn decode_ref(&mut self) -> Result<Option<RecordRef<'_>>> {
         let io_err = |e| crate::Error::io(e, "decoding record reference");
         loop {
             // maybe read more into buffer
@@ -421,6 +438,18 @@ where
     }
 }
 
+impl<R> AsyncDecodeRecordRef for RecordDecoder<R>
+where
+    R: io::AsyncReadExt + Unpin + Send,
+{
+    /// # Cancel safety
+    /// This method is cancel safe. It can be used within a `tokio::select!`
+    /// statement without the potential for corrupting the input stream.
+    async fn decode_record_ref(&mut self) -> Result<Option<RecordRef<'_>>> {
+        self.decode_ref().await
+    }
+}
+
 impl<R> From<MetadataDecoder<R>> for RecordDecoder<R>
 where
     R: io::AsyncReadExt + Unpin,
@@ -843,6 +872,26 @@ mod tests {
         assert_eq!(count, 8);
     }
 
+    async fn count_records(mut decoder: impl AsyncDecodeRecordRef) -> usize {
+        let mut count = 0;
+        while decoder.decode_record_ref().await.unwrap().is_some() {
+            count += 1;
+        }
+        count
+    }
+
+    #[tokio::test]
+    async fn test_decode_record_ref_trait() {
+        let path = format!("{TEST_DATA_PATH}/test_data.mbo.dbn.zst");
+        let decoder = Decoder::from_zstd_file(&path).await.unwrap();
+        assert_eq!(count_records(decoder).await, 2);
+        let mut reader = BufReader::new(tokio::fs::File::open(&path).await.unwrap());
+        let mut metadata_decoder = MetadataDecoder::with_zstd_buffer(&mut reader);
+        metadata_decoder.decode().await.unwrap();
+        let record_decoder = RecordDecoder::from(metadata_decoder);
+        assert_eq!(count_records(record_decoder).await, 2);
+    }
+
     #[tokio::test]
     async fn test_decode_upgrade() -> crate::Result<()> {
         let mut decoder = Decoder::with_upgrade_policy(
@@ -57,7 +57,22 @@ where
         mut reader: R,
         upgrade_policy: VersionUpgradePolicy,
     ) -> crate::Result<Self> {
-        let mut metadata = MetadataDecoder::new(&mut reader).decode()?;
+        let metadata = MetadataDecoder::new(&mut reader).decode()?;
+        Self::with_metadata(reader, metadata, upgrade_policy)
+    }
+
+    /// Creates a new DBN [`Decoder`] from `reader` containing only records, i.e. a
+    /// DBN fragment, and `metadata` that was stored separately. It will decode records
+    /// according to the version in `metadata` and `upgrade_policy`.
+    ///
+    /// # Errors
+    /// This function will return an error if the version in `metadata` is newer than
+    /// the highest supported version.
+    pub fn with_metadata(
+        reader: R,
+        mut metadata: Metadata,
+        upgrade_policy: VersionUpgradePolicy,
+    ) -> crate::Result<Self> {
         // need to get the original version
         let version = metadata.version;
         metadata.upgrade(upgrade_policy);
@@ -162,7 +177,7 @@ impl<R> DecodeRecordRef for Decoder<R>
 where
     R: io::Read,
 {
-    fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef>> {
+    fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
         self.decoder.decode_record_ref()
     }
 }
@@ -318,7 +333,7 @@ where
     /// This function returns an error if the underlying reader returns an
     /// error of a kind other than `io::ErrorKind::UnexpectedEof` upon reading.
     /// It will also return an error if it encounters an invalid record.
-    pub fn decode_ref(&mut self) -> crate::Result<Option<RecordRef>> {
+    pub fn decode_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
         let io_err = |e| crate::Error::io(e, "decoding record reference");
         if let Err(err) = self.reader.read_exact(&mut self.read_buffer[..1]) {
             return silence_eof_error(err).map_err(io_err);
@@ -351,7 +366,7 @@ impl<R> DecodeRecordRef for RecordDecoder<R>
 where
     R: io::Read,
 {
-    fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef>> {
+    fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
         self.decode_ref()
     }
 }
@@ -654,6 +669,7 @@ pub(crate) fn decode_iso8601(raw: u32) -> Result<time::Date, String> {
 mod tests {
     use std::fs::File;
 
+    use proptest::{collection::vec, prelude::*};
     use rstest::rstest;
 
     use super::*;
@@ -663,9 +679,12 @@ mod tests {
         decode::{tests::TEST_DATA_PATH, DynReader},
         encode::{
             dbn::Encoder, DbnEncodable, DbnRecordEncoder, DynWriter, EncodeDbn, EncodeRecord,
+            EncodeRecordRef,
         },
-        rtype, Compression, Error, ErrorMsg, ImbalanceMsg, InstrumentDefMsg, MboMsg, Mbp10Msg,
-        Mbp1Msg, MetadataBuilder, OhlcvMsg, RecordHeader, Result, StatMsg, TbboMsg, TradeMsg,
+        rtype,
+        testing::synthetic_data,
+        Compression, Error, ErrorMsg, ImbalanceMsg, InstrumentDefMsg, MboMsg, Mbp10Msg, Mbp1Msg,
+        MetadataBuilder, OhlcvMsg, RecordEnum, RecordHeader, Result, StatMsg, TbboMsg, TradeMsg,
         WithTsOut, SYMBOL_CSTR_LEN,
     };
 
@@ -1046,4 +1065,46 @@ mod tests {
         decoder.decode_records::<InstrumentDefMsg>()?;
         Ok(())
     }
+
+    proptest! {
+        #[test]
+        fn prop_dbn_identity_records(
+            metadata in any::<Metadata>(),
+            recs in vec(any::<RecordEnum>(), 0..16),
+            compression in prop_oneof![Just(Compression::None), Just(Compression::ZStd)],
+        ) {
+            // Upgrading would alter records
+            let metadata = Metadata { version: crate::DBN_VERSION, symbol_cstr_len: SYMBOL_CSTR_LEN, ..metadata };
+            let mut buffer = Vec::new();
+            let mut encoder = Encoder::new(DynWriter::new(&mut buffer, compression)?, &metadata)?;
+            for rec in recs.iter() {
+                encoder.encode_record_ref(RecordRef::from(rec))?;
+            }
+            drop(encoder);
+            let mut decoder = Decoder::new(DynReader::inferred_with_buffer(buffer.as_slice())?)?;
+            prop_assert_eq!(decoder.metadata(), &metadata);
+            for rec in recs.iter() {
+                let exp = RecordRef::from(rec);
+                let res = decoder.decode_record_ref()?.unwrap();
+                prop_assert_eq!(res.as_ref(), exp.as_ref());
+            }
+            prop_assert!(decoder.decode_record_ref()?.is_none());
+        }
+
+        #[test]
+        fn prop_dbn_identity_synthetic(data in synthetic_data(0..1_000)) {
+            let mut buffer = Vec::new();
+            Encoder::with_zstd(&mut buffer, &data.metadata(Schema::Mbo))?
+                .encode_records(data.mbo.as_slice())?;
+            let decoder = Decoder::with_zstd(buffer.as_slice())?;
+            prop_assert_eq!(decoder.metadata(), &data.metadata(Schema::Mbo));
+            prop_assert_eq!(&decoder.decode_records::<MboMsg>()?, &data.mbo);
+
+            let mut buffer = Vec::new();
+            Encoder::new(&mut buffer, &data.metadata(Schema::Trades))?
+                .encode_records(data.trades.as_slice())?;
+            let decoder = Decoder::new(buffer.as_slice())?;
+            prop_assert_eq!(decoder.decode_records::<TradeMsg>()?, data.trades);
+        }
+    }
 }
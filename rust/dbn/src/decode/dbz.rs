@@ -97,7 +97,7 @@ impl<R: io::BufRead> Decoder<R> {
 }
 
 impl<R: io::BufRead> DecodeRecordRef for Decoder<R> {
-    fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef>> {
+    fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
         let io_err = |e| crate::Error::io(e, "decoding record reference");
         if let Err(err) = self.reader.read_exact(&mut self.read_buffer[..1]) {
             return silence_eof_error(err).map_err(io_err);
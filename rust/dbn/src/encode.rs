@@ -5,12 +5,31 @@ pub mod dbn;
 mod dyn_encoder;
 mod dyn_writer;
 pub mod json;
+mod layout;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "async")]
+mod zstd_async_writer;
 
-use std::{fmt, io, num::NonZeroU64};
+use std::{fmt, io, mem, num::NonZeroU64};
 
 use streaming_iterator::StreamingIterator;
 
 // Re-exports
+#[cfg(feature = "parquet")]
+pub use self::parquet::Encoder as ParquetEncoder;
+#[cfg(feature = "async")]
+pub use self::{
+    csv::AsyncEncoder as AsyncCsvEncoder,
+    dbn::{
+        AsyncEncoder as AsyncDbnEncoder, AsyncMetadataEncoder as AsyncDbnMetadataEncoder,
+        AsyncRecordEncoder as AsyncDbnRecordEncoder,
+    },
+    dyn_encoder::{DynAsyncEncoder, DynAsyncEncoderBuilder},
+    dyn_writer::DynAsyncWriter,
+    json::AsyncEncoder as AsyncJsonEncoder,
+    zstd_async_writer::ZstdAsyncWriter,
+};
 pub use self::{
     csv::Encoder as CsvEncoder,
     dbn::{
@@ -21,20 +40,11 @@ pub use self::{
     dyn_writer::DynWriter,
     json::Encoder as JsonEncoder,
 };
-#[cfg(feature = "async")]
-pub use self::{
-    dbn::{
-        AsyncEncoder as AsyncDbnEncoder, AsyncMetadataEncoder as AsyncDbnMetadataEncoder,
-        AsyncRecordEncoder as AsyncDbnRecordEncoder,
-    },
-    dyn_writer::DynAsyncWriter,
-    json::AsyncEncoder as AsyncJsonEncoder,
-};
 
 use crate::{
     decode::{DbnMetadata, DecodeRecordRef},
-    rtype_method_dispatch, rtype_ts_out_method_dispatch, Error, HasRType, Record, RecordRef,
-    Result,
+    rtype_dispatch, rtype_method_dispatch, rtype_ts_out_method_dispatch, Error, HasRType, Record,
+    RecordRef, Result,
 };
 
 use self::{csv::serialize::CsvSerialize, json::serialize::JsonSerialize};
@@ -202,13 +212,87 @@ pub trait EncodeRecordTextExt: EncodeRecord + EncodeRecordRef {
 /// The default Zstandard compression level used.
 pub const ZSTD_COMPRESSION_LEVEL: i32 = 0;
 
-fn zstd_encoder<'a, W: io::Write>(writer: W) -> Result<zstd::stream::AutoFinishEncoder<'a, W>> {
-    let mut zstd_encoder = zstd::Encoder::new(writer, ZSTD_COMPRESSION_LEVEL)
-        .map_err(|e| Error::io(e, "creating zstd encoder"))?;
+/// Settings for Zstandard compression. The default matches the compression used by
+/// [`DbnEncoder::with_zstd()`] and [`DynWriter::new()`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ZstdOptions {
+    /// The compression level. Higher levels compress better at the expense of speed.
+    /// `0` selects Zstandard's default level. Defaults to [`ZSTD_COMPRESSION_LEVEL`].
+    pub level: i32,
+    /// The number of background threads to compress with. `0` compresses on the
+    /// calling thread. Other values require the `zstdmt` feature.
+    pub threads: u32,
+    /// Whether to enable long-distance matching, which improves the compression of
+    /// large inputs with repetition far apart, such as archives.
+    pub long_distance_matching: bool,
+    /// A dictionary trained on similar data, which improves the compression of small
+    /// outputs. The same dictionary is required for decompression.
+    pub dictionary: Option<Vec<u8>>,
+}
+
+fn zstd_encoder<'a, W: io::Write>(
+    writer: W,
+    options: &ZstdOptions,
+) -> Result<zstd::stream::AutoFinishEncoder<'a, W>> {
+    Ok(unfinished_zstd_encoder(writer, options)?.auto_finish())
+}
+
+/// Like [`zstd_encoder`] but the caller must call `finish()`, allowing for handling
+/// errors when writing the end of the frame.
+fn unfinished_zstd_encoder<'a, W: io::Write>(
+    writer: W,
+    options: &ZstdOptions,
+) -> Result<zstd::Encoder<'a, W>> {
+    let mut zstd_encoder = if let Some(dictionary) = options.dictionary.as_deref() {
+        zstd::Encoder::with_dictionary(writer, options.level, dictionary)
+    } else {
+        zstd::Encoder::new(writer, options.level)
+    }
+    .map_err(|e| Error::io(e, "creating zstd encoder"))?;
     zstd_encoder
         .include_checksum(true)
         .map_err(|e| Error::io(e, "setting zstd checksum"))?;
-    Ok(zstd_encoder.auto_finish())
+    if options.threads > 0 {
+        #[cfg(feature = "zstdmt")]
+        zstd_encoder
+            .multithread(options.threads)
+            .map_err(|e| Error::io(e, "setting zstd threads"))?;
+        #[cfg(not(feature = "zstdmt"))]
+        return Err(Error::BadArgument {
+            param_name: "options".to_owned(),
+            desc: "Zstd compression with threads requires the `zstdmt` feature".to_owned(),
+        });
+    }
+    if options.long_distance_matching {
+        zstd_encoder
+            .long_distance_matching(true)
+            .map_err(|e| Error::io(e, "enabling zstd long-distance matching"))?;
+    }
+    Ok(zstd_encoder)
+}
+
+pub(crate) fn record_ref<R: DbnEncodable>(record: &R) -> RecordRef<'_> {
+    // Safety: `record` is a DBN record whose length is encoded in its header
+    unsafe { RecordRef::new(record.as_ref()) }
+}
+
+/// Returns the `ts_out` value of `record` if `R` is a [`WithTsOut`](crate::WithTsOut)
+/// wrapper, which is detected by comparing its size to the size of the type of the
+/// inner record.
+pub(crate) fn ts_out_of<R: DbnEncodable>(record: &R) -> Option<u64> {
+    fn size_of<T: HasRType>(_: &T) -> usize {
+        mem::size_of::<T>()
+    }
+
+    let size = rtype_dispatch!(record_ref(record), size_of).ok()?;
+    (mem::size_of::<R>() == size + mem::size_of::<u64>()).then(|| ts_out_from_end(record.as_ref()))
+}
+
+/// Reads a `ts_out` value from the last 8 bytes of `bytes`.
+pub(crate) fn ts_out_from_end(bytes: &[u8]) -> u64 {
+    let mut ts_out = [0; 8];
+    ts_out.copy_from_slice(&bytes[bytes.len() - mem::size_of::<u64>()..]);
+    u64::from_le_bytes(ts_out)
 }
 
 #[cfg(test)]
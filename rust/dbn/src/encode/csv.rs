@@ -1,6 +1,12 @@
 //! Encoding of DBN records into comma-separated values (CSV).
 
-pub(crate) mod serialize;
+mod projection;
+#[doc(hidden)]
+pub mod serialize;
 mod sync;
 
-pub use sync::{Encoder, EncoderBuilder};
+pub use sync::{Encoder, EncoderBuilder, LineTerminator, QuoteStyle};
+#[cfg(feature = "async")]
+mod r#async;
+#[cfg(feature = "async")]
+pub use r#async::{Encoder as AsyncEncoder, EncoderBuilder as AsyncEncoderBuilder};
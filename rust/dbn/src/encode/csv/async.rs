@@ -0,0 +1,515 @@
+use std::{
+    io::Write,
+    mem,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use tokio::io;
+
+use super::{sync, LineTerminator, QuoteStyle};
+use crate::{
+    encode::{DbnEncodable, EncodeRecord, EncodeRecordRef, EncodeRecordTextExt},
+    Error, RType, RecordRef, Result, Schema,
+};
+
+/// Type for encoding files and streams of DBN records in CSV.
+///
+/// Records are serialized with the synchronous [`Encoder`](super::Encoder) into an
+/// in-memory buffer, which is then written to the underlying writer.
+///
+/// Note that encoding [`Metadata`](crate::Metadata) in CSV is not supported.
+pub struct Encoder<W>
+where
+    W: io::AsyncWriteExt + Unpin,
+{
+    writer: W,
+    encoder: sync::Encoder<Buffer>,
+    buffer: Buffer,
+    // Reused between writes to avoid reallocating
+    pending: Vec<u8>,
+}
+
+/// Helper for constructing an async CSV [`Encoder`].
+///
+/// It has the same options as the synchronous [`EncoderBuilder`](super::EncoderBuilder).
+pub struct EncoderBuilder<W>
+where
+    W: io::AsyncWriteExt + Unpin,
+{
+    writer: W,
+    buffer: Buffer,
+    builder: sync::EncoderBuilder<Buffer>,
+}
+
+/// The output of the synchronous encoder, shared with the async encoder so it can be
+/// drained after each write.
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W> EncoderBuilder<W>
+where
+    W: io::AsyncWriteExt + Unpin,
+{
+    /// Creates a new async CSV encoder builder.
+    pub fn new(writer: W) -> Self {
+        let buffer = Buffer::default();
+        Self {
+            writer,
+            builder: sync::EncoderBuilder::new(buffer.clone()),
+            buffer,
+        }
+    }
+
+    /// Sets whether the CSV encoder will serialize price fields as a decimal. Defaults
+    /// to `false`.
+    pub fn use_pretty_px(mut self, use_pretty_px: bool) -> Self {
+        self.builder = self.builder.use_pretty_px(use_pretty_px);
+        self
+    }
+
+    /// Sets whether the CSV encoder will serialize timestamp fields as ISO8601 datetime
+    /// strings. Defaults to `false`.
+    pub fn use_pretty_ts(mut self, use_pretty_ts: bool) -> Self {
+        self.builder = self.builder.use_pretty_ts(use_pretty_ts);
+        self
+    }
+
+    /// Sets whether the CSV encoder will write a header row when it's created.
+    /// Defaults to `true`. If `false`, a header row can still be written with
+    /// [`Encoder::encode_header()`] or [`Encoder::encode_header_for_schema()`].
+    pub fn write_header(mut self, write_header: bool) -> Self {
+        self.builder = self.builder.write_header(write_header);
+        self
+    }
+
+    /// Sets the schema that will be encoded. This is required if writing a header row.
+    ///
+    /// # Errors
+    /// This function returns an error if `schema` is `None`. It accepts to an `Option` to
+    /// more easily work with [`Metadata::schema`](crate::Metadata::schema).
+    pub fn schema(mut self, schema: Option<Schema>) -> crate::Result<Self> {
+        self.builder = self.builder.schema(schema)?;
+        Ok(self)
+    }
+
+    /// Sets whether to add a header field "ts_out". Defaults to `false`.
+    pub fn ts_out(mut self, ts_out: bool) -> Self {
+        self.builder = self.builder.ts_out(ts_out);
+        self
+    }
+
+    /// Sets whether to add a header field "symbol". Defaults to `false`.
+    pub fn with_symbol(mut self, with_symbol: bool) -> Self {
+        self.builder = self.builder.with_symbol(with_symbol);
+        self
+    }
+
+    /// Sets the field delimiter, e.g. `b'\t'` or `b'|'`. Defaults to `b','`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.builder = self.builder.delimiter(delimiter);
+        self
+    }
+
+    /// Sets when fields are quoted. Defaults to [`QuoteStyle::Necessary`].
+    pub fn quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.builder = self.builder.quote_style(quote_style);
+        self
+    }
+
+    /// Sets the line terminator. Defaults to [`LineTerminator::Lf`].
+    pub fn line_terminator(mut self, line_terminator: LineTerminator) -> Self {
+        self.builder = self.builder.line_terminator(line_terminator);
+        self
+    }
+
+    /// Sets the fields to encode in the given order. See
+    /// [`EncoderBuilder::fields()`](super::EncoderBuilder::fields).
+    pub fn fields<S: Into<String>>(mut self, fields: impl IntoIterator<Item = S>) -> Self {
+        self.builder = self.builder.fields(fields);
+        self
+    }
+
+    /// Renames the header field for `field` to `header`. Encoding a record without
+    /// `field` will return an error.
+    pub fn rename_field(mut self, field: impl Into<String>, header: impl Into<String>) -> Self {
+        self.builder = self.builder.rename_field(field, header);
+        self
+    }
+
+    /// Sets the string to encode in place of undefined prices, timestamps, and sizes.
+    /// See [`EncoderBuilder::null_value()`](super::EncoderBuilder::null_value).
+    pub fn null_value(mut self, null_value: impl Into<String>) -> Self {
+        self.builder = self.builder.null_value(null_value);
+        self
+    }
+
+    /// Sets the record types to encode in a single table with the union of their
+    /// fields. See [`EncoderBuilder::union()`](super::EncoderBuilder::union).
+    pub fn union(mut self, rtypes: impl IntoIterator<Item = RType>) -> Self {
+        self.builder = self.builder.union(rtypes);
+        self
+    }
+
+    /// Creates the new encoder with the previously specified settings and if
+    /// `write_header` is `true`, encodes the header row.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to write the header row or `union`
+    /// is empty.
+    pub async fn build(self) -> crate::Result<Encoder<W>> {
+        let mut encoder = Encoder::from_sync(self.writer, self.builder.build()?, self.buffer);
+        encoder.write_buffered().await?;
+        Ok(encoder)
+    }
+}
+
+impl<W> Encoder<W>
+where
+    W: io::AsyncWriteExt + Unpin,
+{
+    /// Creates a builder for configuring an `Encoder` object.
+    pub fn builder(writer: W) -> EncoderBuilder<W> {
+        EncoderBuilder::new(writer)
+    }
+
+    /// Creates a new [`Encoder`] that will write to `writer`. If `use_pretty_px`
+    /// is `true`, price fields will be serialized as a decimal. If `pretty_ts` is
+    /// `true`, timestamp fields will be serialized in a ISO8601 datetime string.
+    pub fn new(writer: W, use_pretty_px: bool, use_pretty_ts: bool) -> Self {
+        let buffer = Buffer::default();
+        let encoder = sync::Encoder::new(buffer.clone(), use_pretty_px, use_pretty_ts);
+        Self::from_sync(writer, encoder, buffer)
+    }
+
+    fn from_sync(writer: W, encoder: sync::Encoder<Buffer>, buffer: Buffer) -> Self {
+        Self {
+            writer,
+            encoder,
+            buffer,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Encodes the CSV header for the record type `R`, i.e. the names of each of the
+    /// fields to the output.
+    ///
+    /// If `with_symbol` is `true`, will add a header field for "symbol". This should
+    /// only be used with [`Self::encode_record_with_sym()`] and
+    /// [`Self::encode_ref_with_sym()`], otherwise there will be a mismatch between the
+    /// number of fields in the header and the body.
+    ///
+    /// # Errors
+    /// This function returns an error if there's an error writing to `writer`.
+    pub async fn encode_header<R: DbnEncodable>(&mut self, with_symbol: bool) -> Result<()> {
+        self.encoder.encode_header::<R>(with_symbol)?;
+        self.write_buffered().await
+    }
+
+    /// Encodes the CSV header for `schema`, i.e. the names of each of the fields to
+    /// the output.
+    ///
+    /// If `ts_out` is `true`, it will add a header field "ts_out".
+    ///
+    /// If `with_symbol` is `true`, it will add a header field for "symbol". This should
+    /// only be used with [`Self::encode_record_with_sym()`] and
+    /// [`Self::encode_ref_with_sym()`], otherwise there will be a mismatch between the
+    /// number of fields in the header and the body.
+    ///
+    /// # Errors
+    /// This function returns an error if there's an error writing to `writer`.
+    pub async fn encode_header_for_schema(
+        &mut self,
+        schema: Schema,
+        ts_out: bool,
+        with_symbol: bool,
+    ) -> Result<()> {
+        self.encoder
+            .encode_header_for_schema(schema, ts_out, with_symbol)?;
+        self.write_buffered().await
+    }
+
+    /// Encodes a single DBN record of type `R`.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to write to the underlying writer
+    /// or there's a serialization error.
+    ///
+    /// # Cancel safety
+    /// This method is not cancellation safe. If the method is used in
+    /// `tokio::select!` statement and another branch completes first, then the
+    /// record may have been partially written.
+    pub async fn encode_record<R: DbnEncodable>(&mut self, record: &R) -> Result<()> {
+        self.encoder.encode_record(record)?;
+        self.write_buffered().await
+    }
+
+    /// Encodes a single DBN record of type `R` along with the record's text symbol.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to write to the underlying writer
+    /// or there's a serialization error.
+    ///
+    /// # Cancel safety
+    /// This method is not cancellation safe. If the method is used in
+    /// `tokio::select!` statement and another branch completes first, then the
+    /// record may have been partially written.
+    pub async fn encode_record_with_sym<R: DbnEncodable>(
+        &mut self,
+        record: &R,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        self.encoder.encode_record_with_sym(record, symbol)?;
+        self.write_buffered().await
+    }
+
+    /// Encodes a single DBN [`RecordRef`].
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to write to the underlying writer
+    /// or there's a serialization error.
+    ///
+    /// # Cancel safety
+    /// This method is not cancellation safe. If the method is used in
+    /// `tokio::select!` statement and another branch completes first, then the
+    /// record may have been partially written.
+    pub async fn encode_record_ref(&mut self, record: RecordRef<'_>) -> Result<()> {
+        self.encoder.encode_record_ref(record)?;
+        self.write_buffered().await
+    }
+
+    /// Encodes a single DBN [`RecordRef`] with an optional `ts_out` (see
+    /// [`record::WithTsOut`](crate::record::WithTsOut)).
+    ///
+    /// # Safety
+    /// `ts_out` must be `false` if `record` does not have an appended `ts_out`.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to write to the underlying writer
+    /// or there's a serialization error.
+    pub async unsafe fn encode_record_ref_ts_out(
+        &mut self,
+        record: RecordRef<'_>,
+        ts_out: bool,
+    ) -> Result<()> {
+        self.encoder.encode_record_ref_ts_out(record, ts_out)?;
+        self.write_buffered().await
+    }
+
+    /// Encodes a single DBN [`RecordRef`] along with the record's text symbol.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to write to the underlying writer
+    /// or there's a serialization error.
+    pub async fn encode_ref_with_sym(
+        &mut self,
+        record: RecordRef<'_>,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        self.encoder.encode_ref_with_sym(record, symbol)?;
+        self.write_buffered().await
+    }
+
+    /// Encodes a single DBN [`RecordRef`] with an optional `ts_out` (see
+    /// [`record::WithTsOut`](crate::record::WithTsOut)) along with the record's text
+    /// symbol.
+    ///
+    /// # Safety
+    /// `ts_out` must be `false` if `record` does not have an appended `ts_out`.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to write to the underlying writer
+    /// or there's a serialization error.
+    pub async unsafe fn encode_ref_ts_out_with_sym(
+        &mut self,
+        record: RecordRef<'_>,
+        ts_out: bool,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        self.encoder
+            .encode_ref_ts_out_with_sym(record, ts_out, symbol)?;
+        self.write_buffered().await
+    }
+
+    /// Flushes any buffered content to the true output.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to flush the underlying writer.
+    pub async fn flush(&mut self) -> Result<()> {
+        self.write_buffered().await?;
+        self.writer
+            .flush()
+            .await
+            .map_err(|e| Error::io(e, "flushing output"))
+    }
+
+    /// Writes the output of the synchronous encoder to `writer`.
+    async fn write_buffered(&mut self) -> Result<()> {
+        self.encoder.flush()?;
+        mem::swap(
+            &mut *self.buffer.0.lock().unwrap_or_else(PoisonError::into_inner),
+            &mut self.pending,
+        );
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let res = self.writer.write_all(&self.pending).await;
+        self.pending.clear();
+        res.map_err(|e| Error::io(e, "writing record"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_char;
+
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+    use crate::{
+        encode::test_data::RECORD_HEADER,
+        enums::rtype,
+        record::{MboMsg, RecordHeader, TradeMsg, WithTsOut},
+        FIXED_PRICE_SCALE,
+    };
+
+    fn mbo() -> MboMsg {
+        MboMsg {
+            hd: RecordHeader::new::<MboMsg>(
+                rtype::MBO,
+                RECORD_HEADER.publisher_id,
+                RECORD_HEADER.instrument_id,
+                RECORD_HEADER.ts_event,
+            ),
+            order_id: 16,
+            price: 5500,
+            size: 3,
+            flags: 128,
+            channel_id: 14,
+            action: 'R' as c_char,
+            side: 'N' as c_char,
+            ts_recv: 1658441891000000000,
+            ts_in_delta: 22_000,
+            sequence: 1_002_375,
+        }
+    }
+
+    /// Encodes `records` with the sync encoder to compare against.
+    fn encode_sync(records: &[MboMsg], use_pretty_px: bool, use_pretty_ts: bool) -> String {
+        let mut buffer = Vec::new();
+        let mut encoder = sync::Encoder::builder(&mut buffer)
+            .use_pretty_px(use_pretty_px)
+            .use_pretty_ts(use_pretty_ts)
+            .schema(Some(Schema::Mbo))
+            .unwrap()
+            .build()
+            .unwrap();
+        for record in records {
+            encoder.encode_record(record).unwrap();
+        }
+        encoder.flush().unwrap();
+        drop(encoder);
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_matches_sync_encoder() {
+        let records = [mbo(), mbo()];
+        for (use_pretty_px, use_pretty_ts) in [(false, false), (true, true)] {
+            let mut buffer = Vec::new();
+            let mut encoder = Encoder::builder(&mut buffer)
+                .use_pretty_px(use_pretty_px)
+                .use_pretty_ts(use_pretty_ts)
+                .schema(Some(Schema::Mbo))
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            encoder.encode_record(&records[0]).await.unwrap();
+            encoder
+                .encode_record_ref(RecordRef::from(&records[1]))
+                .await
+                .unwrap();
+            encoder.flush().await.unwrap();
+            assert_eq!(
+                String::from_utf8(buffer).unwrap(),
+                encode_sync(&records, use_pretty_px, use_pretty_ts)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_header_written_on_build() {
+        let mut buffer = Vec::new();
+        Encoder::builder(&mut buffer)
+            .schema(Some(Schema::Trades))
+            .unwrap()
+            .ts_out(true)
+            .with_symbol(true)
+            .build()
+            .await
+            .unwrap();
+        let header = String::from_utf8(buffer).unwrap();
+        assert!(header.starts_with("ts_recv,ts_event,rtype,"));
+        assert!(header.ends_with(",ts_out,symbol\n"));
+    }
+
+    #[tokio::test]
+    async fn test_encode_ref_ts_out_with_sym() {
+        let record = WithTsOut::new(
+            TradeMsg {
+                hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 323, 1658441851000000000),
+                price: 5 * FIXED_PRICE_SCALE,
+                size: 2,
+                action: 'T' as c_char,
+                side: 'A' as c_char,
+                flags: 0,
+                depth: 0,
+                ts_recv: 1658441891000000000,
+                ts_in_delta: 0,
+                sequence: 1,
+            },
+            1658441899000000000,
+        );
+        let mut writer = tokio::io::BufWriter::new(Vec::new());
+        let mut encoder = Encoder::builder(&mut writer)
+            .write_header(false)
+            .use_pretty_px(true)
+            .fields(["price", "ts_out", "symbol"])
+            .build()
+            .await
+            .unwrap();
+        unsafe { encoder.encode_ref_ts_out_with_sym(RecordRef::from(&record), true, Some("ESZ4")) }
+            .await
+            .unwrap();
+        encoder.flush().await.unwrap();
+        writer.shutdown().await.unwrap();
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "5.000000000,1658441899000000000,ESZ4\n"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! Column selection, header renaming, null rendering, and union tables for the CSV
+//! encoder.
+
+use std::{collections::HashMap, io};
+
+use crate::{
+    encode::layout::{Column, Layout, SYMBOL, TS_OUT},
+    pretty::{fmt_px, fmt_ts},
+    reflect::{self, FieldDesc, FieldHint, FieldValue},
+    Error, RType, Record, RecordRef, Result, UNDEF_ORDER_SIZE, UNDEF_PRICE, UNDEF_TIMESTAMP,
+};
+
+/// Encodes records through their field descriptors rather than their
+/// [`CsvSerialize`](super::serialize::CsvSerialize) implementation so columns can be
+/// selected, reordered, and renamed, undefined values rendered differently, and records
+/// of different types encoded in a single table.
+#[derive(Debug)]
+pub(super) struct Projection {
+    selection: Option<Vec<String>>,
+    renames: HashMap<String, String>,
+    null_value: Option<String>,
+    union: Option<Union>,
+    /// Whether a header should be written with the first record.
+    pub header_pending: bool,
+    /// Cached layouts for each record type encountered.
+    layouts: Vec<Layout>,
+}
+
+/// The columns of a table of records of different types.
+#[derive(Debug)]
+struct Union {
+    rtypes: Vec<u8>,
+    /// The union of the fields of the record types in the order they first appear.
+    names: Vec<&'static str>,
+}
+
+impl Projection {
+    pub fn new(
+        selection: Option<Vec<String>>,
+        renames: HashMap<String, String>,
+        null_value: Option<String>,
+        union: Option<Vec<RType>>,
+    ) -> Self {
+        Self {
+            selection,
+            renames,
+            null_value,
+            union: union.map(Union::new),
+            header_pending: false,
+            layouts: Vec::new(),
+        }
+    }
+
+    /// Returns the fields of the first record type of the union, if any. Because all
+    /// records are encoded with the same columns, they can be used to write the header
+    /// before any records.
+    pub fn union_fields(&self) -> Option<&'static [FieldDesc]> {
+        self.union
+            .as_ref()
+            .and_then(|union| union.rtypes.first())
+            .and_then(|&rtype| RType::try_from(rtype).ok())
+            .map(reflect::rtype_fields)
+    }
+
+    /// Writes the header row for records with `fields`.
+    pub fn write_header<W: io::Write>(
+        &mut self,
+        writer: &mut csv::Writer<W>,
+        fields: &'static [FieldDesc],
+        ts_out: bool,
+        with_symbol: bool,
+    ) -> Result<()> {
+        let layout = self.resolve(fields, ts_out, with_symbol)?;
+        let renames = &self.renames;
+        let columns = &self.layouts[layout].columns;
+        writer
+            .write_record(columns.iter().map(|col| {
+                let name = col.name();
+                renames.get(name).map_or(name, String::as_str)
+            }))
+            .map_err(|e| csv_err(e, "header"))?;
+        self.header_pending = false;
+        Ok(())
+    }
+
+    /// Writes `record`, first writing the header if one is pending.
+    pub fn write_record<W: io::Write>(
+        &mut self,
+        writer: &mut csv::Writer<W>,
+        record: RecordRef,
+        ts_out: Option<u64>,
+        symbol: Option<Option<&str>>,
+        use_pretty_px: bool,
+        use_pretty_ts: bool,
+    ) -> Result<()> {
+        let fields = record.fields().ok_or_else(|| {
+            Error::encode(format!(
+                "can't encode record with unknown rtype {:#04X}",
+                record.header().rtype
+            ))
+        })?;
+        if let Some(union) = &self.union {
+            let rtype = record.header().rtype;
+            if !union.rtypes.contains(&rtype) {
+                return Err(Error::encode(format!(
+                    "can't encode record with rtype {:#04X} that isn't part of the CSV union",
+                    rtype
+                )));
+            }
+        }
+        if self.header_pending {
+            self.write_header(writer, fields, ts_out.is_some(), symbol.is_some())?;
+        }
+        let layout = self.resolve(fields, ts_out.is_some(), symbol.is_some())?;
+        let columns = &self.layouts[layout].columns;
+        let null_value = self.null_value.as_deref();
+        let mut encode = || -> csv::Result<()> {
+            for col in columns {
+                match col {
+                    Column::Field(desc) => match desc.value(record) {
+                        Some(value) => write_value(
+                            writer,
+                            desc.hint(),
+                            value,
+                            null_value,
+                            use_pretty_px,
+                            use_pretty_ts,
+                        )?,
+                        None => writer.write_field([])?,
+                    },
+                    Column::Missing(_) => writer.write_field(null_value.unwrap_or_default())?,
+                    Column::TsOut => write_ts(
+                        writer,
+                        ts_out.unwrap_or_default(),
+                        null_value,
+                        use_pretty_ts,
+                    )?,
+                    Column::Symbol => writer.write_field(symbol.flatten().unwrap_or_default())?,
+                }
+            }
+            // end of line
+            writer.write_record(None::<&[u8]>)
+        };
+        encode().map_err(|e| csv_err(e, &format!("{record:?}")))
+    }
+
+    /// Returns the index of the cached layout for the record type with `fields`,
+    /// creating it if the record type hasn't been encountered with the same `ts_out`
+    /// and symbol column.
+    fn resolve(
+        &mut self,
+        fields: &'static [FieldDesc],
+        ts_out: bool,
+        with_symbol: bool,
+    ) -> Result<usize> {
+        if let Some(i) = self
+            .layouts
+            .iter()
+            .position(|layout| layout.matches(fields, ts_out, with_symbol))
+        {
+            return Ok(i);
+        }
+        let layout = Layout::new(
+            fields,
+            ts_out,
+            with_symbol,
+            self.union.as_ref().map(|union| union.names.as_slice()),
+            self.selection.as_deref(),
+        )?;
+        if let Some(name) = self.renames.keys().find(|&name| {
+            name != TS_OUT
+                && name != SYMBOL
+                && !layout.available.iter().any(|col| col.name() == name)
+        }) {
+            return Err(Error::BadArgument {
+                param_name: "rename_field".to_owned(),
+                desc: format!("can't rename unknown field '{name}'"),
+            });
+        }
+        self.layouts.push(layout);
+        Ok(self.layouts.len() - 1)
+    }
+}
+
+impl Union {
+    fn new(rtypes: Vec<RType>) -> Self {
+        let mut names = Vec::new();
+        for &rtype in &rtypes {
+            for field in reflect::rtype_fields(rtype) {
+                if !names.contains(&field.name()) {
+                    names.push(field.name());
+                }
+            }
+        }
+        Self {
+            rtypes: rtypes.into_iter().map(|rtype| rtype as u8).collect(),
+            names,
+        }
+    }
+}
+
+fn write_value<W: io::Write>(
+    writer: &mut csv::Writer<W>,
+    hint: Option<FieldHint>,
+    value: FieldValue,
+    null_value: Option<&str>,
+    use_pretty_px: bool,
+    use_pretty_ts: bool,
+) -> csv::Result<()> {
+    match (hint, value, null_value) {
+        (Some(FieldHint::FixedPrice), FieldValue::I64(UNDEF_PRICE), Some(null_value))
+        | (Some(FieldHint::OrderSize), FieldValue::U32(UNDEF_ORDER_SIZE), Some(null_value)) => {
+            writer.write_field(null_value)
+        }
+        (Some(FieldHint::FixedPrice), FieldValue::I64(UNDEF_PRICE), None) if use_pretty_px => {
+            writer.write_field([])
+        }
+        (Some(FieldHint::FixedPrice), FieldValue::I64(px), _) if use_pretty_px => {
+            writer.write_field(fmt_px(px))
+        }
+        (Some(FieldHint::UnixNanos), FieldValue::U64(ts), _) => {
+            write_ts(writer, ts, null_value, use_pretty_ts)
+        }
+        // Handle NUL byte
+        (_, FieldValue::Char('\0'), _) => writer.write_field([]),
+        (_, FieldValue::CStr(_), _) => writer.write_field(value.as_str().unwrap_or_default()),
+        (_, value, _) => writer.write_field(value.to_string()),
+    }
+}
+
+fn write_ts<W: io::Write>(
+    writer: &mut csv::Writer<W>,
+    ts: u64,
+    null_value: Option<&str>,
+    use_pretty_ts: bool,
+) -> csv::Result<()> {
+    match (ts, null_value) {
+        (UNDEF_TIMESTAMP, Some(null_value)) => writer.write_field(null_value),
+        (0 | UNDEF_TIMESTAMP, _) if use_pretty_ts => writer.write_field([]),
+        _ if use_pretty_ts => writer.write_field(fmt_ts(ts)),
+        _ => writer.write_field(itoa::Buffer::new().format(ts)),
+    }
+}
+
+fn csv_err(err: csv::Error, ctx: &str) -> Error {
+    match err.into_kind() {
+        csv::ErrorKind::Io(err) => Error::io(err, format!("serializing {ctx}")),
+        e => Error::encode(format!("failed to serialize {ctx}: {e:?}")),
+    }
+}
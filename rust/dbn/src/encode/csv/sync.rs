@@ -1,12 +1,16 @@
-use std::{io, num::NonZeroU64};
+use std::{collections::HashMap, io, num::NonZeroU64};
 
 use streaming_iterator::StreamingIterator;
 
+use super::projection::Projection;
 use crate::{
     decode::{DbnMetadata, DecodeRecordRef},
-    encode::{DbnEncodable, EncodeDbn, EncodeRecord, EncodeRecordRef, EncodeRecordTextExt},
-    rtype_method_dispatch, rtype_ts_out_method_dispatch, schema_method_dispatch,
-    schema_ts_out_method_dispatch, Error, RType, Record, Result, Schema,
+    encode::{
+        record_ref, ts_out_from_end, ts_out_of, DbnEncodable, EncodeDbn, EncodeRecord,
+        EncodeRecordRef, EncodeRecordTextExt,
+    },
+    reflect, registry, rtype_method_dispatch, rtype_ts_out_method_dispatch, schema_method_dispatch,
+    schema_ts_out_method_dispatch, Error, RType, Record, RecordRef, Result, Schema,
 };
 
 /// Type for encoding files and streams of DBN records in CSV.
@@ -21,12 +25,61 @@ where
     has_written_header: bool,
     use_pretty_px: bool,
     use_pretty_ts: bool,
+    projection: Option<Projection>,
+}
+
+/// How fields are quoted in CSV output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// Only quote fields when necessary, e.g. when they contain a delimiter or quote.
+    #[default]
+    Necessary,
+    /// Quote every field.
+    Always,
+    /// Quote every field that isn't a number.
+    NonNumeric,
+    /// Never quote fields, even if it results in invalid CSV.
+    Never,
+}
+
+/// The line terminator written after each CSV row.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineTerminator {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`
+    CrLf,
+}
+
+impl From<QuoteStyle> for csv::QuoteStyle {
+    fn from(value: QuoteStyle) -> Self {
+        match value {
+            QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+            QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+            QuoteStyle::Never => csv::QuoteStyle::Never,
+        }
+    }
+}
+
+impl From<LineTerminator> for csv::Terminator {
+    fn from(value: LineTerminator) -> Self {
+        match value {
+            LineTerminator::Lf => csv::Terminator::Any(b'\n'),
+            LineTerminator::CrLf => csv::Terminator::CRLF,
+        }
+    }
 }
 
 /// Helper for constructing a CSV [`Encoder`].
 ///
 /// If writing a CSV header (`write_header`), which is enabled by default,
-/// `schema` is required, otherwise no fields are required.
+/// `schema` or [`union`](Self::union) is required, otherwise no fields are required.
+///
+/// Selecting [`fields`](Self::fields), [renaming](Self::rename_field) header fields,
+/// setting a [`null_value`](Self::null_value), or encoding a [`union`](Self::union)
+/// switches to a slower encoding path based on [field reflection](crate::reflect).
 pub struct EncoderBuilder<W>
 where
     W: io::Write,
@@ -38,6 +91,13 @@ where
     schema: Option<Schema>,
     ts_out: bool,
     with_symbol: bool,
+    delimiter: u8,
+    quote_style: QuoteStyle,
+    line_terminator: LineTerminator,
+    fields: Option<Vec<String>>,
+    renames: HashMap<String, String>,
+    null_value: Option<String>,
+    union: Option<Vec<RType>>,
 }
 
 impl<W> EncoderBuilder<W>
@@ -54,6 +114,13 @@ where
             schema: None,
             ts_out: false,
             with_symbol: false,
+            delimiter: b',',
+            quote_style: QuoteStyle::default(),
+            line_terminator: LineTerminator::default(),
+            fields: None,
+            renames: HashMap::new(),
+            null_value: None,
+            union: None,
         }
     }
 
@@ -104,22 +171,108 @@ where
         self
     }
 
+    /// Sets the field delimiter, e.g. `b'\t'` or `b'|'`. Defaults to `b','`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets when fields are quoted. Defaults to [`QuoteStyle::Necessary`].
+    pub fn quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.quote_style = quote_style;
+        self
+    }
+
+    /// Sets the line terminator. Defaults to [`LineTerminator::Lf`].
+    pub fn line_terminator(mut self, line_terminator: LineTerminator) -> Self {
+        self.line_terminator = line_terminator;
+        self
+    }
+
+    /// Sets the fields to encode in the given order, using the same names as the
+    /// header, including "ts_out" and "symbol". By default, all fields are encoded.
+    /// Encoding a record without one of the fields will return an error.
+    pub fn fields<S: Into<String>>(mut self, fields: impl IntoIterator<Item = S>) -> Self {
+        self.fields = Some(fields.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Renames the header field for `field` to `header`. Encoding a record without
+    /// `field` will return an error.
+    pub fn rename_field(mut self, field: impl Into<String>, header: impl Into<String>) -> Self {
+        self.renames.insert(field.into(), header.into());
+        self
+    }
+
+    /// Sets the string to encode in place of undefined prices ([`UNDEF_PRICE`]),
+    /// timestamps ([`UNDEF_TIMESTAMP`]), and order sizes ([`UNDEF_ORDER_SIZE`]), e.g. `""`,
+    /// `"NaN"`, or `"null"`. By default, undefined values are encoded like any other
+    /// value, except prices and timestamps are left empty when pretty.
+    ///
+    /// [`UNDEF_PRICE`]: crate::UNDEF_PRICE
+    /// [`UNDEF_TIMESTAMP`]: crate::UNDEF_TIMESTAMP
+    /// [`UNDEF_ORDER_SIZE`]: crate::UNDEF_ORDER_SIZE
+    pub fn null_value(mut self, null_value: impl Into<String>) -> Self {
+        self.null_value = Some(null_value.into());
+        self
+    }
+
+    /// Sets the record types to encode in a single table whose columns are the union
+    /// of the fields of `rtypes` in the order they first appear. The `rtype` column
+    /// distinguishes the record types and columns a record doesn't have are left
+    /// empty, or set to the [`null_value`](Self::null_value). This allows encoding
+    /// mixed-schema DBN. By default, only a single record type can be encoded.
+    ///
+    /// Encoding a record of a type not in `rtypes` will return an error.
+    pub fn union(mut self, rtypes: impl IntoIterator<Item = RType>) -> Self {
+        self.union = Some(rtypes.into_iter().collect());
+        self
+    }
+
     /// Creates the new encoder with the previously specified settings and if
     /// `write_header` is `true`, encodes the header row.
     ///
     /// # Errors
-    /// This function returns an error if it fails to write the header row.
+    /// This function returns an error if it fails to write the header row or `union`
+    /// is empty.
     pub fn build(self) -> crate::Result<Encoder<W>> {
-        let mut encoder = Encoder::new(self.writer, self.use_pretty_px, self.use_pretty_ts);
-        if self.write_header {
-            let Some(schema) = self.schema else {
-                return Err(Error::BadArgument {
-                    param_name: "schema".to_owned(),
-                    desc: "need to specify schema in order to write header".to_owned(),
-                });
-            };
-            encoder.encode_header_for_schema(schema, self.ts_out, self.with_symbol)?;
+        if self.union.as_ref().is_some_and(Vec::is_empty) {
+            return Err(Error::BadArgument {
+                param_name: "union".to_owned(),
+                desc: "need at least one record type".to_owned(),
+            });
+        }
+        let csv_writer = csv::WriterBuilder::new()
+            .has_headers(false) // need to write our own custom header
+            .delimiter(self.delimiter)
+            .quote_style(self.quote_style.into())
+            .terminator(self.line_terminator.into())
+            .from_writer(self.writer);
+        let projection = (self.fields.is_some()
+            || !self.renames.is_empty()
+            || self.null_value.is_some()
+            || self.union.is_some())
+        .then(|| Projection::new(self.fields, self.renames, self.null_value, self.union));
+        let mut encoder = Encoder::from_csv_writer(
+            csv_writer,
+            self.use_pretty_px,
+            self.use_pretty_ts,
+            projection,
+        );
+        if !self.write_header {
+            return Ok(encoder);
+        }
+        if let Some(res) = encoder.encode_union_header(self.ts_out, self.with_symbol) {
+            res?;
+            return Ok(encoder);
         }
+        let Some(schema) = self.schema else {
+            return Err(Error::BadArgument {
+                param_name: "schema".to_owned(),
+                desc: "need to specify schema in order to write header".to_owned(),
+            });
+        };
+        encoder.encode_header_for_schema(schema, self.ts_out, self.with_symbol)?;
         Ok(encoder)
     }
 }
@@ -140,11 +293,21 @@ where
         let csv_writer = csv::WriterBuilder::new()
             .has_headers(false) // need to write our own custom header
             .from_writer(writer);
+        Self::from_csv_writer(csv_writer, use_pretty_px, use_pretty_ts, None)
+    }
+
+    fn from_csv_writer(
+        writer: csv::Writer<W>,
+        use_pretty_px: bool,
+        use_pretty_ts: bool,
+        projection: Option<Projection>,
+    ) -> Self {
         Self {
-            writer: csv_writer,
+            writer,
             use_pretty_px,
             use_pretty_ts,
             has_written_header: false,
+            projection,
         }
     }
 
@@ -161,9 +324,16 @@ where
     /// [`Self::encode_ref_with_sym()`], otherwise there will be a mismatch between the
     /// number of fields in the header and the body.
     ///
+    /// When the encoder was built with a selection of fields, renamed header fields, or
+    /// a null value, the header is instead written with the next record.
+    ///
     /// # Errors
     /// This function returns an error if there's an error writing to `writer`.
     pub fn encode_header<R: DbnEncodable>(&mut self, with_symbol: bool) -> Result<()> {
+        if let Some(projection) = self.projection.as_mut() {
+            projection.header_pending = true;
+            return Ok(());
+        }
         R::serialize_header(&mut self.writer)?;
         if with_symbol {
             self.writer.write_field("symbol")?;
@@ -192,11 +362,31 @@ where
         ts_out: bool,
         with_symbol: bool,
     ) -> Result<()> {
-        schema_ts_out_method_dispatch!(schema, ts_out, self, encode_header, with_symbol)?;
+        if let Some(projection) = self.projection.as_mut() {
+            projection.write_header(
+                &mut self.writer,
+                reflect::schema_fields(schema),
+                ts_out,
+                with_symbol,
+            )?;
+        } else {
+            schema_ts_out_method_dispatch!(schema, ts_out, self, encode_header, with_symbol)?;
+        }
         self.has_written_header = true;
         Ok(())
     }
 
+    /// Encodes the header of a union table. Returns `None` if the encoder wasn't built
+    /// with a union.
+    fn encode_union_header(&mut self, ts_out: bool, with_symbol: bool) -> Option<Result<()>> {
+        let projection = self.projection.as_mut()?;
+        // The columns of a union don't depend on the record type
+        let fields = projection.union_fields()?;
+        let res = projection.write_header(&mut self.writer, fields, ts_out, with_symbol);
+        self.has_written_header |= res.is_ok();
+        Some(res)
+    }
+
     fn encode_record_impl<R: DbnEncodable>(&mut self, record: &R) -> csv::Result<()> {
         match (self.use_pretty_px, self.use_pretty_ts) {
             (true, true) => record.serialize_to::<_, true, true>(&mut self.writer),
@@ -209,6 +399,94 @@ where
     fn encode_symbol(&mut self, symbol: Option<&str>) -> csv::Result<()> {
         self.writer.write_field(symbol.unwrap_or_default())
     }
+
+    /// Encodes a record through the projection, first encoding the header if one is
+    /// pending. Returns `None` if the encoder has no projection.
+    fn encode_projected(
+        &mut self,
+        record: RecordRef,
+        ts_out: Option<u64>,
+        symbol: Option<Option<&str>>,
+    ) -> Option<Result<()>> {
+        let projection = self.projection.as_mut()?;
+        let has_pending_header = projection.header_pending;
+        let res = projection.write_record(
+            &mut self.writer,
+            record,
+            ts_out,
+            symbol,
+            self.use_pretty_px,
+            self.use_pretty_ts,
+        );
+        self.has_written_header |= has_pending_header && res.is_ok();
+        Some(res)
+    }
+
+    /// Encodes up to `limit` mixed-schema records from `decoder` in a union table.
+    fn encode_decoded_union<D: DecodeRecordRef + DbnMetadata>(
+        &mut self,
+        mut decoder: D,
+        limit: Option<NonZeroU64>,
+    ) -> Result<()> {
+        if self
+            .projection
+            .as_ref()
+            .and_then(Projection::union_fields)
+            .is_none()
+        {
+            return Err(Error::encode("can't encode a CSV with mixed schemas"));
+        }
+        let ts_out = decoder.metadata().ts_out;
+        if !self.has_written_header {
+            self.encode_union_header(ts_out, false).unwrap()?;
+        }
+        let mut i = 0;
+        while let Some(record) = decoder.decode_record_ref()? {
+            // Safety: It's safe to cast to `WithTsOut` because we're passing in the `ts_out`
+            // from the metadata header.
+            unsafe { self.encode_record_ref_ts_out(record, ts_out) }?;
+            i += 1;
+            if limit.is_some_and(|limit| i == limit.get()) {
+                break;
+            }
+        }
+        self.flush()
+    }
+
+    /// Encodes a record of a type registered with [`registry::register()`], first
+    /// encoding its header if no header has been written. Returns `None` if the rtype
+    /// of `record` isn't registered.
+    fn encode_registered(
+        &mut self,
+        record: RecordRef,
+        ts_out: bool,
+        symbol: Option<Option<&str>>,
+    ) -> Option<Result<()>> {
+        let registration = registry::registration(record.header().rtype)?;
+        let mut encode = || {
+            let fields =
+                registration.csv_fields(record, self.use_pretty_px, self.use_pretty_ts, ts_out)?;
+            let csv_err = |e: csv::Error| match e.into_kind() {
+                csv::ErrorKind::Io(err) => Error::io(err, format!("serializing {record:?}")),
+                e => Error::encode(format!("failed to serialize {record:?}: {e:?}")),
+            };
+            if !self.has_written_header {
+                let header = registration.csv_header(ts_out)?;
+                self.writer
+                    .write_record(header.iter().chain(symbol.map(|_| b"symbol".as_slice())))
+                    .map_err(csv_err)?;
+                self.has_written_header = true;
+            }
+            self.writer
+                .write_record(
+                    fields
+                        .iter()
+                        .chain(symbol.map(|s| s.unwrap_or_default().as_bytes())),
+                )
+                .map_err(csv_err)
+        };
+        Some(encode())
+    }
 }
 
 impl<W> EncodeRecord for Encoder<W>
@@ -216,6 +494,9 @@ where
     W: io::Write,
 {
     fn encode_record<R: DbnEncodable>(&mut self, record: &R) -> Result<()> {
+        if let Some(res) = self.encode_projected(record_ref(record), ts_out_of(record), None) {
+            return res;
+        }
         match self
             .encode_record_impl(record)
             // write new line
@@ -240,16 +521,29 @@ impl<W> EncodeRecordRef for Encoder<W>
 where
     W: io::Write,
 {
-    fn encode_record_ref(&mut self, record: crate::RecordRef) -> Result<()> {
-        rtype_method_dispatch!(record, self, encode_record)?
+    fn encode_record_ref(&mut self, record: RecordRef) -> Result<()> {
+        if let Some(res) = self.encode_projected(record, None, None) {
+            return res;
+        }
+        match rtype_method_dispatch!(record, self, encode_record) {
+            Ok(res) => res,
+            Err(err) => self
+                .encode_registered(record, false, None)
+                .unwrap_or(Err(err)),
+        }
     }
 
-    unsafe fn encode_record_ref_ts_out(
-        &mut self,
-        record: crate::RecordRef,
-        ts_out: bool,
-    ) -> Result<()> {
-        rtype_ts_out_method_dispatch!(record, ts_out, self, encode_record)?
+    unsafe fn encode_record_ref_ts_out(&mut self, record: RecordRef, ts_out: bool) -> Result<()> {
+        let ts_out_val = ts_out.then(|| ts_out_from_end(record.bytes()));
+        if let Some(res) = self.encode_projected(record, ts_out_val, None) {
+            return res;
+        }
+        match rtype_ts_out_method_dispatch!(record, ts_out, self, encode_record) {
+            Ok(res) => res,
+            Err(err) => self
+                .encode_registered(record, ts_out, None)
+                .unwrap_or(Err(err)),
+        }
     }
 }
 
@@ -312,7 +606,7 @@ where
             self.flush()?;
             Ok(())
         } else {
-            Err(Error::encode("can't encode a CSV with mixed schemas"))
+            self.encode_decoded_union(decoder, None)
         }
     }
 
@@ -341,7 +635,7 @@ where
             self.flush()?;
             Ok(())
         } else {
-            Err(Error::encode("can't encode a CSV with mixed schemas"))
+            self.encode_decoded_union(decoder, Some(limit))
         }
     }
 }
@@ -355,6 +649,11 @@ where
         record: &R,
         symbol: Option<&str>,
     ) -> Result<()> {
+        if let Some(res) =
+            self.encode_projected(record_ref(record), ts_out_of(record), Some(symbol))
+        {
+            return res;
+        }
         match self
             .encode_record_impl(record)
             .and_then(|_| self.encode_symbol(symbol))
@@ -368,12 +667,44 @@ where
             }),
         }
     }
+
+    fn encode_ref_with_sym(&mut self, record: RecordRef, symbol: Option<&str>) -> Result<()> {
+        if let Some(res) = self.encode_projected(record, None, Some(symbol)) {
+            return res;
+        }
+        match rtype_method_dispatch!(record, self, encode_record_with_sym, symbol) {
+            Ok(res) => res,
+            Err(err) => self
+                .encode_registered(record, false, Some(symbol))
+                .unwrap_or(Err(err)),
+        }
+    }
+
+    unsafe fn encode_ref_ts_out_with_sym(
+        &mut self,
+        record: RecordRef,
+        ts_out: bool,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        let ts_out_val = ts_out.then(|| ts_out_from_end(record.bytes()));
+        if let Some(res) = self.encode_projected(record, ts_out_val, Some(symbol)) {
+            return res;
+        }
+        match rtype_ts_out_method_dispatch!(record, ts_out, self, encode_record_with_sym, symbol) {
+            Ok(res) => res,
+            Err(err) => self
+                .encode_registered(record, ts_out, Some(symbol))
+                .unwrap_or(Err(err)),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::{array, io::BufWriter, os::raw::c_char};
 
+    use rstest::rstest;
+
     use super::*;
     use crate::{
         encode::test_data::{VecStream, BID_ASK, RECORD_HEADER},
@@ -385,7 +716,7 @@ mod tests {
             str_to_c_chars, ImbalanceMsg, InstrumentDefMsg, MboMsg, Mbp10Msg, Mbp1Msg, OhlcvMsg,
             RecordHeader, StatMsg, StatusMsg, TradeMsg, WithTsOut,
         },
-        RecordRef, FIXED_PRICE_SCALE,
+        FIXED_PRICE_SCALE,
     };
 
     const HEADER_CSV: &str = "1658441851000000000,4,1,323";
@@ -470,7 +801,7 @@ mod tests {
             ts_recv: 1658441891000000000,
             ts_in_delta: 22_000,
             sequence: 1_002_375,
-            levels: array::from_fn(|_| BID_ASK.clone()),
+            levels: array::from_fn(|_| BID_ASK),
         }];
         let mut buffer = Vec::new();
         let writer = BufWriter::new(&mut buffer);
@@ -768,4 +1099,236 @@ mod tests {
         let orig_header = snd_line.split_once(",ts_out,symbol").unwrap().0;
         assert_eq!(fst_line, orig_header);
     }
+
+    #[test]
+    fn test_dialect() {
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::builder(&mut buffer)
+            .schema(Some(Schema::Ohlcv1D))
+            .unwrap()
+            .delimiter(b'\t')
+            .quote_style(QuoteStyle::NonNumeric)
+            .line_terminator(LineTerminator::CrLf)
+            .with_symbol(true)
+            .build()
+            .unwrap();
+        let bar = OhlcvMsg {
+            hd: RecordHeader::new::<OhlcvMsg>(rtype::OHLCV_1D, 1, 2, 3),
+            open: 4,
+            high: 5,
+            low: 6,
+            close: 7,
+            volume: 8,
+        };
+        encoder.encode_record_with_sym(&bar, Some("ES")).unwrap();
+        drop(encoder);
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "\"ts_event\"\t\"rtype\"\t\"publisher_id\"\t\"instrument_id\"\t\"open\"\t\"high\"\t\"low\"\t\"close\"\t\"volume\"\t\"symbol\"\r\n\
+            3\t35\t1\t2\t4\t5\t6\t7\t8\t\"ES\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_fields_and_renames() {
+        let trade = TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 323, 1658441851000000000),
+            price: 5 * FIXED_PRICE_SCALE,
+            size: 3,
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::builder(&mut buffer)
+            .schema(Some(Schema::Trades))
+            .unwrap()
+            .with_symbol(true)
+            .use_pretty_px(true)
+            .fields(["symbol", "price", "size", "ts_event"])
+            .rename_field("price", "px")
+            .build()
+            .unwrap();
+        encoder.encode_record_with_sym(&trade, Some("ES")).unwrap();
+        drop(encoder);
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "symbol,px,size,ts_event\nES,5.000000000,3,1658441851000000000\n"
+        );
+    }
+
+    #[test]
+    fn test_fields_header_with_first_record() {
+        let data = vec![WithTsOut::new(
+            TradeMsg {
+                hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 323, 1658441851000000000),
+                ..Default::default()
+            },
+            1678480044000000000,
+        )];
+        let mut buffer = Vec::new();
+        Encoder::builder(&mut buffer)
+            .write_header(false)
+            .fields(["ts_out", "instrument_id"])
+            .build()
+            .unwrap()
+            .encode_records(data.as_slice())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "ts_out,instrument_id\n1678480044000000000,323\n"
+        );
+    }
+
+    #[rstest]
+    #[case::empty("", false, ",,,")]
+    #[case::nan("NaN", false, "NaN,NaN,NaN,NaN")]
+    #[case::pretty_null("null", true, "null,null,null,null")]
+    fn test_null_value(#[case] null_value: &str, #[case] pretty: bool, #[case] exp: &str) {
+        let mbp1 = Mbp1Msg {
+            hd: RecordHeader::new::<Mbp1Msg>(rtype::MBP_1, 1, 323, 1658441851000000000),
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::builder(&mut buffer)
+            .write_header(false)
+            .use_pretty_px(pretty)
+            .use_pretty_ts(pretty)
+            .fields(["price", "size", "ts_recv", "bid_px_00"])
+            .null_value(null_value)
+            .build()
+            .unwrap();
+        encoder.encode_record_ref(RecordRef::from(&mbp1)).unwrap();
+        drop(encoder);
+        assert_eq!(String::from_utf8(buffer).unwrap().trim_end(), exp);
+    }
+
+    #[test]
+    fn test_null_value_only_order_sizes() {
+        let mbp1 = Mbp1Msg {
+            hd: RecordHeader::new::<Mbp1Msg>(rtype::MBP_1, 1, u32::MAX, 1658441851000000000),
+            sequence: u32::MAX,
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::builder(&mut buffer)
+            .write_header(false)
+            .fields(["instrument_id", "sequence", "size"])
+            .null_value("NA")
+            .build()
+            .unwrap();
+        encoder.encode_record_ref(RecordRef::from(&mbp1)).unwrap();
+        drop(encoder);
+        assert_eq!(
+            String::from_utf8(buffer).unwrap().trim_end(),
+            "4294967295,4294967295,NA"
+        );
+    }
+
+    #[test]
+    fn test_null_value_matches_default_encoding() {
+        let mbo = MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 323, 1658441851000000000),
+            ts_recv: 1658441891000000000,
+            price: 5500,
+            size: 3,
+            action: 'A' as c_char,
+            side: 'N' as c_char,
+            ..Default::default()
+        };
+        let mut exp = Vec::new();
+        Encoder::new(&mut exp, true, true)
+            .encode_records(std::slice::from_ref(&mbo))
+            .unwrap();
+        let mut buffer = Vec::new();
+        Encoder::builder(&mut buffer)
+            .write_header(false)
+            .use_pretty_px(true)
+            .use_pretty_ts(true)
+            .null_value("NaN")
+            .build()
+            .unwrap()
+            .encode_records(&[mbo])
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            String::from_utf8(exp).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case::fields(Encoder::builder(Vec::new()).fields(["price", "bid_px_00"]), "unknown field 'bid_px_00'")]
+    #[case::rename(Encoder::builder(Vec::new()).rename_field("px", "price"), "can't rename unknown field 'px'")]
+    fn test_unknown_field(#[case] builder: EncoderBuilder<Vec<u8>>, #[case] exp: &str) {
+        let mut encoder = builder.write_header(false).build().unwrap();
+        let err = encoder
+            .encode_record(&TradeMsg {
+                hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 323, 0),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains(exp), "{err}");
+    }
+
+    #[test]
+    fn test_union() {
+        let trade = TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 323, 1658441851000000000),
+            price: 5500,
+            size: 3,
+            action: 'T' as c_char,
+            side: 'A' as c_char,
+            ts_recv: 1658441891000000000,
+            ..Default::default()
+        };
+        let status = StatusMsg {
+            hd: RecordHeader::new::<StatusMsg>(rtype::STATUS, 1, 323, 1658441851000000000),
+            ts_recv: 1658441891000000000,
+            group: str_to_c_chars("group").unwrap(),
+            trading_status: 3,
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::builder(&mut buffer)
+            .union([RType::Mbp0, RType::Status])
+            .build()
+            .unwrap();
+        encoder.encode_record(&status).unwrap();
+        encoder.encode_record(&trade).unwrap();
+        let err = encoder
+            .encode_record(&StatMsg {
+                hd: RecordHeader::new::<StatMsg>(rtype::STATISTICS, 1, 323, 0),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(matches!(err, Error::Encode(_)), "{err:?}");
+        drop(encoder);
+        assert_eq!(
+            String::from_utf8(buffer).expect("valid UTF-8"),
+            "ts_recv,ts_event,rtype,publisher_id,instrument_id,action,side,depth,price,size,flags,ts_in_delta,sequence,group,trading_status,halt_reason,trading_event\n\
+            1658441891000000000,1658441851000000000,18,1,323,,,,,,,,,group,3,0,0\n\
+            1658441891000000000,1658441851000000000,0,1,323,T,A,0,5500,3,0,0,0,,,,\n"
+        );
+
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::builder(&mut buffer)
+            .union([RType::Status, RType::Mbp0])
+            .fields(["rtype", "price", "trading_status"])
+            .null_value("NA")
+            .build()
+            .unwrap();
+        encoder.encode_record(&trade).unwrap();
+        encoder.encode_record(&status).unwrap();
+        drop(encoder);
+        assert_eq!(
+            String::from_utf8(buffer).expect("valid UTF-8"),
+            "rtype,price,trading_status\n0,5500,NA\n18,NA,3\n"
+        );
+    }
+
+    #[test]
+    fn test_empty_union() {
+        let res = Encoder::builder(Vec::new()).union([]).build();
+        assert!(
+            matches!(&res, Err(Error::BadArgument { param_name, .. }) if param_name == "union")
+        );
+    }
 }
@@ -1,6 +1,6 @@
 //! Encoding DBN records into DBN, Zstandard-compressed or not.
 mod sync;
-pub use sync::{Encoder, MetadataEncoder, RecordEncoder};
+pub use sync::{update_file_metadata, Encoder, MetadataEncoder, MetadataUpdate, RecordEncoder};
 
 #[cfg(feature = "async")]
 mod r#async;
@@ -4,8 +4,10 @@ use async_compression::tokio::write::ZstdEncoder;
 use tokio::io;
 
 use crate::{
-    encode::DbnEncodable, record_ref::RecordRef, Error, Metadata, Result, SymbolMapping,
-    DBN_VERSION, NULL_LIMIT, NULL_RECORD_COUNT, NULL_SCHEMA, NULL_STYPE, UNDEF_TIMESTAMP,
+    encode::{DbnEncodable, ZstdAsyncWriter, ZstdOptions},
+    record_ref::RecordRef,
+    Error, Metadata, Result, SymbolMapping, DBN_VERSION, NULL_LIMIT, NULL_RECORD_COUNT,
+    NULL_SCHEMA, NULL_STYPE, UNDEF_TIMESTAMP,
 };
 
 /// An async encoder for DBN streams.
@@ -107,6 +109,33 @@ where
     }
 }
 
+impl<W> Encoder<ZstdAsyncWriter<W>>
+where
+    W: io::AsyncWriteExt + Unpin,
+{
+    /// Creates a new async [`Encoder`] that will Zstandard compress the DBN data
+    /// written to `writer` with the compression level, threads, and dictionary from
+    /// `options`.
+    ///
+    /// # Errors
+    /// This function will return an error if it fails to initialize the Zstd
+    /// compression, such as when the dictionary is invalid, or it fails to encode
+    /// `metadata` to `writer`.
+    ///
+    /// # Cancel safety
+    /// This method is not cancellation safe. If the method is used in
+    /// `tokio::select!` statement and another branch completes first, then the
+    /// metadata may have been partially written, but future calls will begin writing
+    /// the encoded metadata from the beginning.
+    pub async fn with_zstd_options(
+        writer: W,
+        metadata: &Metadata,
+        options: &ZstdOptions,
+    ) -> Result<Self> {
+        Self::new(ZstdAsyncWriter::new(writer, options)?, metadata).await
+    }
+}
+
 /// An async encoder of DBN records.
 pub struct RecordEncoder<W>
 where
@@ -591,4 +620,74 @@ mod tests {
         assert!(decoded.end.is_none());
         assert!(decoded.limit.is_none());
     }
+
+    #[cfg(feature = "zstdmt")]
+    #[tokio::test]
+    async fn test_encode_with_zstd_options() {
+        use tokio::io::AsyncWriteExt;
+
+        let metadata = MetadataBuilder::new()
+            .dataset(XNAS_ITCH.to_owned())
+            .schema(Some(Schema::Mbo))
+            .start(1697240529000000000)
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .build();
+        let options = ZstdOptions {
+            level: 19,
+            threads: 2,
+            long_distance_matching: true,
+            dictionary: None,
+        };
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::with_zstd_options(&mut buffer, &metadata, &options)
+            .await
+            .unwrap();
+        encoder.get_mut().shutdown().await.unwrap();
+        let decoded = crate::decode::AsyncDbnDecoder::with_zstd(buffer.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(*decoded.metadata(), metadata);
+    }
+
+    #[tokio::test]
+    async fn test_encode_with_zstd_dictionary() {
+        use tokio::io::AsyncWriteExt;
+
+        use crate::decode::{DbnMetadata, DecodeRecordRef};
+
+        const TEST_DATA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/data");
+        let dictionary = std::fs::read(format!("{TEST_DATA_PATH}/test_data.mbo.dbn")).unwrap();
+        let mut decoder = crate::decode::DynDecoder::from_file(
+            format!("{TEST_DATA_PATH}/test_data.mbp-10.dbn.zst"),
+            crate::VersionUpgradePolicy::AsIs,
+        )
+        .unwrap();
+        let options = ZstdOptions {
+            dictionary: Some(dictionary.clone()),
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::with_zstd_options(&mut buffer, decoder.metadata(), &options)
+            .await
+            .unwrap();
+        let mut expected = Vec::new();
+        while let Some(rec) = decoder.decode_record_ref().unwrap() {
+            encoder.encode_record_ref(rec).await.unwrap();
+            expected.push(rec.as_ref().to_vec());
+        }
+        encoder.get_mut().shutdown().await.unwrap();
+        // Content_Checksum_flag of the frame header descriptor
+        assert_eq!(buffer[4] & 0x04, 0x04);
+        let mut res_decoder = crate::decode::DbnDecoder::new(
+            crate::decode::DynReader::with_zstd_dictionary(buffer.as_slice(), &dictionary).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res_decoder.metadata(), decoder.metadata());
+        for exp in expected {
+            let rec = res_decoder.decode_record_ref().unwrap().unwrap();
+            assert_eq!(rec.as_ref(), exp.as_slice());
+        }
+        assert!(res_decoder.decode_record_ref().unwrap().is_none());
+    }
 }
@@ -1,12 +1,18 @@
 use std::{
-    io::{self, SeekFrom},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom},
     mem,
     num::NonZeroU64,
+    path::{Path, PathBuf},
 };
 
 use crate::{
     compat::version_symbol_cstr_len,
-    encode::{zstd_encoder, DbnEncodable, EncodeDbn, EncodeRecord, EncodeRecordRef},
+    decode::{zstd::starts_with_prefix as starts_with_zstd_prefix, DbnMetadataDecoder},
+    encode::{
+        unfinished_zstd_encoder, zstd_encoder, DbnEncodable, EncodeDbn, EncodeRecord,
+        EncodeRecordRef, ZstdOptions,
+    },
     enums::Schema,
     record_ref::RecordRef,
     Error, Metadata, Result, SymbolMapping, DBN_VERSION, NULL_LIMIT, NULL_RECORD_COUNT,
@@ -58,7 +64,21 @@ where
     /// This function will return an error if it fails to encode `metadata` to
     /// `writer`.
     pub fn with_zstd(writer: W, metadata: &Metadata) -> Result<Self> {
-        Encoder::new(zstd_encoder(writer)?, metadata)
+        Self::with_zstd_options(writer, metadata, &ZstdOptions::default())
+    }
+
+    /// Creates a new DBN [`Encoder`] that will write Zstd-compressed output to
+    /// `writer` with the compression level, threads, and dictionary from `options`.
+    ///
+    /// # Errors
+    /// This function will return an error if it fails to configure the Zstd
+    /// compression or it fails to encode `metadata` to `writer`.
+    pub fn with_zstd_options(
+        writer: W,
+        metadata: &Metadata,
+        options: &ZstdOptions,
+    ) -> Result<Self> {
+        Encoder::new(zstd_encoder(writer, options)?, metadata)
     }
 }
 
@@ -357,6 +377,193 @@ where
             .map_err(|e| Error::io(e, "seeking back to end"))?;
         Ok(())
     }
+
+    /// Overwrites the metadata in an existing DBN buffer whose encoded metadata is
+    /// `encoded_length` bytes long, not including the 8-byte prelude. The new metadata
+    /// is padded to `encoded_length` so the position of the records is unchanged.
+    /// Returns `false` without writing anything if `metadata` doesn't fit.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to seek to the position
+    /// to update the metadata or it fails to write to the underlying writer.
+    pub fn overwrite(&mut self, metadata: &Metadata, encoded_length: u32) -> Result<bool> {
+        const PRELUDE_LEN: usize = 8;
+
+        if Self::calc_length(metadata) > encoded_length {
+            return Ok(false);
+        }
+        let mut buffer = Vec::with_capacity(PRELUDE_LEN + encoded_length as usize);
+        MetadataEncoder::new(&mut buffer).encode(metadata)?;
+        // Keep the original length so the decoder skips the padding
+        buffer[4..PRELUDE_LEN].copy_from_slice(&encoded_length.to_le_bytes());
+        buffer.resize(PRELUDE_LEN + encoded_length as usize, 0);
+        self.writer
+            .seek(SeekFrom::Start(0))
+            .map_err(|e| Error::io(e, "seeking to write position"))?;
+        self.writer
+            .write_all(&buffer)
+            .map_err(|e| Error::io(e, "writing DBN metadata"))?;
+        self.writer
+            .seek(SeekFrom::End(0))
+            .map_err(|e| Error::io(e, "seeking back to end"))?;
+        Ok(true)
+    }
+}
+
+/// How [`update_file_metadata()`] wrote the updated metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetadataUpdate {
+    /// The updated metadata fit in the space of the existing metadata and was patched
+    /// in place.
+    InPlace,
+    /// The file was rewritten with the updated metadata followed by a copy of the
+    /// records, either because the metadata grew or the file is Zstandard-compressed.
+    Copied,
+}
+
+/// Updates the metadata of the DBN file at `path`, Zstandard-compressed or not, by
+/// applying `update` to its current metadata. The metadata of uncompressed files is
+/// patched in place when the updated metadata isn't longer than the existing metadata,
+/// otherwise the records are streamed to a temporary file that replaces the original.
+///
+/// # Errors
+/// This function returns an error if it fails to read or write the file or if `update`
+/// returns an error. It will also return an error if `update` changes the `version` or
+/// `symbol_cstr_len`, which would change how the records are encoded.
+pub fn update_file_metadata(
+    path: impl AsRef<Path>,
+    update: impl FnOnce(&mut Metadata) -> Result<()>,
+) -> Result<MetadataUpdate> {
+    let path = path.as_ref();
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| {
+            Error::io(
+                e,
+                format!("opening file to update at path '{}'", path.display()),
+            )
+        })?;
+    let mut prefix = [0; 4];
+    file.read_exact(&mut prefix)
+        .and_then(|_| file.rewind())
+        .map_err(|e| Error::io(e, "reading file prefix"))?;
+    if starts_with_zstd_prefix(&prefix) {
+        let mut reader =
+            zstd::stream::Decoder::new(file).map_err(|e| Error::io(e, "creating zstd decoder"))?;
+        let metadata = updated_metadata(&mut reader, update)?;
+        copy_with_metadata(path, &metadata, reader, true)?;
+        return Ok(MetadataUpdate::Copied);
+    }
+    let metadata = updated_metadata(&mut file, update)?;
+    let encoded_length = file
+        .stream_position()
+        .map_err(|e| Error::io(e, "getting metadata length"))?
+        - 8;
+    if MetadataEncoder::new(&mut file).overwrite(&metadata, encoded_length as u32)? {
+        Ok(MetadataUpdate::InPlace)
+    } else {
+        copy_with_metadata(path, &metadata, BufReader::new(file), false)?;
+        Ok(MetadataUpdate::Copied)
+    }
+}
+
+fn updated_metadata(
+    reader: impl io::Read,
+    update: impl FnOnce(&mut Metadata) -> Result<()>,
+) -> Result<Metadata> {
+    let mut metadata = DbnMetadataDecoder::new(reader).decode()?;
+    let (version, symbol_cstr_len) = (metadata.version, metadata.symbol_cstr_len);
+    update(&mut metadata)?;
+    if metadata.version != version || metadata.symbol_cstr_len != symbol_cstr_len {
+        return Err(Error::BadArgument {
+            param_name: "update".to_owned(),
+            desc: "can't change the version or symbol_cstr_len of existing DBN".to_owned(),
+        });
+    }
+    Ok(metadata)
+}
+
+/// Writes `metadata` followed by the rest of `records` to a temporary file that then
+/// replaces the file at `path`.
+fn copy_with_metadata(
+    path: &Path,
+    metadata: &Metadata,
+    records: impl io::Read,
+    is_zstd: bool,
+) -> Result<()> {
+    let permissions = fs::metadata(path)
+        .map_err(|e| {
+            Error::io(
+                e,
+                format!("reading permissions of file at path '{}'", path.display()),
+            )
+        })?
+        .permissions();
+    let (tmp_path, tmp_file) = create_temp_file(path)?;
+    let res = tmp_file
+        .set_permissions(permissions)
+        .map_err(|e| Error::io(e, "setting permissions of temporary file"))
+        .and_then(|_| {
+            let writer = BufWriter::new(tmp_file);
+            let writer = if is_zstd {
+                let mut encoder = unfinished_zstd_encoder(writer, &ZstdOptions::default())?;
+                write_with_metadata(&mut encoder, metadata, records)?;
+                encoder
+                    .finish()
+                    .map_err(|e| Error::io(e, "finishing zstd frame"))?
+            } else {
+                let mut writer = writer;
+                write_with_metadata(&mut writer, metadata, records)?;
+                writer
+            };
+            writer
+                .into_inner()
+                .map_err(|e| Error::io(e.into_error(), "flushing temporary file"))?;
+            fs::rename(&tmp_path, path)
+                .map_err(|e| Error::io(e, format!("replacing file at path '{}'", path.display())))
+        });
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    res
+}
+
+/// Exclusively creates a new temporary file next to `path` so it can be renamed over
+/// it, never reusing an existing file.
+fn create_temp_file(path: &Path) -> Result<(PathBuf, File)> {
+    const MAX_ATTEMPTS: u32 = 100;
+
+    let mut attempt = 0;
+    loop {
+        let mut tmp_path = PathBuf::from(path);
+        tmp_path
+            .as_mut_os_string()
+            .push(format!(".{}.{attempt}.tmp", std::process::id()));
+        match File::options().write(true).create_new(true).open(&tmp_path) {
+            Ok(file) => return Ok((tmp_path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < MAX_ATTEMPTS => {
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(Error::io(
+                    e,
+                    format!("creating temporary file at path '{}'", tmp_path.display()),
+                ))
+            }
+        }
+    }
+}
+
+fn write_with_metadata(
+    mut writer: impl io::Write,
+    metadata: &Metadata,
+    mut records: impl io::Read,
+) -> Result<()> {
+    MetadataEncoder::new(&mut writer).encode(metadata)?;
+    io::copy(&mut records, &mut writer).map_err(|e| Error::io(e, "copying records"))?;
+    Ok(())
 }
 
 /// Type for encoding Databento Binary Encoding (DBN) records (not metadata).
@@ -434,16 +641,21 @@ where
 mod tests {
     use std::{io::Seek, mem};
 
+    use proptest::prelude::*;
     use rstest::rstest;
 
     use super::*;
     use crate::{
         datasets::{GLBX_MDP3, XNAS_ITCH},
-        decode::{dbn::MetadataDecoder, FromLittleEndianSlice},
+        decode::{
+            dbn::MetadataDecoder, DbnMetadata, DecodeRecordRef, DynDecoder, FromLittleEndianSlice,
+        },
         enums::{SType, Schema},
-        MappingInterval, MetadataBuilder,
+        MappingInterval, MetadataBuilder, VersionUpgradePolicy,
     };
 
+    const TEST_DATA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/data");
+
     #[test]
     fn test_encode_decode_metadata_identity() {
         let metadata = Metadata {
@@ -610,6 +822,140 @@ mod tests {
         assert_eq!(res.limit, new_limit);
     }
 
+    #[test]
+    fn test_overwrite() {
+        let orig_metadata = MetadataBuilder::new()
+            .dataset(GLBX_MDP3.to_owned())
+            .schema(Some(Schema::Mbo))
+            .start(0)
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .symbols(vec!["ESZ3".to_owned(), "NQZ3".to_owned()])
+            .build();
+        let mut buffer = Vec::new();
+        MetadataEncoder::new(&mut buffer)
+            .encode(&orig_metadata)
+            .unwrap();
+        let encoded_length = buffer.len() as u32 - 8;
+        buffer.extend_from_slice(b"records");
+        let mut cursor = io::Cursor::new(&mut buffer);
+
+        let grown = Metadata {
+            symbols: vec!["ESZ3".to_owned(), "NQZ3".to_owned(), "YMZ3".to_owned()],
+            ..orig_metadata.clone()
+        };
+        assert!(!MetadataEncoder::new(&mut cursor)
+            .overwrite(&grown, encoded_length)
+            .unwrap());
+        let shrunk = Metadata {
+            dataset: XNAS_ITCH.to_owned(),
+            symbols: vec!["AAPL".to_owned()],
+            ..orig_metadata
+        };
+        assert!(MetadataEncoder::new(&mut cursor)
+            .overwrite(&shrunk, encoded_length)
+            .unwrap());
+        let mut reader = buffer.as_slice();
+        let res = MetadataDecoder::new(&mut reader).decode().unwrap();
+        assert_eq!(res, shrunk);
+        assert_eq!(reader, b"records");
+    }
+
+    #[rstest]
+    fn test_update_file_metadata(
+        #[values("dbn", "dbn.zst")] extension: &str,
+        #[values(false, true)] should_grow: bool,
+    ) {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join(format!("test.{extension}"));
+        fs::copy(
+            format!("{}/test_data.mbo.{extension}", TEST_DATA_PATH),
+            &path,
+        )
+        .unwrap();
+        let read_file = || {
+            let mut decoder = DynDecoder::from_file(&path, VersionUpgradePolicy::AsIs).unwrap();
+            let mut records = Vec::new();
+            while let Some(rec) = decoder.decode_record_ref().unwrap() {
+                records.extend_from_slice(rec.as_ref());
+            }
+            (decoder.metadata().clone(), records)
+        };
+        let (orig_metadata, orig_records) = read_file();
+        let symbols = if should_grow {
+            (0..100).map(|i| format!("SYM{i}")).collect()
+        } else {
+            Vec::new()
+        };
+        let res = update_file_metadata(&path, |metadata| {
+            metadata.dataset = XNAS_ITCH.to_owned();
+            metadata.symbols = symbols.clone();
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            res,
+            if extension == "dbn" && !should_grow {
+                MetadataUpdate::InPlace
+            } else {
+                MetadataUpdate::Copied
+            }
+        );
+        let (metadata, records) = read_file();
+        assert_eq!(
+            metadata,
+            Metadata {
+                dataset: XNAS_ITCH.to_owned(),
+                symbols,
+                ..orig_metadata
+            }
+        );
+        assert_eq!(records, orig_records);
+        // Only the updated file remains
+        assert_eq!(fs::read_dir(tmp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_update_file_metadata_copy_keeps_permissions_and_existing_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("test.dbn.zst");
+        fs::copy(format!("{}/test_data.mbo.dbn.zst", TEST_DATA_PATH), &path).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        let existing = tmp_dir
+            .path()
+            .join(format!("test.dbn.zst.{}.0.tmp", std::process::id()));
+        fs::write(&existing, b"existing").unwrap();
+        let res = update_file_metadata(&path, |metadata| {
+            metadata.dataset = XNAS_ITCH.to_owned();
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(res, MetadataUpdate::Copied);
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+        assert_eq!(fs::read(&existing).unwrap(), b"existing");
+        assert_eq!(fs::read_dir(tmp_dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_update_file_metadata_version_change() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("test.dbn");
+        fs::copy(format!("{}/test_data.mbo.dbn", TEST_DATA_PATH), &path).unwrap();
+        let orig = fs::read(&path).unwrap();
+        let res = update_file_metadata(&path, |metadata| {
+            metadata.version = 1;
+            Ok(())
+        });
+        assert!(matches!(res, Err(Error::BadArgument { .. })));
+        assert_eq!(fs::read(&path).unwrap(), orig);
+    }
+
     #[rstest]
     #[case(1)]
     #[case(2)]
@@ -651,4 +997,76 @@ mod tests {
         assert_eq!(calc_length as usize + 8, buffer.len());
         assert_eq!(MetadataEncoder::<Vec<u8>>::MIN_ENCODED_SIZE, buffer.len());
     }
+
+    #[rstest]
+    #[case::level(ZstdOptions { level: 19, long_distance_matching: true, ..Default::default() })]
+    #[cfg_attr(feature = "zstdmt", case::threads(ZstdOptions { threads: 2, ..Default::default() }))]
+    #[case::dictionary(ZstdOptions {
+        dictionary: Some(fs::read(format!("{TEST_DATA_PATH}/test_data.mbo.dbn")).unwrap()),
+        ..Default::default()
+    })]
+    fn test_encode_with_zstd_options(#[case] options: ZstdOptions) {
+        let mut decoder = DynDecoder::from_file(
+            format!("{TEST_DATA_PATH}/test_data.mbp-10.dbn.zst"),
+            VersionUpgradePolicy::AsIs,
+        )
+        .unwrap();
+        let mut buffer = Vec::new();
+        let mut encoder =
+            Encoder::with_zstd_options(&mut buffer, decoder.metadata(), &options).unwrap();
+        let mut expected = Vec::new();
+        while let Some(rec) = decoder.decode_record_ref().unwrap() {
+            encoder.encode_record_ref(rec).unwrap();
+            expected.push(rec.as_ref().to_vec());
+        }
+        drop(encoder);
+        assert!(crate::decode::zstd::starts_with_prefix(&buffer));
+        let reader = if let Some(dictionary) = options.dictionary.as_deref() {
+            crate::decode::DynReader::with_zstd_dictionary(buffer.as_slice(), dictionary)
+        } else {
+            crate::decode::DynReader::inferred_with_buffer(buffer.as_slice())
+        }
+        .unwrap();
+        let mut res_decoder = crate::decode::DbnDecoder::new(reader).unwrap();
+        assert_eq!(res_decoder.metadata(), decoder.metadata());
+        for exp in expected {
+            let rec = res_decoder.decode_record_ref().unwrap().unwrap();
+            assert_eq!(rec.as_ref(), exp.as_slice());
+        }
+        assert!(res_decoder.decode_record_ref().unwrap().is_none());
+    }
+
+    #[cfg(not(feature = "zstdmt"))]
+    #[test]
+    fn test_encode_with_zstd_threads_requires_feature() {
+        let options = ZstdOptions {
+            threads: 2,
+            ..Default::default()
+        };
+        let metadata = MetadataBuilder::new()
+            .dataset(GLBX_MDP3.to_owned())
+            .schema(Some(Schema::Mbo))
+            .start(0)
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .build();
+        let res = Encoder::with_zstd_options(Vec::new(), &metadata, &options);
+        assert!(
+            matches!(res, Err(Error::BadArgument { param_name, .. }) if param_name == "options")
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_encode_decode_metadata_identity(metadata in any::<Metadata>()) {
+            let mut buffer = Vec::new();
+            MetadataEncoder::new(&mut buffer).encode(&metadata)?;
+            prop_assert_eq!(
+                MetadataEncoder::<Vec<u8>>::calc_length(&metadata) as usize + 8,
+                buffer.len()
+            );
+            let res = MetadataDecoder::new(buffer.as_slice()).decode()?;
+            prop_assert_eq!(res, metadata);
+        }
+    }
 }
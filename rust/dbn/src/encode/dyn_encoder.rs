@@ -1,14 +1,15 @@
-use std::io;
+use std::{collections::HashMap, io};
 
 use streaming_iterator::StreamingIterator;
 
 use super::{
-    CsvEncoder, DbnEncodable, DbnEncoder, DynWriter, EncodeDbn, EncodeRecord, EncodeRecordRef,
-    EncodeRecordTextExt, JsonEncoder,
+    record_ref, CsvEncoder, DbnEncodable, DbnEncoder, DynWriter, EncodeDbn, EncodeRecord,
+    EncodeRecordRef, EncodeRecordTextExt, JsonEncoder, ZstdOptions,
 };
 use crate::{
     decode::{DbnMetadata, DecodeRecordRef},
-    Compression, Encoding, Metadata, RecordRef, Result, Schema,
+    registry, rtype, rtype_dispatch, rtype_ts_out_dispatch, Compression, Encoding, Error, Metadata,
+    RType, Record, RecordRef, Result, Schema,
 };
 
 /// An encoder whose [`Encoding`] and [`Compression`] can be set at runtime.
@@ -25,22 +26,45 @@ where
     Dbn(DbnEncoder<DynWriter<'a, W>>),
     Csv(CsvEncoder<DynWriter<'a, W>>),
     Json(JsonEncoder<DynWriter<'a, W>>),
+    #[cfg(feature = "parquet")]
+    Parquet(super::ParquetEncoder<W>),
+    Split(SplitEncoder<'a, W>),
 }
 
+/// Creates a writer for the output of a record type from its name.
+type MakeWriter<W> = Box<dyn FnMut(&str) -> io::Result<W>>;
+
 /// Helper for constructing a [`DynEncoder`].
 pub struct DynEncoderBuilder<'m, W>
 where
     W: io::Write,
 {
-    writer: W,
+    output: Output<W>,
     encoding: Encoding,
     compression: Compression,
     metadata: &'m Metadata,
+    settings: Settings,
+}
+
+enum Output<W> {
+    Writer(W),
+    Split(MakeWriter<W>),
+}
+
+/// The settings of a [`DynEncoderBuilder`] that are independent of the output.
+#[derive(Clone)]
+struct Settings {
     write_header: bool,
     should_pretty_print: bool,
     use_pretty_px: bool,
     use_pretty_ts: bool,
     with_symbol: bool,
+    delimiter: u8,
+    fields: Option<Vec<String>>,
+    renames: Vec<(String, String)>,
+    null_value: Option<String>,
+    union: Option<Vec<RType>>,
+    zstd_options: ZstdOptions,
 }
 
 impl<'m, W> DynEncoderBuilder<'m, W>
@@ -54,17 +78,57 @@ where
         encoding: Encoding,
         compression: Compression,
         metadata: &'m Metadata,
+    ) -> Self {
+        Self::with_output(Output::Writer(writer), encoding, compression, metadata)
+    }
+
+    /// Creates a new builder for an encoder that encodes each record type to a
+    /// separate output created with `make_writer`, e.g. a CSV file per schema for
+    /// mixed-schema DBN. Only CSV and JSON encodings are supported.
+    ///
+    /// `make_writer` is called with the name of the record type the first time a
+    /// record of that type is encoded. The name is the schema for record types with
+    /// one, e.g. `"trades"` or `"definition"`, otherwise the [`RType`], e.g. `"system"`.
+    /// When [`Metadata::schema`] is [`Schema::Tbbo`], it's used instead of `"mbp-1"`.
+    /// CSV headers are written for each output unless `write_header` is `false`.
+    pub fn new_split(
+        make_writer: impl FnMut(&str) -> io::Result<W> + 'static,
+        encoding: Encoding,
+        compression: Compression,
+        metadata: &'m Metadata,
+    ) -> Self {
+        Self::with_output(
+            Output::Split(Box::new(make_writer)),
+            encoding,
+            compression,
+            metadata,
+        )
+    }
+
+    fn with_output(
+        output: Output<W>,
+        encoding: Encoding,
+        compression: Compression,
+        metadata: &'m Metadata,
     ) -> Self {
         Self {
-            writer,
+            output,
             encoding,
             compression,
             metadata,
-            write_header: true,
-            should_pretty_print: false,
-            use_pretty_px: false,
-            use_pretty_ts: false,
-            with_symbol: false,
+            settings: Settings {
+                write_header: true,
+                should_pretty_print: false,
+                use_pretty_px: false,
+                use_pretty_ts: false,
+                with_symbol: false,
+                delimiter: b',',
+                fields: None,
+                renames: Vec::new(),
+                null_value: None,
+                union: None,
+                zstd_options: ZstdOptions::default(),
+            },
         }
     }
 
@@ -72,7 +136,7 @@ where
     /// CSV. Defaults to `true`. If `false`, a header row can still be written with
     /// [`DynEncoder::encode_header()`] or [`DynEncoder::encode_header_for_schema()`].
     pub fn write_header(mut self, write_header: bool) -> Self {
-        self.write_header = write_header;
+        self.settings.write_header = write_header;
         self
     }
 
@@ -88,63 +152,406 @@ where
     /// indentation if encoding JSON. Defaults to `false` where each JSON object is
     /// compact with no spacing.
     pub fn should_pretty_print(mut self, should_pretty_print: bool) -> Self {
-        self.should_pretty_print = should_pretty_print;
+        self.settings.should_pretty_print = should_pretty_print;
         self
     }
 
     /// Sets whether the encoder will serialize price fields as a decimal in CSV and
     /// JSON encodings. Defaults to `false`.
     pub fn use_pretty_px(mut self, use_pretty_px: bool) -> Self {
-        self.use_pretty_px = use_pretty_px;
+        self.settings.use_pretty_px = use_pretty_px;
         self
     }
 
     /// Sets whether the encoder will serialize timestamp fields as ISO8601 datetime
     /// strings in CSV and JSON encodings. Defaults to `false`.
     pub fn use_pretty_ts(mut self, use_pretty_ts: bool) -> Self {
-        self.use_pretty_ts = use_pretty_ts;
+        self.settings.use_pretty_ts = use_pretty_ts;
         self
     }
 
-    /// Sets whether to add a header field "symbol" if encoding CSV. Defaults to
-    /// `false`.
+    /// Sets whether to add a header field "symbol" if encoding CSV or a `symbol`
+    /// column if encoding Parquet. Defaults to `false`.
     pub fn with_symbol(mut self, with_symbol: bool) -> Self {
-        self.with_symbol = with_symbol;
+        self.settings.with_symbol = with_symbol;
+        self
+    }
+
+    /// Sets the field delimiter if encoding CSV. Defaults to `b','`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.settings.delimiter = delimiter;
+        self
+    }
+
+    /// Sets the fields to encode in the given order if encoding CSV. By default, all
+    /// fields are encoded. See [`csv::EncoderBuilder::fields()`](super::csv::EncoderBuilder::fields).
+    pub fn fields<S: Into<String>>(mut self, fields: impl IntoIterator<Item = S>) -> Self {
+        self.settings.fields = Some(fields.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Renames the header field for `field` to `header` if encoding CSV.
+    pub fn rename_field(mut self, field: impl Into<String>, header: impl Into<String>) -> Self {
+        self.settings.renames.push((field.into(), header.into()));
+        self
+    }
+
+    /// Sets the string to encode in place of undefined prices, timestamps, and sizes if
+    /// encoding CSV. See
+    /// [`csv::EncoderBuilder::null_value()`](super::csv::EncoderBuilder::null_value).
+    pub fn null_value(mut self, null_value: impl Into<String>) -> Self {
+        self.settings.null_value = Some(null_value.into());
+        self
+    }
+
+    /// Sets the record types to encode in a single table with the union of their
+    /// fields if encoding CSV. See
+    /// [`csv::EncoderBuilder::union()`](super::csv::EncoderBuilder::union).
+    pub fn union(mut self, rtypes: impl IntoIterator<Item = RType>) -> Self {
+        self.settings.union = Some(rtypes.into_iter().collect());
+        self
+    }
+
+    /// Sets the Zstandard compression level, threads, and dictionary used when
+    /// `compression` is [`Compression::ZStd`]. When encoding Parquet, only the level
+    /// applies.
+    pub fn zstd_options(mut self, zstd_options: ZstdOptions) -> Self {
+        self.settings.zstd_options = zstd_options;
         self
     }
 
     /// Creates the new encoder with the previously specified settings and if
     /// `write_header` is `true`, encodes the header row.
     ///
+    /// When encoding Parquet, `compression` applies to the column chunks instead of
+    /// the whole output.
+    ///
     /// # Errors
     /// This function returns an error if it fails to write the CSV header row or the
-    /// DBN metadata.
+    /// DBN metadata, if `encoding` is Parquet and the `parquet` feature isn't
+    /// enabled, or if splitting records by type and `encoding` isn't CSV or JSON.
     pub fn build<'a>(self) -> crate::Result<DynEncoder<'a, W>> {
-        let writer = DynWriter::new(self.writer, self.compression)?;
-        Ok(DynEncoder(match self.encoding {
-            Encoding::Dbn => DynEncoderImpl::Dbn(DbnEncoder::new(writer, self.metadata)?),
-            Encoding::Csv => {
-                let builder = CsvEncoder::builder(writer)
-                    .use_pretty_px(self.use_pretty_px)
-                    .use_pretty_ts(self.use_pretty_ts)
-                    .write_header(self.write_header)
-                    .ts_out(self.metadata.ts_out)
-                    .with_symbol(self.with_symbol);
-                DynEncoderImpl::Csv(if self.write_header {
-                    builder.schema(self.metadata.schema)?.build()?
-                } else {
-                    builder.build()?
-                })
-            }
-            Encoding::Json => DynEncoderImpl::Json(
-                JsonEncoder::builder(writer)
-                    .should_pretty_print(self.should_pretty_print)
-                    .use_pretty_px(self.use_pretty_px)
-                    .use_pretty_ts(self.use_pretty_ts)
-                    .build(),
+        match self.output {
+            Output::Writer(writer) => build(
+                writer,
+                self.encoding,
+                self.compression,
+                self.metadata,
+                self.settings,
             ),
-        }))
+            Output::Split(make_writer) => Ok(DynEncoder(DynEncoderImpl::Split(SplitEncoder::new(
+                make_writer,
+                self.encoding,
+                self.compression,
+                self.metadata,
+                self.settings,
+            )?))),
+        }
+    }
+}
+
+fn build<'a, W: io::Write>(
+    writer: W,
+    encoding: Encoding,
+    compression: Compression,
+    metadata: &Metadata,
+    settings: Settings,
+) -> crate::Result<DynEncoder<'a, W>> {
+    if encoding == Encoding::Parquet {
+        return build_parquet(writer, compression, metadata, settings);
+    }
+    let writer = DynWriter::with_zstd_options(writer, compression, &settings.zstd_options)?;
+    Ok(DynEncoder(match encoding {
+        Encoding::Dbn => DynEncoderImpl::Dbn(DbnEncoder::new(writer, metadata)?),
+        Encoding::Csv => {
+            let mut builder = CsvEncoder::builder(writer)
+                .use_pretty_px(settings.use_pretty_px)
+                .use_pretty_ts(settings.use_pretty_ts)
+                .write_header(settings.write_header)
+                .ts_out(metadata.ts_out)
+                .with_symbol(settings.with_symbol)
+                .delimiter(settings.delimiter);
+            if let Some(fields) = settings.fields {
+                builder = builder.fields(fields);
+            }
+            for (field, header) in settings.renames {
+                builder = builder.rename_field(field, header);
+            }
+            if let Some(null_value) = settings.null_value {
+                builder = builder.null_value(null_value);
+            }
+            DynEncoderImpl::Csv(if let Some(union) = settings.union {
+                builder.union(union).build()?
+            } else if settings.write_header {
+                builder.schema(metadata.schema)?.build()?
+            } else {
+                builder.build()?
+            })
+        }
+        Encoding::Json => DynEncoderImpl::Json(
+            JsonEncoder::builder(writer)
+                .should_pretty_print(settings.should_pretty_print)
+                .use_pretty_px(settings.use_pretty_px)
+                .use_pretty_ts(settings.use_pretty_ts)
+                .build(),
+        ),
+        Encoding::Parquet => unreachable!("handled above"),
+    }))
+}
+
+#[cfg(feature = "parquet")]
+fn build_parquet<'a, W: io::Write>(
+    writer: W,
+    compression: Compression,
+    metadata: &Metadata,
+    settings: Settings,
+) -> crate::Result<DynEncoder<'a, W>> {
+    use parquet::basic::{Compression as ParquetCompression, ZstdLevel};
+
+    let compression = match compression {
+        Compression::None => ParquetCompression::UNCOMPRESSED,
+        Compression::ZStd if settings.zstd_options.level == 0 => {
+            ParquetCompression::ZSTD(ZstdLevel::default())
+        }
+        Compression::ZStd => {
+            ParquetCompression::ZSTD(ZstdLevel::try_new(settings.zstd_options.level).map_err(
+                |e| crate::Error::BadArgument {
+                    param_name: "zstd_options".to_owned(),
+                    desc: e.to_string(),
+                },
+            )?)
+        }
+        compression => {
+            return Err(crate::Error::BadArgument {
+                param_name: "compression".to_owned(),
+                desc: format!("{compression} compression isn't supported with Parquet"),
+            })
+        }
+    };
+    Ok(DynEncoder(DynEncoderImpl::Parquet(
+        super::ParquetEncoder::builder(writer, metadata)
+            .compression(compression)
+            .with_symbol(settings.with_symbol)
+            .build()?,
+    )))
+}
+
+#[cfg(not(feature = "parquet"))]
+fn build_parquet<'a, W: io::Write>(
+    _writer: W,
+    _compression: Compression,
+    _metadata: &Metadata,
+    _settings: Settings,
+) -> crate::Result<DynEncoder<'a, W>> {
+    Err(crate::Error::BadArgument {
+        param_name: "encoding".to_owned(),
+        desc: "Parquet encoding requires the `parquet` feature".to_owned(),
+    })
+}
+
+/// Encodes each record type to a separate output.
+struct SplitEncoder<'a, W>
+where
+    W: io::Write,
+{
+    make_writer: MakeWriter<W>,
+    encoding: Encoding,
+    compression: Compression,
+    metadata: Metadata,
+    settings: Settings,
+    encoders: HashMap<u8, DynEncoder<'a, W>>,
+}
+
+impl<'a, W> SplitEncoder<'a, W>
+where
+    W: io::Write,
+{
+    fn new(
+        make_writer: MakeWriter<W>,
+        encoding: Encoding,
+        compression: Compression,
+        metadata: &Metadata,
+        settings: Settings,
+    ) -> Result<Self> {
+        if !matches!(encoding, Encoding::Csv | Encoding::Json) {
+            return Err(Error::BadArgument {
+                param_name: "encoding".to_owned(),
+                desc: format!("can only split {encoding} by record type"),
+            });
+        }
+        Ok(Self {
+            make_writer,
+            encoding,
+            compression,
+            metadata: metadata.clone(),
+            settings,
+            encoders: HashMap::new(),
+        })
+    }
+
+    /// Returns the encoder for the type of `record`, creating it and calling
+    /// `write_header` with it if it's the first record of its type.
+    fn encoder(
+        &mut self,
+        record: RecordRef,
+        write_header: impl FnOnce(&mut DynEncoder<'a, W>) -> Result<()>,
+    ) -> Result<&mut DynEncoder<'a, W>> {
+        let rtype = record.header().rtype;
+        if !self.encoders.contains_key(&rtype) {
+            let name = self.output_name(rtype);
+            let writer = (self.make_writer)(&name)
+                .map_err(|e| Error::io(e, format!("creating output for {name}")))?;
+            let mut encoder = build(
+                writer,
+                self.encoding,
+                self.compression,
+                &self.metadata,
+                Settings {
+                    write_header: false,
+                    ..self.settings.clone()
+                },
+            )?;
+            if self.settings.write_header {
+                write_header(&mut encoder)?;
+            }
+            self.encoders.insert(rtype, encoder);
+        }
+        Ok(self.encoders.get_mut(&rtype).unwrap())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        for encoder in self.encoders.values_mut() {
+            encoder.finish()?;
+        }
+        Ok(())
+    }
+
+    fn output_name(&self, rtype: u8) -> String {
+        if let Some(schema) = self
+            .metadata
+            .schema
+            .filter(|&schema| RType::from(schema) as u8 == rtype)
+        {
+            schema.to_string()
+        } else if let Some(schema) = rtype::try_into_schema(rtype) {
+            schema.to_string()
+        } else if let Ok(rtype) = RType::try_from(rtype) {
+            rtype.as_str().to_owned()
+        } else if let Some(registration) = registry::registration(rtype) {
+            // Strip module path
+            registration
+                .type_name()
+                .rsplit("::")
+                .next()
+                .unwrap_or_default()
+                .to_owned()
+        } else {
+            format!("rtype-{rtype:#04x}")
+        }
+    }
+}
+
+impl<'a, W> EncodeRecord for SplitEncoder<'a, W>
+where
+    W: io::Write,
+{
+    fn encode_record<R: DbnEncodable>(&mut self, record: &R) -> Result<()> {
+        self.encoder(record_ref(record), |encoder| {
+            encoder.encode_header::<R>(false)
+        })?
+        .encode_record(record)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        for encoder in self.encoders.values_mut() {
+            encoder.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, W> EncodeRecordRef for SplitEncoder<'a, W>
+where
+    W: io::Write,
+{
+    fn encode_record_ref(&mut self, record: RecordRef) -> Result<()> {
+        self.encoder(record, |encoder| write_ref_header(encoder, record, false))?
+            .encode_record_ref(record)
     }
+
+    unsafe fn encode_record_ref_ts_out(&mut self, record: RecordRef, ts_out: bool) -> Result<()> {
+        self.encoder(record, |encoder| {
+            write_ref_ts_out_header(encoder, record, ts_out, false)
+        })?
+        .encode_record_ref_ts_out(record, ts_out)
+    }
+}
+
+impl<'a, W> EncodeDbn for SplitEncoder<'a, W> where W: io::Write {}
+
+impl<'a, W> EncodeRecordTextExt for SplitEncoder<'a, W>
+where
+    W: io::Write,
+{
+    fn encode_record_with_sym<R: DbnEncodable>(
+        &mut self,
+        record: &R,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        self.encoder(record_ref(record), |encoder| {
+            encoder.encode_header::<R>(true)
+        })?
+        .encode_record_with_sym(record, symbol)
+    }
+
+    fn encode_ref_with_sym(&mut self, record: RecordRef, symbol: Option<&str>) -> Result<()> {
+        self.encoder(record, |encoder| write_ref_header(encoder, record, true))?
+            .encode_ref_with_sym(record, symbol)
+    }
+
+    unsafe fn encode_ref_ts_out_with_sym(
+        &mut self,
+        record: RecordRef,
+        ts_out: bool,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        self.encoder(record, |encoder| {
+            write_ref_ts_out_header(encoder, record, ts_out, true)
+        })?
+        .encode_ref_ts_out_with_sym(record, ts_out, symbol)
+    }
+}
+
+fn write_header<W: io::Write, R: DbnEncodable>(
+    _record: &R,
+    encoder: &mut DynEncoder<W>,
+    with_symbol: bool,
+) -> Result<()> {
+    encoder.encode_header::<R>(with_symbol)
+}
+
+/// Writes the header for the type of `record`. Records of registered types write
+/// their own headers.
+fn write_ref_header<W: io::Write>(
+    encoder: &mut DynEncoder<W>,
+    record: RecordRef,
+    with_symbol: bool,
+) -> Result<()> {
+    rtype_dispatch!(record, write_header, encoder, with_symbol).unwrap_or(Ok(()))
+}
+
+/// Writes the header for the type of `record` with `ts_out`. Records of registered
+/// types write their own headers.
+///
+/// # Safety
+/// `ts_out` must be `true` only if `record` has `ts_out` appended.
+unsafe fn write_ref_ts_out_header<W: io::Write>(
+    encoder: &mut DynEncoder<W>,
+    record: RecordRef,
+    ts_out: bool,
+    with_symbol: bool,
+) -> Result<()> {
+    rtype_ts_out_dispatch!(record, ts_out, write_header, encoder, with_symbol).unwrap_or(Ok(()))
 }
 
 impl<'a, W> DynEncoder<'a, W>
@@ -220,6 +627,25 @@ where
             _ => Ok(()),
         }
     }
+
+    /// Completes the output. For Parquet, this writes any buffered records and the
+    /// file footer, without which the file can't be read. It's a no-op for the other
+    /// encodings.
+    ///
+    /// The Parquet footer is also written when the encoder is dropped, but any error
+    /// is then ignored, so this method should be called after encoding the last record.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to write to the underlying writer
+    /// or the Parquet encoder was already finished.
+    pub fn finish(&mut self) -> Result<()> {
+        match &mut self.0 {
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(encoder) => encoder.finish(),
+            DynEncoderImpl::Split(encoder) => encoder.finish(),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl<'a, W> EncodeRecord for DynEncoder<'a, W>
@@ -279,6 +705,19 @@ where
     ) -> Result<()> {
         self.0.encode_record_with_sym(record, symbol)
     }
+
+    fn encode_ref_with_sym(&mut self, record: RecordRef, symbol: Option<&str>) -> Result<()> {
+        self.0.encode_ref_with_sym(record, symbol)
+    }
+
+    unsafe fn encode_ref_ts_out_with_sym(
+        &mut self,
+        record: RecordRef,
+        ts_out: bool,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        self.0.encode_ref_ts_out_with_sym(record, ts_out, symbol)
+    }
 }
 
 impl<'a, W> EncodeRecord for DynEncoderImpl<'a, W>
@@ -290,6 +729,9 @@ where
             DynEncoderImpl::Dbn(enc) => enc.encode_record(record),
             DynEncoderImpl::Csv(enc) => enc.encode_record(record),
             DynEncoderImpl::Json(enc) => enc.encode_record(record),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(enc) => enc.encode_record(record),
+            DynEncoderImpl::Split(enc) => enc.encode_record(record),
         }
     }
 
@@ -298,6 +740,9 @@ where
             DynEncoderImpl::Dbn(enc) => enc.flush(),
             DynEncoderImpl::Csv(enc) => enc.flush(),
             DynEncoderImpl::Json(enc) => enc.flush(),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(enc) => enc.flush(),
+            DynEncoderImpl::Split(enc) => enc.flush(),
         }
     }
 }
@@ -311,6 +756,9 @@ where
             DynEncoderImpl::Dbn(enc) => enc.encode_record_ref(record),
             DynEncoderImpl::Csv(enc) => enc.encode_record_ref(record),
             DynEncoderImpl::Json(enc) => enc.encode_record_ref(record),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(enc) => enc.encode_record_ref(record),
+            DynEncoderImpl::Split(enc) => enc.encode_record_ref(record),
         }
     }
 
@@ -319,6 +767,9 @@ where
             DynEncoderImpl::Dbn(enc) => enc.encode_record_ref_ts_out(record, ts_out),
             DynEncoderImpl::Csv(enc) => enc.encode_record_ref_ts_out(record, ts_out),
             DynEncoderImpl::Json(enc) => enc.encode_record_ref_ts_out(record, ts_out),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(enc) => enc.encode_record_ref_ts_out(record, ts_out),
+            DynEncoderImpl::Split(enc) => enc.encode_record_ref_ts_out(record, ts_out),
         }
     }
 }
@@ -332,6 +783,9 @@ where
             DynEncoderImpl::Dbn(encoder) => encoder.encode_records(records),
             DynEncoderImpl::Csv(encoder) => encoder.encode_records(records),
             DynEncoderImpl::Json(encoder) => encoder.encode_records(records),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(encoder) => encoder.encode_records(records),
+            DynEncoderImpl::Split(encoder) => encoder.encode_records(records),
         }
     }
 
@@ -343,6 +797,9 @@ where
             DynEncoderImpl::Dbn(encoder) => encoder.encode_stream(stream),
             DynEncoderImpl::Csv(encoder) => encoder.encode_stream(stream),
             DynEncoderImpl::Json(encoder) => encoder.encode_stream(stream),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(encoder) => encoder.encode_stream(stream),
+            DynEncoderImpl::Split(encoder) => encoder.encode_stream(stream),
         }
     }
 
@@ -351,6 +808,9 @@ where
             DynEncoderImpl::Dbn(encoder) => encoder.encode_decoded(decoder),
             DynEncoderImpl::Csv(encoder) => encoder.encode_decoded(decoder),
             DynEncoderImpl::Json(encoder) => encoder.encode_decoded(decoder),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(encoder) => encoder.encode_decoded(decoder),
+            DynEncoderImpl::Split(encoder) => encoder.encode_decoded(decoder),
         }
     }
 }
@@ -369,6 +829,831 @@ where
             Self::Dbn(encoder) => encoder.encode_record(record),
             Self::Csv(encoder) => encoder.encode_record_with_sym(record, symbol),
             Self::Json(encoder) => encoder.encode_record_with_sym(record, symbol),
+            #[cfg(feature = "parquet")]
+            Self::Parquet(encoder) => encoder.encode_record_with_sym(record, symbol),
+            Self::Split(encoder) => encoder.encode_record_with_sym(record, symbol),
+        }
+    }
+
+    fn encode_ref_with_sym(&mut self, record: RecordRef, symbol: Option<&str>) -> Result<()> {
+        match self {
+            // Not supported for DBN so ignore `symbol`
+            Self::Dbn(encoder) => encoder.encode_record_ref(record),
+            Self::Csv(encoder) => encoder.encode_ref_with_sym(record, symbol),
+            Self::Json(encoder) => encoder.encode_ref_with_sym(record, symbol),
+            #[cfg(feature = "parquet")]
+            Self::Parquet(encoder) => encoder.encode_ref_with_sym(record, symbol),
+            Self::Split(encoder) => encoder.encode_ref_with_sym(record, symbol),
+        }
+    }
+
+    unsafe fn encode_ref_ts_out_with_sym(
+        &mut self,
+        record: RecordRef,
+        ts_out: bool,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        match self {
+            // Not supported for DBN so ignore `symbol`
+            Self::Dbn(encoder) => encoder.encode_record_ref_ts_out(record, ts_out),
+            Self::Csv(encoder) => encoder.encode_ref_ts_out_with_sym(record, ts_out, symbol),
+            Self::Json(encoder) => encoder.encode_ref_ts_out_with_sym(record, ts_out, symbol),
+            #[cfg(feature = "parquet")]
+            Self::Parquet(encoder) => encoder.encode_ref_ts_out_with_sym(record, ts_out, symbol),
+            Self::Split(encoder) => encoder.encode_ref_ts_out_with_sym(record, ts_out, symbol),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub use r#async::{DynEncoder as DynAsyncEncoder, DynEncoderBuilder as DynAsyncEncoderBuilder};
+
+#[cfg(feature = "async")]
+mod r#async {
+    use tokio::io::{self, AsyncWriteExt};
+
+    use super::Settings;
+    use crate::{
+        encode::{
+            AsyncCsvEncoder, AsyncDbnEncoder, AsyncJsonEncoder, DbnEncodable, DynAsyncWriter,
+            ZstdOptions,
+        },
+        Compression, Encoding, Error, Metadata, RType, RecordRef, Result, Schema,
+    };
+
+    /// An async encoder whose [`Encoding`] and [`Compression`] can be set at runtime.
+    ///
+    /// Parquet encoding isn't supported.
+    pub struct DynEncoder<W>(DynEncoderImpl<W>)
+    where
+        W: io::AsyncWriteExt + Unpin;
+
+    // [`DynEncoder`] isn't cloned so this isn't a concern.
+    #[allow(clippy::large_enum_variant)]
+    enum DynEncoderImpl<W>
+    where
+        W: io::AsyncWriteExt + Unpin,
+    {
+        Dbn(AsyncDbnEncoder<DynAsyncWriter<W>>),
+        Csv(AsyncCsvEncoder<DynAsyncWriter<W>>),
+        Json(AsyncJsonEncoder<DynAsyncWriter<W>>),
+    }
+
+    /// Helper for constructing an async [`DynEncoder`].
+    pub struct DynEncoderBuilder<'m, W>
+    where
+        W: io::AsyncWriteExt + Unpin,
+    {
+        writer: W,
+        encoding: Encoding,
+        compression: Compression,
+        metadata: &'m Metadata,
+        settings: Settings,
+    }
+
+    impl<'m, W> DynEncoderBuilder<'m, W>
+    where
+        W: io::AsyncWriteExt + Unpin,
+    {
+        /// Creates a new builder. All required fields for the builder are passed to this
+        /// function.
+        pub fn new(
+            writer: W,
+            encoding: Encoding,
+            compression: Compression,
+            metadata: &'m Metadata,
+        ) -> Self {
+            Self {
+                writer,
+                encoding,
+                compression,
+                metadata,
+                settings: Settings {
+                    write_header: true,
+                    should_pretty_print: false,
+                    use_pretty_px: false,
+                    use_pretty_ts: false,
+                    with_symbol: false,
+                    delimiter: b',',
+                    fields: None,
+                    renames: Vec::new(),
+                    null_value: None,
+                    union: None,
+                    zstd_options: ZstdOptions::default(),
+                },
+            }
+        }
+
+        /// Sets whether the encoder will write a header row when it's created if
+        /// encoding CSV. Defaults to `true`. If `false`, a header row can still be
+        /// written with [`DynEncoder::encode_header()`] or
+        /// [`DynEncoder::encode_header_for_schema()`].
+        pub fn write_header(mut self, write_header: bool) -> Self {
+            self.settings.write_header = write_header;
+            self
+        }
+
+        /// Sets all three pretty options together: `should_pretty_print`,
+        /// `use_pretty_px`, and `use_pretty_ts`. By default all are `false`.
+        pub fn all_pretty(self, all_pretty: bool) -> Self {
+            self.should_pretty_print(all_pretty)
+                .use_pretty_px(all_pretty)
+                .use_pretty_ts(all_pretty)
+        }
+
+        /// Sets whether the encoder should encode nicely-formatted JSON objects with
+        /// indentation if encoding JSON. Defaults to `false` where each JSON object is
+        /// compact with no spacing.
+        pub fn should_pretty_print(mut self, should_pretty_print: bool) -> Self {
+            self.settings.should_pretty_print = should_pretty_print;
+            self
+        }
+
+        /// Sets whether the encoder will serialize price fields as a decimal in CSV and
+        /// JSON encodings. Defaults to `false`.
+        pub fn use_pretty_px(mut self, use_pretty_px: bool) -> Self {
+            self.settings.use_pretty_px = use_pretty_px;
+            self
+        }
+
+        /// Sets whether the encoder will serialize timestamp fields as ISO8601 datetime
+        /// strings in CSV and JSON encodings. Defaults to `false`.
+        pub fn use_pretty_ts(mut self, use_pretty_ts: bool) -> Self {
+            self.settings.use_pretty_ts = use_pretty_ts;
+            self
+        }
+
+        /// Sets whether to add a header field "symbol" if encoding CSV. Defaults to
+        /// `false`.
+        pub fn with_symbol(mut self, with_symbol: bool) -> Self {
+            self.settings.with_symbol = with_symbol;
+            self
+        }
+
+        /// Sets the field delimiter if encoding CSV. Defaults to `b','`.
+        pub fn delimiter(mut self, delimiter: u8) -> Self {
+            self.settings.delimiter = delimiter;
+            self
+        }
+
+        /// Sets the fields to encode in the given order if encoding CSV. By default,
+        /// all fields are encoded. See
+        /// [`csv::EncoderBuilder::fields()`](crate::encode::csv::EncoderBuilder::fields).
+        pub fn fields<S: Into<String>>(mut self, fields: impl IntoIterator<Item = S>) -> Self {
+            self.settings.fields = Some(fields.into_iter().map(Into::into).collect());
+            self
+        }
+
+        /// Renames the header field for `field` to `header` if encoding CSV.
+        pub fn rename_field(mut self, field: impl Into<String>, header: impl Into<String>) -> Self {
+            self.settings.renames.push((field.into(), header.into()));
+            self
+        }
+
+        /// Sets the string to encode in place of undefined prices, timestamps, and
+        /// sizes if encoding CSV. See
+        /// [`csv::EncoderBuilder::null_value()`](crate::encode::csv::EncoderBuilder::null_value).
+        pub fn null_value(mut self, null_value: impl Into<String>) -> Self {
+            self.settings.null_value = Some(null_value.into());
+            self
         }
+
+        /// Sets the record types to encode in a single table with the union of their
+        /// fields if encoding CSV. See
+        /// [`csv::EncoderBuilder::union()`](crate::encode::csv::EncoderBuilder::union).
+        pub fn union(mut self, rtypes: impl IntoIterator<Item = RType>) -> Self {
+            self.settings.union = Some(rtypes.into_iter().collect());
+            self
+        }
+
+        /// Sets the Zstandard compression level, threads, and dictionary used when
+        /// `compression` is [`Compression::ZStd`].
+        pub fn zstd_options(mut self, zstd_options: ZstdOptions) -> Self {
+            self.settings.zstd_options = zstd_options;
+            self
+        }
+
+        /// Creates the new encoder with the previously specified settings and if
+        /// `write_header` is `true`, encodes the header row.
+        ///
+        /// # Errors
+        /// This function returns an error if it fails to write the CSV header row or
+        /// the DBN metadata, if `encoding` is Parquet, or if the Zstd options are
+        /// invalid.
+        pub async fn build(self) -> Result<DynEncoder<W>> {
+            let Self {
+                writer,
+                encoding,
+                compression,
+                metadata,
+                settings,
+            } = self;
+            if encoding == Encoding::Parquet {
+                return Err(Error::BadArgument {
+                    param_name: "encoding".to_owned(),
+                    desc: "Parquet encoding isn't supported by the async encoder".to_owned(),
+                });
+            }
+            let writer =
+                DynAsyncWriter::with_zstd_options(writer, compression, &settings.zstd_options)?;
+            Ok(DynEncoder(match encoding {
+                Encoding::Dbn => DynEncoderImpl::Dbn(AsyncDbnEncoder::new(writer, metadata).await?),
+                Encoding::Csv => {
+                    let mut builder = AsyncCsvEncoder::builder(writer)
+                        .use_pretty_px(settings.use_pretty_px)
+                        .use_pretty_ts(settings.use_pretty_ts)
+                        .write_header(settings.write_header)
+                        .ts_out(metadata.ts_out)
+                        .with_symbol(settings.with_symbol)
+                        .delimiter(settings.delimiter);
+                    if let Some(fields) = settings.fields {
+                        builder = builder.fields(fields);
+                    }
+                    for (field, header) in settings.renames {
+                        builder = builder.rename_field(field, header);
+                    }
+                    if let Some(null_value) = settings.null_value {
+                        builder = builder.null_value(null_value);
+                    }
+                    DynEncoderImpl::Csv(if let Some(union) = settings.union {
+                        builder.union(union).build().await?
+                    } else if settings.write_header {
+                        builder.schema(metadata.schema)?.build().await?
+                    } else {
+                        builder.build().await?
+                    })
+                }
+                Encoding::Json => DynEncoderImpl::Json(AsyncJsonEncoder::new(
+                    writer,
+                    settings.should_pretty_print,
+                    settings.use_pretty_px,
+                    settings.use_pretty_ts,
+                )),
+                Encoding::Parquet => unreachable!("handled above"),
+            }))
+        }
+    }
+
+    impl<W> DynEncoder<W>
+    where
+        W: io::AsyncWriteExt + Unpin,
+    {
+        /// Constructs a new instance of [`DynEncoder`].
+        ///
+        /// Note: `should_pretty_print`, `use_pretty_px`, and `use_pretty_ts` are ignored
+        /// if `encoding` is `Dbn`.
+        ///
+        /// # Errors
+        /// This function returns an error if it fails to encode the DBN metadata or the
+        /// CSV header, or if `encoding` is Parquet.
+        pub async fn new(
+            writer: W,
+            encoding: Encoding,
+            compression: Compression,
+            metadata: &Metadata,
+            should_pretty_print: bool,
+            use_pretty_px: bool,
+            use_pretty_ts: bool,
+        ) -> Result<Self> {
+            Self::builder(writer, encoding, compression, metadata)
+                .should_pretty_print(should_pretty_print)
+                .use_pretty_px(use_pretty_px)
+                .use_pretty_ts(use_pretty_ts)
+                .build()
+                .await
+        }
+
+        /// Creates a builder for configuring a `DynEncoder` object.
+        pub fn builder(
+            writer: W,
+            encoding: Encoding,
+            compression: Compression,
+            metadata: &Metadata,
+        ) -> DynEncoderBuilder<'_, W> {
+            DynEncoderBuilder::new(writer, encoding, compression, metadata)
+        }
+
+        /// Returns a reference to the underlying writer.
+        pub fn get_ref(&self) -> &DynAsyncWriter<W> {
+            match &self.0 {
+                DynEncoderImpl::Dbn(enc) => enc.get_ref(),
+                DynEncoderImpl::Csv(enc) => enc.get_ref(),
+                DynEncoderImpl::Json(enc) => enc.get_ref(),
+            }
+        }
+
+        /// Returns a mutable reference to the underlying writer. Compressed output
+        /// isn't complete until the writer is shut down, e.g. with
+        /// [`shutdown()`](Self::shutdown).
+        pub fn get_mut(&mut self) -> &mut DynAsyncWriter<W> {
+            match &mut self.0 {
+                DynEncoderImpl::Dbn(enc) => enc.get_mut(),
+                DynEncoderImpl::Csv(enc) => enc.get_mut(),
+                DynEncoderImpl::Json(enc) => enc.get_mut(),
+            }
+        }
+
+        /// Encodes the CSV header for the record type `R`, i.e. the names of each of the
+        /// fields to the output.
+        ///
+        /// If `with_symbol` is `true`, will add a header field for "symbol".
+        ///
+        /// # Errors
+        /// This function returns an error if there's an error writing to `writer`.
+        pub async fn encode_header<R: DbnEncodable>(&mut self, with_symbol: bool) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Csv(encoder) => encoder.encode_header::<R>(with_symbol).await,
+                _ => Ok(()),
+            }
+        }
+
+        /// Encodes the CSV header for `schema`, i.e. the names of each of the fields to
+        /// the output.
+        ///
+        /// If `ts_out` is `true`, will add a header field "ts_out". If `with_symbol` is
+        /// `true`, will add a header field "symbol".
+        ///
+        /// # Errors
+        /// This function returns an error if there's an error writing to `writer`.
+        pub async fn encode_header_for_schema(
+            &mut self,
+            schema: Schema,
+            ts_out: bool,
+            with_symbol: bool,
+        ) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Csv(encoder) => {
+                    encoder
+                        .encode_header_for_schema(schema, ts_out, with_symbol)
+                        .await
+                }
+                _ => Ok(()),
+            }
+        }
+
+        /// Encodes a single DBN record of type `R`.
+        ///
+        /// # Errors
+        /// This function returns an error if it's unable to write to the underlying
+        /// writer or there's a serialization error.
+        ///
+        /// # Cancel safety
+        /// This method is not cancellation safe. If the method is used in
+        /// `tokio::select!` statement and another branch completes first, then the
+        /// record may have been partially written.
+        pub async fn encode_record<R: DbnEncodable>(&mut self, record: &R) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Dbn(enc) => enc.encode_record(record).await,
+                DynEncoderImpl::Csv(enc) => enc.encode_record(record).await,
+                DynEncoderImpl::Json(enc) => enc.encode_record(record).await,
+            }
+        }
+
+        /// Encodes a single DBN [`RecordRef`].
+        ///
+        /// # Errors
+        /// This function returns an error if it's unable to write to the underlying
+        /// writer or there's a serialization error.
+        ///
+        /// # Cancel safety
+        /// This method is not cancellation safe. If the method is used in
+        /// `tokio::select!` statement and another branch completes first, then the
+        /// record may have been partially written.
+        pub async fn encode_record_ref(&mut self, record: RecordRef<'_>) -> Result<()> {
+            // Safety: `ts_out` is `false`
+            unsafe { self.encode_record_ref_ts_out(record, false) }.await
+        }
+
+        /// Encodes a single DBN [`RecordRef`] with an optional `ts_out` (see
+        /// [`record::WithTsOut`](crate::record::WithTsOut)).
+        ///
+        /// # Safety
+        /// `ts_out` must be `false` if `record` does not have an appended `ts_out`.
+        ///
+        /// # Errors
+        /// This function returns an error if it's unable to write to the underlying
+        /// writer or there's a serialization error.
+        pub async unsafe fn encode_record_ref_ts_out(
+            &mut self,
+            record: RecordRef<'_>,
+            ts_out: bool,
+        ) -> Result<()> {
+            match &mut self.0 {
+                // `ts_out` is included in the record bytes
+                DynEncoderImpl::Dbn(enc) => enc.encode_record_ref(record).await,
+                DynEncoderImpl::Csv(enc) => enc.encode_record_ref_ts_out(record, ts_out).await,
+                DynEncoderImpl::Json(enc) => enc.encode_record_ref(record, ts_out).await,
+            }
+        }
+
+        /// Encodes a single DBN record of type `R` along with the record's text symbol.
+        /// The symbol is ignored if encoding DBN.
+        ///
+        /// # Errors
+        /// This function returns an error if it's unable to write to the underlying
+        /// writer or there's a serialization error.
+        ///
+        /// # Cancel safety
+        /// This method is not cancellation safe. If the method is used in
+        /// `tokio::select!` statement and another branch completes first, then the
+        /// record may have been partially written.
+        pub async fn encode_record_with_sym<R: DbnEncodable>(
+            &mut self,
+            record: &R,
+            symbol: Option<&str>,
+        ) -> Result<()> {
+            match &mut self.0 {
+                // Not supported for DBN so ignore `symbol`
+                DynEncoderImpl::Dbn(enc) => enc.encode_record(record).await,
+                DynEncoderImpl::Csv(enc) => enc.encode_record_with_sym(record, symbol).await,
+                DynEncoderImpl::Json(enc) => enc.encode_record_with_sym(record, symbol).await,
+            }
+        }
+
+        /// Encodes a single DBN [`RecordRef`] along with the record's text symbol. The
+        /// symbol is ignored if encoding DBN.
+        ///
+        /// # Errors
+        /// This function returns an error if it's unable to write to the underlying
+        /// writer or there's a serialization error.
+        ///
+        /// # Cancel safety
+        /// This method is not cancellation safe. If the method is used in
+        /// `tokio::select!` statement and another branch completes first, then the
+        /// record may have been partially written.
+        pub async fn encode_ref_with_sym(
+            &mut self,
+            record: RecordRef<'_>,
+            symbol: Option<&str>,
+        ) -> Result<()> {
+            // Safety: `ts_out` is `false`
+            unsafe { self.encode_ref_ts_out_with_sym(record, false, symbol) }.await
+        }
+
+        /// Encodes a single DBN [`RecordRef`] with an optional `ts_out` (see
+        /// [`record::WithTsOut`](crate::record::WithTsOut)) along with the record's text
+        /// symbol. The symbol is ignored if encoding DBN.
+        ///
+        /// # Safety
+        /// `ts_out` must be `false` if `record` does not have an appended `ts_out`.
+        ///
+        /// # Errors
+        /// This function returns an error if it's unable to write to the underlying
+        /// writer or there's a serialization error.
+        pub async unsafe fn encode_ref_ts_out_with_sym(
+            &mut self,
+            record: RecordRef<'_>,
+            ts_out: bool,
+            symbol: Option<&str>,
+        ) -> Result<()> {
+            match &mut self.0 {
+                // Not supported for DBN so ignore `symbol`. `ts_out` is included in the
+                // record bytes
+                DynEncoderImpl::Dbn(enc) => enc.encode_record_ref(record).await,
+                DynEncoderImpl::Csv(enc) => {
+                    enc.encode_ref_ts_out_with_sym(record, ts_out, symbol).await
+                }
+                DynEncoderImpl::Json(enc) => {
+                    enc.encode_ref_ts_out_with_sym(record, ts_out, symbol).await
+                }
+            }
+        }
+
+        /// Flushes any buffered content to the true output.
+        ///
+        /// # Errors
+        /// This function returns an error if it's unable to flush the underlying writer.
+        pub async fn flush(&mut self) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Dbn(enc) => enc.flush().await,
+                DynEncoderImpl::Csv(enc) => enc.flush().await,
+                DynEncoderImpl::Json(enc) => enc.flush().await,
+            }
+        }
+
+        /// Flushes any buffered content and shuts down the writer, which writes the end
+        /// of compressed output. No records can be encoded afterwards.
+        ///
+        /// # Errors
+        /// This function returns an error if it's unable to flush or shut down the
+        /// underlying writer.
+        pub async fn shutdown(&mut self) -> Result<()> {
+            self.flush().await?;
+            self.get_mut()
+                .shutdown()
+                .await
+                .map_err(|e| Error::io(e, "shutting down output"))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::io::Read;
+
+        use rstest::rstest;
+
+        use super::*;
+        use crate::{
+            decode::{DbnMetadata, DecodeRecordRef, DynDecoder, DynReader},
+            encode::{DynEncoder as SyncDynEncoder, EncodeRecordRef, EncodeRecordTextExt},
+            VersionUpgradePolicy,
+        };
+
+        const TEST_DATA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/data");
+
+        #[rstest]
+        #[tokio::test]
+        async fn test_matches_sync_encoder(
+            #[values(Encoding::Dbn, Encoding::Csv, Encoding::Json)] encoding: Encoding,
+            #[values(Compression::None, Compression::ZStd)] compression: Compression,
+            #[values(false, true)] with_symbol: bool,
+        ) {
+            let mut decoder = DynDecoder::from_file(
+                format!("{TEST_DATA_PATH}/test_data.mbp-1.dbn.zst"),
+                VersionUpgradePolicy::AsIs,
+            )
+            .unwrap();
+            let metadata = decoder.metadata().clone();
+            let mut expected = Vec::new();
+            let mut sync_encoder =
+                SyncDynEncoder::builder(&mut expected, encoding, Compression::None, &metadata)
+                    .all_pretty(true)
+                    .with_symbol(with_symbol)
+                    .build()
+                    .unwrap();
+            let mut output = Vec::new();
+            let mut encoder = DynEncoder::builder(&mut output, encoding, compression, &metadata)
+                .all_pretty(true)
+                .with_symbol(with_symbol)
+                .build()
+                .await
+                .unwrap();
+            while let Some(record) = decoder.decode_record_ref().unwrap() {
+                if with_symbol {
+                    sync_encoder
+                        .encode_ref_with_sym(record, Some("ESH4"))
+                        .unwrap();
+                    encoder
+                        .encode_ref_with_sym(record, Some("ESH4"))
+                        .await
+                        .unwrap();
+                } else {
+                    sync_encoder.encode_record_ref(record).unwrap();
+                    encoder.encode_record_ref(record).await.unwrap();
+                }
+            }
+            drop(sync_encoder);
+            encoder.shutdown().await.unwrap();
+            let mut decompressed = Vec::new();
+            DynReader::with_buffer(output.as_slice(), compression)
+                .unwrap()
+                .read_to_end(&mut decompressed)
+                .unwrap();
+            assert_eq!(decompressed, expected);
+        }
+
+        #[rstest]
+        #[tokio::test]
+        async fn test_encode_header_for_schema(#[values(false, true)] with_symbol: bool) {
+            let metadata = crate::MetadataBuilder::new()
+                .dataset("XNAS.ITCH".to_owned())
+                .schema(Some(Schema::Trades))
+                .start(0)
+                .stype_in(None)
+                .stype_out(crate::SType::InstrumentId)
+                .build();
+            let mut expected = Vec::new();
+            SyncDynEncoder::builder(&mut expected, Encoding::Csv, Compression::None, &metadata)
+                .write_header(false)
+                .build()
+                .unwrap()
+                .encode_header_for_schema(Schema::Trades, false, with_symbol)
+                .unwrap();
+            let mut output = Vec::new();
+            let mut encoder =
+                DynEncoder::builder(&mut output, Encoding::Csv, Compression::None, &metadata)
+                    .write_header(false)
+                    .build()
+                    .await
+                    .unwrap();
+            encoder
+                .encode_header_for_schema(Schema::Trades, false, with_symbol)
+                .await
+                .unwrap();
+            encoder.shutdown().await.unwrap();
+            assert_eq!(output, expected);
+            assert_eq!(
+                std::str::from_utf8(&output)
+                    .unwrap()
+                    .trim_end()
+                    .ends_with(",symbol"),
+                with_symbol
+            );
+        }
+
+        #[tokio::test]
+        async fn test_parquet_unsupported() {
+            let metadata = crate::MetadataBuilder::new()
+                .dataset("XNAS.ITCH".to_owned())
+                .schema(Some(Schema::Trades))
+                .start(0)
+                .stype_in(None)
+                .stype_out(crate::SType::InstrumentId)
+                .build();
+            let res =
+                DynEncoder::builder(Vec::new(), Encoding::Parquet, Compression::None, &metadata)
+                    .build()
+                    .await;
+            assert!(
+                matches!(res, Err(Error::BadArgument { param_name, .. }) if param_name == "encoding")
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+    use super::*;
+    use crate::{MetadataBuilder, RecordHeader, SType, StatMsg, SystemMsg, TradeMsg};
+
+    type Outputs = Rc<RefCell<BTreeMap<String, Vec<u8>>>>;
+
+    struct SharedWriter {
+        name: String,
+        outputs: Outputs,
+    }
+
+    impl io::Write for SharedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.outputs
+                .borrow_mut()
+                .get_mut(&self.name)
+                .unwrap()
+                .extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn split_encoder(
+        encoding: Encoding,
+        schema: Option<Schema>,
+    ) -> (DynEncoder<'static, SharedWriter>, Outputs) {
+        let outputs = Outputs::default();
+        let metadata = MetadataBuilder::new()
+            .dataset("XNAS.ITCH".to_owned())
+            .schema(schema)
+            .start(0)
+            .stype_in(None)
+            .stype_out(SType::InstrumentId)
+            .build();
+        let make_writer = {
+            let outputs = outputs.clone();
+            move |name: &str| {
+                outputs.borrow_mut().insert(name.to_owned(), Vec::new());
+                Ok(SharedWriter {
+                    name: name.to_owned(),
+                    outputs: outputs.clone(),
+                })
+            }
+        };
+        let encoder =
+            DynEncoderBuilder::new_split(make_writer, encoding, Compression::None, &metadata)
+                .build()
+                .unwrap();
+        (encoder, outputs)
+    }
+
+    #[test]
+    fn test_split_csv() {
+        let (mut encoder, outputs) = split_encoder(Encoding::Csv, None);
+        let trade = TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 323, 0),
+            price: 5500,
+            ..Default::default()
+        };
+        let stat = StatMsg {
+            hd: RecordHeader::new::<StatMsg>(rtype::STATISTICS, 1, 323, 0),
+            price: 6000,
+            ..Default::default()
+        };
+        let heartbeat = SystemMsg::heartbeat(0);
+        encoder.encode_record(&trade).unwrap();
+        encoder.encode_record_ref(RecordRef::from(&stat)).unwrap();
+        encoder
+            .encode_record_ref(RecordRef::from(&heartbeat))
+            .unwrap();
+        encoder.encode_record(&trade).unwrap();
+        drop(encoder);
+        let outputs = outputs.take();
+        assert_eq!(
+            outputs.keys().collect::<Vec<_>>(),
+            ["statistics", "system", "trades"]
+        );
+        let lines = |name: &str| {
+            String::from_utf8(outputs[name].clone())
+                .unwrap()
+                .lines()
+                .map(|line| line.split(',').map(ToOwned::to_owned).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+        let trades = lines("trades");
+        assert_eq!(trades.len(), 3);
+        assert_eq!(trades[0][0], "ts_recv");
+        assert_eq!(trades[1][8], "5500");
+        assert_eq!(trades[1], trades[2]);
+        let stats = lines("statistics");
+        assert_eq!(stats.len(), 2);
+        assert!(stats[0].contains(&"stat_type".to_owned()));
+        let system = lines("system");
+        assert_eq!(system.len(), 2);
+        assert!(system[1].contains(&"Heartbeat".to_owned()));
+    }
+
+    #[test]
+    fn test_split_json_uses_metadata_schema() {
+        let (mut encoder, outputs) = split_encoder(Encoding::Json, Some(Schema::Tbbo));
+        let tbbo = crate::TbboMsg {
+            hd: RecordHeader::new::<crate::TbboMsg>(rtype::MBP_1, 1, 323, 0),
+            ..Default::default()
+        };
+        encoder.encode_record(&tbbo).unwrap();
+        drop(encoder);
+        let outputs = outputs.take();
+        assert_eq!(outputs.keys().collect::<Vec<_>>(), ["tbbo"]);
+        assert_eq!(String::from_utf8_lossy(&outputs["tbbo"]).lines().count(), 1);
+    }
+
+    #[test]
+    fn test_split_dbn_unsupported() {
+        let metadata = MetadataBuilder::new()
+            .dataset("XNAS.ITCH".to_owned())
+            .schema(None)
+            .start(0)
+            .stype_in(None)
+            .stype_out(SType::InstrumentId)
+            .build();
+        let res = DynEncoderBuilder::new_split(
+            |_: &str| Ok(Vec::new()),
+            Encoding::Dbn,
+            Compression::None,
+            &metadata,
+        )
+        .build();
+        assert!(matches!(res, Err(Error::BadArgument { .. })));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_finish_error() {
+        /// Fails once more than `limit` bytes have been written.
+        struct LimitedWriter {
+            written: usize,
+            limit: usize,
+        }
+
+        impl io::Write for LimitedWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.written + buf.len() > self.limit {
+                    return Err(io::ErrorKind::StorageFull.into());
+                }
+                self.written += buf.len();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let metadata = MetadataBuilder::new()
+            .dataset("XNAS.ITCH".to_owned())
+            .schema(Some(Schema::Trades))
+            .start(0)
+            .stype_in(None)
+            .stype_out(SType::InstrumentId)
+            .build();
+        // Only enough for the leading magic bytes
+        let writer = LimitedWriter {
+            written: 0,
+            limit: 4,
+        };
+        let mut encoder =
+            DynEncoder::builder(writer, Encoding::Parquet, Compression::None, &metadata)
+                .build()
+                .unwrap();
+        encoder
+            .encode_record(&TradeMsg {
+                hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 1, 0),
+                ..Default::default()
+            })
+            .unwrap();
+        encoder.flush().unwrap();
+        assert!(matches!(encoder.finish(), Err(Error::Io { .. })));
     }
 }
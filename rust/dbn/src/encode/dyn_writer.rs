@@ -1,9 +1,15 @@
 use std::io;
 
-use super::zstd_encoder;
+use super::{zstd_encoder, ZstdOptions};
+#[cfg(not(all(feature = "gzip", feature = "lz4", feature = "xz")))]
+use crate::error::compression_feature_error;
 use crate::{Compression, Result};
 
-/// Type for runtime polymorphism over whether encoding uncompressed or ZStd-compressed
+/// The xz compression preset used, equivalent to the `xz` CLI's default.
+#[cfg(feature = "xz")]
+const XZ_COMPRESSION_LEVEL: u32 = 6;
+
+/// Type for runtime polymorphism over whether encoding uncompressed or compressed
 /// DBN records. Implements [`std::io::Write`].
 pub struct DynWriter<'a, W>(DynWriterImpl<'a, W>)
 where
@@ -15,6 +21,12 @@ where
 {
     Uncompressed(W),
     ZStd(zstd::stream::AutoFinishEncoder<'a, W>),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+    #[cfg(feature = "lz4")]
+    Lz4(Lz4Encoder<W>),
+    #[cfg(feature = "xz")]
+    Xz(liblzma::write::XzEncoder<W>),
 }
 
 impl<'a, W> DynWriter<'a, W>
@@ -24,11 +36,44 @@ where
     /// Create a new instance of [`DynWriter`] which will wrap `writer` with `compression`.
     ///
     /// # Errors
-    /// This function returns an error if it fails to initialize the Zstd compression.
+    /// This function returns an error if it fails to initialize the Zstd compression
+    /// or the feature for `compression` isn't enabled.
     pub fn new(writer: W, compression: Compression) -> Result<Self> {
+        Self::with_zstd_options(writer, compression, &ZstdOptions::default())
+    }
+
+    /// Create a new instance of [`DynWriter`] which will wrap `writer` with
+    /// `compression`, using `zstd_options` if `compression` is [`Compression::ZStd`].
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to initialize the Zstd compression
+    /// or the feature for `compression` isn't enabled.
+    pub fn with_zstd_options(
+        writer: W,
+        compression: Compression,
+        zstd_options: &ZstdOptions,
+    ) -> Result<Self> {
         match compression {
             Compression::None => Ok(Self(DynWriterImpl::Uncompressed(writer))),
-            Compression::ZStd => zstd_encoder(writer).map(|enc| Self(DynWriterImpl::ZStd(enc))),
+            Compression::ZStd => {
+                zstd_encoder(writer, zstd_options).map(|enc| Self(DynWriterImpl::ZStd(enc)))
+            }
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Self(DynWriterImpl::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            )))),
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => Ok(Self(DynWriterImpl::Lz4(Lz4Encoder(
+                lz4_flex::frame::FrameEncoder::new(writer),
+            )))),
+            #[cfg(feature = "xz")]
+            Compression::Xz => Ok(Self(DynWriterImpl::Xz(liblzma::write::XzEncoder::new(
+                writer,
+                XZ_COMPRESSION_LEVEL,
+            )))),
+            #[cfg(not(all(feature = "gzip", feature = "lz4", feature = "xz")))]
+            compression => Err(compression_feature_error(compression)),
         }
     }
 
@@ -37,6 +82,25 @@ where
         match &mut self.0 {
             DynWriterImpl::Uncompressed(w) => w,
             DynWriterImpl::ZStd(enc) => enc.get_mut(),
+            #[cfg(feature = "gzip")]
+            DynWriterImpl::Gzip(enc) => enc.get_mut(),
+            #[cfg(feature = "lz4")]
+            DynWriterImpl::Lz4(enc) => enc.0.get_mut(),
+            #[cfg(feature = "xz")]
+            DynWriterImpl::Xz(enc) => enc.get_mut(),
+        }
+    }
+
+    fn as_dyn_write(&mut self) -> &mut dyn io::Write {
+        match &mut self.0 {
+            DynWriterImpl::Uncompressed(writer) => writer,
+            DynWriterImpl::ZStd(writer) => writer,
+            #[cfg(feature = "gzip")]
+            DynWriterImpl::Gzip(writer) => writer,
+            #[cfg(feature = "lz4")]
+            DynWriterImpl::Lz4(writer) => &mut writer.0,
+            #[cfg(feature = "xz")]
+            DynWriterImpl::Xz(writer) => writer,
         }
     }
 }
@@ -46,38 +110,35 @@ where
     W: io::Write,
 {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        match &mut self.0 {
-            DynWriterImpl::Uncompressed(writer) => writer.write(buf),
-            DynWriterImpl::ZStd(writer) => writer.write(buf),
-        }
+        self.as_dyn_write().write(buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        match &mut self.0 {
-            DynWriterImpl::Uncompressed(writer) => writer.flush(),
-            DynWriterImpl::ZStd(writer) => writer.flush(),
-        }
+        self.as_dyn_write().flush()
     }
 
     fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
-        match &mut self.0 {
-            DynWriterImpl::Uncompressed(writer) => writer.write_vectored(bufs),
-            DynWriterImpl::ZStd(writer) => writer.write_vectored(bufs),
-        }
+        self.as_dyn_write().write_vectored(bufs)
     }
 
     fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        match &mut self.0 {
-            DynWriterImpl::Uncompressed(writer) => writer.write_all(buf),
-            DynWriterImpl::ZStd(writer) => writer.write_all(buf),
-        }
+        self.as_dyn_write().write_all(buf)
     }
 
     fn write_fmt(&mut self, fmt: std::fmt::Arguments<'_>) -> io::Result<()> {
-        match &mut self.0 {
-            DynWriterImpl::Uncompressed(writer) => writer.write_fmt(fmt),
-            DynWriterImpl::ZStd(writer) => writer.write_fmt(fmt),
-        }
+        self.as_dyn_write().write_fmt(fmt)
+    }
+}
+
+/// Writes the end of the LZ4 frame when dropped like the other encoders, while still
+/// allowing access to the inner writer.
+#[cfg(feature = "lz4")]
+struct Lz4Encoder<W: io::Write>(lz4_flex::frame::FrameEncoder<W>);
+
+#[cfg(feature = "lz4")]
+impl<W: io::Write> Drop for Lz4Encoder<W> {
+    fn drop(&mut self) {
+        let _ = self.0.try_finish();
     }
 }
 
@@ -91,10 +152,20 @@ mod r#async {
         task::{Context, Poll},
     };
 
-    use async_compression::tokio::write::ZstdEncoder;
+    #[cfg(feature = "gzip")]
+    use async_compression::tokio::write::GzipEncoder;
+    #[cfg(feature = "lz4")]
+    use async_compression::tokio::write::Lz4Encoder;
+    #[cfg(feature = "xz")]
+    use async_compression::tokio::write::XzEncoder;
     use tokio::io;
 
-    use crate::enums::Compression;
+    #[cfg(not(all(feature = "gzip", feature = "lz4", feature = "xz")))]
+    use crate::error::compression_feature_error;
+    use crate::{
+        encode::{ZstdAsyncWriter, ZstdOptions},
+        enums::Compression,
+    };
 
     /// An object that allows for abstracting over compressed and uncompressed output.
     pub struct DynWriter<W>(DynWriterImpl<W>)
@@ -106,7 +177,13 @@ mod r#async {
         W: io::AsyncWriteExt + Unpin,
     {
         Uncompressed(W),
-        ZStd(ZstdEncoder<W>),
+        ZStd(ZstdAsyncWriter<W>),
+        #[cfg(feature = "gzip")]
+        Gzip(GzipEncoder<W>),
+        #[cfg(feature = "lz4")]
+        Lz4(Lz4Encoder<W>),
+        #[cfg(feature = "xz")]
+        Xz(XzEncoder<W>),
     }
 
     impl<W> DynWriter<W>
@@ -115,11 +192,41 @@ mod r#async {
     {
         /// Creates a new instance of [`DynWriter`] which will wrap `writer` with
         /// `compression`.
-        pub fn new(writer: W, compression: Compression) -> Self {
-            Self(match compression {
+        ///
+        /// # Errors
+        /// This function returns an error if it fails to initialize the Zstd
+        /// compression or the feature for `compression` isn't enabled.
+        pub fn new(writer: W, compression: Compression) -> crate::Result<Self> {
+            Self::with_zstd_options(writer, compression, &ZstdOptions::default())
+        }
+
+        /// Creates a new instance of [`DynWriter`] which will wrap `writer` with
+        /// `compression`, using `zstd_options` if `compression` is
+        /// [`Compression::ZStd`].
+        ///
+        /// # Errors
+        /// This function returns an error if it fails to initialize the Zstd
+        /// compression, such as when the dictionary is invalid, or the feature for
+        /// `compression` isn't enabled.
+        pub fn with_zstd_options(
+            writer: W,
+            compression: Compression,
+            zstd_options: &ZstdOptions,
+        ) -> crate::Result<Self> {
+            Ok(Self(match compression {
                 Compression::None => DynWriterImpl::Uncompressed(writer),
-                Compression::ZStd => DynWriterImpl::ZStd(ZstdEncoder::new(writer)),
-            })
+                Compression::ZStd => {
+                    DynWriterImpl::ZStd(ZstdAsyncWriter::new(writer, zstd_options)?)
+                }
+                #[cfg(feature = "gzip")]
+                Compression::Gzip => DynWriterImpl::Gzip(GzipEncoder::new(writer)),
+                #[cfg(feature = "lz4")]
+                Compression::Lz4 => DynWriterImpl::Lz4(Lz4Encoder::new(writer)),
+                #[cfg(feature = "xz")]
+                Compression::Xz => DynWriterImpl::Xz(XzEncoder::new(writer)),
+                #[cfg(not(all(feature = "gzip", feature = "lz4", feature = "xz")))]
+                compression => return Err(compression_feature_error(compression)),
+            }))
         }
 
         /// Returns a mutable reference to the underlying writer.
@@ -127,6 +234,12 @@ mod r#async {
             match &mut self.0 {
                 DynWriterImpl::Uncompressed(w) => w,
                 DynWriterImpl::ZStd(enc) => enc.get_mut(),
+                #[cfg(feature = "gzip")]
+                DynWriterImpl::Gzip(enc) => enc.get_mut(),
+                #[cfg(feature = "lz4")]
+                DynWriterImpl::Lz4(enc) => enc.get_mut(),
+                #[cfg(feature = "xz")]
+                DynWriterImpl::Xz(enc) => enc.get_mut(),
             }
         }
     }
@@ -143,6 +256,12 @@ mod r#async {
             match &mut self.0 {
                 DynWriterImpl::Uncompressed(w) => io::AsyncWrite::poll_write(Pin::new(w), cx, buf),
                 DynWriterImpl::ZStd(enc) => io::AsyncWrite::poll_write(Pin::new(enc), cx, buf),
+                #[cfg(feature = "gzip")]
+                DynWriterImpl::Gzip(enc) => io::AsyncWrite::poll_write(Pin::new(enc), cx, buf),
+                #[cfg(feature = "lz4")]
+                DynWriterImpl::Lz4(enc) => io::AsyncWrite::poll_write(Pin::new(enc), cx, buf),
+                #[cfg(feature = "xz")]
+                DynWriterImpl::Xz(enc) => io::AsyncWrite::poll_write(Pin::new(enc), cx, buf),
             }
         }
 
@@ -150,6 +269,12 @@ mod r#async {
             match &mut self.0 {
                 DynWriterImpl::Uncompressed(w) => io::AsyncWrite::poll_flush(Pin::new(w), cx),
                 DynWriterImpl::ZStd(enc) => io::AsyncWrite::poll_flush(Pin::new(enc), cx),
+                #[cfg(feature = "gzip")]
+                DynWriterImpl::Gzip(enc) => io::AsyncWrite::poll_flush(Pin::new(enc), cx),
+                #[cfg(feature = "lz4")]
+                DynWriterImpl::Lz4(enc) => io::AsyncWrite::poll_flush(Pin::new(enc), cx),
+                #[cfg(feature = "xz")]
+                DynWriterImpl::Xz(enc) => io::AsyncWrite::poll_flush(Pin::new(enc), cx),
             }
         }
 
@@ -157,6 +282,12 @@ mod r#async {
             match &mut self.0 {
                 DynWriterImpl::Uncompressed(w) => io::AsyncWrite::poll_shutdown(Pin::new(w), cx),
                 DynWriterImpl::ZStd(enc) => io::AsyncWrite::poll_shutdown(Pin::new(enc), cx),
+                #[cfg(feature = "gzip")]
+                DynWriterImpl::Gzip(enc) => io::AsyncWrite::poll_shutdown(Pin::new(enc), cx),
+                #[cfg(feature = "lz4")]
+                DynWriterImpl::Lz4(enc) => io::AsyncWrite::poll_shutdown(Pin::new(enc), cx),
+                #[cfg(feature = "xz")]
+                DynWriterImpl::Xz(enc) => io::AsyncWrite::poll_shutdown(Pin::new(enc), cx),
             }
         }
     }
@@ -1,8 +1,10 @@
 //! Encoding of DBN records into newline-delimited JSON (ndjson).
 
-pub(crate) mod serialize;
+mod projection;
+#[doc(hidden)]
+pub mod serialize;
 mod sync;
-pub use sync::{Encoder, EncoderBuilder};
+pub use sync::{Encoder, EncoderBuilder, RecordTag};
 #[cfg(feature = "async")]
 mod r#async;
 #[cfg(feature = "async")]
@@ -1,9 +1,9 @@
 use tokio::io;
 
-use super::serialize::to_json_string;
+use super::serialize::{to_json_string, to_json_string_with_sym};
 use crate::{
-    encode::DbnEncodable, record_ref::RecordRef, rtype_ts_out_async_method_dispatch, Error,
-    Metadata, Result,
+    encode::DbnEncodable, record_ref::RecordRef, registry, rtype_ts_out_async_method_dispatch,
+    Error, Metadata, Record, Result,
 };
 
 /// Type for encoding files and streams of DBN records in newline-delimited JSON (ndjson).
@@ -111,7 +111,87 @@ where
         record_ref: RecordRef<'_>,
         ts_out: bool,
     ) -> Result<()> {
-        rtype_ts_out_async_method_dispatch!(record_ref, ts_out, self, encode_record)?
+        match rtype_ts_out_async_method_dispatch!(record_ref, ts_out, self, encode_record) {
+            Ok(res) => res,
+            Err(err) => self
+                .encode_registered(record_ref, ts_out, None)
+                .await
+                .unwrap_or(Err(err)),
+        }
+    }
+
+    /// Encodes a single DBN record of type `R` along with the record's text symbol.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to write to the underlying
+    /// writer.
+    ///
+    /// # Cancel safety
+    /// This method is not cancellation safe. If the method is used in
+    /// `tokio::select!` statement and another branch completes first, then the
+    /// record may have been partially written, but future calls will begin writing the
+    /// encoded record from the beginning.
+    pub async fn encode_record_with_sym<R: DbnEncodable>(
+        &mut self,
+        record: &R,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        let json = to_json_string_with_sym(
+            record,
+            self.should_pretty_print,
+            self.use_pretty_px,
+            self.use_pretty_ts,
+            symbol,
+        );
+        match self.writer.write_all(json.as_bytes()).await {
+            Ok(()) => Ok(()),
+            Err(e) => Err(Error::io(e, "writing record")),
+        }
+    }
+
+    /// Encodes a single DBN [`RecordRef`] along with the record's text symbol.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to write to the underlying writer
+    /// or there's a serialization error.
+    pub async fn encode_ref_with_sym(
+        &mut self,
+        record_ref: RecordRef<'_>,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        // Safety: `ts_out` is `false`
+        unsafe { self.encode_ref_ts_out_with_sym(record_ref, false, symbol) }.await
+    }
+
+    /// Encodes a single DBN [`RecordRef`] with an optional `ts_out` (see
+    /// [`record::WithTsOut`](crate::record::WithTsOut)) along with the record's text
+    /// symbol.
+    ///
+    /// # Safety
+    /// `ts_out` must be `false` if `record` does not have an appended `ts_out`.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to write to the underlying writer
+    /// or there's a serialization error.
+    pub async unsafe fn encode_ref_ts_out_with_sym(
+        &mut self,
+        record_ref: RecordRef<'_>,
+        ts_out: bool,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        match rtype_ts_out_async_method_dispatch!(
+            record_ref,
+            ts_out,
+            self,
+            encode_record_with_sym,
+            symbol
+        ) {
+            Ok(res) => res,
+            Err(err) => self
+                .encode_registered(record_ref, ts_out, Some(symbol))
+                .await
+                .unwrap_or(Err(err)),
+        }
     }
 
     /// Flushes any buffered content to the true output.
@@ -124,6 +204,34 @@ where
             .await
             .map_err(|e| Error::io(e, "flushing output"))
     }
+
+    /// Encodes a record of a type registered with [`registry::register()`]. Returns
+    /// `None` if the rtype of `record` isn't registered.
+    async fn encode_registered(
+        &mut self,
+        record: RecordRef<'_>,
+        ts_out: bool,
+        symbol: Option<Option<&str>>,
+    ) -> Option<Result<()>> {
+        let registration = registry::registration(record.header().rtype)?;
+        let json = match registration.json(
+            record,
+            self.should_pretty_print,
+            self.use_pretty_px,
+            self.use_pretty_ts,
+            ts_out,
+            symbol,
+        ) {
+            Ok(json) => json,
+            Err(e) => return Some(Err(e)),
+        };
+        Some(
+            self.writer
+                .write_all(json.as_bytes())
+                .await
+                .map_err(|e| Error::io(e, "writing record")),
+        )
+    }
 }
 
 #[cfg(test)]
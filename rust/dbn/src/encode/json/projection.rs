@@ -0,0 +1,320 @@
+//! Field selection, flattening, and value rendering options for the JSON encoder.
+
+use std::mem;
+
+use super::RecordTag;
+use crate::{
+    encode::layout::{Column, Layout, SYMBOL, TS_OUT},
+    enums::{rtype, RType},
+    json_writer::{JsonObjectWriter, JsonWriter, JsonWriterValue, PrettyJsonWriter, NULL},
+    pretty::{fmt_px, fmt_ts},
+    reflect::{FieldDesc, FieldHint, FieldValue},
+    Error, Record, RecordHeader, RecordRef, Result, UNDEF_PRICE, UNDEF_TIMESTAMP,
+};
+
+/// Encodes records through their field descriptors rather than their
+/// [`JsonSerialize`](super::serialize::JsonSerialize) implementation so fields can be
+/// selected and flattened and values rendered differently.
+#[derive(Debug)]
+pub(super) struct Projection {
+    selection: Option<Vec<String>>,
+    flatten: bool,
+    tag: Option<RecordTag>,
+    use_enum_names: bool,
+    quote_64_bit_ints: bool,
+    /// The cached layout of the last record type and its entries.
+    layout: Option<(Layout, Vec<Entry>)>,
+}
+
+#[derive(Debug)]
+enum Entry {
+    Field(&'static FieldDesc),
+    /// A column the record type doesn't have.
+    Missing(&'static str),
+    /// Fields of the record header nested under `hd`.
+    Header(Vec<&'static FieldDesc>),
+    /// Fields of each book level keyed by their name without the level suffix.
+    Levels(Vec<(usize, Vec<(&'static str, &'static FieldDesc)>)>),
+    TsOut,
+    Symbol,
+}
+
+#[derive(Clone, Copy)]
+struct ValueOptions {
+    use_pretty_px: bool,
+    use_pretty_ts: bool,
+    use_enum_names: bool,
+    quote_64_bit_ints: bool,
+}
+
+/// A number that has already been formatted.
+struct RawNumber<'a>(&'a str);
+
+impl JsonWriterValue for RawNumber<'_> {
+    fn write_json<W: JsonWriter>(self, writer: &mut W) {
+        writer.json_number_str(self.0);
+    }
+}
+
+impl Projection {
+    pub fn new(
+        selection: Option<Vec<String>>,
+        flatten: bool,
+        tag: Option<RecordTag>,
+        use_enum_names: bool,
+        quote_64_bit_ints: bool,
+    ) -> Self {
+        Self {
+            selection,
+            flatten,
+            tag,
+            use_enum_names,
+            quote_64_bit_ints,
+            layout: None,
+        }
+    }
+
+    /// Serializes `record` to a JSON string terminated by a newline.
+    #[allow(clippy::too_many_arguments)]
+    pub fn serialize(
+        &mut self,
+        record: RecordRef,
+        ts_out: Option<u64>,
+        symbol: Option<Option<&str>>,
+        should_pretty_print: bool,
+        use_pretty_px: bool,
+        use_pretty_ts: bool,
+    ) -> Result<String> {
+        let fields = record.fields().ok_or_else(|| {
+            Error::encode(format!(
+                "can't encode record with unknown rtype {:#04X}",
+                record.header().rtype
+            ))
+        })?;
+        self.resolve(fields, ts_out.is_some(), symbol.is_some())?;
+        let (_, entries) = self.layout.as_ref().unwrap();
+        let tag = self.tag.map(|tag| tag_name(tag, record.header().rtype));
+        let opts = ValueOptions {
+            use_pretty_px,
+            use_pretty_ts,
+            use_enum_names: self.use_enum_names,
+            quote_64_bit_ints: self.quote_64_bit_ints,
+        };
+        let mut res = String::new();
+        if should_pretty_print {
+            let mut pretty = PrettyJsonWriter::with_indent(&mut res, "    ");
+            let mut writer = JsonObjectWriter::new(&mut pretty);
+            write_record(&mut writer, entries, record, ts_out, symbol, tag, opts);
+        } else {
+            let mut writer = JsonObjectWriter::new(&mut res);
+            write_record(&mut writer, entries, record, ts_out, symbol, tag, opts);
+        }
+        res.push('\n');
+        Ok(res)
+    }
+
+    /// Updates the cached layout if the record type, `ts_out`, or symbol changed.
+    fn resolve(
+        &mut self,
+        fields: &'static [FieldDesc],
+        ts_out: bool,
+        with_symbol: bool,
+    ) -> Result<()> {
+        if self
+            .layout
+            .as_ref()
+            .is_some_and(|(layout, _)| layout.matches(fields, ts_out, with_symbol))
+        {
+            return Ok(());
+        }
+        let layout = Layout::new(fields, ts_out, with_symbol, None, self.selection.as_deref())?;
+        let entries = if self.flatten {
+            layout.columns.iter().copied().map(Entry::from).collect()
+        } else {
+            nest(&layout.columns)
+        };
+        self.layout = Some((layout, entries));
+        Ok(())
+    }
+}
+
+impl From<Column> for Entry {
+    fn from(col: Column) -> Self {
+        match col {
+            Column::Field(desc) => Entry::Field(desc),
+            Column::Missing(name) => Entry::Missing(name),
+            Column::TsOut => Entry::TsOut,
+            Column::Symbol => Entry::Symbol,
+        }
+    }
+}
+
+/// Groups header fields into an `hd` object and book level fields into a `levels`
+/// array, matching the default JSON encoding. Each group is placed at the position of
+/// its first field.
+fn nest(columns: &[Column]) -> Vec<Entry> {
+    let mut entries = Vec::with_capacity(columns.len());
+    for &col in columns {
+        let Column::Field(desc) = col else {
+            entries.push(Entry::from(col));
+            continue;
+        };
+        if desc.offset() < mem::size_of::<RecordHeader>() {
+            if let Some(Entry::Header(hd)) = entries
+                .iter_mut()
+                .find(|entry| matches!(entry, Entry::Header(_)))
+            {
+                hd.push(desc);
+            } else {
+                entries.push(Entry::Header(vec![desc]));
+            }
+        } else if let Some((key, idx)) = level_key(desc.name()) {
+            if let Some(Entry::Levels(levels)) = entries
+                .iter_mut()
+                .find(|entry| matches!(entry, Entry::Levels(_)))
+            {
+                if let Some((_, level)) = levels.iter_mut().find(|(i, _)| *i == idx) {
+                    level.push((key, desc));
+                } else {
+                    levels.push((idx, vec![(key, desc)]));
+                }
+            } else {
+                entries.push(Entry::Levels(vec![(idx, vec![(key, desc)])]));
+            }
+        } else {
+            entries.push(Entry::Field(desc));
+        }
+    }
+    for entry in entries.iter_mut() {
+        if let Entry::Levels(levels) = entry {
+            levels.sort_by_key(|(idx, _)| *idx);
+        }
+    }
+    entries
+}
+
+/// Splits the flattened name of a book level field like `bid_px_00` into its name
+/// within the level and the level index.
+fn level_key(name: &'static str) -> Option<(&'static str, usize)> {
+    let (key, idx) = name.rsplit_once('_')?;
+    if idx.len() != 2 || !idx.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((key, idx.parse().ok()?))
+}
+
+fn write_record<J: JsonWriter>(
+    writer: &mut JsonObjectWriter<J>,
+    entries: &[Entry],
+    record: RecordRef,
+    ts_out: Option<u64>,
+    symbol: Option<Option<&str>>,
+    tag: Option<Option<&str>>,
+    opts: ValueOptions,
+) {
+    if let Some(tag) = tag {
+        writer.value("type", tag);
+    }
+    for entry in entries {
+        match entry {
+            Entry::Field(desc) => write_field(writer, desc.name(), desc, record, opts),
+            Entry::Missing(name) => writer.value(name, NULL),
+            Entry::Header(descs) => {
+                let mut hd_writer = writer.object("hd");
+                for desc in descs {
+                    write_field(&mut hd_writer, desc.name(), desc, record, opts);
+                }
+            }
+            Entry::Levels(levels) => {
+                let mut arr_writer = writer.array("levels");
+                for (_, level) in levels {
+                    let mut item_writer = arr_writer.object();
+                    for (key, desc) in level {
+                        write_field(&mut item_writer, key, desc, record, opts);
+                    }
+                }
+            }
+            Entry::TsOut => write_ts(writer, TS_OUT, ts_out.unwrap_or_default(), opts),
+            Entry::Symbol => writer.value(SYMBOL, symbol.flatten()),
+        }
+    }
+}
+
+fn write_field<J: JsonWriter>(
+    writer: &mut JsonObjectWriter<J>,
+    key: &str,
+    desc: &FieldDesc,
+    record: RecordRef,
+    opts: ValueOptions,
+) {
+    let Some(value) = desc.value(record) else {
+        writer.value(key, NULL);
+        return;
+    };
+    if let (true, Some(FieldHint::Enum(field_enum))) = (opts.use_enum_names, desc.hint()) {
+        if let Some(name) = field_enum.variant_name(value) {
+            writer.value(key, name.as_str());
+            return;
+        }
+    }
+    match (desc.hint(), value) {
+        (Some(FieldHint::FixedPrice), FieldValue::I64(UNDEF_PRICE)) if opts.use_pretty_px => {
+            writer.value(key, NULL)
+        }
+        (Some(FieldHint::FixedPrice), FieldValue::I64(px)) if opts.use_pretty_px => {
+            writer.value(key, &fmt_px(px))
+        }
+        (Some(FieldHint::UnixNanos), FieldValue::U64(ts)) => write_ts(writer, key, ts, opts),
+        (_, FieldValue::Char('\0')) => writer.value(key, NULL),
+        (_, FieldValue::Char(c)) => {
+            let mut buf = [0; 4];
+            writer.value(key, &*c.encode_utf8(&mut buf))
+        }
+        (_, FieldValue::CStr(_)) => writer.value(key, value.as_str().unwrap_or_default()),
+        (_, FieldValue::I64(v)) => write_int_64(writer, key, itoa::Buffer::new().format(v), opts),
+        (_, FieldValue::U64(v)) => write_int_64(writer, key, itoa::Buffer::new().format(v), opts),
+        (_, FieldValue::I8(v)) => writer.value(key, v),
+        (_, FieldValue::I16(v)) => writer.value(key, v),
+        (_, FieldValue::I32(v)) => writer.value(key, v),
+        (_, FieldValue::U8(v)) => writer.value(key, v),
+        (_, FieldValue::U16(v)) => writer.value(key, v),
+        (_, FieldValue::U32(v)) => writer.value(key, v),
+    }
+}
+
+fn write_ts<J: JsonWriter>(
+    writer: &mut JsonObjectWriter<J>,
+    key: &str,
+    ts: u64,
+    opts: ValueOptions,
+) {
+    if opts.use_pretty_ts {
+        match ts {
+            0 | UNDEF_TIMESTAMP => writer.value(key, NULL),
+            ts => writer.value(key, &fmt_ts(ts)),
+        }
+    } else {
+        write_int_64(writer, key, itoa::Buffer::new().format(ts), opts);
+    }
+}
+
+fn write_int_64<J: JsonWriter>(
+    writer: &mut JsonObjectWriter<J>,
+    key: &str,
+    formatted: &str,
+    opts: ValueOptions,
+) {
+    if opts.quote_64_bit_ints {
+        // Convert to string to avoid a loss of precision
+        writer.value(key, formatted);
+    } else {
+        writer.value(key, RawNumber(formatted));
+    }
+}
+
+fn tag_name(tag: RecordTag, rtype: u8) -> Option<&'static str> {
+    match tag {
+        RecordTag::RType => RType::try_from(rtype).ok().map(|rtype| rtype.as_str()),
+        RecordTag::Schema => rtype::try_into_schema(rtype).map(|schema| schema.as_str()),
+    }
+}
@@ -1,9 +1,16 @@
 use std::io;
 
-use super::serialize::{to_json_string, to_json_string_with_sym};
+use super::{
+    projection::Projection,
+    serialize::{to_json_string, to_json_string_with_sym},
+};
 use crate::{
-    encode::{DbnEncodable, EncodeDbn, EncodeRecord, EncodeRecordRef, EncodeRecordTextExt},
-    rtype_method_dispatch, rtype_ts_out_method_dispatch, Error, Metadata, Result,
+    encode::{
+        record_ref, ts_out_from_end, ts_out_of, DbnEncodable, EncodeDbn, EncodeRecord,
+        EncodeRecordRef, EncodeRecordTextExt,
+    },
+    registry, rtype_method_dispatch, rtype_ts_out_method_dispatch, Error, Metadata, Record,
+    RecordRef, Result,
 };
 
 /// Type for encoding files and streams of DBN records in newline-delimited JSON (ndjson).
@@ -15,11 +22,27 @@ where
     should_pretty_print: bool,
     use_pretty_px: bool,
     use_pretty_ts: bool,
+    projection: Option<Projection>,
+}
+
+/// A name identifying the type of each record, added under the key `"type"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordTag {
+    /// The name of the [`RType`](crate::RType), e.g. `"mbp-0"`.
+    RType,
+    /// The name of the [`Schema`](crate::Schema), e.g. `"trades"`. Will be `null` for
+    /// records that don't correspond to a schema, such as error and system messages.
+    Schema,
 }
 
 /// Helper for constructing a JSON [`Encoder`].
 ///
 /// No fields are required.
+///
+/// Selecting [`fields`](Self::fields), [flattening](Self::flatten) records, adding a
+/// [`tag`](Self::tag), [using enum names](Self::use_enum_names), or disabling
+/// [quoting of 64-bit integers](Self::quote_64_bit_ints) switches to a slower encoding
+/// path based on [field reflection](crate::reflect).
 pub struct EncoderBuilder<W>
 where
     W: io::Write,
@@ -28,6 +51,11 @@ where
     should_pretty_print: bool,
     use_pretty_px: bool,
     use_pretty_ts: bool,
+    fields: Option<Vec<String>>,
+    flatten: bool,
+    tag: Option<RecordTag>,
+    use_enum_names: bool,
+    quote_64_bit_ints: bool,
 }
 
 impl<W> EncoderBuilder<W>
@@ -41,6 +69,11 @@ where
             should_pretty_print: false,
             use_pretty_px: false,
             use_pretty_ts: false,
+            fields: None,
+            flatten: false,
+            tag: None,
+            use_enum_names: false,
+            quote_64_bit_ints: true,
         }
     }
 
@@ -66,15 +99,71 @@ where
         self
     }
 
-    /// Creates the new encoder with the previously specified settings and if
-    /// `write_header` is `true`, encodes the header row.
+    /// Sets the fields to encode in the given order. Fields are named like the columns
+    /// of the CSV encoder, e.g. `ts_event` and `bid_px_00`, including "ts_out" and
+    /// "symbol". By default, all fields are encoded. Encoding a record without one of
+    /// the fields will return an error.
+    ///
+    /// Unless [flattened](Self::flatten), selected record header and book level fields
+    /// are still nested under `hd` and `levels`.
+    pub fn fields<S: Into<String>>(mut self, fields: impl IntoIterator<Item = S>) -> Self {
+        self.fields = Some(fields.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets whether the fields of the record header and book levels will be encoded as
+    /// top-level keys with the same names as the CSV encoder, e.g. `ts_event` and
+    /// `bid_px_00`, instead of nested under `hd` and `levels`. Defaults to `false`.
+    pub fn flatten(mut self, flatten: bool) -> Self {
+        self.flatten = flatten;
+        self
+    }
+
+    /// Sets the name to add to each record to identify its type. Defaults to `None`.
+    pub fn tag(mut self, tag: Option<RecordTag>) -> Self {
+        self.tag = tag;
+        self
+    }
+
+    /// Sets whether enum fields like `side`, `action`, and `stat_type` will be
+    /// serialized as the names of their variants, e.g. `"Bid"`, instead of their raw
+    /// values. Defaults to `false`.
+    pub fn use_enum_names(mut self, use_enum_names: bool) -> Self {
+        self.use_enum_names = use_enum_names;
+        self
+    }
+
+    /// Sets whether 64-bit integers, including prices and timestamps when not pretty,
+    /// will be serialized as strings to avoid a loss of precision in JSON parsers that
+    /// use double-precision floats, like JavaScript's. Defaults to `true`.
+    pub fn quote_64_bit_ints(mut self, quote_64_bit_ints: bool) -> Self {
+        self.quote_64_bit_ints = quote_64_bit_ints;
+        self
+    }
+
+    /// Creates the new encoder with the previously specified settings.
     pub fn build(self) -> Encoder<W> {
-        Encoder::new(
+        let mut encoder = Encoder::new(
             self.writer,
             self.should_pretty_print,
             self.use_pretty_px,
             self.use_pretty_ts,
-        )
+        );
+        if self.fields.is_some()
+            || self.flatten
+            || self.tag.is_some()
+            || self.use_enum_names
+            || !self.quote_64_bit_ints
+        {
+            encoder.projection = Some(Projection::new(
+                self.fields,
+                self.flatten,
+                self.tag,
+                self.use_enum_names,
+                self.quote_64_bit_ints,
+            ));
+        }
+        encoder
     }
 }
 
@@ -96,6 +185,7 @@ where
             should_pretty_print,
             use_pretty_px,
             use_pretty_ts,
+            projection: None,
         }
     }
 
@@ -130,6 +220,56 @@ where
     pub fn get_mut(&mut self) -> &mut W {
         &mut self.writer
     }
+
+    /// Encodes a record through the projection. Returns `None` if the encoder has no
+    /// projection.
+    fn encode_projected(
+        &mut self,
+        record: RecordRef,
+        ts_out: Option<u64>,
+        symbol: Option<Option<&str>>,
+    ) -> Option<Result<()>> {
+        let json = self.projection.as_mut()?.serialize(
+            record,
+            ts_out,
+            symbol,
+            self.should_pretty_print,
+            self.use_pretty_px,
+            self.use_pretty_ts,
+        );
+        Some(json.and_then(|json| {
+            self.writer
+                .write_all(json.as_bytes())
+                .map_err(|e| Error::io(e, "writing record"))
+        }))
+    }
+
+    /// Encodes a record of a type registered with [`registry::register()`]. Returns
+    /// `None` if the rtype of `record` isn't registered.
+    fn encode_registered(
+        &mut self,
+        record: RecordRef,
+        ts_out: bool,
+        symbol: Option<Option<&str>>,
+    ) -> Option<Result<()>> {
+        let registration = registry::registration(record.header().rtype)?;
+        Some(
+            registration
+                .json(
+                    record,
+                    self.should_pretty_print,
+                    self.use_pretty_px,
+                    self.use_pretty_ts,
+                    ts_out,
+                    symbol,
+                )
+                .and_then(|json| {
+                    self.writer
+                        .write_all(json.as_bytes())
+                        .map_err(|e| Error::io(e, "writing record"))
+                }),
+        )
+    }
 }
 
 impl<W> EncodeRecord for Encoder<W>
@@ -137,6 +277,9 @@ where
     W: io::Write,
 {
     fn encode_record<R: DbnEncodable>(&mut self, record: &R) -> Result<()> {
+        if let Some(res) = self.encode_projected(record_ref(record), ts_out_of(record), None) {
+            return res;
+        }
         let json = to_json_string(
             record,
             self.should_pretty_print,
@@ -160,16 +303,29 @@ impl<W> EncodeRecordRef for Encoder<W>
 where
     W: io::Write,
 {
-    fn encode_record_ref(&mut self, record: crate::RecordRef) -> Result<()> {
-        rtype_method_dispatch!(record, self, encode_record)?
+    fn encode_record_ref(&mut self, record: RecordRef) -> Result<()> {
+        if let Some(res) = self.encode_projected(record, None, None) {
+            return res;
+        }
+        match rtype_method_dispatch!(record, self, encode_record) {
+            Ok(res) => res,
+            Err(err) => self
+                .encode_registered(record, false, None)
+                .unwrap_or(Err(err)),
+        }
     }
 
-    unsafe fn encode_record_ref_ts_out(
-        &mut self,
-        record: crate::RecordRef,
-        ts_out: bool,
-    ) -> Result<()> {
-        rtype_ts_out_method_dispatch!(record, ts_out, self, encode_record)?
+    unsafe fn encode_record_ref_ts_out(&mut self, record: RecordRef, ts_out: bool) -> Result<()> {
+        let ts_out_val = ts_out.then(|| ts_out_from_end(record.bytes()));
+        if let Some(res) = self.encode_projected(record, ts_out_val, None) {
+            return res;
+        }
+        match rtype_ts_out_method_dispatch!(record, ts_out, self, encode_record) {
+            Ok(res) => res,
+            Err(err) => self
+                .encode_registered(record, ts_out, None)
+                .unwrap_or(Err(err)),
+        }
     }
 }
 
@@ -184,6 +340,11 @@ where
         record: &R,
         symbol: Option<&str>,
     ) -> Result<()> {
+        if let Some(res) =
+            self.encode_projected(record_ref(record), ts_out_of(record), Some(symbol))
+        {
+            return res;
+        }
         let json = to_json_string_with_sym(
             record,
             self.should_pretty_print,
@@ -196,12 +357,44 @@ where
             Err(e) => Err(Error::io(e, "writing record")),
         }
     }
+
+    fn encode_ref_with_sym(&mut self, record: RecordRef, symbol: Option<&str>) -> Result<()> {
+        if let Some(res) = self.encode_projected(record, None, Some(symbol)) {
+            return res;
+        }
+        match rtype_method_dispatch!(record, self, encode_record_with_sym, symbol) {
+            Ok(res) => res,
+            Err(err) => self
+                .encode_registered(record, false, Some(symbol))
+                .unwrap_or(Err(err)),
+        }
+    }
+
+    unsafe fn encode_ref_ts_out_with_sym(
+        &mut self,
+        record: RecordRef,
+        ts_out: bool,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        let ts_out_val = ts_out.then(|| ts_out_from_end(record.bytes()));
+        if let Some(res) = self.encode_projected(record, ts_out_val, Some(symbol)) {
+            return res;
+        }
+        match rtype_ts_out_method_dispatch!(record, ts_out, self, encode_record_with_sym, symbol) {
+            Ok(res) => res,
+            Err(err) => self
+                .encode_registered(record, ts_out, Some(symbol))
+                .unwrap_or(Err(err)),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::{array, io::BufWriter, num::NonZeroU64, os::raw::c_char};
 
+    use rstest::rstest;
+
     use super::*;
     use crate::{
         compat::SYMBOL_CSTR_LEN_V1,
@@ -345,7 +538,7 @@ mod tests {
             ts_recv: 1658441891000000000,
             ts_in_delta: 22_000,
             sequence: 1_002_375,
-            levels: array::from_fn(|_| BID_ASK.clone()),
+            levels: array::from_fn(|_| BID_ASK),
         }];
         let slice_res = write_json_to_string(data.as_slice(), false, true, true);
         let stream_res = write_json_stream_to_string(data, false, true, true);
@@ -646,6 +839,47 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_metadata_json_round_trip() {
+        let metadata = Metadata {
+            version: 2,
+            dataset: GLBX_MDP3.to_owned(),
+            schema: None,
+            start: 1662734705128748281,
+            end: NonZeroU64::new(1662734720914876944),
+            limit: NonZeroU64::new(100),
+            stype_in: None,
+            stype_out: SType::InstrumentId,
+            ts_out: true,
+            symbol_cstr_len: crate::SYMBOL_CSTR_LEN,
+            symbols: vec!["ES.FUT".to_owned(), "NQ.FUT".to_owned()],
+            partial: vec!["NQ.FUT".to_owned()],
+            not_found: vec!["ZZ.FUT".to_owned()],
+            mappings: vec![SymbolMapping {
+                raw_symbol: "ES.FUT".to_owned(),
+                intervals: vec![MappingInterval {
+                    start_date: time::macros::date!(2022 - 09 - 09),
+                    end_date: time::macros::date!(2022 - 09 - 10),
+                    symbol: "3403".to_owned(),
+                }],
+            }],
+        };
+        for (should_pretty_print, use_pretty_ts) in
+            [(false, false), (false, true), (true, false), (true, true)]
+        {
+            let mut buffer = Vec::new();
+            Encoder::new(&mut buffer, should_pretty_print, false, use_pretty_ts)
+                .encode_metadata(&metadata)
+                .unwrap();
+            let res: Metadata = serde_json::from_slice(&buffer).unwrap();
+            assert_eq!(
+                res, metadata,
+                "pretty_print={should_pretty_print}, pretty_ts={use_pretty_ts}"
+            );
+        }
+    }
+
     #[test]
     fn test_encode_with_ts_out() {
         let records = vec![WithTsOut {
@@ -716,4 +950,187 @@ mod tests {
             {\"hd\":{\"ts_event\":\"0\",\"rtype\":34,\"publisher_id\":10,\"instrument_id\":9},\"open\":\"175000000000\",\"high\":\"177000000000\",\"low\":\"174000000000\",\"close\":\"175000000000\",\"volume\":\"4033445\",\"symbol\":\"AAPL\"}\n",
         );
     }
+
+    fn encode_with_builder(
+        configure: impl FnOnce(EncoderBuilder<&mut Vec<u8>>) -> EncoderBuilder<&mut Vec<u8>>,
+        encode: impl FnOnce(&mut Encoder<&mut Vec<u8>>),
+    ) -> String {
+        let mut buffer = Vec::new();
+        let mut encoder = configure(Encoder::builder(&mut buffer)).build();
+        encode(&mut encoder);
+        drop(encoder);
+        String::from_utf8(buffer).expect("valid UTF-8")
+    }
+
+    fn mbp10() -> Mbp10Msg {
+        Mbp10Msg {
+            hd: RecordHeader::new::<Mbp10Msg>(rtype::MBP_10, 1, 323, 1658441851000000000),
+            price: 5500,
+            size: 3,
+            action: 'M' as c_char,
+            side: 'A' as c_char,
+            flags: 128,
+            depth: 9,
+            ts_recv: 1658441891000000000,
+            ts_in_delta: 22_000,
+            sequence: 1_002_375,
+            levels: array::from_fn(|_| BID_ASK),
+        }
+    }
+
+    #[rstest]
+    fn test_all_fields_matches_default(
+        #[values(false, true)] should_pretty_print: bool,
+        #[values(false, true)] pretty: bool,
+    ) {
+        let rec = WithTsOut::new(mbp10(), 1678480044000000000);
+        fn configure(
+            builder: EncoderBuilder<&mut Vec<u8>>,
+            should_pretty_print: bool,
+            pretty: bool,
+        ) -> EncoderBuilder<&mut Vec<u8>> {
+            builder
+                .should_pretty_print(should_pretty_print)
+                .use_pretty_px(pretty)
+                .use_pretty_ts(pretty)
+        }
+        let exp = encode_with_builder(
+            |builder| configure(builder, should_pretty_print, pretty),
+            |encoder| encoder.encode_record_with_sym(&rec, Some("ES")).unwrap(),
+        );
+        let fields = <Mbp10Msg as crate::reflect::HasFields>::fields()
+            .iter()
+            .map(|f| f.name())
+            .chain(["ts_out", "symbol"]);
+        let res = encode_with_builder(
+            |builder| configure(builder, should_pretty_print, pretty).fields(fields),
+            |encoder| encoder.encode_record_with_sym(&rec, Some("ES")).unwrap(),
+        );
+        assert_eq!(res, exp);
+    }
+
+    #[test]
+    fn test_fields_nested() {
+        let res = encode_with_builder(
+            |builder| builder.fields(["price", "instrument_id", "ask_sz_01", "bid_px_00"]),
+            |encoder| {
+                encoder
+                    .encode_record_ref(RecordRef::from(&mbp10()))
+                    .unwrap()
+            },
+        );
+        assert_eq!(
+            res,
+            r#"{"price":"5500","hd":{"instrument_id":323},"levels":[{"bid_px":"372000000000000"},{"ask_sz":5}]}
+"#
+        );
+    }
+
+    #[test]
+    fn test_flatten() {
+        let rec = WithTsOut::new(mbp10(), 1678480044000000000);
+        let res = encode_with_builder(
+            |builder| {
+                builder.flatten(true).fields([
+                    "ts_event",
+                    "instrument_id",
+                    "bid_px_00",
+                    "ask_ct_09",
+                    "ts_out",
+                ])
+            },
+            |encoder| encoder.encode_record(&rec).unwrap(),
+        );
+        assert_eq!(
+            res,
+            r#"{"ts_event":"1658441851000000000","instrument_id":323,"bid_px_00":"372000000000000","ask_ct_09":2,"ts_out":"1678480044000000000"}
+"#
+        );
+    }
+
+    #[test]
+    fn test_unquoted_64_bit_ints() {
+        let mbo = MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 323, 1658441851000000000),
+            order_id: 16,
+            price: 5500,
+            ..Default::default()
+        };
+        let res = encode_with_builder(
+            |builder| {
+                builder
+                    .quote_64_bit_ints(false)
+                    .fields(["ts_event", "order_id", "price"])
+            },
+            |encoder| encoder.encode_record(&mbo).unwrap(),
+        );
+        assert_eq!(
+            res,
+            r#"{"hd":{"ts_event":1658441851000000000},"order_id":16,"price":5500}
+"#
+        );
+    }
+
+    #[rstest]
+    #[case::rtype(RecordTag::RType, "mbp-0", "statistics")]
+    #[case::schema(RecordTag::Schema, "trades", "statistics")]
+    fn test_enum_names_and_tag(
+        #[case] tag: RecordTag,
+        #[case] exp_trade_tag: &str,
+        #[case] exp_stat_tag: &str,
+    ) {
+        let trade = TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 323, 0),
+            action: 'T' as c_char,
+            side: 'B' as c_char,
+            ..Default::default()
+        };
+        let stat = StatMsg {
+            hd: RecordHeader::new::<StatMsg>(rtype::STATISTICS, 1, 323, 0),
+            stat_type: StatType::OpeningPrice as u16,
+            update_action: StatUpdateAction::New as u8,
+            ..Default::default()
+        };
+        let res = encode_with_builder(
+            |builder| {
+                builder
+                    .tag(Some(tag))
+                    .use_enum_names(true)
+                    .fields(["action", "side"])
+            },
+            |encoder| encoder.encode_record(&trade).unwrap(),
+        );
+        assert_eq!(
+            res,
+            format!("{{\"type\":\"{exp_trade_tag}\",\"action\":\"Trade\",\"side\":\"Bid\"}}\n")
+        );
+        let res = encode_with_builder(
+            |builder| {
+                builder
+                    .tag(Some(tag))
+                    .use_enum_names(true)
+                    .fields(["stat_type", "update_action"])
+            },
+            |encoder| encoder.encode_record(&stat).unwrap(),
+        );
+        assert_eq!(
+            res,
+            format!("{{\"type\":\"{exp_stat_tag}\",\"stat_type\":\"OpeningPrice\",\"update_action\":\"New\"}}\n")
+        );
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::builder(&mut buffer)
+            .fields(["price", "stat_type"])
+            .build();
+        let err = encoder.encode_record(&mbp10()).unwrap_err();
+        assert!(
+            matches!(&err, Error::BadArgument { param_name, desc } if param_name == "fields" && desc.contains("stat_type")),
+            "{err:?}"
+        );
+        drop(encoder);
+        assert!(buffer.is_empty());
+    }
 }
@@ -0,0 +1,113 @@
+//! Resolution of the columns of records encoded through their field descriptors,
+//! shared by the CSV and JSON encoders.
+
+use std::ptr;
+
+use crate::{reflect::FieldDesc, Error, Result};
+
+pub(crate) const TS_OUT: &str = "ts_out";
+pub(crate) const SYMBOL: &str = "symbol";
+
+/// A column of encoded records.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Column {
+    Field(&'static FieldDesc),
+    /// A union column the record type doesn't have.
+    Missing(&'static str),
+    TsOut,
+    Symbol,
+}
+
+/// The columns of a record type with or without `ts_out` and a symbol.
+#[derive(Debug)]
+pub(crate) struct Layout {
+    fields: &'static [FieldDesc],
+    ts_out: bool,
+    with_symbol: bool,
+    /// Every column that can be selected.
+    pub available: Vec<Column>,
+    /// The selected columns in the order they should be encoded.
+    pub columns: Vec<Column>,
+}
+
+impl Layout {
+    /// Resolves the columns for the record type with `fields`. If `union` is passed,
+    /// the available columns are those names in order, with the ones the record type
+    /// doesn't have being [`Column::Missing`]. If `selection` is passed, only those
+    /// columns are encoded in that order.
+    ///
+    /// # Errors
+    /// This function returns an error if `selection` contains an unknown column.
+    pub fn new(
+        fields: &'static [FieldDesc],
+        ts_out: bool,
+        with_symbol: bool,
+        union: Option<&[&'static str]>,
+        selection: Option<&[String]>,
+    ) -> Result<Self> {
+        let mut available: Vec<_> = if let Some(union) = union {
+            union
+                .iter()
+                .map(|&name| {
+                    fields
+                        .iter()
+                        .find(|f| f.name() == name)
+                        .map_or(Column::Missing(name), Column::Field)
+                })
+                .collect()
+        } else {
+            fields.iter().map(Column::Field).collect()
+        };
+        available.extend(ts_out.then_some(Column::TsOut));
+        available.extend(with_symbol.then_some(Column::Symbol));
+        let columns = if let Some(selection) = selection {
+            selection
+                .iter()
+                .map(|name| {
+                    available
+                        .iter()
+                        .find(|col| col.name() == name)
+                        .copied()
+                        .ok_or_else(|| unknown_field(name, &available))
+                })
+                .collect::<Result<_>>()?
+        } else {
+            available.clone()
+        };
+        Ok(Self {
+            fields,
+            ts_out,
+            with_symbol,
+            available,
+            columns,
+        })
+    }
+
+    /// Returns `true` if the layout is for the record type with `fields` and the same
+    /// `ts_out` and symbol columns.
+    pub fn matches(&self, fields: &'static [FieldDesc], ts_out: bool, with_symbol: bool) -> bool {
+        ptr::eq(self.fields, fields) && self.ts_out == ts_out && self.with_symbol == with_symbol
+    }
+}
+
+impl Column {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Field(desc) => desc.name(),
+            Column::Missing(name) => name,
+            Column::TsOut => TS_OUT,
+            Column::Symbol => SYMBOL,
+        }
+    }
+}
+
+fn unknown_field(name: &str, available: &[Column]) -> Error {
+    let names: Vec<_> = available.iter().map(Column::name).collect();
+    Error::BadArgument {
+        param_name: "fields".to_owned(),
+        desc: format!(
+            "unknown field '{name}', expected one of: {}",
+            names.join(", ")
+        ),
+    }
+}
@@ -0,0 +1,455 @@
+//! Encoding of DBN records into [Apache Parquet](https://parquet.apache.org/) files.
+//!
+//! The columns match those of the [`arrow`](crate::arrow) conversion. Because a
+//! Parquet file has a single schema, all records in a file must be of the same type.
+//! The DBN [`Metadata`] is stored as JSON in the file's key-value metadata under
+//! [`METADATA_KEY`] and can be read back with [`read_metadata()`].
+
+use std::io;
+
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression as ParquetCompression, ZstdLevel},
+    file::{
+        metadata::KeyValue,
+        properties::{WriterProperties, DEFAULT_MAX_ROW_GROUP_SIZE},
+        reader::{ChunkReader, FileReader, SerializedFileReader},
+    },
+};
+
+use super::{
+    json::serialize::to_json_string, record_ref, ts_out_from_end, DbnEncodable, EncodeDbn,
+    EncodeRecord, EncodeRecordRef, EncodeRecordTextExt,
+};
+use crate::{
+    arrow::{ArrowOptions, DynRecordBatchBuilder, PriceFormat},
+    reflect::{schema_fields, FieldDesc},
+    Error, Metadata, Record, RecordRef, Result, Schema,
+};
+
+/// The key of the Parquet key-value metadata entry containing the DBN [`Metadata`]
+/// as JSON.
+pub const METADATA_KEY: &str = "dbn_metadata";
+
+/// The number of records converted to Arrow at a time before being passed to the
+/// Parquet writer.
+const BATCH_SIZE: usize = 8192;
+
+/// Helper for constructing a Parquet [`Encoder`].
+pub struct EncoderBuilder<'m, W>
+where
+    W: io::Write,
+{
+    writer: W,
+    metadata: &'m Metadata,
+    row_group_size: usize,
+    compression: ParquetCompression,
+    price_format: PriceFormat,
+    nested_levels: bool,
+    with_symbol: bool,
+}
+
+impl<'m, W> EncoderBuilder<'m, W>
+where
+    W: io::Write,
+{
+    /// Creates a new builder. All required fields for the builder are passed to this
+    /// function.
+    pub fn new(writer: W, metadata: &'m Metadata) -> Self {
+        Self {
+            writer,
+            metadata,
+            row_group_size: DEFAULT_MAX_ROW_GROUP_SIZE,
+            compression: ParquetCompression::ZSTD(ZstdLevel::default()),
+            price_format: PriceFormat::default(),
+            nested_levels: false,
+            with_symbol: false,
+        }
+    }
+
+    /// Sets the maximum number of records in each row group. Defaults to
+    /// [`DEFAULT_MAX_ROW_GROUP_SIZE`].
+    pub fn row_group_size(mut self, row_group_size: usize) -> Self {
+        self.row_group_size = row_group_size;
+        self
+    }
+
+    /// Sets the compression codec of the column chunks. Defaults to Zstandard with
+    /// the default level.
+    pub fn compression(mut self, compression: ParquetCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets how fixed-precision prices are stored. Defaults to [`PriceFormat::Fixed`].
+    pub fn price_format(mut self, price_format: PriceFormat) -> Self {
+        self.price_format = price_format;
+        self
+    }
+
+    /// Sets whether book levels are stored as a single nested `levels` column.
+    /// Defaults to `false`.
+    pub fn nested_levels(mut self, nested_levels: bool) -> Self {
+        self.nested_levels = nested_levels;
+        self
+    }
+
+    /// Sets whether to add a `symbol` column. Defaults to `false`.
+    pub fn with_symbol(mut self, with_symbol: bool) -> Self {
+        self.with_symbol = with_symbol;
+        self
+    }
+
+    /// Creates the new encoder with the previously specified settings.
+    ///
+    /// # Errors
+    /// This function returns an error if `row_group_size` is zero.
+    pub fn build(self) -> Result<Encoder<W>> {
+        if self.row_group_size == 0 {
+            return Err(Error::BadArgument {
+                param_name: "row_group_size".to_owned(),
+                desc: "must be greater than zero".to_owned(),
+            });
+        }
+        let properties = WriterProperties::builder()
+            .set_max_row_group_size(self.row_group_size)
+            .set_compression(self.compression)
+            .set_key_value_metadata(Some(vec![KeyValue::new(
+                METADATA_KEY.to_owned(),
+                to_json_string(self.metadata, false, false, false),
+            )]))
+            .build();
+        Ok(Encoder {
+            writer: self.writer,
+            properties,
+            options: ArrowOptions {
+                price_format: self.price_format,
+                nested_levels: self.nested_levels,
+                with_symbol: self.with_symbol,
+            },
+            schema: self.metadata.schema,
+            ts_out: self.metadata.ts_out,
+            state: State::Pending,
+        })
+    }
+}
+
+/// Type for encoding records of a single type into a Parquet file.
+///
+/// Parquet files can only be read once the footer is written, which happens when
+/// calling [`finish()`](Self::finish) or, ignoring any errors, when the encoder is
+/// dropped.
+pub struct Encoder<W>
+where
+    W: io::Write,
+{
+    writer: W,
+    properties: WriterProperties,
+    options: ArrowOptions,
+    schema: Option<Schema>,
+    ts_out: bool,
+    state: State,
+}
+
+// Only `Writing` is long-lived so boxing wouldn't save memory.
+#[allow(clippy::large_enum_variant)]
+enum State {
+    /// No records have been encoded yet so the record type is unknown.
+    Pending,
+    Writing {
+        fields: &'static [FieldDesc],
+        batch: DynRecordBatchBuilder,
+        // `ArrowWriter` requires a `Send` writer so the output is buffered and moved to
+        // the true writer after each write
+        writer: ArrowWriter<Vec<u8>>,
+    },
+    Finished,
+}
+
+impl<W> Encoder<W>
+where
+    W: io::Write,
+{
+    /// Creates a builder for configuring an `Encoder` object.
+    pub fn builder(writer: W, metadata: &Metadata) -> EncoderBuilder<'_, W> {
+        EncoderBuilder::new(writer, metadata)
+    }
+
+    /// Creates a new [`Encoder`] with the default settings that will write to
+    /// `writer`.
+    ///
+    /// # Errors
+    /// This function doesn't currently return an error, but may in the future.
+    pub fn new(writer: W, metadata: &Metadata) -> Result<Self> {
+        EncoderBuilder::new(writer, metadata).build()
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Writes any buffered records and the Parquet footer. If no records were encoded,
+    /// the columns are derived from the schema in the metadata, if any.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to write to the underlying writer
+    /// or the encoder was already finished.
+    pub fn finish(&mut self) -> Result<()> {
+        if matches!(self.state, State::Pending) {
+            let fields = self.schema.map(schema_fields).unwrap_or_default();
+            self.start(fields)?;
+        }
+        self.write_batch()?;
+        let State::Writing { mut writer, .. } = std::mem::replace(&mut self.state, State::Finished)
+        else {
+            return Err(Error::encode("Parquet encoder already finished"));
+        };
+        writer.finish().map_err(parquet_err)?;
+        Self::drain_into(&mut self.writer, &mut writer)?;
+        EncodeRecord::flush(self)
+    }
+
+    fn start(&mut self, fields: &'static [FieldDesc]) -> Result<()> {
+        let batch = DynRecordBatchBuilder::new(fields, self.options, self.ts_out);
+        let writer =
+            ArrowWriter::try_new(Vec::new(), batch.schema(), Some(self.properties.clone()))
+                .map_err(parquet_err)?;
+        self.state = State::Writing {
+            fields,
+            batch,
+            writer,
+        };
+        Ok(())
+    }
+
+    fn append(&mut self, record: RecordRef, ts_out: bool, symbol: Option<&str>) -> Result<()> {
+        let fields = record.fields().ok_or_else(|| {
+            Error::encode(format!(
+                "unsupported rtype {:#04X} for Parquet",
+                record.header().rtype
+            ))
+        })?;
+        if matches!(self.state, State::Pending) {
+            self.start(fields)?;
+        }
+        let State::Writing {
+            fields: file_fields,
+            batch,
+            ..
+        } = &mut self.state
+        else {
+            return Err(Error::encode("Parquet encoder already finished"));
+        };
+        if !std::ptr::eq(fields, *file_fields) {
+            return Err(Error::encode(format!(
+                "Parquet files can only contain one record type, found unexpected rtype {:#04X}",
+                record.header().rtype
+            )));
+        }
+        let ts_out = ts_out.then(|| ts_out_from_end(record.bytes()));
+        batch.append(record, ts_out, symbol);
+        if batch.len() >= BATCH_SIZE {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    /// Passes the buffered records to the Parquet writer and moves any completed row
+    /// groups to the true writer.
+    fn write_batch(&mut self) -> Result<()> {
+        let State::Writing { batch, writer, .. } = &mut self.state else {
+            return Ok(());
+        };
+        if !batch.is_empty() {
+            writer.write(&batch.finish()?).map_err(parquet_err)?;
+        }
+        Self::drain_into(&mut self.writer, writer)
+    }
+
+    fn drain_into(output: &mut W, writer: &mut ArrowWriter<Vec<u8>>) -> Result<()> {
+        writer
+            .sync()
+            .map_err(|e| Error::io(e, "flushing Parquet writer"))?;
+        // The Parquet writer tracks offsets independently of the length of the buffer,
+        // so draining it doesn't affect the output
+        let bytes = std::mem::take(writer.inner_mut());
+        output
+            .write_all(&bytes)
+            .map_err(|e| Error::io(e, "writing Parquet file"))
+    }
+}
+
+impl<W> Drop for Encoder<W>
+where
+    W: io::Write,
+{
+    fn drop(&mut self) {
+        if !matches!(self.state, State::Finished) {
+            let _ = self.finish();
+        }
+    }
+}
+
+impl<W> EncodeRecord for Encoder<W>
+where
+    W: io::Write,
+{
+    fn encode_record<R: DbnEncodable>(&mut self, record: &R) -> Result<()> {
+        self.append(record_ref(record), false, None)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.write_batch()?;
+        self.writer
+            .flush()
+            .map_err(|e| Error::io(e, "flushing output"))
+    }
+}
+
+impl<W> EncodeRecordRef for Encoder<W>
+where
+    W: io::Write,
+{
+    fn encode_record_ref(&mut self, record: RecordRef) -> Result<()> {
+        self.append(record, false, None)
+    }
+
+    unsafe fn encode_record_ref_ts_out(&mut self, record: RecordRef, ts_out: bool) -> Result<()> {
+        self.append(record, ts_out, None)
+    }
+}
+
+impl<W> EncodeDbn for Encoder<W> where W: io::Write {}
+
+impl<W> EncodeRecordTextExt for Encoder<W>
+where
+    W: io::Write,
+{
+    fn encode_record_with_sym<R: DbnEncodable>(
+        &mut self,
+        record: &R,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        self.append(record_ref(record), false, symbol)
+    }
+
+    fn encode_ref_with_sym(&mut self, record: RecordRef, symbol: Option<&str>) -> Result<()> {
+        self.append(record, false, symbol)
+    }
+
+    unsafe fn encode_ref_ts_out_with_sym(
+        &mut self,
+        record: RecordRef,
+        ts_out: bool,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        self.append(record, ts_out, symbol)
+    }
+}
+
+/// Reads the DBN [`Metadata`] stored in a Parquet file written by [`Encoder`].
+///
+/// # Errors
+/// This function returns an error if `reader` isn't a valid Parquet file or doesn't
+/// contain DBN metadata.
+pub fn read_metadata<R: ChunkReader + 'static>(reader: R) -> Result<Metadata> {
+    let reader = SerializedFileReader::new(reader)
+        .map_err(|e| Error::decode(format!("reading Parquet metadata: {e}")))?;
+    let json = reader
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|kvs| kvs.iter().find(|kv| kv.key == METADATA_KEY))
+        .and_then(|kv| kv.value.as_deref())
+        .ok_or_else(|| Error::decode(format!("missing '{METADATA_KEY}' Parquet metadata")))?;
+    serde_json::from_str(json).map_err(|e| Error::decode(format!("invalid DBN metadata: {e}")))
+}
+
+fn parquet_err(err: parquet::errors::ParquetError) -> Error {
+    Error::encode(format!("writing Parquet: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use arrow_array::{cast::AsArray, types::UInt64Type, RecordBatch};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+    use crate::{
+        decode::{dbn::Decoder, DbnMetadata, DecodeRecord},
+        MboMsg, MetadataBuilder, SType, TradeMsg,
+    };
+
+    const TEST_DATA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/data");
+
+    fn read_batches(file: &File) -> Vec<RecordBatch> {
+        ParquetRecordBatchReaderBuilder::try_new(file.try_clone().unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_encode_decoded() {
+        let decoder = Decoder::from_file(format!("{TEST_DATA_PATH}/test_data.mbo.dbn")).unwrap();
+        let metadata = decoder.metadata().clone();
+        let mut file = tempfile::tempfile().unwrap();
+        let mut target = Encoder::builder(&mut file, &metadata)
+            .row_group_size(1)
+            .build()
+            .unwrap();
+        target.encode_decoded(decoder).unwrap();
+        target.finish().unwrap();
+        drop(target);
+
+        assert_eq!(read_metadata(file.try_clone().unwrap()).unwrap(), metadata);
+        let batches = read_batches(&file);
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 2);
+        let mut decoder =
+            Decoder::from_file(format!("{TEST_DATA_PATH}/test_data.mbo.dbn")).unwrap();
+        let mbo = decoder.decode_record::<MboMsg>().unwrap().unwrap();
+        assert_eq!(
+            batches[0]
+                .column_by_name("order_id")
+                .unwrap()
+                .as_primitive::<UInt64Type>()
+                .value(0),
+            mbo.order_id
+        );
+        let reader = SerializedFileReader::new(file).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+    }
+
+    #[test]
+    fn test_empty_uses_metadata_schema() {
+        let metadata = MetadataBuilder::new()
+            .dataset("XNAS.ITCH".to_owned())
+            .schema(Some(Schema::Trades))
+            .start(0)
+            .stype_in(None)
+            .stype_out(SType::InstrumentId)
+            .build();
+        let mut file = tempfile::tempfile().unwrap();
+        Encoder::new(&mut file, &metadata).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        assert!(reader.schema().field_with_name("price").is_ok());
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 0);
+    }
+
+    #[test]
+    fn test_mixed_record_types() {
+        let decoder = Decoder::from_file(format!("{TEST_DATA_PATH}/test_data.mbo.dbn")).unwrap();
+        let metadata = decoder.metadata().clone();
+        let mut target = Encoder::new(Vec::new(), &metadata).unwrap();
+        target.encode_record(&MboMsg::default()).unwrap();
+        assert!(matches!(
+            target.encode_record(&TradeMsg::default()),
+            Err(Error::Encode(msg)) if msg.contains("one record type")
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    io::Write,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::io;
+
+use super::{unfinished_zstd_encoder, ZstdOptions};
+use crate::Result;
+
+/// An async writer that Zstandard compresses the data written to it with
+/// [`ZstdOptions`], producing the same frames as the sync encoders, including a
+/// checksum. Implements [`tokio::io::AsyncWrite`].
+///
+/// [`AsyncWriteExt::shutdown()`](tokio::io::AsyncWriteExt::shutdown) must be called to
+/// write the end of the frame.
+pub struct ZstdAsyncWriter<W>
+where
+    W: io::AsyncWrite + Unpin,
+{
+    writer: W,
+    /// Compresses to an in-memory buffer that's drained to `writer`.
+    encoder: zstd::Encoder<'static, Vec<u8>>,
+    /// The number of bytes at the start of the buffer that have been written to
+    /// `writer`.
+    written: usize,
+    finished: bool,
+}
+
+impl<W> ZstdAsyncWriter<W>
+where
+    W: io::AsyncWrite + Unpin,
+{
+    /// Creates a new [`ZstdAsyncWriter`] that will write compressed data to `writer`.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to initialize the Zstd compression,
+    /// such as when the dictionary in `options` is invalid.
+    pub fn new(writer: W, options: &ZstdOptions) -> Result<Self> {
+        Ok(Self {
+            writer,
+            encoder: unfinished_zstd_encoder(Vec::new(), options)?,
+            written: 0,
+            finished: false,
+        })
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Writes the compressed data buffered so far to `writer`.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let buffer = self.encoder.get_mut();
+        while self.written < buffer.len() {
+            let n = ready!(Pin::new(&mut self.writer).poll_write(cx, &buffer[self.written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+        buffer.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W> io::AsyncWrite for ZstdAsyncWriter<W>
+where
+    W: io::AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_drain(cx))?;
+        if self.finished {
+            return Poll::Ready(Err(io::Error::other(
+                "write after the end of the zstd frame",
+            )));
+        }
+        Poll::Ready(self.encoder.write(buf))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_drain(cx))?;
+        if !self.finished {
+            self.encoder.flush()?;
+            ready!(self.poll_drain(cx))?;
+        }
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_drain(cx))?;
+        if !self.finished {
+            self.encoder.do_finish()?;
+            self.finished = true;
+            ready!(self.poll_drain(cx))?;
+        }
+        Pin::new(&mut self.writer).poll_shutdown(cx)
+    }
+}
@@ -545,6 +545,9 @@ pub enum Encoding {
     /// JavaScript object notation.
     #[pyo3(name = "JSON")]
     Json = 2,
+    /// Apache Parquet. Requires the `parquet` feature for encoding.
+    #[pyo3(name = "PARQUET")]
+    Parquet = 3,
 }
 
 impl std::str::FromStr for Encoding {
@@ -555,6 +558,7 @@ impl std::str::FromStr for Encoding {
             "dbn" | "dbz" => Ok(Encoding::Dbn),
             "csv" => Ok(Encoding::Csv),
             "json" => Ok(Encoding::Json),
+            "parquet" => Ok(Encoding::Parquet),
             _ => Err(crate::Error::conversion::<Self>(s.to_owned())),
         }
     }
@@ -573,6 +577,7 @@ impl Encoding {
             Encoding::Dbn => "dbn",
             Encoding::Csv => "csv",
             Encoding::Json => "json",
+            Encoding::Parquet => "parquet",
         }
     }
 }
@@ -596,6 +601,15 @@ pub enum Compression {
     /// Zstandard compressed.
     #[pyo3(name = "ZSTD")]
     ZStd = 1,
+    /// gzip compressed.
+    #[pyo3(name = "GZIP")]
+    Gzip = 2,
+    /// LZ4 frame compressed.
+    #[pyo3(name = "LZ4")]
+    Lz4 = 3,
+    /// xz (LZMA2) compressed.
+    #[pyo3(name = "XZ")]
+    Xz = 4,
 }
 
 impl std::str::FromStr for Compression {
@@ -605,6 +619,9 @@ impl std::str::FromStr for Compression {
         match s {
             "none" => Ok(Compression::None),
             "zstd" => Ok(Compression::ZStd),
+            "gzip" => Ok(Compression::Gzip),
+            "lz4" => Ok(Compression::Lz4),
+            "xz" => Ok(Compression::Xz),
             _ => Err(crate::Error::conversion::<Self>(s.to_owned())),
         }
     }
@@ -622,6 +639,21 @@ impl Compression {
         match self {
             Compression::None => "none",
             Compression::ZStd => "zstd",
+            Compression::Gzip => "gzip",
+            Compression::Lz4 => "lz4",
+            Compression::Xz => "xz",
+        }
+    }
+
+    /// Returns the conventional file extension for the compression format without
+    /// a leading `.`, or `None` if uncompressed.
+    pub const fn extension(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::ZStd => Some("zst"),
+            Compression::Gzip => Some("gz"),
+            Compression::Lz4 => Some("lz4"),
+            Compression::Xz => Some("xz"),
         }
     }
 }
@@ -654,6 +686,7 @@ pub mod flags {
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, IntoPrimitive, TryFromPrimitive)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::manual_non_exhaustive)]
 pub enum SecurityUpdateAction {
     /// A new instrument definition.
     Add = b'A',
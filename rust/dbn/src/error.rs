@@ -113,6 +113,15 @@ impl Error {
     }
 }
 
+/// Returns the error for when the feature for the codec of `compression` isn't enabled.
+#[cfg(not(all(feature = "gzip", feature = "lz4", feature = "xz")))]
+pub(crate) fn compression_feature_error(compression: crate::Compression) -> Error {
+    Error::BadArgument {
+        param_name: "compression".to_owned(),
+        desc: format!("{compression} compression requires the `{compression}` feature"),
+    }
+}
+
 pub(crate) fn silence_eof_error<T>(err: std::io::Error) -> std::io::Result<Option<T>> {
     if err.kind() == std::io::ErrorKind::UnexpectedEof {
         Ok(None)
@@ -3,5 +3,5 @@
 // Re-export for version and casing consistency
 pub use json_writer::{
     JSONObjectWriter as JsonObjectWriter, JSONWriter as JsonWriter,
-    PrettyJSONWriter as PrettyJsonWriter, NULL,
+    JSONWriterValue as JsonWriterValue, PrettyJSONWriter as PrettyJsonWriter, NULL,
 };
@@ -18,7 +18,7 @@
 //! - [Decoders](crate::decode) for DBN and DBZ (the precursor to DBN), both
 //!   sync and async, with the `async` feature flag
 //! - [Encoders](crate::encode) for CSV, DBN, and JSON, both sync and async,
-//!   with the `async` feature flag
+//!   with the `async` feature flag, and Parquet with the `parquet` feature flag
 //! - [Normalized market data struct definitions](crate::record) corresponding to the
 //!   different market data schemas offered by Databento
 //! - A [wrapper type](crate::RecordRef) for holding a reference to a record struct of
@@ -26,9 +26,13 @@
 //! - Helper functions and [macros] for common tasks
 //!
 //! # Feature flags
+//! - `arrow`: enables converting records to Apache Arrow record batches
 //! - `async`: enables async decoding and encoding
+//! - `parquet`: enables encoding records as Apache Parquet
 //! - `python`: enables `pyo3` bindings
 //! - `serde`: enables deriving `serde` traits for types
+//! - `testing`: enables `proptest` strategies for records and [`Metadata`] and a
+//!   generator of synthetic market data
 //! - `trivial_copy`: enables deriving the `Copy` trait for records
 
 // Experimental feature to allow docs.rs to display features
@@ -37,22 +41,36 @@
 #![deny(rustdoc::broken_intra_doc_links)]
 #![deny(clippy::missing_errors_doc)]
 
+// Allows the derive macros to refer to `::dbn` both inside and outside of this crate,
+// including in doctests, where `proc_macro_crate` reports `dbn` as the current crate.
+extern crate self as dbn;
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod columnar;
 pub mod compat;
+pub mod continuous;
 pub mod decode;
 pub mod encode;
 pub mod enums;
 pub mod error;
-mod json_writer;
+#[doc(hidden)]
+pub mod json_writer;
 pub mod macros;
 pub mod metadata;
 pub mod pretty;
 pub mod publishers;
 #[cfg(feature = "python")]
 pub mod python;
+pub mod reconcile;
 pub mod record;
 mod record_enum;
 pub mod record_ref;
+pub mod reflect;
+pub mod registry;
 pub mod symbol_map;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 pub use crate::{
     enums::{
@@ -61,7 +79,7 @@ pub use crate::{
         VersionUpgradePolicy,
     },
     error::{Error, Result},
-    metadata::{MappingInterval, Metadata, MetadataBuilder, SymbolMapping},
+    metadata::{MappingInterval, Metadata, MetadataBuilder, SymbolMapping, SymbologyResolution},
     publishers::{Dataset, Publisher, Venue},
     record::{
         BidAskPair, ErrorMsg, HasRType, ImbalanceMsg, InstrumentDefMsg, MboMsg, Mbp10Msg, Mbp1Msg,
@@ -70,7 +88,7 @@ pub use crate::{
     },
     record_enum::{RecordEnum, RecordRefEnum},
     record_ref::RecordRef,
-    symbol_map::{PitSymbolMap, SymbolIndex, TsSymbolMap},
+    symbol_map::{IntervalSymbolMap, PitSymbolMap, SymbolIndex, SymbologyIndex, TsSymbolMap},
 };
 
 /// The current version of the DBN encoding, which is different from the crate version.
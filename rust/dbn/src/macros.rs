@@ -1,7 +1,9 @@
 //! Helper macros for working with multiple RTypes, Schemas, and types of records.
 
 // Re-export
-pub use dbn_macros::{dbn_record, CsvSerialize, DbnAttr, JsonSerialize, PyFieldDesc, RecordDebug};
+pub use dbn_macros::{
+    dbn_record, Columnar, CsvSerialize, DbnAttr, JsonSerialize, PyFieldDesc, RecordDebug,
+};
 
 /// Base macro for type dispatch based on rtype.
 ///
@@ -305,11 +307,11 @@ macro_rules! schema_dispatch {
 
 #[cfg(test)]
 mod tests {
-    use crate::{record::HasRType, schema_method_dispatch};
+    use crate::record::HasRType;
 
     struct Dummy {}
 
-    #[allow(dead_code)]
+    #[allow(dead_code, clippy::extra_unused_type_parameters)]
     impl Dummy {
         fn on_rtype<T: HasRType>(&self) -> bool {
             T::has_rtype(0xFF)
@@ -339,7 +341,6 @@ mod tests {
     #[cfg(feature = "async")]
     mod r#async {
         use super::*;
-        use crate::schema_async_method_dispatch;
 
         #[tokio::test]
         async fn test_self() {
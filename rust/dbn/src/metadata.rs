@@ -1,6 +1,6 @@
 //! Contains [`Metadata`] struct which comes at the beginning of any DBN file or
 //! stream and [`MetadataBuilder`] for creating a [`Metadata`] with defaults.
-use std::num::NonZeroU64;
+use std::{collections::BTreeMap, num::NonZeroU64};
 
 // Dummy derive macro to get around `cfg_attr` incompatibility of several
 // of pyo3's attribute macros. See https://github.com/PyO3/pyo3/issues/780
@@ -10,15 +10,20 @@ pub use dbn_macros::MockPyo3;
 use serde::Deserialize;
 
 use crate::{
-    compat::version_symbol_cstr_len, record::as_u8_slice, PitSymbolMap, SType, Schema, TsSymbolMap,
-    VersionUpgradePolicy,
+    compat::version_symbol_cstr_len, record::as_u8_slice, IntervalSymbolMap, PitSymbolMap, SType,
+    Schema, SymbologyIndex, TsSymbolMap, VersionUpgradePolicy,
 };
 
 /// Information about the data contained in a DBN file or stream. DBN requires the
 /// Metadata to be included at the start of the encoded data.
+///
+/// With the `serde` feature, `Metadata` can be deserialized from the JSON written by
+/// [`json::Encoder::encode_metadata()`](crate::encode::json::Encoder::encode_metadata),
+/// with or without pretty timestamps.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "python", pyo3::pyclass(module = "databento_dbn"))]
 #[cfg_attr(not(feature = "python"), derive(MockPyo3))] // bring `pyo3` attribute into scope
+#[cfg_attr(feature = "serde", derive(Deserialize))]
 pub struct Metadata {
     /// The DBN schema version number. Newly-encoded DBN files will use
     /// [`crate::DBN_VERSION`].
@@ -34,13 +39,16 @@ pub struct Metadata {
     /// The UNIX nanosecond timestamp of the query start, or the first record if the
     /// file was split.
     #[pyo3(get)]
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_ts"))]
     pub start: u64,
     /// The UNIX nanosecond timestamp of the query end, or the last record if the file
     /// was split.
     #[pyo3(get)]
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_opt_ts"))]
     pub end: Option<NonZeroU64>,
     /// The optional maximum number of records for the query.
     #[pyo3(get)]
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_opt_u64"))]
     pub limit: Option<NonZeroU64>,
     /// The input symbology type to map from. `None` indicates a mix, such as in the
     /// case of live data.
@@ -73,7 +81,8 @@ pub struct Metadata {
 impl Metadata {
     /// Creates a builder for building `Metadata`. Call `.dataset(...)`, `.schema(...)`,
     /// `.start(...)` `.stype_in(...)`, and `.stype_out(...)` on the builder to set the
-    /// required fields. Finally call `.build()` to create the `Metadata` instance.
+    /// required fields. Finally call `.build()` or `.try_build()` to create the `Metadata`
+    /// instance.
     pub fn builder() -> MetadataBuilder<Unset, Unset, Unset, Unset, Unset> {
         MetadataBuilder::default()
     }
@@ -118,6 +127,45 @@ impl Metadata {
         TsSymbolMap::from_metadata(self)
     }
 
+    /// Creates a symbology mapping from instrument ID and nanosecond timestamp to text
+    /// symbol. Its memory usage doesn't grow with the number of days in the query
+    /// range, and it can be updated with intraday [`SymbolMappingMsg`](crate::SymbolMappingMsg)s.
+    ///
+    /// # Errors
+    /// This function returns an error if `stype_out` is not [`SType::InstrumentId`] or
+    /// it can't parse a symbol into a `u32` instrument ID.
+    pub fn interval_symbol_map(&self) -> crate::Result<IntervalSymbolMap> {
+        IntervalSymbolMap::from_metadata(self)
+    }
+
+    /// Creates a bidirectional symbology index for looking up symbols by instrument ID
+    /// and instrument IDs by symbol over time.
+    ///
+    /// # Errors
+    /// This function returns an error if `stype_out` is not [`SType::InstrumentId`] or
+    /// it can't parse a symbol into a `u32` instrument ID.
+    pub fn symbology_index(&self) -> crate::Result<SymbologyIndex> {
+        SymbologyIndex::from_metadata(self)
+    }
+
+    /// Replaces the symbology of the metadata with that of `symbology`, e.g. from the
+    /// `symbology.json` of a batch download. `stype_in` and `stype_out` are only
+    /// updated if `symbology` specifies them.
+    pub fn apply_symbology(&mut self, symbology: &SymbologyResolution) {
+        if let Some(stype_in) = symbology.stype_in {
+            self.stype_in = Some(stype_in);
+        }
+        if let Some(stype_out) = symbology.stype_out {
+            self.stype_out = stype_out;
+        }
+        if !symbology.symbols.is_empty() {
+            self.symbols.clone_from(&symbology.symbols);
+        }
+        self.partial.clone_from(&symbology.partial);
+        self.not_found.clone_from(&symbology.not_found);
+        self.mappings = symbology.mappings();
+    }
+
     /// Upgrades the metadata according to `upgrade_policy` if necessary.
     pub fn upgrade(&mut self, upgrade_policy: VersionUpgradePolicy) {
         if self.version < crate::DBN_VERSION && upgrade_policy == VersionUpgradePolicy::Upgrade {
@@ -125,6 +173,287 @@ impl Metadata {
             self.symbol_cstr_len = crate::SYMBOL_CSTR_LEN;
         }
     }
+
+    /// Merges the metadata of several DBN files or streams, e.g. daily files being
+    /// concatenated into one, into a single [`Metadata`] covering all of them.
+    ///
+    /// - `start` is the earliest start and `end` the latest end. `end` is `None` if any
+    ///   end is unspecified
+    /// - `limit` is the sum of the limits, or `None` if any limit is unspecified
+    /// - `symbols` is the union of the requested symbols in order of appearance
+    /// - A symbol is `not_found` if it didn't resolve in any input that requested it,
+    ///   otherwise it's `partial` if it's partial or not found in at least one input
+    /// - The intervals of each `raw_symbol` are combined, with adjacent or overlapping
+    ///   intervals of the same symbol coalesced
+    ///
+    /// Inputs must agree on `dataset`, `stype_out`, and `ts_out`. If the inputs differ
+    /// in `schema` or `stype_in`, the merged value is `None`, indicating a mix. If
+    /// they differ in `version`, the merged metadata has the latest version and records
+    /// from earlier versions need to be upgraded, e.g. with
+    /// [`VersionUpgradePolicy::Upgrade`].
+    ///
+    /// # Errors
+    /// This function returns an error if `metadata` is empty, if the inputs conflict in
+    /// one of the fields that must agree, or if overlapping intervals of the same
+    /// `raw_symbol` map to different symbols.
+    pub fn merge(metadata: &[Metadata]) -> crate::Result<Self> {
+        let Some((first, rest)) = metadata.split_first() else {
+            return Err(crate::Error::BadArgument {
+                param_name: "metadata".to_owned(),
+                desc: "at least one metadata is required to merge".to_owned(),
+            });
+        };
+        let mut res = first.clone();
+        for (i, other) in rest.iter().enumerate() {
+            let i = i + 1;
+            check_same("dataset", &first.dataset, &other.dataset, i)?;
+            check_same("stype_out", &first.stype_out, &other.stype_out, i)?;
+            check_same("ts_out", &first.ts_out, &other.ts_out, i)?;
+            if other.schema != res.schema {
+                res.schema = None;
+            }
+            if other.stype_in != res.stype_in {
+                res.stype_in = None;
+            }
+            res.version = res.version.max(other.version);
+            res.start = res.start.min(other.start);
+            res.end = res.end.zip(other.end).map(|(end, other)| end.max(other));
+            res.limit = res
+                .limit
+                .zip(other.limit)
+                .map(|(limit, other)| limit.saturating_add(other.get()));
+            for symbol in other.symbols.iter() {
+                if !res.symbols.contains(symbol) {
+                    res.symbols.push(symbol.clone());
+                }
+            }
+        }
+        res.symbol_cstr_len = version_symbol_cstr_len(res.version);
+        (res.partial, res.not_found) = merge_unresolved(metadata);
+        res.mappings = merge_mappings(metadata)?;
+        Ok(res)
+    }
+
+    /// Checks the metadata can be encoded in DBN and is internally consistent.
+    ///
+    /// # Errors
+    /// This function returns an error if:
+    /// - `end` comes before `start`
+    /// - `symbol_cstr_len` doesn't match `version`
+    /// - a symbol isn't ASCII or is too long to fit in `symbol_cstr_len` with a null
+    ///   terminator
+    /// - a mapping interval's `end_date` comes before its `start_date`
+    /// - two intervals of the same mapping overlap
+    /// - a symbol that should be an instrument ID according to `stype_in` or
+    ///   `stype_out` can't be parsed into a `u32`
+    pub fn validate(&self) -> crate::Result<()> {
+        if let Some(end) = self.end {
+            if end.get() < self.start {
+                return Err(bad_metadata(
+                    "end",
+                    format!("end {end} comes before start {}", self.start),
+                ));
+            }
+        }
+        let expected_cstr_len = version_symbol_cstr_len(self.version);
+        if self.symbol_cstr_len != expected_cstr_len {
+            return Err(bad_metadata(
+                "symbol_cstr_len",
+                format!(
+                    "symbol_cstr_len {} doesn't match the {expected_cstr_len} of DBN version {}",
+                    self.symbol_cstr_len, self.version
+                ),
+            ));
+        }
+        for (param_name, symbols) in [
+            ("symbols", &self.symbols),
+            ("partial", &self.partial),
+            ("not_found", &self.not_found),
+        ] {
+            for symbol in symbols {
+                self.validate_symbol(param_name, symbol)?;
+            }
+        }
+        for mapping in self.mappings.iter() {
+            self.validate_mapping(mapping)?;
+        }
+        Ok(())
+    }
+
+    fn validate_symbol(&self, param_name: &str, symbol: &str) -> crate::Result<()> {
+        if !symbol.is_ascii() {
+            return Err(bad_metadata(
+                param_name,
+                format!("symbol '{symbol}' contains non-ASCII characters"),
+            ));
+        }
+        // leave room for null terminator
+        if symbol.len() >= self.symbol_cstr_len {
+            return Err(bad_metadata(
+                param_name,
+                format!(
+                    "symbol '{symbol}' is {} characters long, but DBN version {} symbols can be at most {} characters",
+                    symbol.len(),
+                    self.version,
+                    self.symbol_cstr_len - 1
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn validate_mapping(&self, mapping: &SymbolMapping) -> crate::Result<()> {
+        let raw_symbol = &mapping.raw_symbol;
+        self.validate_symbol("mappings", raw_symbol)?;
+        if self.stype_in == Some(SType::InstrumentId) && raw_symbol.parse::<u32>().is_err() {
+            return Err(bad_metadata(
+                "mappings",
+                format!("raw_symbol '{raw_symbol}' isn't a valid instrument ID despite stype_in being instrument_id"),
+            ));
+        }
+        let mut intervals = mapping.intervals.iter().collect::<Vec<_>>();
+        intervals.sort_by_key(|interval| interval.start_date);
+        for interval in intervals.iter() {
+            let MappingInterval {
+                start_date,
+                end_date,
+                symbol,
+            } = interval;
+            if end_date < start_date {
+                return Err(bad_metadata(
+                    "mappings",
+                    format!("interval {start_date} to {end_date} of '{raw_symbol}' ends before it starts"),
+                ));
+            }
+            self.validate_symbol("mappings", symbol)?;
+            // empty symbols are used for unresolved intervals
+            if self.stype_out == SType::InstrumentId
+                && !symbol.is_empty()
+                && symbol.parse::<u32>().is_err()
+            {
+                return Err(bad_metadata(
+                    "mappings",
+                    format!("symbol '{symbol}' of '{raw_symbol}' isn't a valid instrument ID despite stype_out being instrument_id"),
+                ));
+            }
+        }
+        if let Some((prev, next)) = intervals
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .find(|(prev, next)| prev.end_date > next.start_date)
+        {
+            return Err(bad_metadata(
+                "mappings",
+                format!(
+                    "intervals {} to {} and {} to {} of '{raw_symbol}' overlap",
+                    prev.start_date, prev.end_date, next.start_date, next.end_date
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn check_same<T: PartialEq + std::fmt::Display>(
+    field: &str,
+    first: &T,
+    other: &T,
+    i: usize,
+) -> crate::Result<()> {
+    if first == other {
+        Ok(())
+    } else {
+        Err(bad_metadata(
+            field,
+            format!("{field} {other} of metadata {i} conflicts with {field} {first} of metadata 0"),
+        ))
+    }
+}
+
+/// Returns the merged `partial` and `not_found` symbols of `metadata`.
+fn merge_unresolved(metadata: &[Metadata]) -> (Vec<String>, Vec<String>) {
+    let mut candidates = Vec::<&String>::new();
+    for symbol in metadata
+        .iter()
+        .flat_map(|metadata| metadata.partial.iter().chain(metadata.not_found.iter()))
+    {
+        if !candidates.contains(&symbol) {
+            candidates.push(symbol);
+        }
+    }
+    let mut partial = Vec::new();
+    let mut not_found = Vec::new();
+    for symbol in candidates {
+        let is_never_found = metadata
+            .iter()
+            .filter(|metadata| {
+                metadata.symbols.contains(symbol)
+                    || metadata.partial.contains(symbol)
+                    || metadata.not_found.contains(symbol)
+            })
+            .all(|metadata| metadata.not_found.contains(symbol));
+        if is_never_found {
+            not_found.push(symbol.clone());
+        } else {
+            partial.push(symbol.clone());
+        }
+    }
+    (partial, not_found)
+}
+
+/// Combines the intervals of the mappings with the same `raw_symbol`, coalescing
+/// adjacent and overlapping intervals that map to the same symbol.
+fn merge_mappings(metadata: &[Metadata]) -> crate::Result<Vec<SymbolMapping>> {
+    let mut res = Vec::<SymbolMapping>::new();
+    for mapping in metadata
+        .iter()
+        .flat_map(|metadata| metadata.mappings.iter())
+    {
+        if let Some(existing) = res
+            .iter_mut()
+            .find(|existing| existing.raw_symbol == mapping.raw_symbol)
+        {
+            existing.intervals.extend(mapping.intervals.iter().cloned());
+        } else {
+            res.push(mapping.clone());
+        }
+    }
+    for mapping in res.iter_mut() {
+        let mut intervals = std::mem::take(&mut mapping.intervals);
+        intervals.sort_by_key(|interval| interval.start_date);
+        for interval in intervals {
+            match mapping.intervals.last_mut() {
+                Some(last) if last.end_date >= interval.start_date => {
+                    if last.symbol == interval.symbol {
+                        last.end_date = last.end_date.max(interval.end_date);
+                    } else if last.end_date == interval.start_date {
+                        mapping.intervals.push(interval);
+                    } else {
+                        return Err(bad_metadata(
+                            "mappings",
+                            format!(
+                                "'{}' maps to both '{}' and '{}' from {} to {}",
+                                mapping.raw_symbol,
+                                last.symbol,
+                                interval.symbol,
+                                interval.start_date,
+                                last.end_date.min(interval.end_date)
+                            ),
+                        ));
+                    }
+                }
+                _ => mapping.intervals.push(interval),
+            }
+        }
+    }
+    Ok(res)
+}
+
+fn bad_metadata(param_name: &str, desc: String) -> crate::Error {
+    crate::Error::BadArgument {
+        param_name: param_name.to_owned(),
+        desc,
+    }
 }
 
 /// Helper for constructing [`Metadata`] structs with defaults.
@@ -343,6 +672,17 @@ impl MetadataBuilder<String, Option<Schema>, u64, Option<SType>, SType> {
             symbol_cstr_len: version_symbol_cstr_len(self.version),
         }
     }
+
+    /// Constructs a [`Metadata`] object, checking it with [`Metadata::validate()`].
+    ///
+    /// # Errors
+    /// This function returns an error if the metadata is inconsistent or can't be
+    /// encoded in DBN.
+    pub fn try_build(self) -> crate::Result<Metadata> {
+        let metadata = self.build();
+        metadata.validate()?;
+        Ok(metadata)
+    }
 }
 
 impl Default for MetadataBuilder<Unset, Unset, Unset, Unset, Unset> {
@@ -383,28 +723,420 @@ pub struct MappingInterval {
     /// The UTC start date of interval (inclusive).
     #[cfg_attr(
         feature = "serde",
-        serde(rename = "d0", deserialize_with = "deserialize_date")
+        serde(
+            rename = "d0",
+            alias = "start_date",
+            deserialize_with = "deserialize_date"
+        )
     )]
     pub start_date: time::Date,
     /// The UTC end date of interval (exclusive).
     #[cfg_attr(
         feature = "serde",
-        serde(rename = "d1", deserialize_with = "deserialize_date")
+        serde(
+            rename = "d1",
+            alias = "end_date",
+            deserialize_with = "deserialize_date"
+        )
     )]
     pub end_date: time::Date,
     /// The resolved symbol for this interval.
-    #[cfg_attr(feature = "serde", serde(rename = "s"))]
+    #[cfg_attr(feature = "serde", serde(rename = "s", alias = "symbol"))]
     pub symbol: String,
 }
 
+/// The result of symbology resolution, in the format of the `symbology.json` file
+/// included in batch downloads and of the response of the symbology resolve API. With
+/// the `serde` feature, it can be deserialized from JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct SymbologyResolution {
+    /// The resolved symbol mappings for each requested symbol.
+    pub result: BTreeMap<String, Vec<MappingInterval>>,
+    /// The requested symbols.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub symbols: Vec<String>,
+    /// The input symbology type.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stype_in: Option<SType>,
+    /// The output symbology type.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stype_out: Option<SType>,
+    /// Symbols that did not resolve for at least one day in the query time range.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub partial: Vec<String>,
+    /// Symbols that did not resolve for any day in the query time range.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub not_found: Vec<String>,
+}
+
+impl SymbologyResolution {
+    /// Returns the resolved mappings in the form of [`Metadata::mappings`], ordered by
+    /// `raw_symbol`.
+    pub fn mappings(&self) -> Vec<SymbolMapping> {
+        self.result
+            .iter()
+            .map(|(raw_symbol, intervals)| SymbolMapping {
+                raw_symbol: raw_symbol.clone(),
+                intervals: intervals.clone(),
+            })
+            .collect()
+    }
+}
+
 /// The date format used for date strings when serializing [`Metadata`].
 pub const DATE_FORMAT: &[time::format_description::FormatItem<'static>] =
     time::macros::format_description!("[year]-[month]-[day]");
 
+/// Timestamps, dates, and limits are serialized as either integers or strings depending
+/// on the type and whether they're pretty-printed.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IntOrStr {
+    Int(u64),
+    Str(String),
+}
+
+#[cfg(feature = "serde")]
+impl IntOrStr {
+    fn into_u64<E: serde::de::Error>(self) -> Result<u64, E> {
+        match self {
+            IntOrStr::Int(int) => Ok(int),
+            IntOrStr::Str(s) => s.parse().map_err(E::custom),
+        }
+    }
+
+    fn into_ts<E: serde::de::Error>(self) -> Result<u64, E> {
+        match self {
+            IntOrStr::Str(s) if s.contains('T') => {
+                let dt =
+                    time::OffsetDateTime::parse(&s, &time::format_description::well_known::Rfc3339)
+                        .map_err(E::custom)?;
+                u64::try_from(dt.unix_timestamp_nanos()).map_err(E::custom)
+            }
+            int_or_str => int_or_str.into_u64(),
+        }
+    }
+}
+
+/// Pretty timestamps of 0 are serialized as `null`.
+#[cfg(feature = "serde")]
+fn deserialize_ts<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    Option::<IntOrStr>::deserialize(deserializer)?.map_or(Ok(0), IntOrStr::into_ts)
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_opt_ts<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NonZeroU64>, D::Error> {
+    Ok(Option::<IntOrStr>::deserialize(deserializer)?
+        .map(IntOrStr::into_ts)
+        .transpose()?
+        .and_then(NonZeroU64::new))
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_opt_u64<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NonZeroU64>, D::Error> {
+    Ok(Option::<IntOrStr>::deserialize(deserializer)?
+        .map(IntOrStr::into_u64)
+        .transpose()?
+        .and_then(NonZeroU64::new))
+}
+
+/// Accepts both `DATE_FORMAT` strings and `YYYYMMDD` integers.
 #[cfg(feature = "serde")]
 fn deserialize_date<'de, D: serde::Deserializer<'de>>(
     deserializer: D,
 ) -> Result<time::Date, D::Error> {
-    let date_str = String::deserialize(deserializer)?;
-    time::Date::parse(&date_str, DATE_FORMAT).map_err(serde::de::Error::custom)
+    use serde::de::Error;
+
+    match IntOrStr::deserialize(deserializer)? {
+        IntOrStr::Str(date_str) => {
+            time::Date::parse(&date_str, DATE_FORMAT).map_err(D::Error::custom)
+        }
+        IntOrStr::Int(date_int) => {
+            let month =
+                time::Month::try_from((date_int / 100 % 100) as u8).map_err(D::Error::custom)?;
+            time::Date::from_calendar_date(
+                (date_int / 10_000) as i32,
+                month,
+                (date_int % 100) as u8,
+            )
+            .map_err(D::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use super::*;
+
+    fn builder() -> MetadataBuilder<String, Option<Schema>, u64, Option<SType>, SType> {
+        MetadataBuilder::new()
+            .dataset("GLBX.MDP3".to_owned())
+            .schema(Some(Schema::Trades))
+            .start(1688169600000000000)
+            .end(NonZeroU64::new(1688256000000000000))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+    }
+
+    fn mapping(raw_symbol: &str, intervals: &[(time::Date, time::Date, &str)]) -> SymbolMapping {
+        SymbolMapping {
+            raw_symbol: raw_symbol.to_owned(),
+            intervals: intervals
+                .iter()
+                .map(|(start_date, end_date, symbol)| MappingInterval {
+                    start_date: *start_date,
+                    end_date: *end_date,
+                    symbol: (*symbol).to_owned(),
+                })
+                .collect(),
+        }
+    }
+
+    fn desc(res: crate::Result<Metadata>) -> String {
+        match res {
+            Err(crate::Error::BadArgument { desc, .. }) => desc,
+            res => panic!("Expected bad argument error, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_try_build_valid() {
+        let metadata = builder()
+            .symbols(vec!["ESU3".to_owned(), "NQU3".to_owned()])
+            .not_found(vec!["NQU3".to_owned()])
+            .mappings(vec![mapping(
+                "ESU3",
+                &[
+                    (date!(2023 - 07 - 02), date!(2023 - 07 - 03), "5602"),
+                    (date!(2023 - 07 - 01), date!(2023 - 07 - 02), "5482"),
+                    (date!(2023 - 07 - 03), date!(2023 - 07 - 04), ""),
+                ],
+            )])
+            .try_build()
+            .unwrap();
+        assert_eq!(metadata.mappings[0].intervals.len(), 3);
+        assert_eq!(metadata.symbol_cstr_len, crate::SYMBOL_CSTR_LEN);
+    }
+
+    #[test]
+    fn test_try_build_end_before_start() {
+        let res = builder().end(NonZeroU64::new(1)).try_build();
+        assert_eq!(desc(res), "end 1 comes before start 1688169600000000000");
+    }
+
+    #[test]
+    fn test_try_build_symbol_too_long() {
+        let res = builder()
+            .version(1)
+            .symbols(vec!["A".repeat(crate::compat::SYMBOL_CSTR_LEN_V1)])
+            .try_build();
+        assert_eq!(
+            desc(res),
+            format!(
+                "symbol '{}' is 22 characters long, but DBN version 1 symbols can be at most 21 characters",
+                "A".repeat(22)
+            )
+        );
+        assert!(builder()
+            .version(1)
+            .symbols(vec!["A".repeat(21)])
+            .try_build()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_try_build_inverted_interval() {
+        let res = builder()
+            .mappings(vec![mapping(
+                "ESU3",
+                &[(date!(2023 - 07 - 02), date!(2023 - 07 - 01), "5482")],
+            )])
+            .try_build();
+        assert_eq!(
+            desc(res),
+            "interval 2023-07-02 to 2023-07-01 of 'ESU3' ends before it starts"
+        );
+    }
+
+    #[test]
+    fn test_try_build_overlapping_intervals() {
+        let res = builder()
+            .mappings(vec![mapping(
+                "ESU3",
+                &[
+                    (date!(2023 - 07 - 02), date!(2023 - 07 - 04), "5602"),
+                    (date!(2023 - 07 - 01), date!(2023 - 07 - 03), "5482"),
+                ],
+            )])
+            .try_build();
+        assert_eq!(
+            desc(res),
+            "intervals 2023-07-01 to 2023-07-03 and 2023-07-02 to 2023-07-04 of 'ESU3' overlap"
+        );
+    }
+
+    #[test]
+    fn test_try_build_stype_mismatch() {
+        let mappings = vec![mapping(
+            "ESU3",
+            &[(date!(2023 - 07 - 01), date!(2023 - 07 - 02), "ESU3")],
+        )];
+        let res = builder().mappings(mappings.clone()).try_build();
+        assert_eq!(
+            desc(res),
+            "symbol 'ESU3' of 'ESU3' isn't a valid instrument ID despite stype_out being instrument_id"
+        );
+        let res = builder()
+            .stype_in(Some(SType::InstrumentId))
+            .stype_out(SType::RawSymbol)
+            .mappings(mappings)
+            .try_build();
+        assert_eq!(
+            desc(res),
+            "raw_symbol 'ESU3' isn't a valid instrument ID despite stype_in being instrument_id"
+        );
+    }
+
+    #[test]
+    fn test_validate_symbol_cstr_len() {
+        let mut metadata = builder().build();
+        metadata.symbol_cstr_len = crate::compat::SYMBOL_CSTR_LEN_V1;
+        assert!(
+            matches!(metadata.validate(), Err(crate::Error::BadArgument { param_name, .. }) if param_name == "symbol_cstr_len")
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let day1 = builder()
+            .start(1688169600000000000)
+            .end(NonZeroU64::new(1688256000000000000))
+            .limit(NonZeroU64::new(10))
+            .symbols(vec![
+                "ESU3".to_owned(),
+                "NQU3".to_owned(),
+                "CLQ3".to_owned(),
+            ])
+            .partial(vec!["NQU3".to_owned()])
+            .not_found(vec!["CLQ3".to_owned()])
+            .mappings(vec![
+                mapping(
+                    "ESU3",
+                    &[(date!(2023 - 07 - 01), date!(2023 - 07 - 02), "5482")],
+                ),
+                mapping(
+                    "NQU3",
+                    &[(date!(2023 - 07 - 01), date!(2023 - 07 - 02), "1234")],
+                ),
+            ])
+            .build();
+        let day2 = builder()
+            .schema(Some(Schema::Mbo))
+            .start(1688256000000000000)
+            .end(NonZeroU64::new(1688342400000000000))
+            .limit(NonZeroU64::new(5))
+            .symbols(vec![
+                "CLQ3".to_owned(),
+                "ESU3".to_owned(),
+                "ZNU3".to_owned(),
+            ])
+            .not_found(vec!["CLQ3".to_owned(), "ZNU3".to_owned()])
+            .mappings(vec![mapping(
+                "ESU3",
+                &[(date!(2023 - 07 - 02), date!(2023 - 07 - 03), "5482")],
+            )])
+            .build();
+        let day3 = builder()
+            .version(1)
+            .start(1688342400000000000)
+            .end(NonZeroU64::new(1688428800000000000))
+            .symbols(vec!["ZNU3".to_owned()])
+            .mappings(vec![
+                mapping(
+                    "ESU3",
+                    &[(date!(2023 - 07 - 03), date!(2023 - 07 - 04), "5602")],
+                ),
+                mapping(
+                    "ZNU3",
+                    &[(date!(2023 - 07 - 03), date!(2023 - 07 - 04), "42")],
+                ),
+            ])
+            .build();
+        let target = Metadata::merge(&[day1, day2, day3]).unwrap();
+        assert_eq!(target.version, crate::DBN_VERSION);
+        assert_eq!(target.symbol_cstr_len, crate::SYMBOL_CSTR_LEN);
+        assert_eq!(target.schema, None);
+        assert_eq!(target.start, 1688169600000000000);
+        assert_eq!(target.end, NonZeroU64::new(1688428800000000000));
+        // day3 has no limit
+        assert_eq!(target.limit, None);
+        assert_eq!(target.symbols, ["ESU3", "NQU3", "CLQ3", "ZNU3"]);
+        assert_eq!(target.partial, ["NQU3", "ZNU3"]);
+        assert_eq!(target.not_found, ["CLQ3"]);
+        assert_eq!(
+            target.mappings,
+            [
+                mapping(
+                    "ESU3",
+                    &[
+                        (date!(2023 - 07 - 01), date!(2023 - 07 - 03), "5482"),
+                        (date!(2023 - 07 - 03), date!(2023 - 07 - 04), "5602")
+                    ]
+                ),
+                mapping(
+                    "NQU3",
+                    &[(date!(2023 - 07 - 01), date!(2023 - 07 - 02), "1234")]
+                ),
+                mapping(
+                    "ZNU3",
+                    &[(date!(2023 - 07 - 03), date!(2023 - 07 - 04), "42")]
+                ),
+            ]
+        );
+        target.validate().unwrap();
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        assert!(Metadata::merge(&[]).is_err());
+        let base = builder().build();
+        let other = builder().dataset("XNAS.ITCH".to_owned()).build();
+        assert_eq!(
+            desc(Metadata::merge(&[base.clone(), other])),
+            "dataset XNAS.ITCH of metadata 1 conflicts with dataset GLBX.MDP3 of metadata 0"
+        );
+        let other = builder().ts_out(true).build();
+        assert_eq!(
+            desc(Metadata::merge(&[base.clone(), base.clone(), other])),
+            "ts_out true of metadata 2 conflicts with ts_out false of metadata 0"
+        );
+        let other = builder().stype_out(SType::RawSymbol).build();
+        assert!(Metadata::merge(&[base, other]).is_err());
+        let res = Metadata::merge(&[
+            builder()
+                .mappings(vec![mapping(
+                    "ESU3",
+                    &[(date!(2023 - 07 - 01), date!(2023 - 07 - 03), "5482")],
+                )])
+                .build(),
+            builder()
+                .mappings(vec![mapping(
+                    "ESU3",
+                    &[(date!(2023 - 07 - 02), date!(2023 - 07 - 04), "5602")],
+                )])
+                .build(),
+        ]);
+        assert_eq!(
+            desc(res),
+            "'ESU3' maps to both '5482' and '5602' from 2023-07-02 to 2023-07-03"
+        );
+    }
 }
@@ -1,6 +1,8 @@
 //! Python wrappers around dbn functions. These are implemented here instead of in `python/`
 //! to be able to implement [`pyo3`] traits for DBN types.
 #![allow(clippy::too_many_arguments)]
+// `pymethods` expands to non-local impls with pyo3 0.20
+#![allow(non_local_definitions)]
 
 use std::fmt;
 
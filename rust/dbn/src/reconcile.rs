@@ -0,0 +1,423 @@
+//! Reconciling [`Metadata`] with the records it describes. Files that were split,
+//! filtered, or concatenated can carry metadata that no longer matches their records.
+//! [`reconcile()`] computes [`RecordStats`] over a decoder and reports any
+//! [`Mismatch`]es, along with corrected metadata for re-encoding.
+
+use std::{collections::BTreeSet, fmt, num::NonZeroU64};
+
+use crate::{
+    decode::{DbnMetadata, DecodeRecordRef},
+    enums::rtype,
+    symbol_map::date_to_ts,
+    Metadata, RType, Record, RecordRef, SType, Schema, UNDEF_TIMESTAMP,
+};
+
+/// Statistics about the records in a DBN stream that are relevant to its metadata.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecordStats {
+    /// The number of market data records, i.e. excluding gateway messages like symbol
+    /// mappings, system messages, and errors, which don't count towards the `limit`.
+    pub record_count: u64,
+    /// The earliest index timestamp. `None` if there were no records with a defined
+    /// index timestamp.
+    pub first_ts: Option<u64>,
+    /// The latest index timestamp. `None` if there were no records with a defined
+    /// index timestamp.
+    pub last_ts: Option<u64>,
+    /// The rtypes of the market data records, i.e. excluding gateway messages like
+    /// symbol mappings, system messages, and errors.
+    pub rtypes: BTreeSet<u8>,
+    /// The instrument IDs of the market data records.
+    pub instrument_ids: BTreeSet<u32>,
+}
+
+impl RecordStats {
+    /// Creates new empty `RecordStats`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the statistics with `record`.
+    pub fn update(&mut self, record: RecordRef) {
+        let ts = record.raw_index_ts();
+        if ts != UNDEF_TIMESTAMP {
+            self.first_ts = Some(self.first_ts.map_or(ts, |first_ts| first_ts.min(ts)));
+            self.last_ts = Some(self.last_ts.map_or(ts, |last_ts| last_ts.max(ts)));
+        }
+        let header = record.header();
+        if !matches!(
+            header.rtype,
+            rtype::SYMBOL_MAPPING | rtype::SYSTEM | rtype::ERROR
+        ) {
+            self.record_count += 1;
+            self.rtypes.insert(header.rtype);
+            self.instrument_ids.insert(header.instrument_id);
+        }
+    }
+
+    /// Returns the schema of the records given the schema in the metadata, which is
+    /// used to disambiguate schemas that share an rtype like MBP-1 and TBBO. Returns
+    /// `None` if the records are of more than one or no schema.
+    pub fn schema(&self, metadata_schema: Option<Schema>) -> Option<Schema> {
+        let mut rtypes = self.rtypes.iter();
+        match (rtypes.next(), rtypes.next()) {
+            (Some(&rtype), None) => metadata_schema
+                .filter(|&schema| RType::from(schema) as u8 == rtype)
+                .or_else(|| rtype::try_into_schema(rtype)),
+            _ => None,
+        }
+    }
+}
+
+/// A discrepancy between [`Metadata`] and the records it describes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    /// There are records whose index timestamp comes before the metadata `start`.
+    Start {
+        /// The `start` in the metadata.
+        metadata: u64,
+        /// The earliest index timestamp of the records.
+        first_ts: u64,
+    },
+    /// There are records whose index timestamp comes after the metadata `end`.
+    End {
+        /// The `end` in the metadata.
+        metadata: NonZeroU64,
+        /// The latest index timestamp of the records.
+        last_ts: u64,
+    },
+    /// The metadata `schema` doesn't match the records.
+    Schema {
+        /// The `schema` in the metadata.
+        metadata: Option<Schema>,
+        /// The schema of the records, `None` if they're of more than one schema.
+        records: Option<Schema>,
+    },
+    /// There are more records than the metadata `limit`.
+    Limit {
+        /// The `limit` in the metadata.
+        metadata: NonZeroU64,
+        /// The number of records.
+        record_count: u64,
+    },
+    /// Instrument IDs in the records without a symbol mapping in the metadata.
+    UnmappedInstrumentIds(Vec<u32>),
+    /// The number of symbol mapping intervals in the metadata for instrument IDs or
+    /// dates without any records.
+    UnusedMappings(usize),
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn fmt_schema(schema: &Option<Schema>) -> &'static str {
+            schema.map_or("mixed", |schema| schema.as_str())
+        }
+
+        match self {
+            Mismatch::Start { metadata, first_ts } => {
+                write!(f, "first record at {first_ts} is before start {metadata}")
+            }
+            Mismatch::End { metadata, last_ts } => {
+                write!(f, "last record at {last_ts} is after end {metadata}")
+            }
+            Mismatch::Schema { metadata, records } => write!(
+                f,
+                "schema is {} but records are {}",
+                fmt_schema(metadata),
+                fmt_schema(records)
+            ),
+            Mismatch::Limit {
+                metadata,
+                record_count,
+            } => write!(f, "{record_count} records exceed limit {metadata}"),
+            Mismatch::UnmappedInstrumentIds(ids) => {
+                write!(f, "{} instrument IDs have no symbol mapping", ids.len())
+            }
+            Mismatch::UnusedMappings(count) => {
+                write!(f, "{count} symbol mapping intervals are unused")
+            }
+        }
+    }
+}
+
+/// The result of reconciling [`Metadata`] with its records.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Statistics about the records.
+    pub stats: RecordStats,
+    /// The discrepancies between the original metadata and the records.
+    pub mismatches: Vec<Mismatch>,
+    /// The metadata corrected to describe exactly the records: `start` and `end` are
+    /// the first and last index timestamps, the `schema` is that of the records, and
+    /// only the symbol mappings for the instrument IDs and dates of the records are
+    /// kept.
+    pub metadata: Metadata,
+}
+
+impl Reconciliation {
+    /// Reconciles `metadata` with the `stats` of its records.
+    pub fn new(metadata: &Metadata, stats: RecordStats) -> Self {
+        let mut mismatches = Vec::new();
+        let mut corrected = metadata.clone();
+        if let Some(first_ts) = stats.first_ts {
+            if first_ts < metadata.start {
+                mismatches.push(Mismatch::Start {
+                    metadata: metadata.start,
+                    first_ts,
+                });
+            }
+            corrected.start = first_ts;
+        }
+        if let Some(last_ts) = stats.last_ts {
+            if let Some(end) = metadata.end.filter(|end| last_ts > end.get()) {
+                mismatches.push(Mismatch::End {
+                    metadata: end,
+                    last_ts,
+                });
+            }
+            corrected.end = NonZeroU64::new(last_ts);
+        }
+        if !stats.rtypes.is_empty() {
+            let schema = stats.schema(metadata.schema);
+            if schema != metadata.schema {
+                mismatches.push(Mismatch::Schema {
+                    metadata: metadata.schema,
+                    records: schema,
+                });
+            }
+            corrected.schema = schema;
+        }
+        if let Some(limit) = metadata
+            .limit
+            .filter(|limit| stats.record_count > limit.get())
+        {
+            mismatches.push(Mismatch::Limit {
+                metadata: limit,
+                record_count: stats.record_count,
+            });
+            corrected.limit = None;
+        }
+        if !metadata.mappings.is_empty() {
+            Self::reconcile_mappings(metadata, &stats, &mut mismatches, &mut corrected);
+        }
+        Self {
+            stats,
+            mismatches,
+            metadata: corrected,
+        }
+    }
+
+    /// Returns `true` if no mismatches were found.
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    fn reconcile_mappings(
+        metadata: &Metadata,
+        stats: &RecordStats,
+        mismatches: &mut Vec<Mismatch>,
+        corrected: &mut Metadata,
+    ) {
+        let is_inverse = match (metadata.stype_in, metadata.stype_out) {
+            (_, SType::InstrumentId) => false,
+            (Some(SType::InstrumentId), _) => true,
+            // Instrument IDs aren't part of the mappings
+            _ => return,
+        };
+        let (Some(first_ts), Some(last_ts)) = (stats.first_ts, stats.last_ts) else {
+            return;
+        };
+        let mut mapped_ids = BTreeSet::new();
+        let mut unused_count = 0;
+        for mapping in corrected.mappings.iter_mut() {
+            mapping.intervals.retain(|interval| {
+                let iid = if is_inverse {
+                    &mapping.raw_symbol
+                } else {
+                    &interval.symbol
+                };
+                let Ok(iid) = iid.parse::<u32>() else {
+                    // Leave unparsable mappings alone
+                    return true;
+                };
+                let is_used = stats.instrument_ids.contains(&iid)
+                    && date_to_ts(interval.start_date) <= last_ts
+                    && date_to_ts(interval.end_date) > first_ts;
+                if is_used {
+                    mapped_ids.insert(iid);
+                } else {
+                    unused_count += 1;
+                }
+                is_used
+            });
+        }
+        corrected
+            .mappings
+            .retain(|mapping| !mapping.intervals.is_empty());
+        let unmapped_ids: Vec<u32> = stats
+            .instrument_ids
+            .difference(&mapped_ids)
+            .copied()
+            .collect();
+        if !unmapped_ids.is_empty() {
+            mismatches.push(Mismatch::UnmappedInstrumentIds(unmapped_ids));
+        }
+        if unused_count > 0 {
+            mismatches.push(Mismatch::UnusedMappings(unused_count));
+        }
+    }
+}
+
+/// Decodes all the records from `decoder` and reconciles them with its metadata.
+///
+/// # Errors
+/// This function returns an error if it fails to decode a record.
+pub fn reconcile<D>(decoder: &mut D) -> crate::Result<Reconciliation>
+where
+    D: DecodeRecordRef + DbnMetadata,
+{
+    let mut stats = RecordStats::new();
+    while let Some(record) = decoder.decode_record_ref()? {
+        stats.update(record);
+    }
+    Ok(Reconciliation::new(decoder.metadata(), stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{date, datetime};
+
+    use super::*;
+    use crate::{
+        decode::DynDecoder, MappingInterval, MboMsg, SymbolMapping, SymbolMappingMsg, SystemMsg,
+        TradeMsg, VersionUpgradePolicy,
+    };
+
+    const TEST_DATA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/data");
+
+    #[test]
+    fn test_reconcile_consistent_file() {
+        let mut decoder = DynDecoder::from_file(
+            format!("{TEST_DATA_PATH}/test_data.mbo.dbn.zst"),
+            VersionUpgradePolicy::AsIs,
+        )
+        .unwrap();
+        let res = reconcile(&mut decoder).unwrap();
+        assert!(res.is_consistent(), "{:?}", res.mismatches);
+        assert_eq!(res.stats.record_count, 2);
+        assert_eq!(res.stats.rtypes, BTreeSet::from([rtype::MBO]));
+        assert_eq!(res.stats.instrument_ids, BTreeSet::from([5482]));
+        assert_eq!(res.metadata.mappings, decoder.metadata().mappings);
+        assert_eq!(Some(res.metadata.start), res.stats.first_ts);
+        assert!(res.metadata.start >= decoder.metadata().start);
+    }
+
+    #[test]
+    fn test_limit_excludes_gateway_messages() {
+        let metadata = Metadata::builder()
+            .dataset("XNAS.ITCH".to_owned())
+            .schema(Some(Schema::Trades))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .start(0)
+            .limit(NonZeroU64::new(1))
+            .build();
+        let mut stats = RecordStats::new();
+        let mapping = SymbolMappingMsg::new(
+            32,
+            0,
+            SType::RawSymbol,
+            "AAPL",
+            SType::RawSymbol,
+            "AAPL",
+            0,
+            UNDEF_TIMESTAMP,
+        )
+        .unwrap();
+        stats.update(RecordRef::from(&mapping));
+        stats.update(RecordRef::from(&SystemMsg::heartbeat(0)));
+        stats.update(RecordRef::from(&TradeMsg::default()));
+        assert_eq!(stats.record_count, 1);
+        let res = Reconciliation::new(&metadata, stats);
+        assert!(res.is_consistent(), "{:?}", res.mismatches);
+        assert_eq!(res.metadata.limit, metadata.limit);
+    }
+
+    #[test]
+    fn test_reconcile_mismatches() {
+        let ts = |dt: time::OffsetDateTime| dt.unix_timestamp_nanos() as u64;
+        let metadata = Metadata::builder()
+            .dataset("XNAS.ITCH".to_owned())
+            .schema(Some(Schema::Trades))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .start(ts(datetime!(2023-07-01 00:00 UTC)))
+            .end(NonZeroU64::new(ts(datetime!(2023-07-03 00:00 UTC))))
+            .limit(NonZeroU64::new(1))
+            .mappings(vec![
+                SymbolMapping {
+                    raw_symbol: "AAPL".to_owned(),
+                    intervals: vec![MappingInterval {
+                        start_date: date!(2023 - 07 - 01),
+                        end_date: date!(2023 - 07 - 03),
+                        symbol: "32".to_owned(),
+                    }],
+                },
+                SymbolMapping {
+                    raw_symbol: "TSLA".to_owned(),
+                    intervals: vec![MappingInterval {
+                        start_date: date!(2023 - 07 - 01),
+                        end_date: date!(2023 - 07 - 03),
+                        symbol: "10221".to_owned(),
+                    }],
+                },
+            ])
+            .build();
+        let mut stats = RecordStats::new();
+        let mut trade = TradeMsg::default();
+        trade.hd.instrument_id = 32;
+        trade.hd.ts_event = ts(datetime!(2023-06-30 12:00 UTC));
+        trade.ts_recv = trade.hd.ts_event;
+        stats.update(RecordRef::from(&trade));
+        let mut mbo = MboMsg::default();
+        mbo.hd.instrument_id = 99;
+        mbo.hd.ts_event = ts(datetime!(2023-07-02 12:00 UTC));
+        mbo.ts_recv = mbo.hd.ts_event;
+        stats.update(RecordRef::from(&mbo));
+
+        let res = Reconciliation::new(&metadata, stats);
+        assert_eq!(
+            res.mismatches,
+            vec![
+                Mismatch::Start {
+                    metadata: metadata.start,
+                    first_ts: trade.ts_recv
+                },
+                Mismatch::Schema {
+                    metadata: Some(Schema::Trades),
+                    records: None
+                },
+                Mismatch::Limit {
+                    metadata: NonZeroU64::new(1).unwrap(),
+                    record_count: 2
+                },
+                Mismatch::UnmappedInstrumentIds(vec![99]),
+                Mismatch::UnusedMappings(1),
+            ]
+        );
+        assert_eq!(res.metadata.start, trade.ts_recv);
+        assert_eq!(res.metadata.end, NonZeroU64::new(mbo.ts_recv));
+        assert_eq!(res.metadata.schema, None);
+        assert_eq!(res.metadata.limit, None);
+        assert_eq!(res.metadata.mappings, metadata.mappings[..1]);
+    }
+
+    #[test]
+    fn test_schema_disambiguates_with_metadata() {
+        let mut stats = RecordStats::new();
+        stats.rtypes.insert(rtype::MBP_1);
+        assert_eq!(stats.schema(Some(Schema::Tbbo)), Some(Schema::Tbbo));
+        assert_eq!(stats.schema(Some(Schema::Trades)), Some(Schema::Mbp1));
+        assert_eq!(stats.schema(None), Some(Schema::Mbp1));
+    }
+}
@@ -18,7 +18,7 @@ use crate::{
         Action, InstrumentClass, MatchAlgorithm, SecurityUpdateAction, Side, StatType,
         StatUpdateAction, UserDefinedInstrument,
     },
-    macros::{dbn_record, CsvSerialize, JsonSerialize, RecordDebug},
+    macros::{dbn_record, Columnar, CsvSerialize, JsonSerialize, RecordDebug},
     publishers::Publisher,
     Error, Result, SYMBOL_CSTR_LEN,
 };
@@ -33,7 +33,7 @@ pub use conv::{
 /// Common data for all Databento records. Always found at the beginning of a record
 /// struct.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -64,7 +64,7 @@ pub struct RecordHeader {
 /// A market-by-order (MBO) tick message. The record of the
 /// [`Mbo`](crate::enums::Schema::Mbo) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -88,7 +88,7 @@ pub struct MboMsg {
     #[pyo3(get)]
     pub price: i64,
     /// The order quantity.
-    #[dbn(encode_order(5))]
+    #[dbn(encode_order(5), order_size)]
     #[pyo3(get)]
     pub size: u32,
     /// A combination of packet end with matching engine status. See
@@ -102,10 +102,10 @@ pub struct MboMsg {
     pub channel_id: u8,
     /// The event action. Can be **A**dd, **C**ancel, **M**odify, clea**R**,
     /// **T**rade, or **F**ill.
-    #[dbn(c_char, encode_order(2))]
+    #[dbn(c_char, encode_order(2), enum_type(Action))]
     pub action: c_char,
     /// The order side. Can be **A**sk, **B**id or **N**one.
-    #[dbn(c_char, encode_order(3))]
+    #[dbn(c_char, encode_order(3), enum_type(Side))]
     pub side: c_char,
     /// The capture-server-received timestamp expressed as number of nanoseconds since
     /// the UNIX epoch.
@@ -122,7 +122,7 @@ pub struct MboMsg {
 
 /// A level.
 #[repr(C)]
-#[derive(Clone, JsonSerialize, RecordDebug, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, JsonSerialize, RecordDebug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -151,7 +151,7 @@ pub struct BidAskPair {
 /// Market by price implementation with a book depth of 0. Equivalent to
 /// MBP-0. The record of the [`Trades`](crate::enums::Schema::Trades) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -172,13 +172,14 @@ pub struct TradeMsg {
     #[pyo3(get)]
     pub price: i64,
     /// The order quantity.
+    #[dbn(order_size)]
     #[pyo3(get)]
     pub size: u32,
     /// The event action. Always **T**rade in the trades schema.
-    #[dbn(c_char, encode_order(2))]
+    #[dbn(c_char, encode_order(2), enum_type(Action))]
     pub action: c_char,
     /// The aggressing order's side in the trade. Can be **A**sk, **B**id or **N**one.
-    #[dbn(c_char, encode_order(3))]
+    #[dbn(c_char, encode_order(3), enum_type(Side))]
     pub side: c_char,
     /// A combination of packet end with matching engine status. See
     /// [`enums::flags`](crate::enums::flags) for possible values.
@@ -205,7 +206,7 @@ pub struct TradeMsg {
 /// Market by price implementation with a known book depth of 1. The record of the
 /// [`Mbp1`](crate::enums::Schema::Mbp1) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -226,14 +227,15 @@ pub struct Mbp1Msg {
     #[pyo3(get)]
     pub price: i64,
     /// The order quantity.
+    #[dbn(order_size)]
     #[pyo3(get)]
     pub size: u32,
     /// The event action. Can be **A**dd, **C**ancel, **M**odify, clea**R**, or
     /// **T**rade.
-    #[dbn(c_char, encode_order(2))]
+    #[dbn(c_char, encode_order(2), enum_type(Action))]
     pub action: c_char,
     /// The order side. Can be **A**sk, **B**id or **N**one.
-    #[dbn(c_char, encode_order(3))]
+    #[dbn(c_char, encode_order(3), enum_type(Side))]
     pub side: c_char,
     /// A combination of packet end with matching engine status. See
     /// [`enums::flags`](crate::enums::flags) for possible values.
@@ -263,7 +265,7 @@ pub struct Mbp1Msg {
 /// Market by price implementation with a known book depth of 10. The record of the
 /// [`Mbp10`](crate::enums::Schema::Mbp10) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -284,14 +286,15 @@ pub struct Mbp10Msg {
     #[pyo3(get)]
     pub price: i64,
     /// The order quantity.
+    #[dbn(order_size)]
     #[pyo3(get)]
     pub size: u32,
     /// The event action. Can be **A**dd, **C**ancel, **M**odify, clea**R**, or
     /// **T**rade.
-    #[dbn(c_char, encode_order(2))]
+    #[dbn(c_char, encode_order(2), enum_type(Action))]
     pub action: c_char,
     /// The order side. Can be **A**sk, **B**id or **N**one.
-    #[dbn(c_char, encode_order(3))]
+    #[dbn(c_char, encode_order(3), enum_type(Side))]
     pub side: c_char,
     /// A combination of packet end with matching engine status. See
     /// [`enums::flags`](crate::enums::flags) for possible values.
@@ -328,7 +331,7 @@ pub type TbboMsg = Mbp1Msg;
 /// - [`Ohlcv1D`](crate::enums::Schema::Ohlcv1D)
 /// - [`OhlcvEod`](crate::enums::Schema::OhlcvEod)
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -368,7 +371,7 @@ pub struct OhlcvMsg {
 /// [`Status`](crate::enums::Schema::Status) schema.
 #[doc(hidden)]
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -402,7 +405,7 @@ pub struct StatusMsg {
 /// Definition of an instrument. The record of the
 /// [`Definition`](crate::enums::Schema::Definition) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -586,11 +589,11 @@ pub struct InstrumentDefMsg {
     #[cfg_attr(feature = "serde", serde(with = "conv::cstr_serde"))]
     pub strike_price_currency: [c_char; 4],
     /// The classification of the instrument.
-    #[dbn(c_char, encode_order(4))]
+    #[dbn(c_char, encode_order(4), enum_type(InstrumentClass))]
     #[pyo3(set)]
     pub instrument_class: c_char,
     /// The matching algorithm used for the instrument, typically **F**IFO.
-    #[dbn(c_char)]
+    #[dbn(c_char, enum_type(MatchAlgorithm))]
     #[pyo3(set)]
     pub match_algorithm: c_char,
     /// The current trading state of the instrument.
@@ -612,7 +615,7 @@ pub struct InstrumentDefMsg {
     #[pyo3(get, set)]
     pub underlying_product: u8,
     /// Indicates if the instrument definition has been added, modified, or deleted.
-    #[dbn(c_char, encode_order(3))]
+    #[dbn(c_char, encode_order(3), enum_type(SecurityUpdateAction))]
     #[pyo3(set)]
     pub security_update_action: c_char,
     /// The calendar month reflected in the instrument symbol.
@@ -644,7 +647,7 @@ pub struct InstrumentDefMsg {
 
 /// An auction imbalance message.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -697,22 +700,26 @@ pub struct ImbalanceMsg {
     #[pyo3(get)]
     pub lower_collar: i64,
     /// The quantity of shares that are eligible to be matched at `ref_price`.
+    #[dbn(order_size)]
     #[pyo3(get)]
     pub paired_qty: u32,
     /// The quantity of shares that are not paired at `ref_price`.
+    #[dbn(order_size)]
     #[pyo3(get)]
     pub total_imbalance_qty: u32,
     /// Reserved for future use.
+    #[dbn(order_size)]
     #[pyo3(get)]
     pub market_imbalance_qty: u32,
     /// Reserved for future use.
+    #[dbn(order_size)]
     #[pyo3(get)]
     pub unpaired_qty: u32,
     /// Venue-specific character code indicating the auction type.
     #[dbn(c_char)]
     pub auction_type: c_char,
     /// The market side of the `total_imbalance_qty`. Can be **A**sk, **B**id, or **N**one.
-    #[dbn(c_char)]
+    #[dbn(c_char, enum_type(Side))]
     pub side: c_char,
     /// Reserved for future use.
     #[pyo3(get)]
@@ -724,7 +731,7 @@ pub struct ImbalanceMsg {
     #[pyo3(get)]
     pub num_extensions: u8,
     /// Reserved for future use.
-    #[dbn(c_char)]
+    #[dbn(c_char, enum_type(Side))]
     pub unpaired_side: c_char,
     /// Venue-specific character code. For Nasdaq, contains the raw Price Variation Indicator.
     #[dbn(c_char)]
@@ -738,7 +745,7 @@ pub struct ImbalanceMsg {
 /// A statistics message. A catchall for various data disseminated by publishers.
 /// The [`stat_type`](Self::stat_type) indicates the statistic contained in the message.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -774,14 +781,14 @@ pub struct StatMsg {
     /// The delta of `ts_recv - ts_exchange_send`, max 2 seconds.
     pub ts_in_delta: i32,
     /// The type of statistic value contained in the message. Refer to the
-    /// [`StatType`](crate::enums::StatType) for variants.
-    #[dbn(fmt_method)]
+    /// [`StatType`] for variants.
+    #[dbn(fmt_method, enum_type(StatType))]
     pub stat_type: u16,
     /// A channel ID within the venue.
     pub channel_id: u16,
     /// Indicates if the statistic is newly added (1) or deleted (2). (Deleted is only used with
     /// some stat types)
-    #[dbn(fmt_method)]
+    #[dbn(fmt_method, enum_type(StatUpdateAction))]
     pub update_action: u8,
     /// Additional flags associate with certain stat types.
     #[dbn(fmt_binary)]
@@ -794,7 +801,7 @@ pub struct StatMsg {
 
 /// An error message from the Databento Live Subscription Gateway (LSG).
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -825,7 +832,7 @@ pub struct ErrorMsg {
 /// A symbol mapping message which maps a symbol of one [`SType`](crate::enums::SType)
 /// to another.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -872,7 +879,7 @@ pub struct SymbolMappingMsg {
 /// A non-error message from the Databento Live Subscription Gateway (LSG). Also used
 /// for heartbeating.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Columnar, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -1,4 +1,5 @@
 use crate::{
+    registry::{self, RegisteredRecord, RegisteredRecordRef},
     Error, ErrorMsg, ImbalanceMsg, InstrumentDefMsg, MboMsg, Mbp10Msg, Mbp1Msg, OhlcvMsg, RType,
     Record, RecordMut, RecordRef, StatMsg, StatusMsg, SymbolMappingMsg, SystemMsg, TradeMsg,
 };
@@ -30,6 +31,8 @@ pub enum RecordEnum {
     SymbolMapping(SymbolMappingMsg),
     /// A non-error message from the Databento Live Subscription Gateway (LSG).
     System(SystemMsg),
+    /// A user-defined record registered with [`registry::register()`].
+    Registered(RegisteredRecord),
 }
 
 /// An immutable reference to a DBN record of flexible type. Unlike [`RecordRef`], this
@@ -62,6 +65,8 @@ pub enum RecordRefEnum<'a> {
     /// A reference to a non-error message from the Databento Live Subscription Gateway
     /// (LSG).
     System(&'a SystemMsg),
+    /// A reference to a user-defined record registered with [`registry::register()`].
+    Registered(RegisteredRecordRef<'a>),
 }
 
 impl<'a> From<&'a RecordEnum> for RecordRefEnum<'a> {
@@ -79,6 +84,7 @@ impl<'a> From<&'a RecordEnum> for RecordRefEnum<'a> {
             RecordEnum::Error(rec) => Self::Error(rec),
             RecordEnum::SymbolMapping(rec) => Self::SymbolMapping(rec),
             RecordEnum::System(rec) => Self::System(rec),
+            RecordEnum::Registered(rec) => Self::Registered(rec.as_registered_ref()),
         }
     }
 }
@@ -100,6 +106,7 @@ impl<'a> RecordRefEnum<'a> {
             Self::Error(rec) => RecordEnum::from((*rec).clone()),
             Self::SymbolMapping(rec) => RecordEnum::from((*rec).clone()),
             Self::System(rec) => RecordEnum::from((*rec).clone()),
+            Self::Registered(rec) => RecordEnum::Registered(rec.to_owned()),
         }
     }
 }
@@ -108,9 +115,19 @@ impl<'a> TryFrom<RecordRef<'a>> for RecordRefEnum<'a> {
     type Error = Error;
 
     fn try_from(rec_ref: RecordRef<'a>) -> Result<Self, Error> {
+        let rtype = match rec_ref.rtype() {
+            Ok(rtype) => rtype,
+            Err(err) => {
+                return registry::registration(rec_ref.header().rtype)
+                    .map(|registration| {
+                        RecordRefEnum::Registered(RegisteredRecordRef::new(rec_ref, registration))
+                    })
+                    .ok_or(err)
+            }
+        };
         Ok(unsafe {
             #[allow(deprecated)]
-            match rec_ref.rtype()? {
+            match rtype {
                 RType::Mbo => RecordRefEnum::Mbo(rec_ref.get_unchecked()),
                 RType::Mbp0 => RecordRefEnum::Trade(rec_ref.get_unchecked()),
                 RType::Mbp1 => RecordRefEnum::Mbp1(rec_ref.get_unchecked()),
@@ -285,6 +302,7 @@ impl Record for RecordEnum {
             RecordEnum::Error(rec) => rec.header(),
             RecordEnum::SymbolMapping(rec) => rec.header(),
             RecordEnum::System(rec) => rec.header(),
+            RecordEnum::Registered(rec) => rec.header(),
         }
     }
 
@@ -302,6 +320,7 @@ impl Record for RecordEnum {
             RecordEnum::Error(rec) => rec.raw_index_ts(),
             RecordEnum::SymbolMapping(rec) => rec.raw_index_ts(),
             RecordEnum::System(rec) => rec.raw_index_ts(),
+            RecordEnum::Registered(rec) => rec.raw_index_ts(),
         }
     }
 }
@@ -321,6 +340,7 @@ impl RecordMut for RecordEnum {
             RecordEnum::Error(rec) => rec.header_mut(),
             RecordEnum::SymbolMapping(rec) => rec.header_mut(),
             RecordEnum::System(rec) => rec.header_mut(),
+            RecordEnum::Registered(rec) => rec.header_mut(),
         }
     }
 }
@@ -340,6 +360,7 @@ impl<'a> Record for RecordRefEnum<'a> {
             RecordRefEnum::Error(rec) => rec.header(),
             RecordRefEnum::SymbolMapping(rec) => rec.header(),
             RecordRefEnum::System(rec) => rec.header(),
+            RecordRefEnum::Registered(rec) => rec.header(),
         }
     }
 
@@ -357,6 +378,7 @@ impl<'a> Record for RecordRefEnum<'a> {
             RecordRefEnum::Error(rec) => rec.raw_index_ts(),
             RecordRefEnum::SymbolMapping(rec) => rec.raw_index_ts(),
             RecordRefEnum::System(rec) => rec.raw_index_ts(),
+            RecordRefEnum::Registered(rec) => rec.raw_index_ts(),
         }
     }
 }
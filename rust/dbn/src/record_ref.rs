@@ -4,7 +4,8 @@ use std::{fmt::Debug, marker::PhantomData, mem, ptr::NonNull};
 
 use crate::{
     record::{HasRType, Record, RecordHeader},
-    rtype_dispatch, RecordEnum, RecordRefEnum,
+    reflect::{FieldDesc, FieldValue, HasFields},
+    registry, rtype_dispatch, RecordEnum, RecordRefEnum,
 };
 
 /// A wrapper around a non-owning immutable reference to a DBN record. This wrapper
@@ -97,10 +98,40 @@ impl<'a> RecordRef<'a> {
     /// # Errors
     /// This function returns a conversion error if the rtype does not correspond with
     /// any known DBN record type.
-    pub fn as_enum(&self) -> crate::Result<RecordRefEnum> {
+    pub fn as_enum(&self) -> crate::Result<RecordRefEnum<'_>> {
         RecordRefEnum::try_from(*self)
     }
 
+    /// Returns the field descriptors of the record's type or `None` if the rtype is
+    /// neither a built-in nor a [registered](crate::registry) record type.
+    pub fn fields(&self) -> Option<&'static [FieldDesc]> {
+        fn fields_of<R: HasFields>(_: &R) -> &'static [FieldDesc] {
+            R::fields()
+        }
+
+        rtype_dispatch!(self, fields_of).ok().or_else(|| {
+            registry::registration(self.header().rtype).map(|registration| registration.fields())
+        })
+    }
+
+    /// Returns the value of the field named `name`, using the same flattened names as
+    /// the CSV encoder, e.g. `ts_event` or `bid_px_00`. Returns `None` if the record
+    /// doesn't have such a field.
+    pub fn field(&self, name: &str) -> Option<FieldValue<'a>> {
+        self.fields()?
+            .iter()
+            .find(|f| f.name() == name)?
+            .value(*self)
+    }
+
+    /// Returns the raw bytes of the record, tied to the lifetime of the underlying
+    /// buffer rather than `self`.
+    pub(crate) fn bytes(&self) -> &'a [u8] {
+        // # Safety
+        // Assumes the encoded record length is correct.
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr() as *const u8, self.record_size()) }
+    }
+
     /// Returns a reference to the underlying record of type `T` without checking if
     /// this object references a record of type `T`.
     ///
@@ -134,9 +165,7 @@ where
 
 impl<'a> AsRef<[u8]> for RecordRef<'a> {
     fn as_ref(&self) -> &'a [u8] {
-        // # Safety
-        // Assumes the encoded record length is correct.
-        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr() as *const u8, self.record_size()) }
+        self.bytes()
     }
 }
 
@@ -147,7 +176,11 @@ impl<'a> Record for RecordRef<'a> {
     }
 
     fn raw_index_ts(&self) -> u64 {
-        rtype_dispatch!(self, Record::raw_index_ts).unwrap_or_else(|_| self.header().ts_event)
+        rtype_dispatch!(self, Record::raw_index_ts).unwrap_or_else(|_| {
+            registry::registration(self.header().rtype)
+                .map(|registration| registration.raw_index_ts(*self))
+                .unwrap_or(self.header().ts_event)
+        })
     }
 }
 
@@ -166,6 +199,7 @@ impl<'a> From<&'a RecordEnum> for RecordRef<'a> {
             RecordEnum::Error(rec) => Self::from(rec),
             RecordEnum::SymbolMapping(rec) => Self::from(rec),
             RecordEnum::System(rec) => Self::from(rec),
+            RecordEnum::Registered(rec) => rec.as_registered_ref().record_ref(),
         }
     }
 }
@@ -185,6 +219,7 @@ impl<'a> From<RecordRefEnum<'a>> for RecordRef<'a> {
             RecordRefEnum::Error(rec) => Self::from(rec),
             RecordRefEnum::SymbolMapping(rec) => Self::from(rec),
             RecordRefEnum::System(rec) => Self::from(rec),
+            RecordRefEnum::Registered(rec) => rec.record_ref(),
         }
     }
 }
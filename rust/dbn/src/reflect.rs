@@ -0,0 +1,706 @@
+//! Field-level reflection on records.
+//!
+//! Every record type defined with the [`dbn_record`](crate::macros::dbn_record) macro
+//! implements [`HasFields`], which returns a static table of [`FieldDesc`]s describing
+//! the name, byte offset, primitive type, and formatting hint of each field. Nested
+//! fields are flattened with the same names as the CSV encoder's columns, e.g.
+//! `ts_event` from the [`RecordHeader`] and `bid_px_00` from the first book level.
+//!
+//! This allows generic tooling like filters and projections to access fields of a
+//! [`RecordRef`] without matching on every record type:
+//!
+//! ```
+//! use dbn::{reflect::FieldValue, RecordRef, TradeMsg};
+//!
+//! let trade = TradeMsg {
+//!     price: 1_500_000_000,
+//!     size: 10,
+//!     ..Default::default()
+//! };
+//! let rec_ref = RecordRef::from(&trade);
+//! assert_eq!(rec_ref.field("price"), Some(FieldValue::I64(1_500_000_000)));
+//! assert_eq!(rec_ref.field("size").and_then(|v| v.as_u64()), Some(10));
+//! assert!(rec_ref.field("bid_px_00").is_none());
+//! ```
+
+use std::{ffi::c_char, fmt, mem};
+
+use crate::{
+    enums::{
+        Action, InstrumentClass, MatchAlgorithm, SecurityUpdateAction, Side, StatType,
+        StatUpdateAction, UserDefinedInstrument,
+    },
+    record::{BidAskPair, RecordHeader},
+    RType, RecordRef, Schema,
+};
+
+/// A trait for record types with a static table of field descriptors.
+pub trait HasFields {
+    /// Returns the descriptors of all non-skipped fields in encoding order, with nested
+    /// fields flattened.
+    fn fields() -> &'static [FieldDesc];
+
+    /// Returns the descriptor of the field named `name`, if any.
+    fn field_desc(name: &str) -> Option<&'static FieldDesc> {
+        Self::fields().iter().find(|f| f.name() == name)
+    }
+}
+
+/// The primitive type of a record field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    /// A signed 8-bit integer.
+    I8,
+    /// A signed 16-bit integer.
+    I16,
+    /// A signed 32-bit integer.
+    I32,
+    /// A signed 64-bit integer.
+    I64,
+    /// An unsigned 8-bit integer.
+    U8,
+    /// An unsigned 16-bit integer.
+    U16,
+    /// An unsigned 32-bit integer.
+    U32,
+    /// An unsigned 64-bit integer.
+    U64,
+    /// A null-terminated C string stored in a fixed-length array of the given length.
+    CStr(usize),
+}
+
+impl FieldType {
+    /// Returns the size of the field in bytes.
+    pub const fn size(self) -> usize {
+        match self {
+            FieldType::I8 | FieldType::U8 => 1,
+            FieldType::I16 | FieldType::U16 => 2,
+            FieldType::I32 | FieldType::U32 => 4,
+            FieldType::I64 | FieldType::U64 => 8,
+            FieldType::CStr(len) => len,
+        }
+    }
+}
+
+/// A hint for how the value of a field should be interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldHint {
+    /// The integer is a single ASCII character.
+    CChar,
+    /// The integer is a fixed-precision price where every 1 unit corresponds to 1e-9.
+    FixedPrice,
+    /// The integer is a UNIX timestamp in nanoseconds.
+    UnixNanos,
+    /// The integer is an order size, where [`UNDEF_ORDER_SIZE`](crate::UNDEF_ORDER_SIZE)
+    /// denotes an undefined size.
+    OrderSize,
+    /// The integer is the raw value of an enum.
+    Enum(FieldEnum),
+}
+
+impl FieldHint {
+    /// Returns `true` if the integer is a single ASCII character, including enums
+    /// represented by one.
+    pub const fn is_c_char(self) -> bool {
+        match self {
+            FieldHint::CChar => true,
+            FieldHint::Enum(field_enum) => field_enum.is_c_char(),
+            _ => false,
+        }
+    }
+}
+
+/// An enum whose raw value is stored in a field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldEnum {
+    /// [`Action`].
+    Action,
+    /// [`InstrumentClass`].
+    InstrumentClass,
+    /// [`MatchAlgorithm`].
+    MatchAlgorithm,
+    /// [`SecurityUpdateAction`].
+    SecurityUpdateAction,
+    /// [`Side`].
+    Side,
+    /// [`StatType`].
+    StatType,
+    /// [`StatUpdateAction`].
+    StatUpdateAction,
+}
+
+impl FieldEnum {
+    /// Returns `true` if the enum is represented by a single ASCII character.
+    pub const fn is_c_char(self) -> bool {
+        !matches!(self, FieldEnum::StatType | FieldEnum::StatUpdateAction)
+    }
+
+    /// Returns the name of the variant of the enum `value` represents. Returns `None`
+    /// if `value` isn't a valid variant.
+    pub fn variant_name(self, value: FieldValue) -> Option<String> {
+        let raw = match value {
+            FieldValue::Char(c) => u64::from(c),
+            value => value.as_u64()?,
+        };
+        let name = match self {
+            FieldEnum::Action => format!("{:?}", Action::try_from(u8::try_from(raw).ok()?).ok()?),
+            FieldEnum::InstrumentClass => format!(
+                "{:?}",
+                InstrumentClass::try_from(u8::try_from(raw).ok()?).ok()?
+            ),
+            FieldEnum::MatchAlgorithm => format!(
+                "{:?}",
+                MatchAlgorithm::try_from(u8::try_from(raw).ok()?).ok()?
+            ),
+            FieldEnum::SecurityUpdateAction => format!(
+                "{:?}",
+                SecurityUpdateAction::try_from(u8::try_from(raw).ok()?).ok()?
+            ),
+            FieldEnum::Side => format!("{:?}", Side::try_from(u8::try_from(raw).ok()?).ok()?),
+            FieldEnum::StatType => {
+                format!("{:?}", StatType::try_from(u16::try_from(raw).ok()?).ok()?)
+            }
+            FieldEnum::StatUpdateAction => format!(
+                "{:?}",
+                StatUpdateAction::try_from(u8::try_from(raw).ok()?).ok()?
+            ),
+        };
+        Some(name)
+    }
+}
+
+/// The descriptor of a single field in a record.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FieldDesc {
+    name: String,
+    offset: usize,
+    field_type: FieldType,
+    hint: Option<FieldHint>,
+}
+
+impl FieldDesc {
+    /// Creates a new field descriptor.
+    pub fn new(
+        name: impl Into<String>,
+        offset: usize,
+        field_type: FieldType,
+        hint: Option<FieldHint>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            offset,
+            field_type,
+            hint,
+        }
+    }
+
+    /// Returns the name of the field.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the byte offset of the field from the start of the record.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the primitive type of the field.
+    pub fn field_type(&self) -> FieldType {
+        self.field_type
+    }
+
+    /// Returns the formatting hint for the field, if any.
+    pub fn hint(&self) -> Option<FieldHint> {
+        self.hint
+    }
+
+    /// Reads the value of the field from `rec`. Returns `None` if the record is too
+    /// short to contain the field.
+    pub fn value<'a>(&self, rec: RecordRef<'a>) -> Option<FieldValue<'a>> {
+        let bytes = rec
+            .bytes()
+            .get(self.offset..self.offset + self.field_type.size())?;
+        macro_rules! read {
+            ($ty:ty) => {
+                <$ty>::from_ne_bytes(bytes.try_into().ok()?)
+            };
+        }
+        let value = match self.field_type {
+            FieldType::I8 if self.hint.is_some_and(FieldHint::is_c_char) => {
+                FieldValue::Char(read!(i8) as u8 as char)
+            }
+            FieldType::U8 if self.hint.is_some_and(FieldHint::is_c_char) => {
+                FieldValue::Char(read!(u8) as char)
+            }
+            FieldType::I8 => FieldValue::I8(read!(i8)),
+            FieldType::I16 => FieldValue::I16(read!(i16)),
+            FieldType::I32 => FieldValue::I32(read!(i32)),
+            FieldType::I64 => FieldValue::I64(read!(i64)),
+            FieldType::U8 => FieldValue::U8(read!(u8)),
+            FieldType::U16 => FieldValue::U16(read!(u16)),
+            FieldType::U32 => FieldValue::U32(read!(u32)),
+            FieldType::U64 => FieldValue::U64(read!(u64)),
+            FieldType::CStr(_) => FieldValue::CStr(bytes),
+        };
+        Some(value)
+    }
+}
+
+/// The value of a record field read through a [`FieldDesc`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldValue<'a> {
+    /// A signed 8-bit integer.
+    I8(i8),
+    /// A signed 16-bit integer.
+    I16(i16),
+    /// A signed 32-bit integer.
+    I32(i32),
+    /// A signed 64-bit integer.
+    I64(i64),
+    /// An unsigned 8-bit integer.
+    U8(u8),
+    /// An unsigned 16-bit integer.
+    U16(u16),
+    /// An unsigned 32-bit integer.
+    U32(u32),
+    /// An unsigned 64-bit integer.
+    U64(u64),
+    /// A single ASCII character.
+    Char(char),
+    /// The raw bytes of a fixed-length, null-terminated C string.
+    CStr(&'a [u8]),
+}
+
+impl<'a> FieldValue<'a> {
+    /// Returns the value as an `i64` if it's an integer that fits.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            FieldValue::I8(v) => Some(v.into()),
+            FieldValue::I16(v) => Some(v.into()),
+            FieldValue::I32(v) => Some(v.into()),
+            FieldValue::I64(v) => Some(v),
+            FieldValue::U8(v) => Some(v.into()),
+            FieldValue::U16(v) => Some(v.into()),
+            FieldValue::U32(v) => Some(v.into()),
+            FieldValue::U64(v) => v.try_into().ok(),
+            FieldValue::Char(_) | FieldValue::CStr(_) => None,
+        }
+    }
+
+    /// Returns the value as a `u64` if it's an integer that fits.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            FieldValue::U64(v) => Some(v),
+            _ => self.as_i64().and_then(|v| v.try_into().ok()),
+        }
+    }
+
+    /// Returns the value as a `&str` if it's a C string containing valid UTF-8.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match *self {
+            FieldValue::CStr(bytes) => std::str::from_utf8(until_nul(bytes)).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FieldValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            FieldValue::I8(v) => write!(f, "{v}"),
+            FieldValue::I16(v) => write!(f, "{v}"),
+            FieldValue::I32(v) => write!(f, "{v}"),
+            FieldValue::I64(v) => write!(f, "{v}"),
+            FieldValue::U8(v) => write!(f, "{v}"),
+            FieldValue::U16(v) => write!(f, "{v}"),
+            FieldValue::U32(v) => write!(f, "{v}"),
+            FieldValue::U64(v) => write!(f, "{v}"),
+            FieldValue::Char(c) => write!(f, "{c}"),
+            FieldValue::CStr(bytes) => f.write_str(&String::from_utf8_lossy(until_nul(bytes))),
+        }
+    }
+}
+
+/// Returns the field descriptors of the record type of `schema`.
+pub(crate) fn schema_fields(schema: Schema) -> &'static [FieldDesc] {
+    macro_rules! handler {
+        ($r:ty) => {{
+            <$r as HasFields>::fields()
+        }};
+    }
+    crate::schema_dispatch_base!(schema, handler)
+}
+
+/// Returns the field descriptors of the current version of the record type of `rtype`.
+pub(crate) fn rtype_fields(rtype: RType) -> &'static [FieldDesc] {
+    use crate::record::*;
+
+    match rtype {
+        RType::Mbp0 => TradeMsg::fields(),
+        RType::Mbp1 => Mbp1Msg::fields(),
+        RType::Mbp10 => Mbp10Msg::fields(),
+        #[allow(deprecated)]
+        RType::OhlcvDeprecated
+        | RType::Ohlcv1S
+        | RType::Ohlcv1M
+        | RType::Ohlcv1H
+        | RType::Ohlcv1D
+        | RType::OhlcvEod => OhlcvMsg::fields(),
+        RType::Imbalance => ImbalanceMsg::fields(),
+        RType::Status => StatusMsg::fields(),
+        RType::InstrumentDef => InstrumentDefMsg::fields(),
+        RType::SymbolMapping => SymbolMappingMsg::fields(),
+        RType::Error => ErrorMsg::fields(),
+        RType::System => SystemMsg::fields(),
+        RType::Statistics => StatMsg::fields(),
+        RType::Mbo => MboMsg::fields(),
+    }
+}
+
+fn until_nul(bytes: &[u8]) -> &[u8] {
+    bytes
+        .iter()
+        .position(|&b| b == 0)
+        .map_or(bytes, |end| &bytes[..end])
+}
+
+/// Used by the [`dbn_record`](crate::macros::dbn_record) macro to flatten a field into
+/// one or more [`FieldDesc`]s.
+#[doc(hidden)]
+pub trait ReflectField {
+    fn append_fields(
+        fields: &mut Vec<FieldDesc>,
+        name: &str,
+        offset: usize,
+        hint: Option<FieldHint>,
+    );
+}
+
+macro_rules! impl_reflect_field {
+    ($($ty:ty => $field_type:ident),* $(,)?) => {
+        $(
+            impl ReflectField for $ty {
+                fn append_fields(
+                    fields: &mut Vec<FieldDesc>,
+                    name: &str,
+                    offset: usize,
+                    hint: Option<FieldHint>,
+                ) {
+                    fields.push(FieldDesc::new(name, offset, FieldType::$field_type, hint));
+                }
+            }
+        )*
+    };
+}
+
+impl_reflect_field! {
+    i8 => I8,
+    i16 => I16,
+    i32 => I32,
+    i64 => I64,
+    u8 => U8,
+    u16 => U16,
+    u32 => U32,
+    u64 => U64,
+}
+
+impl<const N: usize> ReflectField for [c_char; N] {
+    fn append_fields(
+        fields: &mut Vec<FieldDesc>,
+        name: &str,
+        offset: usize,
+        hint: Option<FieldHint>,
+    ) {
+        fields.push(FieldDesc::new(name, offset, FieldType::CStr(N), hint));
+    }
+}
+
+impl ReflectField for SecurityUpdateAction {
+    fn append_fields(
+        fields: &mut Vec<FieldDesc>,
+        name: &str,
+        offset: usize,
+        _hint: Option<FieldHint>,
+    ) {
+        fields.push(FieldDesc::new(
+            name,
+            offset,
+            FieldType::U8,
+            Some(FieldHint::Enum(FieldEnum::SecurityUpdateAction)),
+        ));
+    }
+}
+
+impl ReflectField for UserDefinedInstrument {
+    fn append_fields(
+        fields: &mut Vec<FieldDesc>,
+        name: &str,
+        offset: usize,
+        _hint: Option<FieldHint>,
+    ) {
+        fields.push(FieldDesc::new(
+            name,
+            offset,
+            FieldType::U8,
+            Some(FieldHint::CChar),
+        ));
+    }
+}
+
+impl ReflectField for RecordHeader {
+    fn append_fields(
+        fields: &mut Vec<FieldDesc>,
+        _name: &str,
+        offset: usize,
+        _hint: Option<FieldHint>,
+    ) {
+        let header_fields = [
+            (
+                "ts_event",
+                mem::offset_of!(RecordHeader, ts_event),
+                FieldType::U64,
+                Some(FieldHint::UnixNanos),
+            ),
+            (
+                "rtype",
+                mem::offset_of!(RecordHeader, rtype),
+                FieldType::U8,
+                None,
+            ),
+            (
+                "publisher_id",
+                mem::offset_of!(RecordHeader, publisher_id),
+                FieldType::U16,
+                None,
+            ),
+            (
+                "instrument_id",
+                mem::offset_of!(RecordHeader, instrument_id),
+                FieldType::U32,
+                None,
+            ),
+        ];
+        fields.extend(
+            header_fields
+                .into_iter()
+                .map(|(name, field_offset, field_type, hint)| {
+                    FieldDesc::new(name, offset + field_offset, field_type, hint)
+                }),
+        );
+    }
+}
+
+impl<const N: usize> ReflectField for [BidAskPair; N] {
+    fn append_fields(
+        fields: &mut Vec<FieldDesc>,
+        _name: &str,
+        offset: usize,
+        _hint: Option<FieldHint>,
+    ) {
+        let level_fields = [
+            (
+                "bid_px",
+                mem::offset_of!(BidAskPair, bid_px),
+                FieldType::I64,
+                Some(FieldHint::FixedPrice),
+            ),
+            (
+                "ask_px",
+                mem::offset_of!(BidAskPair, ask_px),
+                FieldType::I64,
+                Some(FieldHint::FixedPrice),
+            ),
+            (
+                "bid_sz",
+                mem::offset_of!(BidAskPair, bid_sz),
+                FieldType::U32,
+                None,
+            ),
+            (
+                "ask_sz",
+                mem::offset_of!(BidAskPair, ask_sz),
+                FieldType::U32,
+                None,
+            ),
+            (
+                "bid_ct",
+                mem::offset_of!(BidAskPair, bid_ct),
+                FieldType::U32,
+                None,
+            ),
+            (
+                "ask_ct",
+                mem::offset_of!(BidAskPair, ask_ct),
+                FieldType::U32,
+                None,
+            ),
+        ];
+        for i in 0..N {
+            let level_offset = offset + i * mem::size_of::<BidAskPair>();
+            fields.extend(
+                level_fields
+                    .iter()
+                    .map(|&(name, field_offset, field_type, hint)| {
+                        FieldDesc::new(
+                            format!("{name}_{i:02}"),
+                            level_offset + field_offset,
+                            field_type,
+                            hint,
+                        )
+                    }),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_char;
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{
+        compat::InstrumentDefMsgV1,
+        record::{str_to_c_chars, InstrumentDefMsg, MboMsg, Mbp10Msg, Mbp1Msg, StatMsg},
+        rtype, HasRType,
+    };
+
+    #[test]
+    fn test_mbo_fields_match_csv_header() {
+        let names: Vec<_> = MboMsg::fields().iter().map(FieldDesc::name).collect();
+        assert_eq!(
+            names,
+            [
+                "ts_recv",
+                "ts_event",
+                "rtype",
+                "publisher_id",
+                "instrument_id",
+                "action",
+                "side",
+                "price",
+                "size",
+                "channel_id",
+                "order_id",
+                "flags",
+                "ts_in_delta",
+                "sequence"
+            ]
+        );
+        let price = MboMsg::field_desc("price").unwrap();
+        assert_eq!(price.offset(), mem::offset_of!(MboMsg, price));
+        assert_eq!(price.field_type(), FieldType::I64);
+        assert_eq!(price.hint(), Some(FieldHint::FixedPrice));
+        let action = MboMsg::field_desc("action").unwrap();
+        assert_eq!(action.hint(), Some(FieldHint::Enum(FieldEnum::Action)));
+        assert!(action.hint().unwrap().is_c_char());
+        assert_eq!(
+            MboMsg::field_desc("size").unwrap().hint(),
+            Some(FieldHint::OrderSize)
+        );
+    }
+
+    #[test]
+    fn test_enum_variant_name() {
+        let mbo = MboMsg {
+            action: b'T' as c_char,
+            side: b'X' as c_char,
+            ..Default::default()
+        };
+        let rec_ref = RecordRef::from(&mbo);
+        assert_eq!(rec_ref.field("action"), Some(FieldValue::Char('T')));
+        let variant_name = |name| {
+            let desc = MboMsg::field_desc(name).unwrap();
+            let Some(FieldHint::Enum(field_enum)) = desc.hint() else {
+                panic!("{name} isn't an enum");
+            };
+            field_enum.variant_name(desc.value(rec_ref).unwrap())
+        };
+        assert_eq!(variant_name("action").as_deref(), Some("Trade"));
+        // Invalid variant
+        assert_eq!(variant_name("side"), None);
+        let stat = StatMsg {
+            stat_type: StatType::OpenInterest as u16,
+            ..Default::default()
+        };
+        let desc = StatMsg::field_desc("stat_type").unwrap();
+        assert_eq!(desc.hint(), Some(FieldHint::Enum(FieldEnum::StatType)));
+        assert_eq!(
+            FieldEnum::StatType
+                .variant_name(desc.value(RecordRef::from(&stat)).unwrap())
+                .as_deref(),
+            Some("OpenInterest")
+        );
+    }
+
+    #[test]
+    fn test_book_levels_flattened() {
+        assert_eq!(Mbp1Msg::fields().len(), 13 + 6);
+        let desc = Mbp10Msg::field_desc("ask_ct_09").unwrap();
+        assert_eq!(
+            desc.offset(),
+            mem::offset_of!(Mbp10Msg, levels)
+                + 9 * mem::size_of::<BidAskPair>()
+                + mem::offset_of!(BidAskPair, ask_ct)
+        );
+        let mut mbp1 = Mbp1Msg::default();
+        mbp1.levels[0].bid_px = 1_250_000_000;
+        mbp1.levels[0].ask_sz = 7;
+        let rec_ref = RecordRef::from(&mbp1);
+        assert_eq!(
+            rec_ref.field("bid_px_00"),
+            Some(FieldValue::I64(1_250_000_000))
+        );
+        assert_eq!(rec_ref.field("ask_sz_00"), Some(FieldValue::U32(7)));
+        assert_eq!(rec_ref.field("bid_px_01"), None);
+    }
+
+    #[rstest]
+    #[case::header("instrument_id", FieldValue::U32(42))]
+    #[case::c_char("side", FieldValue::Char('B'))]
+    #[case::timestamp("ts_recv", FieldValue::U64(1_700_000_000_000_000_000))]
+    fn test_field_value(#[case] name: &str, #[case] exp: FieldValue) {
+        let mut mbo = MboMsg::default();
+        mbo.hd.instrument_id = 42;
+        mbo.side = b'B' as c_char;
+        mbo.ts_recv = 1_700_000_000_000_000_000;
+        assert_eq!(RecordRef::from(&mbo).field(name), Some(exp));
+    }
+
+    #[test]
+    fn test_versioned_definitions() {
+        let def_v1 = InstrumentDefMsgV1 {
+            raw_symbol: str_to_c_chars("ESZ4").unwrap(),
+            ..Default::default()
+        };
+        let rec_ref = RecordRef::from(&def_v1);
+        assert!(InstrumentDefMsgV1::has_rtype(rtype::INSTRUMENT_DEF));
+        assert_eq!(
+            rec_ref.fields().unwrap().len(),
+            InstrumentDefMsgV1::fields().len()
+        );
+        let raw_symbol = rec_ref.field("raw_symbol").unwrap();
+        assert_eq!(raw_symbol.as_str(), Some("ESZ4"));
+        assert_eq!(raw_symbol.to_string(), "ESZ4");
+        assert_eq!(
+            rec_ref.field("security_update_action"),
+            Some(FieldValue::Char('A'))
+        );
+
+        let def = InstrumentDefMsg::default();
+        assert_eq!(
+            RecordRef::from(&def).field("user_defined_instrument"),
+            Some(FieldValue::Char('N'))
+        );
+    }
+
+    #[test]
+    fn test_field_value_conversions() {
+        assert_eq!(FieldValue::U64(u64::MAX).as_i64(), None);
+        assert_eq!(FieldValue::U64(u64::MAX).as_u64(), Some(u64::MAX));
+        assert_eq!(FieldValue::I32(-1).as_u64(), None);
+        assert_eq!(FieldValue::I32(-1).as_i64(), Some(-1));
+        assert_eq!(FieldValue::Char('A').as_i64(), None);
+    }
+}
@@ -0,0 +1,579 @@
+//! Registration of user-defined record types.
+//!
+//! Records with a private `rtype` outside of [`RType`] are passed through by the
+//! decoders and the DBN encoder as-is. Once a record type has been registered with
+//! [`register()`], it can also be matched on through
+//! [`RecordRef::as_enum()`](crate::RecordRef::as_enum) as a
+//! [`RecordRefEnum::Registered`](crate::RecordRefEnum::Registered) and encoded by the
+//! CSV and JSON encoders.
+//!
+//! A user-defined record type must be `#[repr(C)]` and begin with a [`RecordHeader`]
+//! field named `hd`. The traits required by [`DbnEncodable`] can be derived with the
+//! macros re-exported from this module, which support the same `dbn` attributes as the
+//! built-in records:
+//!
+//! ```
+//! use dbn::{
+//!     registry::{self, dbn_record, CsvSerialize, JsonSerialize},
+//!     RecordHeader,
+//! };
+//!
+//! const CUSTOM_RTYPE: u8 = 0xF0;
+//!
+//! #[repr(C)]
+//! #[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq)]
+//! #[dbn_record(CUSTOM_RTYPE)]
+//! pub struct CustomMsg {
+//!     pub hd: RecordHeader,
+//!     #[dbn(fixed_price)]
+//!     pub fair_px: i64,
+//!     #[dbn(unix_nanos, index_ts)]
+//!     pub ts_recv: u64,
+//! }
+//!
+//! registry::register::<CustomMsg>(CUSTOM_RTYPE)?;
+//! # Ok::<(), dbn::Error>(())
+//! ```
+//!
+//! Note that CSV headers can only be written upfront for a [`Schema`](crate::Schema),
+//! so when encoding registered records to CSV dynamically, build the encoder without
+//! a header and it will be written before the first registered record.
+//!
+//! The dispatch macros like [`rtype_dispatch!`](crate::rtype_dispatch) specialize a
+//! generic function for each built-in record type at compile time, so they can't
+//! dispatch to types registered at runtime and continue to return an error for
+//! registered rtypes. Use [`RecordRef::as_enum()`](crate::RecordRef::as_enum) to
+//! handle registered records instead.
+
+use std::{any::TypeId, collections::BTreeMap, fmt, mem, sync::RwLock};
+
+pub use dbn_macros::{dbn_record, CsvSerialize, DbnAttr, JsonSerialize, RecordDebug};
+
+use crate::{
+    encode::{
+        csv::serialize::CsvSerialize as CsvSerializeTrait,
+        json::serialize::{to_json_string, to_json_string_with_sym},
+        DbnEncodable,
+    },
+    reflect::{FieldDesc, HasFields},
+    Error, HasRType, RType, Record, RecordHeader, RecordMut, RecordRef, Result, WithTsOut,
+};
+
+static REGISTRY: RwLock<BTreeMap<u8, Registration>> = RwLock::new(BTreeMap::new());
+
+/// The largest possible record size, limited by the `length` field of
+/// [`RecordHeader`].
+const MAX_REGISTERED_LEN: usize = u8::MAX as usize * RecordHeader::LENGTH_MULTIPLIER;
+
+/// Registers the record type `R` with `rtype` so it can be dynamically matched and
+/// encoded. Registering the same type with the same `rtype` more than once is a
+/// no-op.
+///
+/// # Errors
+/// This function returns an error if `rtype` is used by a built-in [`RType`] or already
+/// registered for another type, if `R` doesn't have `rtype`, or if the size of `R`
+/// can't be expressed in a [`RecordHeader`].
+pub fn register<R>(rtype: u8) -> Result<()>
+where
+    R: DbnEncodable + HasFields + HasRType + 'static,
+{
+    let bad_rtype = |desc: String| Error::BadArgument {
+        param_name: "rtype".to_owned(),
+        desc,
+    };
+    if let Ok(builtin) = RType::try_from(rtype) {
+        return Err(bad_rtype(format!(
+            "{rtype:#04X} is reserved for {builtin:?} records"
+        )));
+    }
+    if !R::has_rtype(rtype) {
+        return Err(bad_rtype(format!(
+            "{} doesn't have rtype {rtype:#04X}",
+            std::any::type_name::<R>()
+        )));
+    }
+    let size = mem::size_of::<R>();
+    if size < mem::size_of::<RecordHeader>()
+        || size > MAX_REGISTERED_LEN
+        || !size.is_multiple_of(RecordHeader::LENGTH_MULTIPLIER)
+    {
+        return Err(Error::BadArgument {
+            param_name: "R".to_owned(),
+            desc: format!(
+                "size of {size} bytes must be a multiple of {} between {} and {MAX_REGISTERED_LEN}",
+                RecordHeader::LENGTH_MULTIPLIER,
+                mem::size_of::<RecordHeader>()
+            ),
+        });
+    }
+    let registration = Registration::new::<R>(rtype);
+    let mut registry = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+    match registry.get(&rtype) {
+        Some(existing) if existing.type_id == registration.type_id => Ok(()),
+        Some(existing) => Err(bad_rtype(format!(
+            "{rtype:#04X} is already registered for {}",
+            existing.type_name
+        ))),
+        None => {
+            registry.insert(rtype, registration);
+            Ok(())
+        }
+    }
+}
+
+/// Returns the registration for `rtype` if a user-defined record type has been
+/// registered with it.
+pub fn registration(rtype: u8) -> Option<Registration> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&rtype)
+        .copied()
+}
+
+/// Serializes a record to JSON given the pretty print, pretty px, pretty ts, and
+/// `ts_out` flags and an optional symbol.
+type JsonFn = fn(RecordRef, bool, bool, bool, bool, Option<Option<&str>>) -> Result<String>;
+
+/// The type-erased operations of a record type registered with [`register()`].
+#[derive(Clone, Copy)]
+pub struct Registration {
+    rtype: u8,
+    type_id: TypeId,
+    type_name: &'static str,
+    record_size: usize,
+    fields: fn() -> &'static [FieldDesc],
+    fmt_debug: fn(RecordRef, &mut fmt::Formatter<'_>) -> fmt::Result,
+    raw_index_ts: fn(RecordRef) -> u64,
+    csv_header: fn(bool) -> Result<csv::ByteRecord>,
+    csv_fields: fn(RecordRef, bool, bool, bool) -> Result<csv::ByteRecord>,
+    json: JsonFn,
+}
+
+impl Registration {
+    fn new<R: DbnEncodable + HasFields + HasRType + 'static>(rtype: u8) -> Self {
+        Self {
+            rtype,
+            type_id: TypeId::of::<R>(),
+            type_name: std::any::type_name::<R>(),
+            record_size: mem::size_of::<R>(),
+            fields: R::fields,
+            fmt_debug: |rec, f| match get::<R>(rec) {
+                Ok(rec) => fmt::Debug::fmt(rec, f),
+                Err(_) => fmt::Debug::fmt(rec.header(), f),
+            },
+            raw_index_ts: |rec| {
+                get::<R>(rec)
+                    .map(Record::raw_index_ts)
+                    .unwrap_or(rec.header().ts_event)
+            },
+            csv_header: |ts_out| {
+                to_csv_fields(|writer| {
+                    if ts_out {
+                        WithTsOut::<R>::serialize_header(writer)
+                    } else {
+                        R::serialize_header(writer)
+                    }
+                })
+            },
+            csv_fields: |rec, pretty_px, pretty_ts, ts_out| {
+                if ts_out {
+                    let rec = get::<WithTsOut<R>>(rec)?;
+                    to_csv_fields(|writer| serialize_csv(rec, writer, pretty_px, pretty_ts))
+                } else {
+                    let rec = get::<R>(rec)?;
+                    to_csv_fields(|writer| serialize_csv(rec, writer, pretty_px, pretty_ts))
+                }
+            },
+            json: |rec, should_pretty_print, pretty_px, pretty_ts, ts_out, symbol| {
+                fn to_json<T: DbnEncodable>(
+                    rec: &T,
+                    should_pretty_print: bool,
+                    pretty_px: bool,
+                    pretty_ts: bool,
+                    symbol: Option<Option<&str>>,
+                ) -> String {
+                    if let Some(symbol) = symbol {
+                        to_json_string_with_sym(
+                            rec,
+                            should_pretty_print,
+                            pretty_px,
+                            pretty_ts,
+                            symbol,
+                        )
+                    } else {
+                        to_json_string(rec, should_pretty_print, pretty_px, pretty_ts)
+                    }
+                }
+
+                Ok(if ts_out {
+                    let rec = get::<WithTsOut<R>>(rec)?;
+                    to_json(rec, should_pretty_print, pretty_px, pretty_ts, symbol)
+                } else {
+                    let rec = get::<R>(rec)?;
+                    to_json(rec, should_pretty_print, pretty_px, pretty_ts, symbol)
+                })
+            },
+        }
+    }
+
+    /// Returns the rtype the record type was registered with.
+    pub fn rtype(&self) -> u8 {
+        self.rtype
+    }
+
+    /// Returns the name of the registered record type.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns the size of the registered record type in bytes.
+    pub fn record_size(&self) -> usize {
+        self.record_size
+    }
+
+    /// Returns the field descriptors of the registered record type.
+    pub fn fields(&self) -> &'static [FieldDesc] {
+        (self.fields)()
+    }
+
+    pub(crate) fn raw_index_ts(&self, rec: RecordRef) -> u64 {
+        (self.raw_index_ts)(rec)
+    }
+
+    /// Returns the CSV header fields of the registered record type.
+    pub(crate) fn csv_header(&self, ts_out: bool) -> Result<csv::ByteRecord> {
+        (self.csv_header)(ts_out)
+    }
+
+    /// Serializes `rec` to CSV fields.
+    pub(crate) fn csv_fields(
+        &self,
+        rec: RecordRef,
+        pretty_px: bool,
+        pretty_ts: bool,
+        ts_out: bool,
+    ) -> Result<csv::ByteRecord> {
+        (self.csv_fields)(rec, pretty_px, pretty_ts, ts_out)
+    }
+
+    /// Serializes `rec` to a JSON string. If `symbol` is `Some`, a `"symbol"` field is
+    /// included.
+    pub(crate) fn json(
+        &self,
+        rec: RecordRef,
+        should_pretty_print: bool,
+        pretty_px: bool,
+        pretty_ts: bool,
+        ts_out: bool,
+        symbol: Option<Option<&str>>,
+    ) -> Result<String> {
+        (self.json)(
+            rec,
+            should_pretty_print,
+            pretty_px,
+            pretty_ts,
+            ts_out,
+            symbol,
+        )
+    }
+}
+
+impl fmt::Debug for Registration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registration")
+            .field("rtype", &format_args!("{:#04X}", self.rtype))
+            .field("type_name", &self.type_name)
+            .field("record_size", &self.record_size)
+            .finish()
+    }
+}
+
+/// A reference to a record of a type registered with [`register()`].
+#[derive(Clone, Copy)]
+pub struct RegisteredRecordRef<'a> {
+    rec: RecordRef<'a>,
+    registration: Registration,
+}
+
+impl<'a> RegisteredRecordRef<'a> {
+    pub(crate) fn new(rec: RecordRef<'a>, registration: Registration) -> Self {
+        Self { rec, registration }
+    }
+
+    /// Returns the registration of the record's type.
+    pub fn registration(&self) -> &Registration {
+        &self.registration
+    }
+
+    /// Returns the record as a type-erased [`RecordRef`].
+    pub fn record_ref(&self) -> RecordRef<'a> {
+        self.rec
+    }
+
+    /// Returns a reference to the underlying record of type `T` or `None` if it's of
+    /// another type.
+    pub fn get<T: HasRType + 'static>(&self) -> Option<&'a T> {
+        if TypeId::of::<T>() == self.registration.type_id {
+            get::<T>(self.rec).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Copies the record into an owned [`RegisteredRecord`].
+    pub fn to_owned(&self) -> RegisteredRecord {
+        let bytes = self.rec.as_ref();
+        // Use `u64` for the alignment of the records
+        let mut buffer = vec![0_u64; bytes.len().div_ceil(mem::size_of::<u64>())];
+        // Safety: `buffer` is at least as long as `bytes` and they don't overlap.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                buffer.as_mut_ptr().cast::<u8>(),
+                bytes.len(),
+            );
+        }
+        RegisteredRecord {
+            buffer: buffer.into_boxed_slice(),
+            registration: self.registration,
+        }
+    }
+}
+
+impl Record for RegisteredRecordRef<'_> {
+    fn header(&self) -> &RecordHeader {
+        self.rec.header()
+    }
+
+    fn raw_index_ts(&self) -> u64 {
+        self.registration.raw_index_ts(self.rec)
+    }
+}
+
+impl fmt::Debug for RegisteredRecordRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (self.registration.fmt_debug)(self.rec, f)
+    }
+}
+
+/// An owned record of a type registered with [`register()`].
+#[derive(Clone)]
+pub struct RegisteredRecord {
+    buffer: Box<[u64]>,
+    registration: Registration,
+}
+
+impl RegisteredRecord {
+    /// Returns a reference to the record.
+    pub fn as_registered_ref(&self) -> RegisteredRecordRef<'_> {
+        // Safety: `buffer` was copied from a `RecordRef`.
+        let rec = unsafe {
+            RecordRef::new(std::slice::from_raw_parts(
+                self.buffer.as_ptr().cast::<u8>(),
+                self.buffer.len() * mem::size_of::<u64>(),
+            ))
+        };
+        RegisteredRecordRef::new(rec, self.registration)
+    }
+}
+
+impl Record for RegisteredRecord {
+    fn header(&self) -> &RecordHeader {
+        // Safety: `buffer` begins with a `RecordHeader` and is aligned.
+        unsafe { &*self.buffer.as_ptr().cast::<RecordHeader>() }
+    }
+
+    fn raw_index_ts(&self) -> u64 {
+        self.as_registered_ref().raw_index_ts()
+    }
+}
+
+impl RecordMut for RegisteredRecord {
+    fn header_mut(&mut self) -> &mut RecordHeader {
+        // Safety: `buffer` begins with a `RecordHeader` and is aligned.
+        unsafe { &mut *self.buffer.as_mut_ptr().cast::<RecordHeader>() }
+    }
+}
+
+impl fmt::Debug for RegisteredRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_registered_ref().fmt(f)
+    }
+}
+
+/// Returns `rec` as a `T` after checking its length, which isn't validated by the
+/// decoders for unknown rtypes.
+fn get<'a, T: HasRType>(rec: RecordRef<'a>) -> Result<&'a T> {
+    if rec.record_size() < mem::size_of::<T>() {
+        return Err(Error::conversion::<T>(format!(
+            "record with length of {} bytes",
+            rec.record_size()
+        )));
+    }
+    rec.get::<T>()
+        .ok_or_else(|| Error::conversion::<T>(format!("rtype {:#04X}", rec.header().rtype)))
+}
+
+fn serialize_csv<T: CsvSerializeTrait>(
+    rec: &T,
+    writer: &mut csv::Writer<Vec<u8>>,
+    pretty_px: bool,
+    pretty_ts: bool,
+) -> csv::Result<()> {
+    match (pretty_px, pretty_ts) {
+        (true, true) => rec.serialize_to::<_, true, true>(writer),
+        (true, false) => rec.serialize_to::<_, true, false>(writer),
+        (false, true) => rec.serialize_to::<_, false, true>(writer),
+        (false, false) => rec.serialize_to::<_, false, false>(writer),
+    }
+}
+
+/// Because the CSV encoder is generic over its writer, registered records are serialized
+/// to an intermediate buffer and then split back into fields.
+fn to_csv_fields(
+    serialize: impl FnOnce(&mut csv::Writer<Vec<u8>>) -> csv::Result<()>,
+) -> Result<csv::ByteRecord> {
+    let csv_err = |e: csv::Error| Error::encode(format!("failed to serialize to CSV: {e:?}"));
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    serialize(&mut writer)
+        .and_then(|_| writer.write_record(None::<&[u8]>))
+        .map_err(csv_err)?;
+    let buffer = writer
+        .into_inner()
+        .map_err(|e| Error::io(e.into_error(), "flushing CSV buffer"))?;
+    let mut fields = csv::ByteRecord::new();
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(buffer.as_slice())
+        .read_byte_record(&mut fields)
+        .map_err(csv_err)?;
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encode::{
+            csv::Encoder as CsvEncoder, json::Encoder as JsonEncoder, EncodeRecordRef,
+            EncodeRecordTextExt,
+        },
+        rtype, MboMsg, RecordEnum, RecordRefEnum,
+    };
+
+    use super::*;
+
+    const CUSTOM_RTYPE: u8 = 0xF0;
+
+    #[repr(C)]
+    #[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq)]
+    #[dbn_record(CUSTOM_RTYPE)]
+    struct CustomMsg {
+        hd: RecordHeader,
+        #[dbn(fixed_price)]
+        fair_px: i64,
+        #[dbn(unix_nanos, index_ts)]
+        ts_recv: u64,
+        #[dbn(c_char)]
+        side: std::ffi::c_char,
+        _reserved: [u8; 3],
+        venue: [std::ffi::c_char; 4],
+    }
+
+    impl CustomMsg {
+        fn new() -> Self {
+            Self {
+                hd: RecordHeader::new::<Self>(CUSTOM_RTYPE, 1, 2, 3),
+                fair_px: 1_250_000_000,
+                ts_recv: 4,
+                side: b'B' as std::ffi::c_char,
+                _reserved: [0; 3],
+                venue: [b'X' as std::ffi::c_char, b'Y' as std::ffi::c_char, 0, 0],
+            }
+        }
+    }
+
+    #[test]
+    fn test_register_errors() {
+        assert!(register::<MboMsg>(rtype::MBO).is_err());
+        assert!(register::<CustomMsg>(0xF1).is_err());
+        register::<CustomMsg>(CUSTOM_RTYPE).unwrap();
+        // idempotent
+        register::<CustomMsg>(CUSTOM_RTYPE).unwrap();
+        assert_eq!(
+            registration(CUSTOM_RTYPE).unwrap().record_size(),
+            mem::size_of::<CustomMsg>()
+        );
+        assert!(registration(0xF1).is_none());
+    }
+
+    #[test]
+    fn test_as_enum() {
+        register::<CustomMsg>(CUSTOM_RTYPE).unwrap();
+        let rec = CustomMsg::new();
+        let rec_ref = RecordRef::from(&rec);
+        assert_eq!(rec_ref.raw_index_ts(), 4);
+        let RecordRefEnum::Registered(registered) = rec_ref.as_enum().unwrap() else {
+            panic!("Expected registered record");
+        };
+        assert!(registered.get::<CustomMsg>().unwrap() == &rec);
+        assert!(registered.get::<MboMsg>().is_none());
+        let owned = RecordRefEnum::Registered(registered).to_owned();
+        let RecordEnum::Registered(owned) = &owned else {
+            panic!("Expected registered record");
+        };
+        assert!(owned.as_registered_ref().get::<CustomMsg>().unwrap() == &rec);
+        let debug = format!("{owned:?}");
+        assert!(debug.starts_with("CustomMsg { hd: RecordHeader"), "{debug}");
+        assert!(debug.contains("fair_px: 1.250000000"), "{debug}");
+    }
+
+    #[test]
+    fn test_encode_csv() {
+        register::<CustomMsg>(CUSTOM_RTYPE).unwrap();
+        let rec = CustomMsg::new();
+        let mut buffer = Vec::new();
+        let mut encoder = CsvEncoder::new(&mut buffer, true, false);
+        encoder.encode_record_ref(RecordRef::from(&rec)).unwrap();
+        encoder.encode_record_ref(RecordRef::from(&rec)).unwrap();
+        drop(encoder);
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            "ts_event,rtype,publisher_id,instrument_id,fair_px,ts_recv,side,venue\n\
+            3,240,1,2,1.250000000,4,B,XY\n\
+            3,240,1,2,1.250000000,4,B,XY\n"
+        );
+        let mut buffer = Vec::new();
+        let mut encoder = CsvEncoder::new(&mut buffer, false, false);
+        encoder
+            .encode_ref_with_sym(RecordRef::from(&rec), Some("SPY"))
+            .unwrap();
+        drop(encoder);
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            "ts_event,rtype,publisher_id,instrument_id,fair_px,ts_recv,side,venue,symbol\n\
+            3,240,1,2,1250000000,4,B,XY,SPY\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_json() {
+        register::<CustomMsg>(CUSTOM_RTYPE).unwrap();
+        let rec = WithTsOut::new(CustomMsg::new(), 5);
+        let mut buffer = Vec::new();
+        let mut encoder = JsonEncoder::new(&mut buffer, false, false, true);
+        unsafe {
+            encoder
+                .encode_record_ref_ts_out(RecordRef::from(&rec), true)
+                .unwrap()
+        };
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            "{\"hd\":{\"ts_event\":\"1970-01-01T00:00:00.000000003Z\",\"rtype\":240,\"publisher_id\":1,\"instrument_id\":2},\
+            \"fair_px\":\"1250000000\",\"ts_recv\":\"1970-01-01T00:00:00.000000004Z\",\"side\":\"B\",\"venue\":\"XY\",\
+            \"ts_out\":\"1970-01-01T00:00:00.000000005Z\"}\n"
+        );
+    }
+}
@@ -1,10 +1,18 @@
 //! Maps for mapping instrument IDs to human-readable symbols.
 
-use std::{cmp::Ordering, collections::HashMap, ops::Deref, sync::Arc};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    ops::Deref,
+    sync::Arc,
+};
 
 use time::{macros::time, PrimitiveDateTime};
 
-use crate::{compat, Error, HasRType, Metadata, RType, Record, RecordRef, SType, SymbolMappingMsg};
+use crate::{
+    compat, Error, HasRType, Metadata, RType, Record, RecordRef, SType, SymbolMapping,
+    SymbolMappingMsg, SymbologyResolution,
+};
 
 /// A timeseries symbol map. Generally useful for working with historical data
 /// and is commonly built from a [`Metadata`] object via [`Self::from_metadata()`].
@@ -60,6 +68,38 @@ impl TsSymbolMap {
         Self::try_from(metadata)
     }
 
+    /// Creates a new timeseries symbol map from `symbology`, e.g. from the
+    /// `symbology.json` of a batch download.
+    ///
+    /// # Errors
+    /// This function returns an error if neither stype_in or stype_out are
+    /// [`SType::InstrumentId`]. It will also return an error if it can't
+    /// parse a symbol into `u32` instrument ID.
+    pub fn from_symbology(symbology: &SymbologyResolution) -> crate::Result<Self> {
+        let mut res = Self::new();
+        for_each_interval(
+            &symbology.mappings(),
+            is_symbology_inverse(symbology)?,
+            |iid, start_date, end_date, symbol| res.insert(iid, start_date, end_date, symbol),
+        )?;
+        Ok(res)
+    }
+
+    /// Creates a new timeseries symbol map from `mappings` whose interval symbols are
+    /// instrument IDs, e.g. from
+    /// [`ContinuousResolver::resolve()`](crate::continuous::ContinuousResolver::resolve).
+    ///
+    /// # Errors
+    /// This function returns an error if it can't parse a symbol into a `u32`
+    /// instrument ID.
+    pub fn from_mappings(mappings: &[SymbolMapping]) -> crate::Result<Self> {
+        let mut res = Self::new();
+        for_each_interval(mappings, false, |iid, start_date, end_date, symbol| {
+            res.insert(iid, start_date, end_date, symbol)
+        })?;
+        Ok(res)
+    }
+
     /// Inserts a new mapping into the symbol map.
     ///
     /// If the map already had a mapping, the mapping is updated.
@@ -126,37 +166,504 @@ impl TryFrom<&Metadata> for TsSymbolMap {
 
     fn try_from(metadata: &Metadata) -> Result<Self, Error> {
         let mut res = Self::new();
-        if is_inverse(metadata)? {
-            for mapping in metadata.mappings.iter() {
-                let iid = mapping
-                    .raw_symbol
-                    .parse()
-                    .map_err(|_| crate::Error::conversion::<u32>(mapping.raw_symbol.clone()))?;
-                for interval in mapping.intervals.iter() {
-                    // handle old symbology format
-                    if interval.symbol.is_empty() {
-                        continue;
-                    }
-                    let symbol = Arc::new(interval.symbol.clone());
-                    res.insert(iid, interval.start_date, interval.end_date, symbol)?;
+        for_each_interval(
+            &metadata.mappings,
+            is_inverse(metadata)?,
+            |iid, start_date, end_date, symbol| res.insert(iid, start_date, end_date, symbol),
+        )?;
+        Ok(res)
+    }
+}
+
+/// A timeseries symbol map with nanosecond resolution, backed by an ordered map of
+/// non-overlapping `[start, end)` intervals per instrument ID. Unlike [`TsSymbolMap`],
+/// its size doesn't grow with the number of days in a mapping and it can represent
+/// mappings that change intraday.
+///
+/// It can be built from a [`Metadata`] object via [`Self::from_metadata()`] and
+/// updated from a stream of [`SymbolMappingMsg`]s with [`Self::on_record()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntervalSymbolMap(HashMap<u32, BTreeMap<u64, SymbolInterval>>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SymbolInterval {
+    end_ts: u64,
+    symbol: Arc<String>,
+}
+
+impl IntervalSymbolMap {
+    /// Creates a new empty interval symbol map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if there are no mappings.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of mapping intervals in the map.
+    pub fn len(&self) -> usize {
+        self.0.values().map(BTreeMap::len).sum()
+    }
+
+    /// Creates a new interval symbol map from the metadata, where each mapping interval
+    /// begins and ends at midnight UTC.
+    ///
+    /// # Errors
+    /// This function returns an error if neither stype_in or stype_out are
+    /// [`SType::InstrumentId`]. It will also return an error if it can't
+    /// parse a symbol into `u32` instrument ID.
+    pub fn from_metadata(metadata: &Metadata) -> crate::Result<Self> {
+        Self::try_from(metadata)
+    }
+
+    /// Inserts a new mapping for `instrument_id` over the UNIX nanosecond interval
+    /// `[start_ts, end_ts)`. Any existing mappings for the instrument that overlap
+    /// the interval are truncated or replaced.
+    ///
+    /// # Errors
+    /// This function returns an error if `start_ts` comes after `end_ts`.
+    pub fn insert(
+        &mut self,
+        instrument_id: u32,
+        start_ts: u64,
+        end_ts: u64,
+        symbol: Arc<String>,
+    ) -> crate::Result<()> {
+        if !is_nonempty_interval(start_ts, end_ts)? {
+            return Ok(());
+        }
+        let intervals = self.0.entry(instrument_id).or_default();
+        // Truncate an interval overlapping the start, keeping any remainder after the
+        // new interval
+        if let Some((_, prev)) = intervals.range_mut(..start_ts).next_back() {
+            if prev.end_ts > start_ts {
+                let remainder = (prev.end_ts > end_ts).then(|| SymbolInterval {
+                    end_ts: prev.end_ts,
+                    symbol: prev.symbol.clone(),
+                });
+                prev.end_ts = start_ts;
+                if let Some(remainder) = remainder {
+                    intervals.insert(end_ts, remainder);
                 }
             }
+        }
+        // Remove intervals starting within the new interval, keeping any remainder
+        let overlapping: Vec<_> = intervals.range(start_ts..end_ts).map(|(&k, _)| k).collect();
+        for overlap_start in overlapping {
+            let overlap = intervals.remove(&overlap_start).unwrap();
+            if overlap.end_ts > end_ts {
+                intervals.insert(end_ts, overlap);
+            }
+        }
+        let mut start_ts = start_ts;
+        let mut end_ts = end_ts;
+        // Merge with adjacent intervals with the same symbol
+        if let Some((&prev_start, prev)) = intervals.range(..start_ts).next_back() {
+            if prev.end_ts == start_ts && prev.symbol == symbol {
+                start_ts = prev_start;
+            }
+        }
+        if intervals
+            .get(&end_ts)
+            .is_some_and(|next| next.symbol == symbol)
+        {
+            end_ts = intervals.remove(&end_ts).unwrap().end_ts;
+        }
+        intervals.insert(start_ts, SymbolInterval { end_ts, symbol });
+        Ok(())
+    }
+
+    /// Returns the symbol mapping for the given UNIX nanosecond timestamp and
+    /// instrument ID. Returns `None` if no mapping exists.
+    pub fn get(&self, ts: u64, instrument_id: u32) -> Option<&String> {
+        self.0
+            .get(&instrument_id)?
+            .range(..=ts)
+            .next_back()
+            .filter(|(_, interval)| ts < interval.end_ts)
+            .map(|(_, interval)| interval.symbol.as_ref())
+    }
+
+    /// Returns an iterator over the `(start_ts, end_ts, symbol)` mapping intervals for
+    /// `instrument_id` in ascending order.
+    pub fn intervals(&self, instrument_id: u32) -> impl Iterator<Item = (u64, u64, &String)> {
+        self.0
+            .get(&instrument_id)
+            .into_iter()
+            .flatten()
+            .map(|(&start_ts, interval)| (start_ts, interval.end_ts, interval.symbol.as_ref()))
+    }
+
+    /// Handles updating the mappings (if required) for a generic record.
+    ///
+    /// # Errors
+    /// This function returns an error when `record` contains a [`SymbolMappingMsg`] but
+    /// it contains invalid UTF-8 or its `start_ts` comes after its `end_ts`.
+    pub fn on_record(&mut self, record: RecordRef) -> crate::Result<()> {
+        on_symbol_mapping_record(record, |iid, start_ts, end_ts, symbol| {
+            self.insert(iid, start_ts, end_ts, Arc::new(symbol.to_owned()))
+        })
+    }
+
+    /// Handles updating the mappings for a symbol mapping record. An undefined
+    /// `start_ts` or `end_ts` is treated as unbounded.
+    ///
+    /// # Errors
+    /// This function returns an error when `symbol_mapping` contains invalid UTF-8 or
+    /// its `start_ts` comes after its `end_ts`.
+    pub fn on_symbol_mapping<S: compat::SymbolMappingRec>(
+        &mut self,
+        symbol_mapping: &S,
+    ) -> crate::Result<()> {
+        let (start_ts, end_ts) = mapping_bounds(symbol_mapping);
+        self.insert(
+            symbol_mapping.header().instrument_id,
+            start_ts,
+            end_ts,
+            Arc::new(symbol_mapping.stype_out_symbol()?.to_owned()),
+        )
+    }
+
+    /// Returns the intervals for `instrument_id` that overlap or are adjacent to
+    /// `[start_ts, end_ts]`.
+    fn touching(
+        &self,
+        instrument_id: u32,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Vec<(u64, u64, Arc<String>)> {
+        let Some(intervals) = self.0.get(&instrument_id) else {
+            return Vec::new();
+        };
+        let mut res: Vec<_> = intervals
+            .range(..=end_ts)
+            .rev()
+            .take_while(|(_, interval)| interval.end_ts >= start_ts)
+            .map(|(&start, interval)| (start, interval.end_ts, interval.symbol.clone()))
+            .collect();
+        res.reverse();
+        res
+    }
+}
+
+impl SymbolIndex for IntervalSymbolMap {
+    fn get_for_rec<R: Record>(&self, record: &R) -> Option<&String> {
+        self.get(record.raw_index_ts(), record.header().instrument_id)
+    }
+}
+
+impl TryFrom<&Metadata> for IntervalSymbolMap {
+    type Error = Error;
+
+    fn try_from(metadata: &Metadata) -> Result<Self, Error> {
+        let mut res = Self::new();
+        for_each_interval(
+            &metadata.mappings,
+            is_inverse(metadata)?,
+            |iid, start_date, end_date, symbol| {
+                res.insert(iid, date_to_ts(start_date), date_to_ts(end_date), symbol)
+            },
+        )?;
+        Ok(res)
+    }
+}
+
+/// A bidirectional symbology index for looking up the symbol of an instrument ID and
+/// the instrument IDs of a symbol over time, e.g. which instrument IDs `ES.c.0`
+/// mapped to over a date range.
+///
+/// It can be built from a [`Metadata`] object via [`Self::from_metadata()`] and
+/// updated from a stream of [`SymbolMappingMsg`]s with [`Self::on_record()`]. Both the
+/// `stype_in_symbol` and `stype_out_symbol` of a symbol mapping can be looked up, while
+/// instrument IDs map to the `stype_out_symbol`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbologyIndex {
+    by_id: IntervalSymbolMap,
+    by_symbol: HashMap<String, BTreeMap<(u64, u32), u64>>,
+    /// Input symbols, which can map to several instrument IDs at once, e.g. a parent
+    /// symbol.
+    by_stype_in_symbol: HashMap<String, BTreeMap<(u64, u32), u64>>,
+}
+
+/// A mapping between an instrument ID and a symbol over the UNIX nanosecond interval
+/// `[start_ts, end_ts)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MappingSpan<'a> {
+    /// The numeric instrument ID.
+    pub instrument_id: u32,
+    /// The text symbol.
+    pub symbol: &'a str,
+    /// The inclusive start of the mapping interval.
+    pub start_ts: u64,
+    /// The exclusive end of the mapping interval.
+    pub end_ts: u64,
+}
+
+impl SymbologyIndex {
+    /// Creates a new empty symbology index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if there are no mappings.
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+
+    /// Returns the number of mapping intervals in the index.
+    pub fn len(&self) -> usize {
+        self.by_id.len()
+    }
+
+    /// Creates a new symbology index from the metadata, where each mapping interval
+    /// begins and ends at midnight UTC.
+    ///
+    /// # Errors
+    /// This function returns an error if neither stype_in or stype_out are
+    /// [`SType::InstrumentId`]. It will also return an error if it can't
+    /// parse a symbol into `u32` instrument ID.
+    pub fn from_metadata(metadata: &Metadata) -> crate::Result<Self> {
+        Self::try_from(metadata)
+    }
+
+    /// Returns the instrument ID to symbol direction of the index.
+    pub fn symbol_map(&self) -> &IntervalSymbolMap {
+        &self.by_id
+    }
+
+    /// Inserts a new mapping between `instrument_id` and `symbol` over the UNIX
+    /// nanosecond interval `[start_ts, end_ts)`. Any existing mappings for the
+    /// instrument that overlap the interval are truncated or replaced.
+    ///
+    /// # Errors
+    /// This function returns an error if `start_ts` comes after `end_ts`.
+    pub fn insert(
+        &mut self,
+        instrument_id: u32,
+        start_ts: u64,
+        end_ts: u64,
+        symbol: &str,
+    ) -> crate::Result<()> {
+        if !is_nonempty_interval(start_ts, end_ts)? {
+            return Ok(());
+        }
+        // The intervals that may be truncated, replaced, or merged with the new one
+        let affected = self.by_id.touching(instrument_id, start_ts, end_ts);
+        for (start, _, symbol) in affected.iter() {
+            if let Some(intervals) = self.by_symbol.get_mut(symbol.as_str()) {
+                intervals.remove(&(*start, instrument_id));
+            }
+        }
+        self.by_id
+            .insert(instrument_id, start_ts, end_ts, Arc::new(symbol.to_owned()))?;
+        let lower = affected
+            .first()
+            .map_or(start_ts, |(start, ..)| start_ts.min(*start));
+        let upper = affected
+            .last()
+            .map_or(end_ts, |(_, end, _)| end_ts.max(*end));
+        for (start, end, symbol) in self.by_id.touching(instrument_id, lower, upper) {
+            self.by_symbol
+                .entry(symbol.as_ref().clone())
+                .or_default()
+                .insert((start, instrument_id), end);
+        }
+        self.by_symbol.retain(|_, intervals| !intervals.is_empty());
+        Ok(())
+    }
+
+    /// Handles updating the mappings (if required) for a generic record.
+    ///
+    /// # Errors
+    /// This function returns an error when `record` contains a [`SymbolMappingMsg`] but
+    /// it contains invalid UTF-8 or its `start_ts` comes after its `end_ts`.
+    pub fn on_record(&mut self, record: RecordRef) -> crate::Result<()> {
+        if !matches!(record.rtype(), Ok(RType::SymbolMapping)) {
+            return Ok(());
+        }
+        // >= to allow WithTsOut
+        if record.record_size() >= std::mem::size_of::<SymbolMappingMsg>() {
+            // Safety: checked rtype and length
+            self.on_symbol_mapping(unsafe { record.get_unchecked::<SymbolMappingMsg>() })
         } else {
-            for mapping in metadata.mappings.iter() {
-                let symbol = Arc::new(mapping.raw_symbol.clone());
-                for interval in mapping.intervals.iter() {
-                    // handle old symbology format
-                    if interval.symbol.is_empty() {
-                        continue;
-                    }
-                    let iid = interval
-                        .symbol
-                        .parse()
-                        .map_err(|_| crate::Error::conversion::<u32>(interval.symbol.clone()))?;
-                    res.insert(iid, interval.start_date, interval.end_date, symbol.clone())?;
+            // Use get here to get still perform length checks
+            self.on_symbol_mapping(record.get::<compat::SymbolMappingMsgV1>().unwrap())
+        }
+    }
+
+    /// Handles updating the mappings for a symbol mapping record, indexing both its
+    /// `stype_in_symbol` and `stype_out_symbol`. An undefined `start_ts` or `end_ts` is
+    /// treated as unbounded.
+    ///
+    /// # Errors
+    /// This function returns an error when `symbol_mapping` contains invalid UTF-8 or
+    /// its `start_ts` comes after its `end_ts`.
+    pub fn on_symbol_mapping<S: compat::SymbolMappingRec>(
+        &mut self,
+        symbol_mapping: &S,
+    ) -> crate::Result<()> {
+        let (start_ts, end_ts) = mapping_bounds(symbol_mapping);
+        let instrument_id = symbol_mapping.header().instrument_id;
+        let stype_in_symbol = symbol_mapping.stype_in_symbol()?;
+        self.insert(
+            instrument_id,
+            start_ts,
+            end_ts,
+            symbol_mapping.stype_out_symbol()?,
+        )?;
+        if !stype_in_symbol.is_empty() {
+            self.insert_stype_in_symbol(instrument_id, start_ts, end_ts, stype_in_symbol);
+        }
+        Ok(())
+    }
+
+    /// Inserts a mapping from an input symbol to `instrument_id`, merging it with any
+    /// overlapping or adjacent mappings between the same pair.
+    fn insert_stype_in_symbol(
+        &mut self,
+        instrument_id: u32,
+        start_ts: u64,
+        end_ts: u64,
+        symbol: &str,
+    ) {
+        let intervals = self
+            .by_stype_in_symbol
+            .entry(symbol.to_owned())
+            .or_default();
+        let touching: Vec<_> = intervals
+            .range(..=(end_ts, u32::MAX))
+            .filter(|(&(_, iid), &end)| iid == instrument_id && end >= start_ts)
+            .map(|(&key, &end)| (key, end))
+            .collect();
+        let (mut start_ts, mut end_ts) = (start_ts, end_ts);
+        for ((start, iid), end) in touching {
+            intervals.remove(&(start, iid));
+            start_ts = start_ts.min(start);
+            end_ts = end_ts.max(end);
+        }
+        intervals.insert((start_ts, instrument_id), end_ts);
+    }
+
+    /// Returns the symbol `instrument_id` mapped to at the UNIX nanosecond timestamp
+    /// `ts`.
+    pub fn symbol_at(&self, instrument_id: u32, ts: u64) -> Option<&str> {
+        self.by_id.get(ts, instrument_id).map(String::as_str)
+    }
+
+    /// Returns the symbols `instrument_id` mapped to during `date` in UTC, in
+    /// chronological order.
+    pub fn symbols_on(&self, instrument_id: u32, date: time::Date) -> Vec<&str> {
+        let (start_ts, end_ts) = date_bounds(date);
+        self.spans_for_instrument_id(instrument_id, start_ts, end_ts)
+            .into_iter()
+            .map(|span| span.symbol)
+            .collect()
+    }
+
+    /// Returns the mappings of `instrument_id` that overlap the UNIX nanosecond interval
+    /// `[start_ts, end_ts)`, in chronological order.
+    pub fn spans_for_instrument_id(
+        &self,
+        instrument_id: u32,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Vec<MappingSpan<'_>> {
+        self.by_id
+            .intervals(instrument_id)
+            .filter(|&(start, end, _)| start < end_ts && end > start_ts)
+            .map(|(start, end, symbol)| MappingSpan {
+                instrument_id,
+                symbol,
+                start_ts: start,
+                end_ts: end,
+            })
+            .collect()
+    }
+
+    /// Returns the instrument IDs `symbol` mapped to at the UNIX nanosecond timestamp
+    /// `ts`.
+    pub fn instrument_ids_at(&self, symbol: &str, ts: u64) -> Vec<u32> {
+        self.spans_for_symbol(symbol, ts, ts.saturating_add(1))
+            .into_iter()
+            .map(|span| span.instrument_id)
+            .collect()
+    }
+
+    /// Returns the distinct instrument IDs `symbol` mapped to during `date` in UTC, in
+    /// the order they were first mapped.
+    pub fn instrument_ids_on(&self, symbol: &str, date: time::Date) -> Vec<u32> {
+        let (start_ts, end_ts) = date_bounds(date);
+        let mut res = Vec::new();
+        for span in self.spans_for_symbol(symbol, start_ts, end_ts) {
+            if !res.contains(&span.instrument_id) {
+                res.push(span.instrument_id);
+            }
+        }
+        res
+    }
+
+    /// Returns the mappings of `symbol` that overlap the UNIX nanosecond interval
+    /// `[start_ts, end_ts)`, ordered by start time and then instrument ID. `symbol` can
+    /// be either an input or output symbol.
+    pub fn spans_for_symbol(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Vec<MappingSpan<'_>> {
+        let mut spans: Vec<_> = [&self.by_symbol, &self.by_stype_in_symbol]
+            .into_iter()
+            .filter_map(|index| index.get_key_value(symbol))
+            .flat_map(|(symbol, intervals)| {
+                intervals
+                    .range(..(end_ts, 0))
+                    .filter(move |(_, &end)| end > start_ts)
+                    .map(|(&(start, instrument_id), &end)| MappingSpan {
+                        instrument_id,
+                        symbol,
+                        start_ts: start,
+                        end_ts: end,
+                    })
+            })
+            .collect();
+        // Merge the spans of the same instrument from both directions
+        spans.sort_by_key(|span| (span.instrument_id, span.start_ts));
+        let mut res: Vec<MappingSpan> = Vec::with_capacity(spans.len());
+        for span in spans {
+            match res.last_mut() {
+                Some(last)
+                    if last.instrument_id == span.instrument_id && span.start_ts <= last.end_ts =>
+                {
+                    last.end_ts = last.end_ts.max(span.end_ts);
                 }
+                _ => res.push(span),
             }
         }
+        res.sort_by_key(|span| (span.start_ts, span.instrument_id));
+        res
+    }
+}
+
+impl SymbolIndex for SymbologyIndex {
+    fn get_for_rec<R: Record>(&self, record: &R) -> Option<&String> {
+        self.by_id.get_for_rec(record)
+    }
+}
+
+impl TryFrom<&Metadata> for SymbologyIndex {
+    type Error = Error;
+
+    fn try_from(metadata: &Metadata) -> Result<Self, Error> {
+        let mut res = Self::new();
+        for_each_interval(
+            &metadata.mappings,
+            is_inverse(metadata)?,
+            |iid, start_date, end_date, symbol| {
+                res.insert(iid, date_to_ts(start_date), date_to_ts(end_date), &symbol)
+            },
+        )?;
         Ok(res)
     }
 }
@@ -189,14 +696,40 @@ impl PitSymbolMap {
         let is_inverse = is_inverse(metadata)?;
         let datetime = PrimitiveDateTime::new(date, time!(0:00)).assume_utc();
         // need to compare with `end` as a datetime to handle midnight case
-        if date < metadata.start().date() || metadata.end().map_or(false, |end| datetime >= end) {
+        if date < metadata.start().date() || metadata.end().is_some_and(|end| datetime >= end) {
             return Err(crate::Error::BadArgument {
                 param_name: "date".to_owned(),
                 desc: "Outside the query range".to_owned(),
             });
         }
+        Self::from_mappings(&metadata.mappings, is_inverse, date)
+    }
+
+    /// Creates a new `PitSymbolMap` populated with the mappings from `symbology` for
+    /// `date`, e.g. from the `symbology.json` of a batch download.
+    ///
+    /// # Errors
+    /// This function returns an error if neither stype_in or stype_out are
+    /// [`SType::InstrumentId`]. It will also return an error if it can't
+    /// parse a symbol into `u32` instrument ID.
+    pub fn from_symbology(
+        symbology: &SymbologyResolution,
+        date: time::Date,
+    ) -> crate::Result<Self> {
+        Self::from_mappings(
+            &symbology.mappings(),
+            is_symbology_inverse(symbology)?,
+            date,
+        )
+    }
+
+    fn from_mappings(
+        mappings: &[SymbolMapping],
+        is_inverse: bool,
+        date: time::Date,
+    ) -> crate::Result<Self> {
         let mut res = HashMap::new();
-        for mapping in metadata.mappings.iter() {
+        for mapping in mappings.iter() {
             if let Some(interval) = mapping
                 .intervals
                 .iter()
@@ -315,6 +848,130 @@ impl std::ops::Index<u32> for PitSymbolMap {
     }
 }
 
+impl<R: HasRType> std::ops::Index<&R> for IntervalSymbolMap {
+    type Output = String;
+
+    fn index(&self, index: &R) -> &Self::Output {
+        self.get_for_rec(index).unwrap()
+    }
+}
+
+/// Calls `f` with the instrument ID, start date, end date, and symbol of every mapping
+/// interval in `mappings`.
+fn for_each_interval(
+    mappings: &[SymbolMapping],
+    is_inverse: bool,
+    mut f: impl FnMut(u32, time::Date, time::Date, Arc<String>) -> crate::Result<()>,
+) -> crate::Result<()> {
+    if is_inverse {
+        for mapping in mappings.iter() {
+            let iid = mapping
+                .raw_symbol
+                .parse()
+                .map_err(|_| crate::Error::conversion::<u32>(mapping.raw_symbol.clone()))?;
+            for interval in mapping.intervals.iter() {
+                // handle old symbology format
+                if interval.symbol.is_empty() {
+                    continue;
+                }
+                let symbol = Arc::new(interval.symbol.clone());
+                f(iid, interval.start_date, interval.end_date, symbol)?;
+            }
+        }
+    } else {
+        for mapping in mappings.iter() {
+            let symbol = Arc::new(mapping.raw_symbol.clone());
+            for interval in mapping.intervals.iter() {
+                // handle old symbology format
+                if interval.symbol.is_empty() {
+                    continue;
+                }
+                let iid = interval
+                    .symbol
+                    .parse()
+                    .map_err(|_| crate::Error::conversion::<u32>(interval.symbol.clone()))?;
+                f(iid, interval.start_date, interval.end_date, symbol.clone())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Calls `f` with the instrument ID, interval bounds, and output symbol if `record` is
+/// a symbol mapping record of any DBN version.
+fn on_symbol_mapping_record(
+    record: RecordRef,
+    mut f: impl FnMut(u32, u64, u64, &str) -> crate::Result<()>,
+) -> crate::Result<()> {
+    fn handle<S: compat::SymbolMappingRec>(
+        symbol_mapping: &S,
+        f: impl FnOnce(u32, u64, u64, &str) -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        let (start_ts, end_ts) = mapping_bounds(symbol_mapping);
+        f(
+            symbol_mapping.header().instrument_id,
+            start_ts,
+            end_ts,
+            symbol_mapping.stype_out_symbol()?,
+        )
+    }
+
+    if !matches!(record.rtype(), Ok(RType::SymbolMapping)) {
+        return Ok(());
+    }
+    // >= to allow WithTsOut
+    if record.record_size() >= std::mem::size_of::<SymbolMappingMsg>() {
+        // Safety: checked rtype and length
+        handle(
+            unsafe { record.get_unchecked::<SymbolMappingMsg>() },
+            &mut f,
+        )
+    } else {
+        // Use get here to get still perform length checks
+        handle(record.get::<compat::SymbolMappingMsgV1>().unwrap(), &mut f)
+    }
+}
+
+/// Returns the `[start_ts, end_ts)` bounds of a symbol mapping, treating undefined
+/// timestamps as unbounded.
+fn mapping_bounds<S: compat::SymbolMappingRec>(symbol_mapping: &S) -> (u64, u64) {
+    let start_ts = symbol_mapping
+        .start_ts()
+        .map_or(0, |start| start.unix_timestamp_nanos() as u64);
+    let end_ts = symbol_mapping
+        .end_ts()
+        .map_or(u64::MAX, |end| end.unix_timestamp_nanos() as u64);
+    (start_ts, end_ts)
+}
+
+/// Converts `date` to a UNIX nanosecond timestamp of midnight UTC.
+pub(crate) fn date_to_ts(date: time::Date) -> u64 {
+    PrimitiveDateTime::new(date, time!(0:00))
+        .assume_utc()
+        .unix_timestamp_nanos() as u64
+}
+
+/// Returns `false` if the interval `[start_ts, end_ts)` is empty.
+///
+/// # Errors
+/// This function returns an error if `start_ts` comes after `end_ts`.
+fn is_nonempty_interval(start_ts: u64, end_ts: u64) -> crate::Result<bool> {
+    match start_ts.cmp(&end_ts) {
+        Ordering::Less => Ok(true),
+        Ordering::Equal => Ok(false),
+        Ordering::Greater => Err(Error::BadArgument {
+            param_name: "start_ts".to_owned(),
+            desc: "start_ts cannot come after end_ts".to_owned(),
+        }),
+    }
+}
+
+/// Returns the UNIX nanosecond bounds of `date` in UTC.
+fn date_bounds(date: time::Date) -> (u64, u64) {
+    let start_ts = date_to_ts(date);
+    (start_ts, date.next_day().map_or(u64::MAX, date_to_ts))
+}
+
 fn is_inverse(metadata: &Metadata) -> crate::Result<bool> {
     match (metadata.stype_in, metadata.stype_out) {
         (_, SType::InstrumentId) => Ok(false),
@@ -328,6 +985,20 @@ fn is_inverse(metadata: &Metadata) -> crate::Result<bool> {
     }
 }
 
+/// Like [`is_inverse`], but assumes an unspecified `stype_out` is instrument ID.
+fn is_symbology_inverse(symbology: &SymbologyResolution) -> crate::Result<bool> {
+    match (symbology.stype_in, symbology.stype_out) {
+        (_, None | Some(SType::InstrumentId)) => Ok(false),
+        (Some(SType::InstrumentId), _) => Ok(true),
+        _ => {
+            Err(Error::BadArgument {
+                param_name: "symbology".to_owned(),
+                desc: "Can only create symbol maps from symbology where either stype_out or stype_in is instrument ID".to_owned(),
+            })
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::num::NonZeroU64;
@@ -338,7 +1009,7 @@ mod tests {
     use crate::{
         compat::{SymbolMappingMsgV1, SymbolMappingRec},
         publishers::Dataset,
-        MappingInterval, Metadata, Schema, SymbolMapping, UNDEF_TIMESTAMP,
+        rtype, MappingInterval, Metadata, RecordHeader, Schema, SymbolMapping, UNDEF_TIMESTAMP,
     };
 
     use super::*;
@@ -1024,4 +1695,316 @@ mod tests {
         // should have no effect
         assert!(target.is_empty());
     }
+
+    #[test]
+    fn test_interval_symbol_map_matches_ts_symbol_map() {
+        let target = metadata_w_mappings();
+        let ts_symbol_map = target.symbol_map().unwrap();
+        let interval_symbol_map = target.interval_symbol_map().unwrap();
+        assert!(interval_symbol_map.len() < ts_symbol_map.len());
+        for (&(date, iid), symbol) in ts_symbol_map.inner() {
+            let midnight = date_to_ts(date);
+            assert_eq!(
+                interval_symbol_map.get(midnight, iid),
+                Some(symbol.as_ref())
+            );
+            assert_eq!(
+                interval_symbol_map.get(midnight + 86_400_000_000_000 - 1, iid),
+                Some(symbol.as_ref())
+            );
+        }
+        assert!(interval_symbol_map
+            .get(date_to_ts(date!(2023 - 07 - 10)), 8029)
+            .is_none());
+        assert_eq!(
+            interval_symbol_map,
+            metadata_w_inverse_mappings().interval_symbol_map().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_interval_insert_overlapping() {
+        let mut target = IntervalSymbolMap::new();
+        let a = Arc::new("A".to_owned());
+        let b = Arc::new("B".to_owned());
+        target.insert(1, 0, 100, a.clone()).unwrap();
+        target.insert(1, 40, 60, b.clone()).unwrap();
+        assert_eq!(target.get(39, 1).unwrap(), "A");
+        assert_eq!(target.get(40, 1).unwrap(), "B");
+        assert_eq!(target.get(59, 1).unwrap(), "B");
+        assert_eq!(target.get(60, 1).unwrap(), "A");
+        assert!(target.get(100, 1).is_none());
+        assert!(target.get(50, 2).is_none());
+        target.insert(1, 50, 150, a.clone()).unwrap();
+        // Adjacent intervals with the same symbol are merged
+        target.insert(1, 150, 200, a.clone()).unwrap();
+        assert_eq!(
+            target.intervals(1).collect::<Vec<_>>(),
+            [(0, 40, &*a), (40, 50, &*b), (50, 200, &*a)]
+        );
+        target.insert(1, 30, 300, b.clone()).unwrap();
+        assert_eq!(
+            target.intervals(1).collect::<Vec<_>>(),
+            [(0, 30, &*a), (30, 300, &*b)]
+        );
+        assert!(target.insert(1, 10, 5, a).is_err());
+    }
+
+    #[test]
+    fn test_interval_on_record() -> crate::Result<()> {
+        let mut target = IntervalSymbolMap::new();
+        let open = datetime!(2024-01-02 14:30 UTC).unix_timestamp_nanos() as u64;
+        let roll = datetime!(2024-01-02 18:00 UTC).unix_timestamp_nanos() as u64;
+        target.on_record(RecordRef::from(&SymbolMappingMsg::new(
+            1,
+            open,
+            SType::RawSymbol,
+            "ESH4",
+            SType::RawSymbol,
+            "ESH4",
+            open,
+            roll,
+        )?))?;
+        target.on_record(RecordRef::from(&SymbolMappingMsg::new(
+            1,
+            roll,
+            SType::RawSymbol,
+            "ESM4",
+            SType::RawSymbol,
+            "ESM4",
+            roll,
+            UNDEF_TIMESTAMP,
+        )?))?;
+        // Ignores other records
+        target.on_record(RecordRef::from(&crate::MboMsg::default()))?;
+        assert_eq!(target.len(), 2);
+        assert!(target.get(open - 1, 1).is_none());
+        assert_eq!(target.get(open, 1).unwrap(), "ESH4");
+        assert_eq!(target.get(roll - 1, 1).unwrap(), "ESH4");
+        assert_eq!(target.get(roll, 1).unwrap(), "ESM4");
+        assert_eq!(target.get(u64::MAX - 1, 1).unwrap(), "ESM4");
+
+        // Uses the index timestamp rather than `ts_event`
+        let mbo = crate::MboMsg {
+            hd: RecordHeader::new::<crate::MboMsg>(rtype::MBO, 0, 1, roll - 1),
+            ts_recv: roll,
+            ..Default::default()
+        };
+        assert_eq!(target[&mbo], "ESM4");
+        Ok(())
+    }
+
+    #[test]
+    fn test_symbology_index_from_metadata() {
+        let target = metadata_w_mappings().symbology_index().unwrap();
+        assert_eq!(
+            target.instrument_ids_on("TSLA", date!(2023 - 07 - 03)),
+            [10213]
+        );
+        assert_eq!(
+            target.instrument_ids_at("TSLA", date_to_ts(date!(2023 - 07 - 05)) - 1),
+            [10213]
+        );
+        assert_eq!(
+            target.spans_for_symbol(
+                "TSLA",
+                date_to_ts(date!(2023 - 07 - 02)),
+                date_to_ts(date!(2023 - 07 - 06))
+            ),
+            [
+                MappingSpan {
+                    instrument_id: 10221,
+                    symbol: "TSLA",
+                    start_ts: date_to_ts(date!(2023 - 07 - 01)),
+                    end_ts: date_to_ts(date!(2023 - 07 - 03)),
+                },
+                MappingSpan {
+                    instrument_id: 10213,
+                    symbol: "TSLA",
+                    start_ts: date_to_ts(date!(2023 - 07 - 03)),
+                    end_ts: date_to_ts(date!(2023 - 07 - 05)),
+                },
+                MappingSpan {
+                    instrument_id: 10209,
+                    symbol: "TSLA",
+                    start_ts: date_to_ts(date!(2023 - 07 - 05)),
+                    end_ts: date_to_ts(date!(2023 - 07 - 06)),
+                },
+            ]
+        );
+        assert_eq!(target.symbols_on(10213, date!(2023 - 07 - 04)), ["TSLA"]);
+        assert!(target.symbols_on(10213, date!(2023 - 07 - 05)).is_empty());
+        assert!(target
+            .instrument_ids_on("GOOG", date!(2023 - 07 - 03))
+            .is_empty());
+        assert_eq!(
+            target,
+            metadata_w_inverse_mappings().symbology_index().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_symbology_index_intraday() -> crate::Result<()> {
+        let mut target = SymbologyIndex::new();
+        let roll = datetime!(2024-03-14 21:00 UTC).unix_timestamp_nanos() as u64;
+        for (iid, raw_symbol, start_ts, end_ts) in
+            [(1, "ESH4", 0, roll), (2, "ESM4", roll, UNDEF_TIMESTAMP)]
+        {
+            target.on_record(RecordRef::from(&SymbolMappingMsg::new(
+                iid,
+                start_ts,
+                SType::Continuous,
+                "ES.c.0",
+                SType::RawSymbol,
+                raw_symbol,
+                start_ts,
+                end_ts,
+            )?))?;
+        }
+        // Instrument IDs map to the output symbol
+        assert_eq!(target.symbol_at(1, roll - 1), Some("ESH4"));
+        assert_eq!(target.symbol_at(2, u64::MAX - 1), Some("ESM4"));
+        // Both the input and output symbols map to instrument IDs
+        assert_eq!(target.instrument_ids_at("ES.c.0", roll - 1), [1]);
+        assert_eq!(target.instrument_ids_at("ES.c.0", roll), [2]);
+        assert_eq!(target.instrument_ids_at("ESH4", roll - 1), [1]);
+        assert!(target.instrument_ids_at("ESH4", roll).is_empty());
+        assert_eq!(
+            target.instrument_ids_on("ES.c.0", date!(2024 - 03 - 14)),
+            [1, 2]
+        );
+        assert_eq!(
+            target.spans_for_symbol("ES.c.0", 0, u64::MAX),
+            [
+                MappingSpan {
+                    instrument_id: 1,
+                    symbol: "ES.c.0",
+                    start_ts: 0,
+                    end_ts: roll,
+                },
+                MappingSpan {
+                    instrument_id: 2,
+                    symbol: "ES.c.0",
+                    start_ts: roll,
+                    end_ts: u64::MAX,
+                },
+            ]
+        );
+
+        // Overwriting part of a mapping updates both directions of the output symbol
+        target.insert(1, roll - 100, roll, "ESH4 C5000")?;
+        assert_eq!(target.symbol_at(1, roll - 100), Some("ESH4 C5000"));
+        assert_eq!(target.instrument_ids_at("ESH4 C5000", roll - 1), [1]);
+        assert!(target.instrument_ids_at("ESH4", roll - 1).is_empty());
+        assert_eq!(target.instrument_ids_at("ESH4", roll - 101), [1]);
+        // Re-inserting the original mapping merges the intervals again
+        target.insert(1, roll - 100, roll, "ESH4")?;
+        assert_eq!(target.len(), 2);
+        assert!(target.instrument_ids_at("ESH4 C5000", roll - 1).is_empty());
+        assert_eq!(
+            target.spans_for_symbol("ESH4", 0, u64::MAX),
+            [MappingSpan {
+                instrument_id: 1,
+                symbol: "ESH4",
+                start_ts: 0,
+                end_ts: roll,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_symbology_index_parent() -> crate::Result<()> {
+        let mut target = SymbologyIndex::new();
+        target.insert(3, 0, 100, "ES.FUT")?;
+        target.insert(1, 0, 100, "ES.FUT")?;
+        target.insert(2, 50, 100, "ES.FUT")?;
+        assert_eq!(target.instrument_ids_at("ES.FUT", 10), [1, 3]);
+        assert_eq!(target.instrument_ids_at("ES.FUT", 50), [1, 3, 2]);
+        assert!(target.instrument_ids_at("ES.FUT", 100).is_empty());
+        assert!(target.insert(1, 10, 5, "ES.FUT").is_err());
+        Ok(())
+    }
+
+    fn symbology_from_metadata(metadata: &Metadata) -> SymbologyResolution {
+        SymbologyResolution {
+            result: metadata
+                .mappings
+                .iter()
+                .map(|mapping| (mapping.raw_symbol.clone(), mapping.intervals.clone()))
+                .collect(),
+            stype_in: metadata.stype_in,
+            stype_out: Some(metadata.stype_out),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case::forward(metadata_w_mappings())]
+    #[case::inverse(metadata_w_inverse_mappings())]
+    fn test_from_symbology(#[case] metadata: Metadata) {
+        let symbology = symbology_from_metadata(&metadata);
+        assert_eq!(
+            TsSymbolMap::from_symbology(&symbology).unwrap(),
+            metadata.symbol_map().unwrap()
+        );
+        assert_eq!(
+            PitSymbolMap::from_symbology(&symbology, date!(2023 - 07 - 31)).unwrap(),
+            metadata.symbol_map_for_date(date!(2023 - 07 - 31)).unwrap()
+        );
+
+        let mut without_mappings = metadata.clone();
+        without_mappings.mappings.clear();
+        without_mappings.apply_symbology(&symbology);
+        assert_eq!(
+            without_mappings.symbol_map().unwrap(),
+            metadata.symbol_map().unwrap()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_symbology_json() {
+        const JSON: &str = r#"{
+            "result": {
+                "ESM2": [{"d0": "2022-06-06", "d1": "2022-06-10", "s": "3403"}],
+                "NQM2": [{"d0": "2022-06-06", "d1": "2022-06-08", "s": ""}]
+            },
+            "symbols": ["ESM2", "NQM2", "XXX"],
+            "stype_in": "raw_symbol",
+            "stype_out": "instrument_id",
+            "start_date": "2022-06-06",
+            "end_date": "2022-06-10",
+            "partial": [],
+            "not_found": ["XXX"],
+            "message": "Not found",
+            "status": 2
+        }"#;
+        let symbology: SymbologyResolution = serde_json::from_str(JSON).unwrap();
+        assert_eq!(symbology.stype_in, Some(SType::RawSymbol));
+        assert_eq!(symbology.not_found, ["XXX"]);
+        assert_eq!(
+            symbology.mappings()[0],
+            SymbolMapping {
+                raw_symbol: "ESM2".to_owned(),
+                intervals: vec![MappingInterval {
+                    start_date: date!(2022 - 06 - 06),
+                    end_date: date!(2022 - 06 - 10),
+                    symbol: "3403".to_owned(),
+                }],
+            }
+        );
+        let symbol_map = TsSymbolMap::from_symbology(&symbology).unwrap();
+        assert_eq!(symbol_map.len(), 4);
+        assert_eq!(symbol_map[&(date!(2022 - 06 - 09), 3403)], "ESM2");
+        // Resolve API responses don't necessarily include the stypes
+        let minimal: SymbologyResolution = serde_json::from_str(
+            r#"{"result": {"ESM2": [{"d0": "2022-06-06", "d1": "2022-06-07", "s": "3403"}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            PitSymbolMap::from_symbology(&minimal, date!(2022 - 06 - 06)).unwrap()[3403],
+            "ESM2"
+        );
+    }
 }
@@ -0,0 +1,923 @@
+//! [`proptest`](mod@proptest) strategies for every record type and [`Metadata`] as well as a
+//! generator of synthetic market data for testing code that consumes DBN.
+//!
+//! All record types implement [`Arbitrary`], so `any::<MboMsg>()` generates records
+//! with a valid `rtype` and `length` and with enum-valued fields set to one of the
+//! variants of the corresponding enum.
+//!
+//! Requires the `testing` feature.
+
+mod synthetic;
+
+use std::{num::NonZeroU64, os::raw::c_char};
+
+use proptest::{
+    collection::vec,
+    option,
+    prelude::*,
+    sample::{select, SizeRange},
+};
+
+pub use synthetic::{synthetic_data, SyntheticData, SyntheticMarket};
+
+use crate::{
+    compat::version_symbol_cstr_len,
+    enums::{
+        rtype, Action, InstrumentClass, MatchAlgorithm, SType, Schema, SecurityUpdateAction, Side,
+        StatType, StatUpdateAction, UserDefinedInstrument,
+    },
+    record::{
+        BidAskPair, ErrorMsg, HasRType, ImbalanceMsg, InstrumentDefMsg, MboMsg, Mbp10Msg, Mbp1Msg,
+        OhlcvMsg, RecordHeader, StatMsg, StatusMsg, SymbolMappingMsg, SystemMsg, TradeMsg,
+        WithTsOut,
+    },
+    MappingInterval, Metadata, Publisher, RecordEnum, SymbolMapping, DBN_VERSION,
+};
+
+/// Returns a strategy for a [`RecordHeader`] of a record of type `R` with an `rtype`
+/// drawn from `rtypes` and a `length` matching the size of `R`.
+///
+/// # Panics
+/// This function panics if `rtypes` is empty.
+pub fn header<R: HasRType>(rtypes: &[u8]) -> impl Strategy<Value = RecordHeader> {
+    (
+        select(rtypes.to_vec()),
+        publisher_id(),
+        any::<u32>(),
+        any::<u64>(),
+    )
+        .prop_map(|(rtype, publisher_id, instrument_id, ts_event)| {
+            RecordHeader::new::<R>(rtype, publisher_id, instrument_id, ts_event)
+        })
+}
+
+/// Returns a strategy for the ID of a known [`Publisher`].
+pub fn publisher_id() -> impl Strategy<Value = u16> {
+    select(
+        (1..=u8::MAX as u16)
+            .filter(|id| Publisher::try_from(*id).is_ok())
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Returns a strategy for a null-terminated and null-padded fixed-length string of
+/// printable ASCII characters.
+pub fn c_chars<const N: usize>() -> impl Strategy<Value = [c_char; N]> {
+    c_chars_with_len::<N>(0..N)
+}
+
+/// Returns a strategy for a null-padded fixed-length string of printable ASCII
+/// characters whose length is within `len`.
+pub fn c_chars_with_len<const N: usize>(
+    len: impl Into<SizeRange>,
+) -> impl Strategy<Value = [c_char; N]> {
+    vec(0x20u8..0x7F, len).prop_map(|bytes| {
+        let mut res = [0; N];
+        for (c, byte) in res.iter_mut().zip(bytes) {
+            *c = byte as c_char;
+        }
+        res
+    })
+}
+
+/// Returns a strategy for a [`Side`] as a `c_char`.
+pub fn side() -> impl Strategy<Value = c_char> {
+    select(vec![Side::Ask, Side::Bid, Side::None]).prop_map(|side| side as u8 as c_char)
+}
+
+/// Returns a strategy for an [`Action`] as a `c_char`.
+pub fn action() -> impl Strategy<Value = c_char> {
+    select(vec![
+        Action::Modify,
+        Action::Trade,
+        Action::Fill,
+        Action::Cancel,
+        Action::Add,
+        Action::Clear,
+    ])
+    .prop_map(|action| action as u8 as c_char)
+}
+
+/// Returns a strategy for an [`InstrumentClass`] as a `c_char`.
+pub fn instrument_class() -> impl Strategy<Value = c_char> {
+    select(vec![
+        InstrumentClass::Bond,
+        InstrumentClass::Call,
+        InstrumentClass::Future,
+        InstrumentClass::Stock,
+        InstrumentClass::MixedSpread,
+        InstrumentClass::Put,
+        InstrumentClass::FutureSpread,
+        InstrumentClass::OptionSpread,
+        InstrumentClass::FxSpot,
+    ])
+    .prop_map(|class| class as u8 as c_char)
+}
+
+/// Returns a strategy for a [`MatchAlgorithm`] as a `c_char`.
+pub fn match_algorithm() -> impl Strategy<Value = c_char> {
+    select(vec![
+        MatchAlgorithm::Fifo,
+        MatchAlgorithm::Configurable,
+        MatchAlgorithm::ProRata,
+        MatchAlgorithm::FifoLmm,
+        MatchAlgorithm::ThresholdProRata,
+        MatchAlgorithm::FifoTopLmm,
+        MatchAlgorithm::ThresholdProRataLmm,
+        MatchAlgorithm::EurodollarFutures,
+    ])
+    .prop_map(|algo| algo as u8 as c_char)
+}
+
+/// Returns a strategy for a [`SecurityUpdateAction`] as a `c_char`.
+pub fn security_update_action() -> impl Strategy<Value = c_char> {
+    select(vec![
+        SecurityUpdateAction::Add,
+        SecurityUpdateAction::Modify,
+        SecurityUpdateAction::Delete,
+    ])
+    .prop_map(|action| action as u8 as c_char)
+}
+
+/// Returns a strategy for a [`UserDefinedInstrument`].
+pub fn user_defined_instrument() -> impl Strategy<Value = UserDefinedInstrument> {
+    select(vec![UserDefinedInstrument::No, UserDefinedInstrument::Yes])
+}
+
+/// Returns a strategy for a [`StatType`] as a `u16`.
+pub fn stat_type() -> impl Strategy<Value = u16> {
+    select(
+        (1..=u8::MAX as u16)
+            .filter(|stat_type| StatType::try_from(*stat_type).is_ok())
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Returns a strategy for a [`StatUpdateAction`] as a `u8`.
+pub fn stat_update_action() -> impl Strategy<Value = u8> {
+    select(vec![StatUpdateAction::New, StatUpdateAction::Delete]).prop_map(|a| a as u8)
+}
+
+/// Returns a strategy for an [`SType`].
+pub fn stype() -> impl Strategy<Value = SType> {
+    select(vec![
+        SType::InstrumentId,
+        SType::RawSymbol,
+        SType::Continuous,
+        SType::Parent,
+        SType::Nasdaq,
+        SType::Cms,
+    ])
+}
+
+/// Returns a strategy for a [`Schema`].
+pub fn schema() -> impl Strategy<Value = Schema> {
+    select(
+        (0..=u8::MAX as u16)
+            .filter_map(|schema| Schema::try_from(schema).ok())
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Returns a strategy for a text symbol of at most `max_len` characters.
+pub fn symbol(max_len: usize) -> impl Strategy<Value = String> {
+    vec(
+        select(b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789.".to_vec()),
+        1..=max_len,
+    )
+    .prop_map(|bytes| String::from_utf8(bytes).unwrap())
+}
+
+/// Returns a strategy for a UTC date between 1970 and 2100.
+pub fn date() -> impl Strategy<Value = time::Date> {
+    let start = time::Date::from_calendar_date(1970, time::Month::January, 1)
+        .unwrap()
+        .to_julian_day();
+    let end = time::Date::from_calendar_date(2100, time::Month::January, 1)
+        .unwrap()
+        .to_julian_day();
+    (start..end).prop_map(|day| time::Date::from_julian_day(day).unwrap())
+}
+
+/// Returns a strategy for [`Metadata`] of the given DBN `version`. The generated
+/// metadata can be encoded and decoded without loss.
+pub fn metadata_for_version(version: u8) -> impl Strategy<Value = Metadata> {
+    let symbol_cstr_len = version_symbol_cstr_len(version);
+    let max_symbol_len = symbol_cstr_len - 1;
+    (
+        (
+            "[A-Z]{4}\\.[A-Z0-9]{1,10}",
+            option::of(schema()),
+            any::<u64>(),
+            option::of(any::<NonZeroU64>()),
+            option::of(any::<NonZeroU64>()),
+            option::of(stype()),
+            stype(),
+            any::<bool>(),
+        ),
+        (
+            vec(symbol(max_symbol_len), 0..8),
+            vec(symbol(max_symbol_len), 0..4),
+            vec(symbol(max_symbol_len), 0..4),
+            vec(symbol_mapping(max_symbol_len), 0..8),
+        ),
+    )
+        .prop_map(
+            move |(
+                (dataset, schema, start, end, limit, stype_in, stype_out, ts_out),
+                (symbols, partial, not_found, mappings),
+            )| Metadata {
+                version,
+                dataset,
+                schema,
+                start,
+                end,
+                limit,
+                stype_in,
+                stype_out,
+                ts_out,
+                symbol_cstr_len,
+                symbols,
+                partial,
+                not_found,
+                mappings,
+            },
+        )
+}
+
+/// Returns a strategy for a [`SymbolMapping`] with non-overlapping, ascending
+/// intervals and symbols of at most `max_symbol_len` characters.
+pub fn symbol_mapping(max_symbol_len: usize) -> impl Strategy<Value = SymbolMapping> {
+    (
+        symbol(max_symbol_len),
+        date(),
+        vec((1..90i64, symbol(max_symbol_len)), 1..4),
+    )
+        .prop_map(|(raw_symbol, start_date, intervals)| {
+            let mut start_date = start_date;
+            let intervals = intervals
+                .into_iter()
+                .map(|(days, symbol)| {
+                    let end_date = start_date + time::Duration::days(days);
+                    let interval = MappingInterval {
+                        start_date,
+                        end_date,
+                        symbol,
+                    };
+                    start_date = end_date;
+                    interval
+                })
+                .collect();
+            SymbolMapping {
+                raw_symbol,
+                intervals,
+            }
+        })
+}
+
+impl Arbitrary for Metadata {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (1..=DBN_VERSION)
+            .prop_flat_map(metadata_for_version)
+            .boxed()
+    }
+}
+
+impl Arbitrary for BidAskPair {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (
+            any::<i64>(),
+            any::<i64>(),
+            any::<u32>(),
+            any::<u32>(),
+            any::<u32>(),
+            any::<u32>(),
+        )
+            .prop_map(
+                |(bid_px, ask_px, bid_sz, ask_sz, bid_ct, ask_ct)| BidAskPair {
+                    bid_px,
+                    ask_px,
+                    bid_sz,
+                    ask_sz,
+                    bid_ct,
+                    ask_ct,
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for MboMsg {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (
+            header::<Self>(&[rtype::MBO]),
+            any::<u64>(),
+            any::<i64>(),
+            any::<u32>(),
+            any::<u8>(),
+            any::<u8>(),
+            action(),
+            side(),
+            any::<u64>(),
+            any::<i32>(),
+            any::<u32>(),
+        )
+            .prop_map(
+                |(
+                    hd,
+                    order_id,
+                    price,
+                    size,
+                    flags,
+                    channel_id,
+                    action,
+                    side,
+                    ts_recv,
+                    ts_in_delta,
+                    sequence,
+                )| MboMsg {
+                    hd,
+                    order_id,
+                    price,
+                    size,
+                    flags,
+                    channel_id,
+                    action,
+                    side,
+                    ts_recv,
+                    ts_in_delta,
+                    sequence,
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for TradeMsg {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (
+            header::<Self>(&[rtype::MBP_0]),
+            any::<i64>(),
+            any::<u32>(),
+            action(),
+            side(),
+            any::<u8>(),
+            any::<u8>(),
+            any::<u64>(),
+            any::<i32>(),
+            any::<u32>(),
+        )
+            .prop_map(
+                |(hd, price, size, action, side, flags, depth, ts_recv, ts_in_delta, sequence)| {
+                    TradeMsg {
+                        hd,
+                        price,
+                        size,
+                        action,
+                        side,
+                        flags,
+                        depth,
+                        ts_recv,
+                        ts_in_delta,
+                        sequence,
+                    }
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for Mbp1Msg {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (
+            header::<Self>(&[rtype::MBP_1]),
+            any::<TradeMsg>(),
+            any::<[BidAskPair; 1]>(),
+        )
+            .prop_map(|(hd, trade, levels)| Mbp1Msg {
+                hd,
+                price: trade.price,
+                size: trade.size,
+                action: trade.action,
+                side: trade.side,
+                flags: trade.flags,
+                depth: trade.depth,
+                ts_recv: trade.ts_recv,
+                ts_in_delta: trade.ts_in_delta,
+                sequence: trade.sequence,
+                levels,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Mbp10Msg {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (
+            header::<Self>(&[rtype::MBP_10]),
+            any::<TradeMsg>(),
+            any::<[BidAskPair; 10]>(),
+        )
+            .prop_map(|(hd, trade, levels)| Mbp10Msg {
+                hd,
+                price: trade.price,
+                size: trade.size,
+                action: trade.action,
+                side: trade.side,
+                flags: trade.flags,
+                depth: trade.depth,
+                ts_recv: trade.ts_recv,
+                ts_in_delta: trade.ts_in_delta,
+                sequence: trade.sequence,
+                levels,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for OhlcvMsg {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (
+            header::<Self>(&[
+                rtype::OHLCV_1S,
+                rtype::OHLCV_1M,
+                rtype::OHLCV_1H,
+                rtype::OHLCV_1D,
+                rtype::OHLCV_EOD,
+            ]),
+            any::<[i64; 4]>(),
+            any::<u64>(),
+        )
+            .prop_map(|(hd, [open, high, low, close], volume)| OhlcvMsg {
+                hd,
+                open,
+                high,
+                low,
+                close,
+                volume,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for StatusMsg {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (
+            header::<Self>(&[rtype::STATUS]),
+            any::<u64>(),
+            c_chars(),
+            any::<[u8; 3]>(),
+        )
+            .prop_map(
+                |(hd, ts_recv, group, [trading_status, halt_reason, trading_event])| StatusMsg {
+                    hd,
+                    ts_recv,
+                    group,
+                    trading_status,
+                    halt_reason,
+                    trading_event,
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for InstrumentDefMsg {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        let numeric = (
+            any::<[u64; 3]>(),
+            any::<[i64; 10]>(),
+            any::<[i32; 9]>(),
+            any::<[u32; 5]>(),
+            any::<[u16; 4]>(),
+            any::<i16>(),
+            any::<[u8; 10]>(),
+            any::<[i8; 2]>(),
+        );
+        let strings = (
+            (
+                c_chars(),
+                c_chars(),
+                c_chars(),
+                c_chars(),
+                c_chars(),
+                c_chars(),
+            ),
+            (
+                c_chars(),
+                c_chars(),
+                c_chars(),
+                c_chars(),
+                c_chars(),
+                c_chars(),
+            ),
+        );
+        let enums = (
+            instrument_class(),
+            match_algorithm(),
+            security_update_action(),
+            user_defined_instrument(),
+        );
+        (header::<Self>(&[rtype::INSTRUMENT_DEF]), numeric, strings, enums)
+            .prop_map(
+                |(
+                    hd,
+                    (
+                        [ts_recv, expiration, activation],
+                        [min_price_increment, display_factor, high_limit_price, low_limit_price, max_price_variation, trading_reference_price, unit_of_measure_qty, min_price_increment_amount, price_ratio, strike_price],
+                        [inst_attrib_value, market_depth_implied, market_depth, min_lot_size, min_lot_size_block, min_lot_size_round_lot, contract_multiplier, decay_quantity, original_contract_size],
+                        [underlying_id, raw_instrument_id, market_segment_id, max_trade_vol, min_trade_vol],
+                        [trading_reference_date, maturity_year, decay_start_date, channel_id],
+                        appl_id,
+                        [md_security_trading_status, main_fraction, price_display_format, settl_price_type, sub_fraction, underlying_product, maturity_month, maturity_day, maturity_week, tick_rule],
+                        [contract_multiplier_unit, flow_schedule_type],
+                    ),
+                    (
+                        (currency, settl_currency, secsubtype, raw_symbol, group, exchange),
+                        (
+                            asset,
+                            cfi,
+                            security_type,
+                            unit_of_measure,
+                            underlying,
+                            strike_price_currency,
+                        ),
+                    ),
+                    (
+                        instrument_class,
+                        match_algorithm,
+                        security_update_action,
+                        user_defined_instrument,
+                    ),
+                )| InstrumentDefMsg {
+                    hd,
+                    ts_recv,
+                    min_price_increment,
+                    display_factor,
+                    expiration,
+                    activation,
+                    high_limit_price,
+                    low_limit_price,
+                    max_price_variation,
+                    trading_reference_price,
+                    unit_of_measure_qty,
+                    min_price_increment_amount,
+                    price_ratio,
+                    strike_price,
+                    inst_attrib_value,
+                    underlying_id,
+                    raw_instrument_id,
+                    market_depth_implied,
+                    market_depth,
+                    market_segment_id,
+                    max_trade_vol,
+                    min_lot_size,
+                    min_lot_size_block,
+                    min_lot_size_round_lot,
+                    min_trade_vol,
+                    contract_multiplier,
+                    decay_quantity,
+                    original_contract_size,
+                    trading_reference_date,
+                    appl_id,
+                    maturity_year,
+                    decay_start_date,
+                    channel_id,
+                    currency,
+                    settl_currency,
+                    secsubtype,
+                    raw_symbol,
+                    group,
+                    exchange,
+                    asset,
+                    cfi,
+                    security_type,
+                    unit_of_measure,
+                    underlying,
+                    strike_price_currency,
+                    instrument_class,
+                    match_algorithm,
+                    md_security_trading_status,
+                    main_fraction,
+                    price_display_format,
+                    settl_price_type,
+                    sub_fraction,
+                    underlying_product,
+                    security_update_action,
+                    maturity_month,
+                    maturity_day,
+                    maturity_week,
+                    user_defined_instrument,
+                    contract_multiplier_unit,
+                    flow_schedule_type,
+                    tick_rule,
+                    _reserved: Default::default(),
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for ImbalanceMsg {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (
+            header::<Self>(&[rtype::IMBALANCE]),
+            any::<[u64; 2]>(),
+            any::<[i64; 7]>(),
+            any::<[u32; 4]>(),
+            (b'A'..=b'Z').prop_map(|c| c as c_char),
+            side(),
+            any::<[u8; 3]>(),
+            side(),
+            (b'A'..=b'Z').prop_map(|c| c as c_char),
+        )
+            .prop_map(
+                |(
+                    hd,
+                    [ts_recv, auction_time],
+                    [ref_price, cont_book_clr_price, auct_interest_clr_price, ssr_filling_price, ind_match_price, upper_collar, lower_collar],
+                    [paired_qty, total_imbalance_qty, market_imbalance_qty, unpaired_qty],
+                    auction_type,
+                    side,
+                    [auction_status, freeze_status, num_extensions],
+                    unpaired_side,
+                    significant_imbalance,
+                )| ImbalanceMsg {
+                    hd,
+                    ts_recv,
+                    ref_price,
+                    auction_time,
+                    cont_book_clr_price,
+                    auct_interest_clr_price,
+                    ssr_filling_price,
+                    ind_match_price,
+                    upper_collar,
+                    lower_collar,
+                    paired_qty,
+                    total_imbalance_qty,
+                    market_imbalance_qty,
+                    unpaired_qty,
+                    auction_type,
+                    side,
+                    auction_status,
+                    freeze_status,
+                    num_extensions,
+                    unpaired_side,
+                    significant_imbalance,
+                    _dummy: Default::default(),
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for StatMsg {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (
+            header::<Self>(&[rtype::STATISTICS]),
+            any::<[u64; 2]>(),
+            any::<i64>(),
+            any::<i32>(),
+            any::<u32>(),
+            any::<i32>(),
+            stat_type(),
+            any::<u16>(),
+            stat_update_action(),
+            any::<u8>(),
+        )
+            .prop_map(
+                |(
+                    hd,
+                    [ts_recv, ts_ref],
+                    price,
+                    quantity,
+                    sequence,
+                    ts_in_delta,
+                    stat_type,
+                    channel_id,
+                    update_action,
+                    stat_flags,
+                )| StatMsg {
+                    hd,
+                    ts_recv,
+                    ts_ref,
+                    price,
+                    quantity,
+                    sequence,
+                    ts_in_delta,
+                    stat_type,
+                    channel_id,
+                    update_action,
+                    stat_flags,
+                    _dummy: Default::default(),
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for ErrorMsg {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (
+            header::<Self>(&[rtype::ERROR]),
+            c_chars(),
+            any::<u8>(),
+            any::<bool>(),
+        )
+            .prop_map(|(hd, err, code, is_last)| ErrorMsg {
+                hd,
+                err,
+                code,
+                is_last: is_last as u8,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for SymbolMappingMsg {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (
+            header::<Self>(&[rtype::SYMBOL_MAPPING]),
+            stype(),
+            c_chars(),
+            stype(),
+            c_chars(),
+            any::<[u64; 2]>(),
+        )
+            .prop_map(
+                |(
+                    hd,
+                    stype_in,
+                    stype_in_symbol,
+                    stype_out,
+                    stype_out_symbol,
+                    [start_ts, end_ts],
+                )| {
+                    SymbolMappingMsg {
+                        hd,
+                        stype_in: stype_in as u8,
+                        stype_in_symbol,
+                        stype_out: stype_out as u8,
+                        stype_out_symbol,
+                        start_ts,
+                        end_ts,
+                    }
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for SystemMsg {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (header::<Self>(&[rtype::SYSTEM]), c_chars(), any::<u8>())
+            .prop_map(|(hd, msg, code)| SystemMsg { hd, msg, code })
+            .boxed()
+    }
+}
+
+impl<R> Arbitrary for WithTsOut<R>
+where
+    R: HasRType + Arbitrary + 'static,
+{
+    type Parameters = R::Parameters;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+        (any_with::<R>(args), any::<u64>())
+            .prop_map(|(rec, ts_out)| WithTsOut::new(rec, ts_out))
+            .boxed()
+    }
+}
+
+impl Arbitrary for RecordEnum {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            any::<MboMsg>().prop_map(RecordEnum::from),
+            any::<TradeMsg>().prop_map(RecordEnum::from),
+            any::<Mbp1Msg>().prop_map(RecordEnum::from),
+            any::<Mbp10Msg>().prop_map(RecordEnum::from),
+            any::<OhlcvMsg>().prop_map(RecordEnum::from),
+            any::<StatusMsg>().prop_map(RecordEnum::from),
+            any::<InstrumentDefMsg>().prop_map(RecordEnum::from),
+            any::<ImbalanceMsg>().prop_map(RecordEnum::from),
+            any::<StatMsg>().prop_map(RecordEnum::from),
+            any::<ErrorMsg>().prop_map(RecordEnum::from),
+            any::<SymbolMappingMsg>().prop_map(RecordEnum::from),
+            any::<SystemMsg>().prop_map(RecordEnum::from),
+        ]
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use crate::{Record, RecordRef};
+
+    use super::*;
+
+    fn check_record<R: HasRType + Arbitrary>(rec: &R) -> Result<(), TestCaseError> {
+        prop_assert!(R::has_rtype(rec.header().rtype));
+        prop_assert_eq!(rec.record_size(), mem::size_of::<R>());
+        prop_assert!(rec.publisher().is_ok());
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_mbo_valid(rec in any::<MboMsg>()) {
+            check_record(&rec)?;
+            prop_assert!(rec.action().is_ok());
+            prop_assert!(rec.side().is_ok());
+        }
+
+        #[test]
+        fn prop_mbp10_valid(rec in any::<Mbp10Msg>()) {
+            check_record(&rec)?;
+            prop_assert!(rec.action().is_ok());
+            prop_assert!(rec.side().is_ok());
+        }
+
+        #[test]
+        fn prop_definition_valid(rec in any::<InstrumentDefMsg>()) {
+            check_record(&rec)?;
+            prop_assert!(rec.instrument_class().is_ok());
+            prop_assert!(rec.match_algorithm().is_ok());
+            prop_assert!(rec.security_update_action().is_ok());
+            prop_assert!(rec.raw_symbol().is_ok());
+        }
+
+        #[test]
+        fn prop_stat_valid(rec in any::<StatMsg>()) {
+            check_record(&rec)?;
+            prop_assert!(StatType::try_from(rec.stat_type).is_ok());
+            prop_assert!(StatUpdateAction::try_from(rec.update_action).is_ok());
+        }
+
+        #[test]
+        fn prop_with_ts_out_length(rec in any::<WithTsOut<TradeMsg>>()) {
+            prop_assert_eq!(rec.record_size(), mem::size_of::<WithTsOut<TradeMsg>>());
+        }
+
+        #[test]
+        fn prop_record_enum_as_enum(rec in any::<RecordEnum>()) {
+            let rec_ref = RecordRef::from(&rec);
+            prop_assert!(rec_ref.rtype().is_ok());
+            prop_assert!(rec_ref.as_enum().is_ok());
+        }
+
+        #[test]
+        fn prop_metadata_symbols_fit(metadata in any::<Metadata>()) {
+            for symbol in metadata.symbols.iter().chain(metadata.mappings.iter().map(|m| &m.raw_symbol)) {
+                prop_assert!(symbol.len() < metadata.symbol_cstr_len);
+            }
+        }
+    }
+}
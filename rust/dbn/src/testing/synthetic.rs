@@ -0,0 +1,482 @@
+use std::{num::NonZeroU64, ops::Range, os::raw::c_char};
+
+use proptest::prelude::*;
+
+use crate::{
+    enums::{rtype, Action, InstrumentClass, SType, Schema, SecurityUpdateAction, Side},
+    flags,
+    record::{str_to_c_chars, InstrumentDefMsg, MboMsg, RecordHeader, TradeMsg},
+    MappingInterval, Metadata, Publisher, SymbolMapping, FIXED_PRICE_SCALE,
+};
+
+/// Generator of synthetic, time-ordered market data for multiple instruments with
+/// consistent symbology.
+///
+/// The generator simulates a limit order book for each instrument and produces
+/// [`MboMsg`]s along with the [`TradeMsg`]s matching every trade in the order book.
+/// The same `seed` always produces the same data.
+///
+/// # Example
+/// ```
+/// use dbn::{testing::SyntheticMarket, Schema};
+///
+/// let data = SyntheticMarket::new(42)
+///     .instrument(1, "ESH4", 4_750_000_000_000, 250_000_000)
+///     .instrument(2, "NQH4", 16_800_000_000_000, 250_000_000)
+///     .generate(1_000);
+/// let metadata = data.metadata(Schema::Mbo);
+/// assert_eq!(metadata.symbols, vec!["ESH4", "NQH4"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SyntheticMarket {
+    rng: SplitMix64,
+    publisher: Publisher,
+    start: u64,
+    instruments: Vec<InstrumentSpec>,
+}
+
+/// The output of a [`SyntheticMarket`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntheticData {
+    /// The dataset code of the generated data.
+    pub dataset: String,
+    /// The UNIX nanosecond timestamp of the first generated record.
+    pub start: u64,
+    /// The UNIX nanosecond timestamp immediately after the last generated record.
+    pub end: u64,
+    /// One definition for each instrument, all received at `start`.
+    pub definitions: Vec<InstrumentDefMsg>,
+    /// The order book events of all instruments, ordered by `ts_recv`.
+    pub mbo: Vec<MboMsg>,
+    /// The trades of all instruments, ordered by `ts_recv`. Each trade has a
+    /// corresponding [`Action::Trade`] record in `mbo` with the same `ts_recv` and
+    /// `sequence`.
+    pub trades: Vec<TradeMsg>,
+    /// The mapping from each raw symbol to its instrument ID.
+    pub mappings: Vec<SymbolMapping>,
+}
+
+#[derive(Debug, Clone)]
+struct InstrumentSpec {
+    instrument_id: u32,
+    raw_symbol: String,
+    mid_price: i64,
+    tick_size: i64,
+}
+
+#[derive(Debug, Clone)]
+struct RestingOrder {
+    order_id: u64,
+    side: Side,
+    price: i64,
+    size: u32,
+}
+
+/// A small, fast and deterministic pseudo-random number generator.
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+/// Returns a strategy for [`SyntheticData`] from a [`SyntheticMarket`] with the
+/// default instruments and a number of order book events within `event_count`.
+pub fn synthetic_data(event_count: Range<usize>) -> impl Strategy<Value = SyntheticData> {
+    (any::<u64>(), event_count)
+        .prop_map(|(seed, event_count)| SyntheticMarket::new(seed).generate(event_count))
+}
+
+impl SyntheticMarket {
+    /// The default start of generated data: 2024-01-02T14:30:00Z.
+    pub const DEFAULT_START: u64 = 1_704_205_800_000_000_000;
+
+    /// Creates a new generator seeded with `seed`. By default, data is generated for
+    /// [`Publisher::GlbxMdp3Glbx`] starting at [`Self::DEFAULT_START`]. If no
+    /// instruments are added with [`instrument()`](Self::instrument), data is generated
+    /// for three futures.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SplitMix64(seed),
+            publisher: Publisher::GlbxMdp3Glbx,
+            start: Self::DEFAULT_START,
+            instruments: Vec::new(),
+        }
+    }
+
+    /// Sets the publisher of the generated records and returns the generator. The
+    /// dataset of the generated data is the dataset of `publisher`.
+    pub fn publisher(mut self, publisher: Publisher) -> Self {
+        self.publisher = publisher;
+        self
+    }
+
+    /// Sets the UNIX nanosecond timestamp of the first generated record and returns
+    /// the generator.
+    pub fn start(mut self, start: u64) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Adds an instrument with the given ID and raw symbol whose orders will be placed
+    /// within a few ticks of `mid_price`, and returns the generator. Prices are fixed
+    /// precision where every 1 unit corresponds to 1e-9.
+    ///
+    /// # Panics
+    /// This function panics if `tick_size` is not positive.
+    pub fn instrument(
+        mut self,
+        instrument_id: u32,
+        raw_symbol: &str,
+        mid_price: i64,
+        tick_size: i64,
+    ) -> Self {
+        assert!(tick_size > 0, "tick_size must be positive");
+        self.instruments.push(InstrumentSpec {
+            instrument_id,
+            raw_symbol: raw_symbol.to_owned(),
+            mid_price,
+            tick_size,
+        });
+        self
+    }
+
+    /// Generates `event_count` order book events. Trade events produce more than one
+    /// [`MboMsg`].
+    pub fn generate(mut self, event_count: usize) -> SyntheticData {
+        if self.instruments.is_empty() {
+            self = self
+                .instrument(
+                    5482,
+                    "ESH4",
+                    4_750 * FIXED_PRICE_SCALE,
+                    FIXED_PRICE_SCALE / 4,
+                )
+                .instrument(
+                    260937,
+                    "NQH4",
+                    16_800 * FIXED_PRICE_SCALE,
+                    FIXED_PRICE_SCALE / 4,
+                )
+                .instrument(
+                    3403,
+                    "CLG4",
+                    72 * FIXED_PRICE_SCALE,
+                    FIXED_PRICE_SCALE / 100,
+                );
+        }
+        let publisher_id = self.publisher as u16;
+        let definitions = self
+            .instruments
+            .iter()
+            .map(|inst| InstrumentDefMsg {
+                hd: RecordHeader::new::<InstrumentDefMsg>(
+                    rtype::INSTRUMENT_DEF,
+                    publisher_id,
+                    inst.instrument_id,
+                    self.start,
+                ),
+                ts_recv: self.start,
+                min_price_increment: inst.tick_size,
+                display_factor: FIXED_PRICE_SCALE,
+                raw_symbol: str_to_c_chars(&inst.raw_symbol).unwrap(),
+                instrument_class: InstrumentClass::Future as u8 as c_char,
+                security_update_action: SecurityUpdateAction::Add as u8 as c_char,
+                ..Default::default()
+            })
+            .collect();
+
+        let mut books: Vec<Vec<RestingOrder>> = vec![Vec::new(); self.instruments.len()];
+        let mut mbo = Vec::with_capacity(event_count * 2);
+        let mut trades = Vec::new();
+        let mut ts_recv = self.start;
+        let mut next_order_id = 1;
+        for sequence in 1..=event_count as u32 {
+            ts_recv += 1 + self.rng.below(10_000_000);
+            let latency = 1_000 + self.rng.below(50_000);
+            // Clamp to `start` so events never precede the definitions
+            let ts_event = ts_recv.saturating_sub(latency).max(self.start);
+            let idx = self.rng.below(self.instruments.len() as u64) as usize;
+            let inst = &self.instruments[idx];
+            let book = &mut books[idx];
+            let new_mbo =
+                |order_id: u64, action: Action, side: Side, price: i64, size: u32| MboMsg {
+                    hd: RecordHeader::new::<MboMsg>(
+                        rtype::MBO,
+                        publisher_id,
+                        inst.instrument_id,
+                        ts_event,
+                    ),
+                    order_id,
+                    price,
+                    size,
+                    flags: 0,
+                    channel_id: 0,
+                    action: action as u8 as c_char,
+                    side: side as u8 as c_char,
+                    ts_recv,
+                    ts_in_delta: latency as i32,
+                    sequence,
+                };
+            let has_both_sides = book.iter().any(|o| o.side == Side::Bid)
+                && book.iter().any(|o| o.side == Side::Ask);
+            let roll = if has_both_sides {
+                self.rng.below(100)
+            } else {
+                0
+            };
+            let first_new = mbo.len();
+            match roll {
+                // add
+                0..=49 => {
+                    let side = if self.rng.below(2) == 0 {
+                        Side::Bid
+                    } else {
+                        Side::Ask
+                    };
+                    let ticks = 1 + self.rng.below(5) as i64;
+                    let price = match side {
+                        Side::Bid => inst.mid_price - ticks * inst.tick_size,
+                        _ => inst.mid_price + ticks * inst.tick_size,
+                    };
+                    let order = RestingOrder {
+                        order_id: next_order_id,
+                        side,
+                        price,
+                        size: 1 + self.rng.below(50) as u32,
+                    };
+                    next_order_id += 1;
+                    mbo.push(new_mbo(
+                        order.order_id,
+                        Action::Add,
+                        order.side,
+                        order.price,
+                        order.size,
+                    ));
+                    book.push(order);
+                }
+                // cancel
+                50..=69 => {
+                    let order = book.swap_remove(self.rng.below(book.len() as u64) as usize);
+                    mbo.push(new_mbo(
+                        order.order_id,
+                        Action::Cancel,
+                        order.side,
+                        order.price,
+                        order.size,
+                    ));
+                }
+                // modify
+                70..=79 => {
+                    let i = self.rng.below(book.len() as u64) as usize;
+                    let order = &mut book[i];
+                    order.size = 1 + self.rng.below(50) as u32;
+                    mbo.push(new_mbo(
+                        order.order_id,
+                        Action::Modify,
+                        order.side,
+                        order.price,
+                        order.size,
+                    ));
+                }
+                // trade
+                _ => {
+                    let aggressor = if self.rng.below(2) == 0 {
+                        Side::Bid
+                    } else {
+                        Side::Ask
+                    };
+                    // Price-time priority: best price, then lowest order ID
+                    let (i, resting) = book
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, o)| o.side != aggressor)
+                        .min_by_key(|(_, o)| {
+                            let price_rank = if o.side == Side::Bid {
+                                -o.price
+                            } else {
+                                o.price
+                            };
+                            (price_rank, o.order_id)
+                        })
+                        .unwrap();
+                    let size = 1 + self.rng.below(resting.size as u64) as u32;
+                    let (price, resting_id, resting_side) =
+                        (resting.price, resting.order_id, resting.side);
+                    mbo.push(new_mbo(0, Action::Trade, aggressor, price, size));
+                    mbo.push(new_mbo(resting_id, Action::Fill, resting_side, price, size));
+                    if size == book[i].size {
+                        book.swap_remove(i);
+                        mbo.push(new_mbo(resting_id, Action::Cancel, resting_side, price, 0));
+                    } else {
+                        book[i].size -= size;
+                    }
+                    let trade_mbo = &mbo[first_new];
+                    trades.push(TradeMsg {
+                        hd: RecordHeader::new::<TradeMsg>(
+                            rtype::MBP_0,
+                            publisher_id,
+                            inst.instrument_id,
+                            ts_event,
+                        ),
+                        price,
+                        size,
+                        action: Action::Trade as u8 as c_char,
+                        side: aggressor as u8 as c_char,
+                        flags: flags::LAST,
+                        depth: 0,
+                        ts_recv: trade_mbo.ts_recv,
+                        ts_in_delta: trade_mbo.ts_in_delta,
+                        sequence,
+                    });
+                }
+            }
+            // Mark the end of the event
+            mbo.last_mut().unwrap().flags |= flags::LAST;
+        }
+        let end = mbo.last().map(|rec| rec.ts_recv).unwrap_or(self.start) + 1;
+        let start_date = ts_to_date(self.start);
+        let end_date = ts_to_date(end) + time::Duration::DAY;
+        let mappings = self
+            .instruments
+            .iter()
+            .map(|inst| SymbolMapping {
+                raw_symbol: inst.raw_symbol.clone(),
+                intervals: vec![MappingInterval {
+                    start_date,
+                    end_date,
+                    symbol: inst.instrument_id.to_string(),
+                }],
+            })
+            .collect();
+        SyntheticData {
+            dataset: self.publisher.dataset().as_str().to_owned(),
+            start: self.start,
+            end,
+            definitions,
+            mbo,
+            trades,
+            mappings,
+        }
+    }
+}
+
+impl SyntheticData {
+    /// Creates [`Metadata`] for the generated records of the given `schema` with
+    /// mappings from raw symbols to instrument IDs.
+    pub fn metadata(&self, schema: Schema) -> Metadata {
+        Metadata::builder()
+            .dataset(self.dataset.clone())
+            .schema(Some(schema))
+            .start(self.start)
+            .end(NonZeroU64::new(self.end))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .symbols(
+                self.mappings
+                    .iter()
+                    .map(|mapping| mapping.raw_symbol.clone())
+                    .collect(),
+            )
+            .mappings(self.mappings.clone())
+            .build()
+    }
+}
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+fn ts_to_date(ts: u64) -> time::Date {
+    time::OffsetDateTime::from_unix_timestamp_nanos(ts as i128)
+        .unwrap()
+        .date()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Record, SymbolIndex};
+
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        assert_eq!(
+            SyntheticMarket::new(7).generate(500),
+            SyntheticMarket::new(7).generate(500)
+        );
+        assert_ne!(
+            SyntheticMarket::new(7).generate(500),
+            SyntheticMarket::new(8).generate(500)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_time_ordered(data in synthetic_data(1..2_000)) {
+            prop_assert!(data.mbo.windows(2).all(|w| w[0].ts_recv <= w[1].ts_recv));
+            prop_assert!(data.trades.windows(2).all(|w| w[0].ts_recv <= w[1].ts_recv));
+            prop_assert!(data.mbo.iter().all(|rec| rec.ts_recv < data.end));
+        }
+
+        #[test]
+        fn prop_trades_match_mbo(data in synthetic_data(1..2_000)) {
+            let mbo_trades = data
+                .mbo
+                .iter()
+                .filter(|rec| rec.action == Action::Trade as c_char)
+                .collect::<Vec<_>>();
+            prop_assert_eq!(mbo_trades.len(), data.trades.len());
+            for (mbo, trade) in mbo_trades.into_iter().zip(data.trades.iter()) {
+                prop_assert_eq!(mbo.hd.instrument_id, trade.hd.instrument_id);
+                prop_assert_eq!(mbo.ts_recv, trade.ts_recv);
+                prop_assert_eq!(mbo.sequence, trade.sequence);
+                prop_assert_eq!(mbo.price, trade.price);
+                prop_assert_eq!(mbo.size, trade.size);
+                prop_assert_eq!(mbo.side, trade.side);
+            }
+        }
+
+        #[test]
+        fn prop_consistent_symbology(data in synthetic_data(1..500)) {
+            let symbol_map = data.metadata(Schema::Mbo).symbol_map().unwrap();
+            for def in data.definitions.iter() {
+                prop_assert_eq!(
+                    symbol_map.get_for_rec(def).map(String::as_str),
+                    Some(def.raw_symbol().unwrap())
+                );
+            }
+            for rec in data.mbo.iter() {
+                prop_assert!(symbol_map.get_for_rec(rec).is_some(), "{rec:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_start_at_epoch() {
+        let data = SyntheticMarket::new(0).start(0).generate(100);
+        assert!(data.mbo.iter().all(|rec| rec.hd.ts_event <= rec.ts_recv));
+    }
+
+    #[test]
+    fn test_event_flags() {
+        let data = SyntheticMarket::new(0).generate(1_000);
+        for rec in data.mbo.iter() {
+            assert!(rec.header().publisher().is_ok());
+            assert!(rec.action().is_ok());
+        }
+        let last_count = data
+            .mbo
+            .iter()
+            .filter(|rec| rec.flags & flags::LAST != 0)
+            .count();
+        assert_eq!(last_count, 1_000);
+    }
+}
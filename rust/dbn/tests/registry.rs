@@ -0,0 +1,125 @@
+//! Tests user-defined records from outside the `dbn` crate, where the derive macros
+//! resolve `dbn` as a dependency.
+
+use std::ffi::c_char;
+
+use dbn::{
+    decode::{DecodeRecordRef, DynDecoder},
+    encode::{
+        dbn::Encoder as DbnEncoder, DynEncoder, EncodeRecord, EncodeRecordRef, EncodeRecordTextExt,
+    },
+    reflect::FieldValue,
+    registry::{self, dbn_record, CsvSerialize, JsonSerialize},
+    Compression, Encoding, MetadataBuilder, Record, RecordEnum, RecordHeader, RecordRefEnum, SType,
+    VersionUpgradePolicy,
+};
+
+const QUOTE_RTYPE: u8 = 0xE0;
+
+#[repr(C)]
+#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq)]
+#[dbn_record(QUOTE_RTYPE)]
+pub struct InternalQuoteMsg {
+    pub hd: RecordHeader,
+    #[dbn(fixed_price)]
+    pub bid_px: i64,
+    #[dbn(fixed_price)]
+    pub ask_px: i64,
+    #[dbn(unix_nanos, index_ts)]
+    pub ts_recv: u64,
+    #[dbn(c_char)]
+    pub source: c_char,
+    #[dbn(skip)]
+    pub _reserved: [u8; 7],
+}
+
+fn quote(instrument_id: u32, ts: u64) -> InternalQuoteMsg {
+    InternalQuoteMsg {
+        hd: RecordHeader::new::<InternalQuoteMsg>(QUOTE_RTYPE, 0, instrument_id, ts - 1),
+        bid_px: 100_000_000_000,
+        ask_px: 100_250_000_000,
+        ts_recv: ts,
+        source: b'A' as c_char,
+        _reserved: [0; 7],
+    }
+}
+
+#[test]
+fn test_registered_record_round_trip() {
+    registry::register::<InternalQuoteMsg>(QUOTE_RTYPE).unwrap();
+    let metadata = MetadataBuilder::new()
+        .dataset("INTERNAL".to_owned())
+        .schema(None)
+        .start(0)
+        .stype_in(None)
+        .stype_out(SType::InstrumentId)
+        .build();
+    let records = [quote(1, 10), quote(2, 20)];
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = DbnEncoder::new(&mut buffer, &metadata).unwrap();
+        for rec in records.iter() {
+            encoder.encode_record(rec).unwrap();
+        }
+    }
+
+    let mut decoder = DynDecoder::new(
+        buffer.as_slice(),
+        Compression::None,
+        VersionUpgradePolicy::AsIs,
+    )
+    .unwrap();
+    let mut json = Vec::new();
+    let mut json_encoder =
+        DynEncoder::builder(&mut json, Encoding::Json, Compression::None, &metadata)
+            .build()
+            .unwrap();
+    let mut csv = Vec::new();
+    let mut csv_encoder =
+        DynEncoder::builder(&mut csv, Encoding::Csv, Compression::None, &metadata)
+            .write_header(false)
+            .build()
+            .unwrap();
+    let mut decoded = Vec::new();
+    while let Some(rec_ref) = decoder.decode_record_ref().unwrap() {
+        json_encoder.encode_record_ref(rec_ref).unwrap();
+        csv_encoder
+            .encode_ref_with_sym(rec_ref, Some("ABC"))
+            .unwrap();
+        let RecordRefEnum::Registered(registered) = rec_ref.as_enum().unwrap() else {
+            panic!("expected registered record");
+        };
+        assert_eq!(registered.raw_index_ts(), rec_ref.raw_index_ts());
+        assert_eq!(
+            rec_ref.field("ask_px"),
+            Some(FieldValue::I64(100_250_000_000))
+        );
+        assert_eq!(rec_ref.field("source"), Some(FieldValue::Char('A')));
+        decoded.push(RecordRefEnum::Registered(registered).to_owned());
+    }
+    drop(json_encoder);
+    drop(csv_encoder);
+
+    assert_eq!(decoded.len(), records.len());
+    for (exp, decoded) in records.iter().zip(decoded.iter()) {
+        let RecordEnum::Registered(decoded) = decoded else {
+            panic!("expected registered record");
+        };
+        assert_eq!(
+            decoded.as_registered_ref().get::<InternalQuoteMsg>(),
+            Some(exp)
+        );
+        assert_eq!(decoded.raw_index_ts(), exp.ts_recv);
+    }
+    assert_eq!(
+        std::str::from_utf8(&json).unwrap(),
+        "{\"hd\":{\"ts_event\":\"9\",\"rtype\":224,\"publisher_id\":0,\"instrument_id\":1},\"bid_px\":\"100000000000\",\"ask_px\":\"100250000000\",\"ts_recv\":\"10\",\"source\":\"A\"}\n\
+        {\"hd\":{\"ts_event\":\"19\",\"rtype\":224,\"publisher_id\":0,\"instrument_id\":2},\"bid_px\":\"100000000000\",\"ask_px\":\"100250000000\",\"ts_recv\":\"20\",\"source\":\"A\"}\n"
+    );
+    assert_eq!(
+        std::str::from_utf8(&csv).unwrap(),
+        "ts_event,rtype,publisher_id,instrument_id,bid_px,ask_px,ts_recv,source,symbol\n\
+        9,224,0,1,100000000000,100250000000,10,A,ABC\n\
+        19,224,0,2,100000000000,100250000000,20,A,ABC\n"
+    );
+}